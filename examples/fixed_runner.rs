@@ -0,0 +1,120 @@
+//! Compares the allocations and latency of running a small model with a new
+//! `SessionRunArgs` for every run against running it with a `FixedRunner`.
+//!
+//! Only allocations made through Rust's global allocator are counted, since
+//! TensorFlow allocates its own tensors with its own allocator.
+use std::alloc::GlobalAlloc;
+use std::alloc::Layout;
+use std::alloc::System;
+use std::error::Error;
+use std::result::Result;
+use std::sync::atomic::AtomicUsize;
+use std::sync::atomic::Ordering;
+use std::time::Instant;
+use tensorflow::DataType;
+use tensorflow::FixedRunner;
+use tensorflow::Graph;
+use tensorflow::Operation;
+use tensorflow::Session;
+use tensorflow::SessionOptions;
+use tensorflow::SessionRunArgs;
+use tensorflow::Shape;
+use tensorflow::Status;
+use tensorflow::Tensor;
+
+/// The system allocator, counting the allocations made through it.
+struct CountingAlloc;
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for CountingAlloc {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: CountingAlloc = CountingAlloc;
+
+const RUNS: usize = 10_000;
+const SIZE: u64 = 4;
+
+/// Builds y = x * w for a `[1, SIZE]` input and a `[SIZE, SIZE]` weight.
+fn build_graph() -> Result<(Graph, Operation, Operation), Status> {
+    let mut g = Graph::new();
+    let x = {
+        let mut nd = g.new_operation("Placeholder", "x")?;
+        nd.set_attr_type("dtype", DataType::Float)?;
+        nd.set_attr_shape("shape", &Shape::from(&[1, SIZE as i64][..]))?;
+        nd.finish()?
+    };
+    let w = {
+        let mut nd = g.new_operation("Const", "w")?;
+        nd.set_attr_type("dtype", DataType::Float)?;
+        let values = vec![0.5f32; (SIZE * SIZE) as usize];
+        nd.set_attr_tensor("value", Tensor::new(&[SIZE, SIZE]).with_values(&values)?)?;
+        nd.finish()?
+    };
+    let y = {
+        let mut nd = g.new_operation("MatMul", "y")?;
+        nd.add_input(x.clone());
+        nd.add_input(w);
+        nd.finish()?
+    };
+    Ok((g, x, y))
+}
+
+/// Calls `run` `RUNS` times and prints the allocations and time per run.
+fn bench<R>(name: &str, mut run: R) -> Result<(), Box<dyn Error>>
+where
+    R: FnMut() -> Result<(), Status>,
+{
+    let allocations = ALLOCATIONS.load(Ordering::Relaxed);
+    let start = Instant::now();
+    for _ in 0..RUNS {
+        run()?;
+    }
+    let elapsed = start.elapsed();
+    let allocations = ALLOCATIONS.load(Ordering::Relaxed) - allocations;
+    println!(
+        "{}: {:.1} allocations and {:.1} us per run",
+        name,
+        allocations as f64 / RUNS as f64,
+        elapsed.as_secs_f64() * 1e6 / RUNS as f64
+    );
+    Ok(())
+}
+
+fn main() -> Result<(), Box<dyn Error>> {
+    let (graph, x, y) = build_graph()?;
+    let session = Session::new(&SessionOptions::new(), &graph)?;
+
+    let input = Tensor::new(&[1, SIZE]).with_values(&[1.0f32; SIZE as usize])?;
+    bench("SessionRunArgs", || {
+        let mut args = SessionRunArgs::new();
+        args.add_feed(&x, 0, &input);
+        let fetch = args.request_fetch(&y, 0);
+        session.run(&mut args)?;
+        let output: Tensor<f32> = args.fetch(fetch)?;
+        assert_eq!(output[0], 2.0);
+        Ok(())
+    })?;
+
+    let mut runner = FixedRunner::<f32>::new(
+        &session,
+        &[(x.clone().into(), &[1, SIZE])],
+        &[(y.clone().into(), &[1, SIZE])],
+    )?;
+    runner.input_mut(0).copy_from_slice(&[1.0; SIZE as usize]);
+    bench("FixedRunner", || {
+        runner.run()?;
+        assert_eq!(runner.output(0)[0], 2.0);
+        Ok(())
+    })?;
+    Ok(())
+}
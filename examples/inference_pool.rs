@@ -0,0 +1,109 @@
+//! Compares running a small model from many threads on one shared `Session`
+//! with running it on an `InferencePool` of single-threaded sessions.
+use std::error::Error;
+use std::result::Result;
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+use std::time::Instant;
+use tensorflow::DataType;
+use tensorflow::Graph;
+use tensorflow::InferencePool;
+use tensorflow::Operation;
+use tensorflow::Session;
+use tensorflow::SessionOptions;
+use tensorflow::SessionRunArgs;
+use tensorflow::Shape;
+use tensorflow::Status;
+use tensorflow::Tensor;
+
+#[cfg_attr(feature = "examples_system_alloc", global_allocator)]
+#[cfg(feature = "examples_system_alloc")]
+static ALLOCATOR: std::alloc::System = std::alloc::System;
+
+const NUM_THREADS: usize = 8;
+const RUNS_PER_THREAD: usize = 200;
+const SIZE: u64 = 64;
+
+/// Builds y = x * w for a `[1, SIZE]` input and a `[SIZE, SIZE]` weight.
+fn build_graph() -> Result<(Graph, Operation, Operation), Status> {
+    let mut g = Graph::new();
+    let x = {
+        let mut nd = g.new_operation("Placeholder", "x")?;
+        nd.set_attr_type("dtype", DataType::Float)?;
+        nd.set_attr_shape("shape", &Shape::from(&[1, SIZE as i64][..]))?;
+        nd.finish()?
+    };
+    let w = {
+        let mut nd = g.new_operation("Const", "w")?;
+        nd.set_attr_type("dtype", DataType::Float)?;
+        let values = vec![0.5f32; (SIZE * SIZE) as usize];
+        nd.set_attr_tensor("value", Tensor::new(&[SIZE, SIZE]).with_values(&values)?)?;
+        nd.finish()?
+    };
+    let y = {
+        let mut nd = g.new_operation("MatMul", "y")?;
+        nd.add_input(x.clone());
+        nd.add_input(w);
+        nd.finish()?
+    };
+    Ok((g, x, y))
+}
+
+/// Runs the model `RUNS_PER_THREAD` times on each of `NUM_THREADS` threads
+/// and returns the total wall time.
+fn bench<R>(run: R, x: &Operation, y: &Operation) -> Result<Duration, Box<dyn Error>>
+where
+    R: Fn(&mut SessionRunArgs<'_>) -> Result<(), Status> + Send + Sync + 'static,
+{
+    let run = Arc::new(run);
+    let start = Instant::now();
+    let threads: Vec<_> = (0..NUM_THREADS)
+        .map(|_| {
+            let run = run.clone();
+            let x = x.clone();
+            let y = y.clone();
+            thread::spawn(move || -> Result<(), Status> {
+                let input = Tensor::new(&[1, SIZE]).with_values(&[1.0f32; SIZE as usize])?;
+                for _ in 0..RUNS_PER_THREAD {
+                    let mut args = SessionRunArgs::new();
+                    args.add_feed(&x, 0, &input);
+                    let fetch = args.request_fetch(&y, 0);
+                    run(&mut args)?;
+                    let _: Tensor<f32> = args.fetch(fetch)?;
+                }
+                Ok(())
+            })
+        })
+        .collect();
+    for thread in threads {
+        thread.join().expect("benchmark thread panicked")?;
+    }
+    Ok(start.elapsed())
+}
+
+fn main() -> Result<(), Box<dyn Error>> {
+    let (graph, x, y) = build_graph()?;
+
+    let session = Session::new(&SessionOptions::new(), &graph)?;
+    let shared = bench(move |args| session.run(args), &x, &y)?;
+
+    // Serialized ConfigProto with intra_op_parallelism_threads (field 2) and
+    // inter_op_parallelism_threads (field 5) set to 1, so the pooled sessions
+    // don't compete for cores.
+    let mut options = SessionOptions::new();
+    options.set_config(&[0x10, 0x01, 0x28, 0x01])?;
+    let pool = InferencePool::from_graph(&graph, &options, NUM_THREADS)?;
+    let pooled = bench(move |args| pool.run(args), &x, &y)?;
+
+    let total_runs = (NUM_THREADS * RUNS_PER_THREAD) as f64;
+    for (name, elapsed) in &[("shared session", shared), ("inference pool", pooled)] {
+        println!(
+            "{}: {:?} total, {:.1} us per run",
+            name,
+            elapsed,
+            elapsed.as_secs_f64() * 1e6 / total_runs
+        );
+    }
+    Ok(())
+}
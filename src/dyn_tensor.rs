@@ -0,0 +1,143 @@
+use crate::AnyTensor;
+use crate::BFloat16;
+use crate::DataType;
+use crate::FetchToken;
+use crate::QInt16;
+use crate::QInt32;
+use crate::QInt8;
+use crate::QUInt16;
+use crate::QUInt8;
+use crate::Result;
+use crate::SessionRunArgs;
+use crate::Shape;
+use crate::Tensor;
+use crate::TensorType;
+use half::f16;
+use num_complex::Complex;
+use std::any::Any;
+
+macro_rules! dyn_tensor {
+    ($($variant:ident($rust_type:ty),)*) => {
+        /// A tensor whose element type is only known at runtime, such as one
+        /// returned by `SessionRunArgs::fetch_dynamic`.
+        ///
+        /// There is one variant for each `TensorType`, named after its
+        /// `DataType`, and every `Tensor` converts into a `DynTensor`.
+        ///
+        /// ```
+        /// # use tensorflow::DataType;
+        /// # use tensorflow::DynTensor;
+        /// # use tensorflow::Tensor;
+        /// let tensor = DynTensor::from(Tensor::new(&[2]).with_values(&[1i64, 2])?);
+        /// assert_eq!(tensor.dtype(), DataType::Int64);
+        /// assert!(tensor.clone().downcast::<i32>().is_none());
+        /// assert_eq!(&tensor.downcast::<i64>().unwrap()[..], &[1, 2]);
+        /// # Ok::<(), Box<dyn std::error::Error>>(())
+        /// ```
+        #[derive(Debug, Clone)]
+        #[allow(missing_docs)]
+        pub enum DynTensor {
+            $($variant(Tensor<$rust_type>),)*
+        }
+
+        impl DynTensor {
+            /// Returns the element type of the tensor.
+            pub fn dtype(&self) -> DataType {
+                match self {
+                    $(DynTensor::$variant(_) => DataType::$variant,)*
+                }
+            }
+
+            /// Returns the tensor's dimensions as a Shape.
+            pub fn shape(&self) -> Shape {
+                match self {
+                    $(DynTensor::$variant(tensor) => tensor.shape(),)*
+                }
+            }
+
+            /// Returns the tensor if its element type is `T`, or `None`
+            /// otherwise.
+            pub fn downcast<T: TensorType>(self) -> Option<Tensor<T>> {
+                let tensor: Box<dyn Any> = match self {
+                    $(DynTensor::$variant(tensor) => Box::new(tensor),)*
+                };
+                tensor.downcast().ok().map(|tensor| *tensor)
+            }
+
+            /// Returns the tensor as a type-erased tensor which can be fed.
+            pub(crate) fn as_any_tensor(&self) -> &dyn AnyTensor {
+                match self {
+                    $(DynTensor::$variant(tensor) => tensor,)*
+                }
+            }
+
+            /// Fetches the output of `token` as the `TensorType` of `dtype`,
+            /// or returns `None` if no `TensorType` has that `DataType`.
+            pub(crate) fn fetch(
+                args: &mut SessionRunArgs<'_>,
+                token: FetchToken,
+                dtype: DataType,
+            ) -> Option<Result<Self>> {
+                match dtype {
+                    $(DataType::$variant => Some(args.fetch(token).map(DynTensor::$variant)),)*
+                    _ => None,
+                }
+            }
+        }
+
+        $(
+            impl From<Tensor<$rust_type>> for DynTensor {
+                fn from(tensor: Tensor<$rust_type>) -> Self {
+                    DynTensor::$variant(tensor)
+                }
+            }
+        )*
+    };
+}
+
+dyn_tensor! {
+    Float(f32),
+    Double(f64),
+    Int32(i32),
+    UInt8(u8),
+    Int16(i16),
+    Int8(i8),
+    String(String),
+    Complex64(Complex<f32>),
+    Int64(i64),
+    Bool(bool),
+    QInt8(QInt8),
+    QUInt8(QUInt8),
+    QInt32(QInt32),
+    BFloat16(BFloat16),
+    QInt16(QInt16),
+    QUInt16(QUInt16),
+    UInt16(u16),
+    Complex128(Complex<f64>),
+    Half(f16),
+    UInt32(u32),
+    UInt64(u64),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn dyn_tensor() {
+        let tensor = DynTensor::from(Tensor::new(&[2, 1]).with_values(&[1.0f32, 2.0]).unwrap());
+        assert_eq!(tensor.dtype(), DataType::Float);
+        assert_eq!(tensor.shape(), Shape::from(&[2u64, 1][..]));
+        assert!(tensor.clone().downcast::<f64>().is_none());
+        assert_eq!(&tensor.downcast::<f32>().unwrap()[..], &[1.0, 2.0]);
+
+        let tensor = DynTensor::from(Tensor::from("a".to_string()));
+        assert_eq!(tensor.dtype(), DataType::String);
+        assert_eq!(tensor.shape(), Shape::from(&[] as &[u64]));
+        assert_eq!(&tensor.downcast::<String>().unwrap()[..], &["a"]);
+
+        let tensor = DynTensor::from(Tensor::from(QInt8::from(3)));
+        assert_eq!(tensor.dtype(), DataType::QInt8);
+        assert!(tensor.downcast::<i8>().is_none());
+    }
+}
@@ -0,0 +1,126 @@
+use crate::protos;
+use crate::Code;
+use crate::Graph;
+use crate::ImportGraphDefOptions;
+use crate::Result;
+use crate::Session;
+use crate::SessionOptions;
+use crate::SessionRunArgs;
+use protobuf::Message;
+
+/// The device the fallback copy of the graph is placed on.
+const FALLBACK_DEVICE: &str = "/device:CPU:0";
+
+/// A session which retries a run on the CPU if it fails with
+/// `ResourceExhausted`, e.g. because the GPU ran out of memory.
+///
+/// A copy of the graph with every operation placed on the CPU is prepared
+/// when the session is created, together with a second session for it.  This
+/// doubles the memory used for the graph and for any variables, and a run
+/// which falls back takes the time of the failed run plus the time of the
+/// CPU run, which is usually much slower than the GPU.  Falling back trades
+/// that latency for not failing requests under memory pressure.
+///
+/// The operations in the `SessionRunArgs` passed to `run` must belong to
+/// the original graph; they are mapped to the CPU copy by name.  Variables
+/// are not shared between the two sessions, so this is meant for inference
+/// with graphs whose state is constant.
+///
+/// ```rust,ignore
+/// let session = FallbackSession::new(&options, &graph)?;
+/// let mut args = SessionRunArgs::new();
+/// args.add_feed(&x, 0, &input);
+/// let y = args.request_fetch(&output, 0);
+/// session.run(&mut args)?;
+/// ```
+#[derive(Debug)]
+pub struct FallbackSession {
+    session: Session,
+    fallback_graph: Graph,
+    fallback_session: Session,
+}
+
+impl FallbackSession {
+    /// Creates a session over `graph`, and a session over a copy of it
+    /// placed on the CPU.
+    pub fn new(options: &SessionOptions, graph: &Graph) -> Result<Self> {
+        let mut proto: protos::graph::GraphDef = protobuf::parse_from_bytes(&graph.graph_def()?)
+            .map_err(|e| invalid_arg!("Invalid serialized GraphDef: {}", e))?;
+        for node in proto.mut_node().iter_mut() {
+            node.set_device(FALLBACK_DEVICE.to_string());
+        }
+        let graph_def = proto
+            .write_to_bytes()
+            .map_err(|e| invalid_arg!("Unable to serialize GraphDef: {}", e))?;
+        let mut fallback_graph = Graph::new();
+        fallback_graph.import_graph_def(&graph_def, &ImportGraphDefOptions::new())?;
+        Ok(FallbackSession {
+            session: Session::new(options, graph)?,
+            fallback_session: Session::new(options, &fallback_graph)?,
+            fallback_graph,
+        })
+    }
+
+    /// Returns the session over the original graph.
+    pub fn session(&self) -> &Session {
+        &self.session
+    }
+
+    /// Runs `args` like `Session::run`, retrying on the CPU if the run fails
+    /// with `ResourceExhausted`.  After a retry, the fetches and run metadata
+    /// of `args` are those of the CPU run.
+    pub fn run(&self, args: &mut SessionRunArgs<'_>) -> Result<()> {
+        match self.session.run(args) {
+            Err(status) if status.code() == Code::ResourceExhausted => self.run_fallback(args),
+            result => result,
+        }
+    }
+
+    fn run_fallback(&self, args: &mut SessionRunArgs<'_>) -> Result<()> {
+        let mut fallback_args = args.remapped_to(&self.fallback_graph)?;
+        self.fallback_session.run(&mut fallback_args)?;
+        args.take_results_from(&mut fallback_args);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::DataType;
+    use crate::Tensor;
+
+    #[test]
+    fn run_fallback() {
+        let mut g = Graph::new();
+        let x = {
+            let mut nd = g.new_operation("Placeholder", "x").unwrap();
+            nd.set_attr_type("dtype", DataType::Float).unwrap();
+            nd.finish().unwrap()
+        };
+        let y = {
+            let mut nd = g.new_operation("Square", "y").unwrap();
+            nd.add_input(x.clone());
+            nd.finish().unwrap()
+        };
+        let session = FallbackSession::new(&SessionOptions::new(), &g).unwrap();
+        for operation in session.fallback_graph.operation_iter() {
+            assert_eq!(operation.device().unwrap(), FALLBACK_DEVICE);
+        }
+
+        let x_value = Tensor::from(3.0f32);
+        let mut args = SessionRunArgs::new();
+        args.add_feed(&x, 0, &x_value);
+        let token = args.request_fetch(&y, 0);
+        session.run(&mut args).unwrap();
+        assert_eq!(args.fetch::<f32>(token).unwrap()[0], 9.0);
+
+        // The fallback fills in the fetches of the original args.
+        let x_value = Tensor::from(4.0f32);
+        let mut args = SessionRunArgs::new();
+        args.add_feed(&x, 0, &x_value);
+        let token = args.request_fetch(&y, 0);
+        session.run_fallback(&mut args).unwrap();
+        assert_eq!(args.fetch::<f32>(token).unwrap()[0], 16.0);
+    }
+}
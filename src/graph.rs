@@ -1,4 +1,5 @@
 use super::buffer::Buffer;
+use super::protos;
 use super::AnyTensor;
 use super::Code;
 use super::DataType;
@@ -14,7 +15,13 @@ use libc::c_uchar;
 use libc::c_uint;
 use libc::c_void;
 use libc::size_t;
+use protobuf::Message;
 use std;
+use std::collections::hash_map::Entry;
+use std::collections::BTreeSet;
+use std::collections::HashMap;
+use std::collections::HashSet;
+use std::collections::VecDeque;
 use std::ffi::CStr;
 use std::ffi::CString;
 use std::ffi::NulError;
@@ -29,6 +36,19 @@ use std::str::Utf8Error;
 use std::sync::Arc;
 use tensorflow_sys as tf;
 
+// TF_UpdateEdge is missing from the headers which tensorflow-sys/src/bindgen.rs
+// was generated from, so it is declared here rather than in the generated
+// bindings, where it would be lost when they are regenerated.  It can be
+// removed once the bindings include it.
+extern "C" {
+    fn TF_UpdateEdge(
+        graph: *mut tf::TF_Graph,
+        new_src: tf::TF_Output,
+        dst: tf::TF_Input,
+        status: *mut tf::TF_Status,
+    );
+}
+
 #[derive(Debug)]
 struct GraphLifetime;
 
@@ -59,16 +79,34 @@ impl Drop for GraphImpl {
 #[derive(Debug)]
 pub struct ImportGraphDefOptions {
     inner: *mut tf::TF_ImportGraphDefOptions,
+    max_graph_def_size: Option<usize>,
+    max_nodes: Option<usize>,
+    reject_unknown_ops: bool,
+}
+
+impl Default for ImportGraphDefOptions {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
-impl_new!(
-    ImportGraphDefOptions,
-    TF_NewImportGraphDefOptions,
-    "Creates a default ImportGraphDefOptions."
-);
 impl_drop!(ImportGraphDefOptions, TF_DeleteImportGraphDefOptions);
 
 impl ImportGraphDefOptions {
+    /// Creates a default ImportGraphDefOptions.
+    pub fn new() -> Self {
+        unsafe {
+            let inner = tf::TF_NewImportGraphDefOptions();
+            assert!(!inner.is_null());
+            ImportGraphDefOptions {
+                inner,
+                max_graph_def_size: None,
+                max_nodes: None,
+                reject_unknown_ops: false,
+            }
+        }
+    }
+
     /// Set the prefix to be prepended to the names of nodes in `graph_def` that will
     /// be imported into `graph`.
     pub fn set_prefix(&mut self, prefix: &str) -> std::result::Result<(), NulError> {
@@ -194,6 +232,95 @@ impl ImportGraphDefOptions {
         }
         Ok(())
     }
+
+    /// Set the maximum size in bytes of a serialized `GraphDef` that will be
+    /// imported. Larger graph definitions are rejected with an
+    /// `InvalidArgument` error before they are parsed. Defaults to no limit.
+    pub fn set_max_graph_def_size(&mut self, max_graph_def_size: Option<usize>) {
+        self.max_graph_def_size = max_graph_def_size;
+    }
+
+    /// Set the maximum number of nodes that will be imported, counting nodes
+    /// in the graph as well as nodes in the bodies of library functions.
+    /// Larger graph definitions are rejected with an `InvalidArgument` error.
+    /// Defaults to no limit.
+    pub fn set_max_nodes(&mut self, max_nodes: Option<usize>) {
+        self.max_nodes = max_nodes;
+    }
+
+    /// Set whether to check that every node uses an op that is registered or
+    /// defined in the graph or in the `GraphDef`'s function library before
+    /// importing. If an unknown op is found, a `NotFound` error listing the
+    /// unknown ops is returned and the graph is unmodified. Defaults to false.
+    ///
+    /// Together with `set_max_graph_def_size` and `set_max_nodes`, this is
+    /// intended for importing graph definitions from untrusted sources.
+    pub fn set_reject_unknown_ops(&mut self, reject_unknown_ops: bool) {
+        self.reject_unknown_ops = reject_unknown_ops;
+    }
+
+    /// Checks `graph_def` against the limits set on the options.
+    fn validate(&self, graph: &Graph, graph_def: &[u8]) -> Result<()> {
+        if let Some(max_size) = self.max_graph_def_size {
+            if graph_def.len() > max_size {
+                return Err(invalid_arg!(
+                    "GraphDef is {} bytes, which exceeds the limit of {} bytes",
+                    graph_def.len(),
+                    max_size
+                ));
+            }
+        }
+        if self.max_nodes.is_none() && !self.reject_unknown_ops {
+            return Ok(());
+        }
+        let proto: protos::graph::GraphDef = protobuf::parse_from_bytes(graph_def)
+            .map_err(|e| invalid_arg!("Invalid serialized GraphDef: {}", e))?;
+        let functions = proto.get_library().get_function();
+        if let Some(max_nodes) = self.max_nodes {
+            let num_nodes = proto.get_node().len()
+                + functions
+                    .iter()
+                    .map(|f| f.get_node_def().len())
+                    .sum::<usize>();
+            if num_nodes > max_nodes {
+                return Err(invalid_arg!(
+                    "GraphDef has {} nodes, which exceeds the limit of {} nodes",
+                    num_nodes,
+                    max_nodes
+                ));
+            }
+        }
+        if self.reject_unknown_ops {
+            let library_ops: HashSet<&str> = functions
+                .iter()
+                .map(|f| f.get_signature().get_name())
+                .collect();
+            let mut unknown_ops = BTreeSet::new();
+            let nodes = proto
+                .get_node()
+                .iter()
+                .chain(functions.iter().flat_map(|f| f.get_node_def()));
+            for node in nodes {
+                let op = node.get_op();
+                if !library_ops.contains(op)
+                    && !unknown_ops.contains(op)
+                    && graph.get_op_def(op).is_err()
+                {
+                    unknown_ops.insert(op);
+                }
+            }
+            if !unknown_ops.is_empty() {
+                return Err(Status::new_set_lossy(
+                    Code::NotFound,
+                    &format!(
+                        "GraphDef uses unknown ops: {}",
+                        unknown_ops.into_iter().collect::<Vec<_>>().join(", ")
+                    ),
+                ));
+            }
+        }
+        Ok(())
+    }
 }
 
 ////////////////////////
@@ -325,6 +452,7 @@ impl Graph {
                 ),
                 graph: self,
                 finished: false,
+                attr_names: HashSet::new(),
             })
         }
     }
@@ -365,6 +493,23 @@ impl Graph {
         }
     }
 
+    /// Returns the value of the `Const` operation named `operation_name`, e.g.
+    /// to inspect the weights of a frozen graph without running a session.
+    /// Returns an error if the operation doesn't exist, isn't a `Const`, or
+    /// its value doesn't have type `T`.
+    pub fn const_value<T: TensorType>(&self, operation_name: &str) -> Result<Tensor<T>> {
+        let operation = self.operation_by_name_required(operation_name)?;
+        let op_type = operation.op_type()?;
+        if op_type != "Const" {
+            return Err(invalid_arg!(
+                "Operation {:?} is a {}, not a Const",
+                operation_name,
+                op_type
+            ));
+        }
+        operation.get_attr_tensor("value")
+    }
+
     /// Finds a unique operation name.  The pattern must contain exactly one
     /// '{}' placeholder to indicate where a unique ID can be inserted, e.g.
     /// 'Add_{}' or 'while_loop_{}/Merge', and the function returns an integer
@@ -391,6 +536,621 @@ impl Graph {
         }
     }
 
+    /// Returns the operations in the graph in topological order, i.e. every
+    /// operation comes after all of its data and control inputs.  Among
+    /// operations whose inputs are all satisfied, the order in which they
+    /// were added to the graph is preserved.
+    ///
+    /// The back edges of while loops, which come from `NextIteration` ops,
+    /// are ignored.  Any other cycle results in an `InvalidArgument` error.
+    pub fn topological_order(&self) -> Result<Vec<Operation>> {
+        let (order, blocked) = self.sort_topologically();
+        if let Some(operation) = blocked.first() {
+            return Err(invalid_arg!(
+                "Graph contains a cycle involving operation {}",
+                operation.name()?
+            ));
+        }
+        Ok(order)
+    }
+
+    /// Sorts the operations topologically, returning the sorted operations
+    /// and, in graph order, the operations which could not be sorted because
+    /// they are on a cycle or depend on one.
+    fn sort_topologically(&self) -> (Vec<Operation>, Vec<Operation>) {
+        let operations: Vec<_> = self.operation_iter().collect();
+        let positions: HashMap<_, _> = operations
+            .iter()
+            .enumerate()
+            .map(|(i, operation)| (operation.inner, i))
+            .collect();
+        let mut num_pending_inputs = vec![0; operations.len()];
+        let mut consumers = vec![Vec::new(); operations.len()];
+        for (i, operation) in operations.iter().enumerate() {
+            let data_inputs = (0..operation.num_inputs())
+                .map(|index| operation.input(index).0)
+                .filter(|input| match input.op_type() {
+                    Ok(op_type) => op_type != "NextIteration" && op_type != "RefNextIteration",
+                    Err(_) => true,
+                });
+            for input in data_inputs.chain(operation.control_inputs()) {
+                num_pending_inputs[i] += 1;
+                consumers[positions[&input.inner]].push(i);
+            }
+        }
+        let mut ready: VecDeque<_> = (0..operations.len())
+            .filter(|i| num_pending_inputs[*i] == 0)
+            .collect();
+        let mut order = Vec::with_capacity(operations.len());
+        while let Some(i) = ready.pop_front() {
+            order.push(operations[i].clone());
+            for consumer in &consumers[i] {
+                num_pending_inputs[*consumer] -= 1;
+                if num_pending_inputs[*consumer] == 0 {
+                    ready.push_back(*consumer);
+                }
+            }
+        }
+        let blocked = (0..operations.len())
+            .filter(|i| num_pending_inputs[*i] > 0)
+            .map(|i| operations[i].clone())
+            .collect();
+        (order, blocked)
+    }
+
+    /// Returns a rough estimate of the floating point operations needed to
+    /// run every operation in the graph once, e.g. to compare the cost of
+    /// models.
+    ///
+    /// The estimate is computed from the statically inferred shapes.  Matrix
+    /// multiplications and convolutions count two operations per
+    /// multiply-accumulate, element-wise operations one per output element
+    /// and reductions one per input element.  Operations which only move or
+    /// hold data, such as `Reshape` and `Const`, count zero.  Operations of
+    /// other types, or whose shapes are not fully known, count zero too and
+    /// are listed in `FlopEstimate::unestimated`.
+    pub fn estimate_flops(&self) -> Result<FlopEstimate> {
+        let mut estimate = FlopEstimate {
+            flops: 0,
+            unestimated: Vec::new(),
+        };
+        for operation in self.operation_iter() {
+            match self.operation_flops(&operation)? {
+                Some(flops) => estimate.flops = estimate.flops.saturating_add(flops),
+                None => estimate.unestimated.push(operation.name()?),
+            }
+        }
+        Ok(estimate)
+    }
+
+    /// Returns the estimated floating point operations of `operation`, or
+    /// `None` if they can't be estimated.
+    fn operation_flops(&self, operation: &Operation) -> Result<Option<u64>> {
+        let dims = |operation: Operation, index: c_int| -> Result<Option<Vec<u64>>> {
+            Ok(known_dims(&self.tensor_shape(Output { operation, index })?))
+        };
+        let input_dims = |index: usize| {
+            let (input, input_index) = operation.input(index);
+            dims(input, input_index as c_int)
+        };
+        let elements = |dims: Option<Vec<u64>>| dims.map(|dims| saturating_product(&dims));
+        let op_type = operation.op_type()?;
+        let flops = match op_type.as_str() {
+            "Assign"
+            | "ConcatV2"
+            | "Const"
+            | "ExpandDims"
+            | "Fill"
+            | "Gather"
+            | "GatherV2"
+            | "Identity"
+            | "IdentityN"
+            | "NoOp"
+            | "Pack"
+            | "Placeholder"
+            | "PlaceholderWithDefault"
+            | "ReadVariableOp"
+            | "Reshape"
+            | "Shape"
+            | "Slice"
+            | "Squeeze"
+            | "StopGradient"
+            | "StridedSlice"
+            | "Tile"
+            | "Transpose"
+            | "Unpack"
+            | "VarHandleOp"
+            | "Variable"
+            | "VariableV2" => Some(0),
+            "MatMul" | "BatchMatMul" | "BatchMatMulV2" | "BatchMatMulV3" => {
+                let transposed = if op_type == "MatMul" {
+                    operation.get_attr_bool("transpose_a")?
+                } else {
+                    operation.get_attr_bool("adj_x")?
+                };
+                match (elements(dims(operation.clone(), 0)?), input_dims(0)?) {
+                    (Some(output), Some(a)) if a.len() >= 2 => {
+                        let k = a[a.len() - if transposed { 2 } else { 1 }];
+                        Some(saturating_product(&[2, output, k]))
+                    }
+                    _ => None,
+                }
+            }
+            "Conv2D" | "DepthwiseConv2dNative" => {
+                match (elements(dims(operation.clone(), 0)?), input_dims(1)?) {
+                    // The filter is [height, width, in_channels, out_channels]
+                    // or [height, width, in_channels, channel_multiplier].
+                    (Some(output), Some(filter)) if filter.len() == 4 => {
+                        let depth = if op_type == "Conv2D" { filter[2] } else { 1 };
+                        Some(saturating_product(&[
+                            2, output, filter[0], filter[1], depth,
+                        ]))
+                    }
+                    _ => None,
+                }
+            }
+            "Abs" | "Add" | "AddN" | "AddV2" | "BiasAdd" | "Cast" | "Div" | "Elu" | "Equal"
+            | "Erf" | "Exp" | "Greater" | "Less" | "Log" | "Maximum" | "Minimum" | "Mul"
+            | "Neg" | "RealDiv" | "Relu" | "Relu6" | "Rsqrt" | "Select" | "SelectV2" | "Selu"
+            | "Sigmoid" | "Softmax" | "Sqrt" | "Square" | "SquaredDifference" | "Sub" | "Tanh" => {
+                elements(dims(operation.clone(), 0)?)
+            }
+            "Max" | "Mean" | "Min" | "Prod" | "Sum" => elements(input_dims(0)?),
+            _ => None,
+        };
+        Ok(flops)
+    }
+
+    /// Returns the number of parameters of the graph, i.e. the number of
+    /// elements of its variables, and of its floating point constants which
+    /// are not scalars, which hold the weights of frozen graphs.  Variables
+    /// whose shapes are not fully known are not counted.
+    pub fn count_parameters(&self) -> Result<u64> {
+        let mut count = 0u64;
+        for operation in self.operation_iter() {
+            let shape = match operation.op_type()?.as_str() {
+                "VarHandleOp" | "Variable" | "VariableV2" => operation.get_attr_shape("shape")?,
+                "Const" => match operation.get_attr_type("dtype")? {
+                    DataType::Float | DataType::Double | DataType::Half | DataType::BFloat16 => {
+                        self.tensor_shape(Output {
+                            operation,
+                            index: 0,
+                        })?
+                    }
+                    _ => continue,
+                },
+                _ => continue,
+            };
+            if let Some(dims) = known_dims(&shape) {
+                if !dims.is_empty() {
+                    count = count.saturating_add(saturating_product(&dims));
+                }
+            }
+        }
+        Ok(count)
+    }
+
+    /// Checks the graph for problems which would otherwise only show up as
+    /// confusing errors when creating a session or running the graph, and
+    /// returns an `InvalidArgument` error listing all of them.  See
+    /// `GraphProblem` for the problems which are detected.
+    pub fn validate(&self) -> Result<()> {
+        let problems = self.problems()?;
+        if problems.is_empty() {
+            return Ok(());
+        }
+        let descriptions: Vec<_> = problems.iter().map(ToString::to_string).collect();
+        Err(invalid_arg!(
+            "Graph is invalid:\n{}",
+            descriptions.join("\n")
+        ))
+    }
+
+    /// Returns the problems found in the graph, which are the same ones
+    /// `validate` reports.
+    pub fn problems(&self) -> Result<Vec<GraphProblem>> {
+        let mut problems = Vec::new();
+        let (_, blocked) = self.sort_topologically();
+        if !blocked.is_empty() {
+            let mut operations = Vec::with_capacity(blocked.len());
+            for operation in &blocked {
+                operations.push(operation.name()?);
+            }
+            problems.push(GraphProblem::Cycle { operations });
+        }
+        let proto: protos::graph::GraphDef = protobuf::parse_from_bytes(&self.graph_def()?)
+            .map_err(|e| invalid_arg!("Invalid serialized GraphDef: {}", e))?;
+        for node in proto.get_node() {
+            for input in node.get_input() {
+                let producer = match self.operation_by_name(input_node_name(input))? {
+                    Some(producer) => producer,
+                    None => {
+                        problems.push(GraphProblem::UnknownInput {
+                            operation: node.get_name().to_string(),
+                            input: input.clone(),
+                        });
+                        continue;
+                    }
+                };
+                if input.starts_with('^') {
+                    continue;
+                }
+                let index = match input.rfind(':') {
+                    Some(pos) => input[pos + 1..].parse::<usize>().ok(),
+                    None => Some(0),
+                };
+                if !matches!(index, Some(index) if index < producer.num_outputs()) {
+                    problems.push(GraphProblem::UnknownInput {
+                        operation: node.get_name().to_string(),
+                        input: input.clone(),
+                    });
+                }
+            }
+        }
+        Ok(problems)
+    }
+
+    /// Returns a copy of the graph without the operations named in `names`.
+    ///
+    /// Operations which consume the outputs of a removed operation are
+    /// removed too, and control dependencies on removed operations are
+    /// dropped.  Operations which are then no longer needed, because all of
+    /// their consumers were removed, are also left out, so removing e.g. an
+    /// auxiliary loss removes the subgraph which only computed that loss.
+    /// Operations which had no consumers in the original graph are kept.
+    ///
+    /// Since operations can't be removed from a graph, this builds a new
+    /// graph by importing a pruned `GraphDef`.  Returns a `NotFound` error if
+    /// any of the names are not in the graph.
+    pub fn without_ops(&self, names: &[&str]) -> Result<Graph> {
+        let mut proto: protos::graph::GraphDef = protobuf::parse_from_bytes(&self.graph_def()?)
+            .map_err(|e| invalid_arg!("Invalid serialized GraphDef: {}", e))?;
+        let node_names: HashSet<&str> = proto.get_node().iter().map(|n| n.get_name()).collect();
+        for name in names {
+            if !node_names.contains(name) {
+                return Err(Status::new_set_lossy(
+                    Code::NotFound,
+                    &format!("Operation {:?} not found", name),
+                ));
+            }
+        }
+        // Remove the named nodes and everything which consumes their outputs.
+        // GraphDefs from TF_GraphToGraphDef list nodes after their inputs,
+        // apart from loop back edges, so iterate until nothing changes.
+        let mut removed: HashSet<String> = names.iter().map(|n| n.to_string()).collect();
+        loop {
+            let num_removed = removed.len();
+            for node in proto.get_node() {
+                let consumes_removed = node.get_input().iter().any(|input| {
+                    !input.starts_with('^') && removed.contains(input_node_name(input))
+                });
+                if consumes_removed {
+                    removed.insert(node.get_name().to_string());
+                }
+            }
+            if removed.len() == num_removed {
+                break;
+            }
+        }
+        let mut has_consumers = HashSet::new();
+        for node in proto.get_node() {
+            for input in node.get_input() {
+                has_consumers.insert(input_node_name(input).to_string());
+            }
+        }
+        // Keep whatever the remaining sinks of the original graph depend on.
+        let nodes: HashMap<&str, &protos::node_def::NodeDef> = proto
+            .get_node()
+            .iter()
+            .map(|node| (node.get_name(), node))
+            .collect();
+        let mut pending: Vec<&str> = proto
+            .get_node()
+            .iter()
+            .map(|node| node.get_name())
+            .filter(|name| !has_consumers.contains(*name) && !removed.contains(*name))
+            .collect();
+        let mut kept = HashSet::new();
+        while let Some(name) = pending.pop() {
+            if removed.contains(name) || !kept.insert(name.to_string()) {
+                continue;
+            }
+            for input in nodes[name].get_input() {
+                pending.push(input_node_name(input));
+            }
+        }
+        let mut pruned = protobuf::RepeatedField::new();
+        for mut node in proto.take_node().into_iter() {
+            if !kept.contains(node.get_name()) {
+                continue;
+            }
+            let inputs = node.take_input();
+            node.set_input(
+                inputs
+                    .into_iter()
+                    .filter(|input| !removed.contains(input_node_name(input)))
+                    .collect(),
+            );
+            pruned.push(node);
+        }
+        proto.set_node(pruned);
+        let graph_def = proto
+            .write_to_bytes()
+            .map_err(|e| invalid_arg!("Unable to serialize GraphDef: {}", e))?;
+        let mut graph = Graph::new();
+        graph.import_graph_def(&graph_def, &ImportGraphDefOptions::new())?;
+        Ok(graph)
+    }
+
+    /// Returns a copy of the graph in which each `Conv2D -> BiasAdd -> Relu`
+    /// chain is replaced by a single `_FusedConv2D` operation, which is
+    /// faster for inference.
+    ///
+    /// The fused operation takes the name of the `Relu`, so consumers of the
+    /// chain are unaffected.  Chains are only fused if the `Conv2D` and
+    /// `BiasAdd` have no other consumers, all three operations are on the
+    /// same device, the type is `float` or `double` and the data format is
+    /// `NHWC`, which is what the CPU kernel supports.  Other chains are left
+    /// as they are, as is the whole graph if the TensorFlow runtime does not
+    /// have `_FusedConv2D`.
+    pub fn fuse_conv_bias_relu(&self) -> Result<Graph> {
+        let mut proto: protos::graph::GraphDef = protobuf::parse_from_bytes(&self.graph_def()?)
+            .map_err(|e| invalid_arg!("Invalid serialized GraphDef: {}", e))?;
+        let mut fused = HashMap::new();
+        if self.get_op_def("_FusedConv2D").is_ok() {
+            let nodes: HashMap<&str, &protos::node_def::NodeDef> = proto
+                .get_node()
+                .iter()
+                .map(|node| (node.get_name(), node))
+                .collect();
+            let mut num_consumers: HashMap<&str, usize> = HashMap::new();
+            for node in proto.get_node() {
+                for input in node.get_input() {
+                    *num_consumers.entry(input_node_name(input)).or_insert(0) += 1;
+                }
+            }
+            // Returns the node producing the first output of `node` if it has
+            // type `op` and no other consumers.
+            let sole_producer = |node: &protos::node_def::NodeDef, op: &str| {
+                let input = node.get_input().first()?;
+                if input.starts_with('^') || (input.contains(':') && !input.ends_with(":0")) {
+                    return None;
+                }
+                let producer = nodes.get(input_node_name(input))?;
+                if producer.get_op() == op && num_consumers[producer.get_name()] == 1 {
+                    Some(*producer)
+                } else {
+                    None
+                }
+            };
+            let data_format =
+                |node: &protos::node_def::NodeDef| match node.get_attr().get("data_format") {
+                    Some(value) => value.get_s().to_vec(),
+                    None => b"NHWC".to_vec(),
+                };
+            for relu in proto.get_node() {
+                if relu.get_op() != "Relu" {
+                    continue;
+                }
+                let bias_add = match sole_producer(relu, "BiasAdd") {
+                    Some(bias_add) => bias_add,
+                    None => continue,
+                };
+                let conv = match sole_producer(bias_add, "Conv2D") {
+                    Some(conv) => conv,
+                    None => continue,
+                };
+                let dtype = conv.get_attr().get("T").map(|value| value.get_field_type());
+                let fusible = bias_add.get_input().len() >= 2
+                    && conv.get_input().len() >= 2
+                    && (dtype == Some(protos::types::DataType::DT_FLOAT)
+                        || dtype == Some(protos::types::DataType::DT_DOUBLE))
+                    && data_format(conv) == b"NHWC"
+                    && data_format(bias_add) == b"NHWC"
+                    && conv.get_device() == relu.get_device()
+                    && bias_add.get_device() == relu.get_device();
+                if !fusible {
+                    continue;
+                }
+                let mut node = protos::node_def::NodeDef::new();
+                node.set_name(relu.get_name().to_string());
+                node.set_op("_FusedConv2D".to_string());
+                node.set_device(relu.get_device().to_string());
+                let data_inputs = vec![
+                    conv.get_input()[0].clone(),
+                    conv.get_input()[1].clone(),
+                    bias_add.get_input()[1].clone(),
+                ];
+                let mut control_inputs = Vec::new();
+                for input in [conv, bias_add, relu]
+                    .iter()
+                    .flat_map(|node| node.get_input())
+                    .filter(|input| input.starts_with('^'))
+                {
+                    if !control_inputs.contains(input) {
+                        control_inputs.push(input.clone());
+                    }
+                }
+                node.set_input(data_inputs.into_iter().chain(control_inputs).collect());
+                let mut attrs = conv.get_attr().clone();
+                let mut num_args = protos::attr_value::AttrValue::new();
+                num_args.set_i(1);
+                attrs.insert("num_args".to_string(), num_args);
+                let mut fused_ops = protos::attr_value::AttrValue_ListValue::new();
+                fused_ops.set_s(vec![b"BiasAdd".to_vec(), b"Relu".to_vec()].into());
+                let mut fused_ops_value = protos::attr_value::AttrValue::new();
+                fused_ops_value.set_list(fused_ops);
+                attrs.insert("fused_ops".to_string(), fused_ops_value);
+                let mut epsilon = protos::attr_value::AttrValue::new();
+                epsilon.set_f(0.0);
+                attrs.insert("epsilon".to_string(), epsilon);
+                node.set_attr(attrs);
+                fused.insert(conv.get_name().to_string(), None);
+                fused.insert(bias_add.get_name().to_string(), None);
+                fused.insert(relu.get_name().to_string(), Some(node));
+            }
+        }
+        let mut nodes = protobuf::RepeatedField::new();
+        for node in proto.take_node().into_iter() {
+            match fused.remove(node.get_name()) {
+                Some(Some(fused_node)) => nodes.push(fused_node),
+                Some(None) => {}
+                None => nodes.push(node),
+            }
+        }
+        proto.set_node(nodes);
+        let graph_def = proto
+            .write_to_bytes()
+            .map_err(|e| invalid_arg!("Unable to serialize GraphDef: {}", e))?;
+        let mut graph = Graph::new();
+        graph.import_graph_def(&graph_def, &ImportGraphDefOptions::new())?;
+        Ok(graph)
+    }
+
+    /// Returns a copy of the graph in which structurally identical `Const`
+    /// operations are merged into one, e.g. the many copies of the same axis
+    /// tensor in machine-generated graphs.
+    ///
+    /// Constants are identical if they have the same device, attributes
+    /// (including the value) and control inputs.  The first of each group of
+    /// identical constants is kept, and the consumers of the others, as well
+    /// as colocation constraints on them, are redirected to it.
+    pub fn dedup_constants(&self) -> Result<Graph> {
+        let mut proto: protos::graph::GraphDef = protobuf::parse_from_bytes(&self.graph_def()?)
+            .map_err(|e| invalid_arg!("Invalid serialized GraphDef: {}", e))?;
+        let mut kept: HashMap<Vec<u8>, String> = HashMap::new();
+        let mut replacements: HashMap<String, String> = HashMap::new();
+        for node in proto.get_node() {
+            if node.get_op() != "Const" {
+                continue;
+            }
+            // Serialize the parts which make constants identical in a fixed
+            // order, since the attributes are a map.
+            let mut key = protos::node_def::NodeDef::new();
+            key.set_device(node.get_device().to_string());
+            let mut control_inputs = node.get_input().to_vec();
+            control_inputs.sort();
+            key.set_input(control_inputs.into());
+            let mut key = key
+                .write_to_bytes()
+                .map_err(|e| invalid_arg!("Unable to serialize NodeDef: {}", e))?;
+            let mut attrs: Vec<_> = node.get_attr().iter().collect();
+            attrs.sort_by_key(|(name, _)| name.as_str());
+            for (name, value) in attrs {
+                let value = value
+                    .write_to_bytes()
+                    .map_err(|e| invalid_arg!("Unable to serialize AttrValue: {}", e))?;
+                for bytes in &[name.as_bytes(), &value[..]] {
+                    key.extend_from_slice(&(bytes.len() as u64).to_le_bytes());
+                    key.extend_from_slice(bytes);
+                }
+            }
+            match kept.entry(key) {
+                Entry::Occupied(entry) => {
+                    replacements.insert(node.get_name().to_string(), entry.get().clone());
+                }
+                Entry::Vacant(entry) => {
+                    entry.insert(node.get_name().to_string());
+                }
+            }
+        }
+        let mut nodes = protobuf::RepeatedField::new();
+        for mut node in proto.take_node().into_iter() {
+            if replacements.contains_key(node.get_name()) {
+                continue;
+            }
+            let mut inputs: Vec<String> = Vec::new();
+            for input in node.take_input().into_iter() {
+                let input = match replacements.get(input_node_name(&input)) {
+                    Some(replacement) if input.starts_with('^') => format!("^{}", replacement),
+                    Some(replacement) => match input.rfind(':') {
+                        Some(pos) => format!("{}{}", replacement, &input[pos..]),
+                        None => replacement.clone(),
+                    },
+                    None => input,
+                };
+                // Merged constants may leave duplicate control inputs.
+                if !input.starts_with('^') || !inputs.contains(&input) {
+                    inputs.push(input);
+                }
+            }
+            node.set_input(inputs.into());
+            if let Some(class) = node.mut_attr().get_mut("_class") {
+                for location in class.mut_list().mut_s().iter_mut() {
+                    let replacement = std::str::from_utf8(location)
+                        .ok()
+                        .and_then(|location| location.strip_prefix("loc:@"))
+                        .and_then(|name| replacements.get(name));
+                    if let Some(replacement) = replacement {
+                        *location = format!("loc:@{}", replacement).into_bytes();
+                    }
+                }
+            }
+            nodes.push(node);
+        }
+        proto.set_node(nodes);
+        let graph_def = proto
+            .write_to_bytes()
+            .map_err(|e| invalid_arg!("Unable to serialize GraphDef: {}", e))?;
+        let mut graph = Graph::new();
+        graph.import_graph_def(&graph_def, &ImportGraphDefOptions::new())?;
+        Ok(graph)
+    }
+
+    /// Returns a hash of the structure of the graph, which can be used as a
+    /// cache key for sessions built from equivalent graphs.
+    ///
+    /// The hash is computed from the op types, devices, attributes and
+    /// inputs of the nodes, not from the serialized `GraphDef`, so it does
+    /// not depend on the order in which nodes were added.  Node names are
+    /// included with any `_<number>` suffix removed, so graphs which differ
+    /// only in the suffixes added to make names unique hash the same.  The
+    /// function library is not included.  The hash is stable across runs
+    /// and platforms.
+    pub fn content_hash(&self) -> Result<u64> {
+        let proto: protos::graph::GraphDef = protobuf::parse_from_bytes(&self.graph_def()?)
+            .map_err(|e| invalid_arg!("Invalid serialized GraphDef: {}", e))?;
+        let mut node_hashes: HashMap<&str, u64> = HashMap::new();
+        for node in proto.get_node() {
+            let mut hasher = ContentHasher::new();
+            hasher.write_str(node.get_op());
+            hasher.write_str(&strip_name_suffix(node.get_name()));
+            hasher.write_str(node.get_device());
+            let mut attrs: Vec<_> = node.get_attr().iter().collect();
+            attrs.sort_by_key(|(name, _)| name.as_str());
+            for (name, value) in attrs {
+                hasher.write_str(name);
+                hasher.write_attr_value(value)?;
+            }
+            let mut control_inputs = Vec::new();
+            for input in node.get_input() {
+                if let Some(input) = input.strip_prefix('^') {
+                    control_inputs.push(input_hash(&node_hashes, input));
+                    continue;
+                }
+                let (input, index) = match input.rfind(':') {
+                    Some(pos) => (&input[..pos], &input[pos + 1..]),
+                    None => (input.as_str(), "0"),
+                };
+                hasher.write_u64(input_hash(&node_hashes, input));
+                hasher.write_str(index);
+            }
+            // The order of control inputs is not significant.
+            control_inputs.sort_unstable();
+            for control_input in control_inputs {
+                hasher.write_u64(control_input);
+            }
+            node_hashes.insert(node.get_name(), hasher.finish());
+        }
+        let mut hashes: Vec<_> = node_hashes.values().cloned().collect();
+        hashes.sort_unstable();
+        let mut hasher = ContentHasher::new();
+        for hash in hashes {
+            hasher.write_u64(hash);
+        }
+        Ok(hasher.finish())
+    }
+
     /// Iterates over the operations in the graph.
     pub fn operation_iter(&self) -> OperationIter<'_> {
         OperationIter {
@@ -399,6 +1159,73 @@ impl Graph {
         }
     }
 
+    /// Returns the outputs of the placeholders in the graph, which are likely
+    /// to be the inputs of the graph.
+    ///
+    /// This is a heuristic for graphs which don't come with a signature, such
+    /// as frozen graphs.  Placeholders with defaults are not included, since
+    /// they don't need to be fed.
+    pub fn guess_inputs(&self) -> Vec<Output> {
+        let mut inputs = Vec::new();
+        for operation in self.operation_iter() {
+            let is_placeholder = match operation.op_type() {
+                Ok(op_type) => op_type == "Placeholder" || op_type == "PlaceholderV2",
+                Err(_) => false,
+            };
+            if is_placeholder {
+                inputs.extend(operation_outputs(&operation));
+            }
+        }
+        inputs
+    }
+
+    /// Returns the name, type and shape of each input found by
+    /// `guess_inputs`, in the order of the operations in the graph.
+    ///
+    /// Dimensions which are `None` in the shape, such as the batch dimension
+    /// of most models, accept any size.  A server can advertise these shapes
+    /// and check incoming tensors against them with
+    /// `Shape::is_compatible_with`.
+    pub fn input_signature(&self) -> Result<Vec<(String, DataType, Shape)>> {
+        self.guess_inputs()
+            .into_iter()
+            .map(|input| {
+                let name = input.operation.name()?;
+                let dtype = input.operation.output_type(input.index as usize);
+                let shape = self.tensor_shape(input)?;
+                Ok((name, dtype, shape))
+            })
+            .collect()
+    }
+
+    /// Returns the outputs of the operations whose results aren't used by any
+    /// other operation, which are likely to be the outputs of the graph.
+    ///
+    /// This is a heuristic for graphs which don't come with a signature, such
+    /// as frozen graphs.  An operation is considered an output if it has at
+    /// least one output, none of its outputs are consumed, it has no control
+    /// outputs, and it isn't a placeholder.
+    pub fn guess_outputs(&self) -> Vec<Output> {
+        let mut outputs = Vec::new();
+        for operation in self.operation_iter() {
+            let num_outputs = operation.num_outputs();
+            if num_outputs == 0 || operation.num_control_outputs() > 0 {
+                continue;
+            }
+            if (0..num_outputs).any(|i| !operation.output_consumers(i).is_empty()) {
+                continue;
+            }
+            let is_placeholder = match operation.op_type() {
+                Ok(op_type) => op_type.starts_with("Placeholder"),
+                Err(_) => false,
+            };
+            if !is_placeholder {
+                outputs.extend(operation_outputs(&operation));
+            }
+        }
+        outputs
+    }
+
     /// Returns the graph definition as a protobuf.
     pub fn graph_def(&self) -> Result<Vec<u8>> {
         let mut status = Status::new();
@@ -414,6 +1241,101 @@ impl Graph {
         }
     }
 
+    /// Returns the graph definition as a protobuf, with an `_output_shapes`
+    /// attribute on each node listing the shapes inferred for its outputs.
+    ///
+    /// Shapes are inferred as operations are added, e.g. by the op
+    /// constructors in the `ops` module, so this is where the hints are
+    /// attached.  Some tools rely on them when importing a graph, like
+    /// Python's `as_graph_def(add_shapes=True)`.  Existing `_output_shapes`
+    /// attributes are replaced.
+    pub fn graph_def_with_output_shapes(&self) -> Result<Vec<u8>> {
+        let mut proto: protos::graph::GraphDef = protobuf::parse_from_bytes(&self.graph_def()?)
+            .map_err(|e| invalid_arg!("Invalid serialized GraphDef: {}", e))?;
+        for node in proto.mut_node().iter_mut() {
+            let operation = self.operation_by_name_required(node.get_name())?;
+            let mut shapes = protos::attr_value::AttrValue_ListValue::new();
+            for output in operation_outputs(&operation) {
+                shapes
+                    .mut_shape()
+                    .push(self.tensor_shape(output)?.into_proto());
+            }
+            let mut value = protos::attr_value::AttrValue::new();
+            value.set_list(shapes);
+            node.mut_attr().insert("_output_shapes".to_string(), value);
+        }
+        proto
+            .write_to_bytes()
+            .map_err(|e| invalid_arg!("Unable to serialize GraphDef: {}", e))
+    }
+
+    /// Returns the graph definition as a protobuf, with `outputs` marked as
+    /// the outputs of the graph, so the IO contract survives exporting to a
+    /// runtime which doesn't read signatures.  Use `marked_outputs` to read
+    /// the marks back after importing the graph.
+    ///
+    /// Each node with marked outputs gets an `_output_indices` attribute with
+    /// the indices of its marked outputs, and an `_output_positions`
+    /// attribute with their positions in `outputs`.
+    pub fn export_with_outputs(&self, outputs: &[Output]) -> Result<Vec<u8>> {
+        let mut marks: HashMap<String, (Vec<i64>, Vec<i64>)> = HashMap::new();
+        for (position, output) in outputs.iter().enumerate() {
+            let name = output.operation.name()?;
+            if self.operation_by_name(&name)?.is_none() {
+                return Err(Status::new_set_lossy(
+                    Code::NotFound,
+                    &format!("Operation {:?} not found", name),
+                ));
+            }
+            let (indices, positions) = marks.entry(name).or_default();
+            indices.push(output.index as i64);
+            positions.push(position as i64);
+        }
+        let mut proto: protos::graph::GraphDef = protobuf::parse_from_bytes(&self.graph_def()?)
+            .map_err(|e| invalid_arg!("Invalid serialized GraphDef: {}", e))?;
+        for node in proto.mut_node().iter_mut() {
+            if let Some((indices, positions)) = marks.remove(node.get_name()) {
+                for (attr_name, values) in &[
+                    ("_output_indices", indices),
+                    ("_output_positions", positions),
+                ] {
+                    let mut list = protos::attr_value::AttrValue_ListValue::new();
+                    list.set_i(values.clone());
+                    let mut value = protos::attr_value::AttrValue::new();
+                    value.set_list(list);
+                    node.mut_attr().insert(attr_name.to_string(), value);
+                }
+            }
+        }
+        proto
+            .write_to_bytes()
+            .map_err(|e| invalid_arg!("Unable to serialize GraphDef: {}", e))
+    }
+
+    /// Returns the outputs marked by `export_with_outputs`, in the order in
+    /// which they were given.
+    pub fn marked_outputs(&self) -> Result<Vec<Output>> {
+        let mut outputs = Vec::new();
+        for operation in self.operation_iter() {
+            let indices = match operation.get_attr_int_list("_output_indices") {
+                Ok(indices) => indices,
+                Err(_) => continue,
+            };
+            let positions = operation.get_attr_int_list("_output_positions")?;
+            for (index, position) in indices.into_iter().zip(positions) {
+                outputs.push((
+                    position,
+                    Output {
+                        operation: operation.clone(),
+                        index: index as c_int,
+                    },
+                ));
+            }
+        }
+        outputs.sort_by_key(|(position, _)| *position);
+        Ok(outputs.into_iter().map(|(_, output)| output).collect())
+    }
+
     /// Returns the number of dimensions of the Tensor referenced by `output`.
     ///
     /// If the number of dimensions in the shape is unknown, returns -1.
@@ -477,6 +1399,7 @@ impl Graph {
         graph_def: &[u8],
         options: &ImportGraphDefOptions,
     ) -> Result<()> {
+        options.validate(self, graph_def)?;
         let buf = Buffer::from(graph_def);
         let mut status = Status::new();
         unsafe {
@@ -496,6 +1419,7 @@ impl Graph {
         graph_def: &[u8],
         options: &ImportGraphDefOptions,
     ) -> Result<ImportGraphDefResults> {
+        options.validate(self, graph_def)?;
         let buf = Buffer::from(graph_def);
         let mut status = Status::new();
         unsafe {
@@ -518,6 +1442,7 @@ impl Graph {
         graph_def: &[u8],
         options: &ImportGraphDefOptions,
     ) -> Result<Vec<Output>> {
+        options.validate(self, graph_def)?;
         let buf = Buffer::from(graph_def);
         let mut status = Status::new();
         let n = options.num_return_outputs();
@@ -540,6 +1465,36 @@ impl Graph {
             .collect())
     }
 
+    /// Copies all operations of `other` into this graph.  Each connection
+    /// `(src, dst)` replaces every use of the output `dst` of `other` with the
+    /// output `src` of this graph, which connects the two graphs.  Imported
+    /// operations are renamed if their names are already taken.
+    ///
+    /// Returns the outputs of the copied operations, keyed by their names in
+    /// `other` in the form `name:index`.  Remapped outputs map to the
+    /// corresponding output of this graph.
+    pub fn merge_from(
+        &mut self,
+        other: &Graph,
+        connections: &[(Output, Output)],
+    ) -> Result<HashMap<String, Output>> {
+        let mut options = ImportGraphDefOptions::new();
+        options.set_uniquify_names(true);
+        for (src, dst) in connections {
+            options.add_input_mapping(&dst.operation.name()?, dst.index as usize, src)?;
+        }
+        let mut keys = Vec::new();
+        for operation in other.operation_iter() {
+            let name = operation.name()?;
+            for index in 0..operation.num_outputs() {
+                options.add_return_output(&name, index)?;
+                keys.push(format!("{}:{}", name, index));
+            }
+        }
+        let outputs = self.import_graph_def_with_return_outputs(&other.graph_def()?, &options)?;
+        Ok(keys.into_iter().zip(outputs).collect())
+    }
+
     /// Adds a copy of function `func` and optionally its gradient function
     /// `grad` to the graph. Once `func`/`grad` is added to the graph, it can be
     /// called by creating an operation using the function's name. Any changes
@@ -711,7 +1666,16 @@ impl Graph {
             )
         };
         status.into_result()?;
-        Ok(Function { inner: f })
+        let mut function = Function { inner: f };
+        if opts.noinline {
+            let mut attr = protos::attr_value::AttrValue::new();
+            attr.set_b(true);
+            let attr = attr
+                .write_to_bytes()
+                .map_err(|e| invalid_arg!("Unable to serialize AttrValue: {}", e))?;
+            function.set_attr_value_proto("_noinline", &attr)?;
+        }
+        Ok(function)
     }
 
     /// Returns the number of functions registered in the graph.
@@ -732,6 +1696,45 @@ impl Graph {
         }
     }
 
+    /// Adds the functions in a serialized [`FunctionDefLibrary`
+    /// proto](https://github.com/tensorflow/tensorflow/blob/master/tensorflow/core/framework/function.proto)
+    /// to the graph, registering the gradients listed in the library.
+    ///
+    /// `import_graph_def` already imports the library of the `GraphDef`, so
+    /// this is only needed for libraries which are stored separately.
+    /// Functions which are already in the graph with the same definition are
+    /// left as they are.
+    pub fn add_function_library(&mut self, library: &[u8]) -> Result<()> {
+        let library: protos::function::FunctionDefLibrary = protobuf::parse_from_bytes(library)
+            .map_err(|e| invalid_arg!("Invalid serialized FunctionDefLibrary: {}", e))?;
+        let mut functions = HashMap::new();
+        for function_def in library.get_function() {
+            let bytes = function_def
+                .write_to_bytes()
+                .map_err(|e| invalid_arg!("Unable to serialize FunctionDef: {}", e))?;
+            functions.insert(
+                function_def.get_signature().get_name(),
+                Function::import_function_def(&bytes)?,
+            );
+        }
+        let mut gradients = HashMap::new();
+        for gradient in library.get_gradient() {
+            let grad = functions.get(gradient.get_gradient_func()).ok_or_else(|| {
+                invalid_arg!(
+                    "Gradient function {} of {} is not in the library",
+                    gradient.get_gradient_func(),
+                    gradient.get_function_name()
+                )
+            })?;
+            gradients.insert(gradient.get_function_name(), grad);
+        }
+        for function_def in library.get_function() {
+            let name = function_def.get_signature().get_name();
+            self.copy_function(&functions[name], gradients.get(name).cloned())?;
+        }
+        Ok(())
+    }
+
     /// Returns the serialized OpDef proto with name `op_name`, or a bad status if no
     /// such op exists. This can return OpDefs of functions copied into the graph.
     pub fn get_op_def(&self, op_name: &str) -> Result<Vec<u8>> {
@@ -807,6 +1810,11 @@ impl Graph {
     /// uniqueness, subsequent calls to the same graph will append an
     /// incremental tag to the prefix: "gradients_1/", "gradients_2/", ...
     ///
+    /// The `y`s may themselves be gradient outputs, which allows computing
+    /// higher-order gradients.  In that case, if `prefix` is None, the new
+    /// gradient nodes are nested under the scope of the `y`s, e.g.
+    /// "gradients/gradients/" for second-order gradients.
+    ///
     /// WARNING: This function does not yet support all the gradients that
     /// python supports. See
     /// https://www.tensorflow.org/code/tensorflow/cc/gradients/README.md
@@ -834,7 +1842,11 @@ impl Graph {
             Some(v) => v.as_ptr(),
             None => ptr::null(),
         };
-        let prefix_cstr = match prefix {
+        let nested_prefix = match prefix {
+            Some(_) => None,
+            None => self.nested_gradients_prefix(y)?,
+        };
+        let prefix_cstr = match prefix.or(nested_prefix.as_deref()) {
             Some(s) => Some(CString::new(s)?),
             None => None,
         };
@@ -869,19 +1881,573 @@ impl Graph {
         }
     }
 
-    pub(crate) fn inner(&self) -> *mut tf::TF_Graph {
-        self.gimpl.inner
-    }
+    /// Like `add_gradients`, but with the `aggregation_method` and
+    /// `gate_gradients` options of `tf.gradients` in Python.
+    ///
+    /// `method` chooses how the gradients which flow to a tensor from several
+    /// consumers are summed.  If `gate_gradients` is true, none of the
+    /// returned gradients can be used before all of them are computed, like
+    /// `GATE_GRAPH` in Python, which makes the results deterministic when the
+    /// gradients are used to update variables which other gradients read.
+    /// Gating the gradients of each operation separately (`GATE_OP`) is not
+    /// supported, since the C API builds the gradient operations.
+    ///
+    /// The gradients are built in a copy of the graph and only the final
+    /// operations are imported, so the `AddN` operations which are replaced
+    /// are never added to this graph, and the graph is unchanged if an error
+    /// is returned.
+    pub fn add_gradients_with_aggregation(
+        &mut self,
+        prefix: Option<&str>,
+        y: &[Output],
+        x: &[Output],
+        dx: Option<&[Output]>,
+        method: AggregationMethod,
+        gate_gradients: bool,
+    ) -> Result<Vec<Option<Output>>> {
+        if method == AggregationMethod::AddN && !gate_gradients {
+            return self.add_gradients(prefix, y, x, dx);
+        }
+        let mut scratch = Graph::new();
+        scratch.import_graph_def(&self.graph_def()?, &ImportGraphDefOptions::new())?;
+        let scratch_y = outputs_by_name(&scratch, y)?;
+        let scratch_x = outputs_by_name(&scratch, x)?;
+        let scratch_dx = match dx {
+            Some(dx) => Some(outputs_by_name(&scratch, dx)?),
+            None => None,
+        };
+        let mut dy = Vec::with_capacity(x.len());
+        for gradient in
+            scratch.add_gradients(prefix, &scratch_y, &scratch_x, scratch_dx.as_deref())?
+        {
+            dy.push(match gradient {
+                Some(gradient) => Some((gradient.operation.name()?, gradient.index)),
+                None => None,
+            });
+        }
 
-    pub(crate) unsafe fn from_c(inner: *mut tf::TF_Graph) -> Self {
-        Graph {
-            gimpl: Arc::new(GraphImpl {
-                inner,
-                owned: false,
-            }),
-            lifetime: GraphLifetime,
+        let mut existing = HashSet::new();
+        for operation in self.operation_iter() {
+            existing.insert(operation.name()?);
         }
-    }
+        let mut proto: protos::graph::GraphDef = protobuf::parse_from_bytes(&scratch.graph_def()?)
+            .map_err(|e| invalid_arg!("Invalid serialized GraphDef: {}", e))?;
+        let mut nodes = Vec::new();
+        for node in proto.take_node().into_iter() {
+            if existing.contains(node.get_name()) {
+                continue;
+            }
+            if node.get_op() == "AddN" {
+                let shape = scratch.tensor_shape(Output {
+                    operation: scratch.operation_by_name_required(node.get_name())?,
+                    index: 0,
+                })?;
+                nodes.extend(aggregate_gradients(node, method, &shape));
+            } else {
+                nodes.push(node);
+            }
+        }
+
+        if gate_gradients {
+            let mut gradients: Vec<(String, c_int)> = Vec::new();
+            for gradient in dy.iter().flatten() {
+                if !gradients.contains(gradient) {
+                    gradients.push(gradient.clone());
+                }
+            }
+            if !gradients.is_empty() {
+                let mut taken: HashSet<String> = existing.clone();
+                taken.extend(nodes.iter().map(|node| node.get_name().to_string()));
+                let scope = gradients_scope(&nodes);
+                let mut gate = protos::node_def::NodeDef::new();
+                gate.set_name(unique_name(
+                    &mut taken,
+                    &format!("{}/tuple/group_deps", scope),
+                ));
+                gate.set_op("NoOp".to_string());
+                let mut control_inputs = Vec::new();
+                for (name, _) in &gradients {
+                    let control_input = format!("^{}", name);
+                    if !control_inputs.contains(&control_input) {
+                        control_inputs.push(control_input);
+                    }
+                }
+                gate.set_input(control_inputs.into());
+                let mut gated = HashMap::new();
+                for (name, index) in gradients {
+                    let mut dtype = protos::attr_value::AttrValue::new();
+                    dtype.set_field_type(
+                        scratch
+                            .operation_by_name_required(&name)?
+                            .output_type(index as usize)
+                            .into_proto(),
+                    );
+                    let mut identity = protos::node_def::NodeDef::new();
+                    identity.set_name(unique_name(
+                        &mut taken,
+                        &format!("{}/tuple/control_dependency", scope),
+                    ));
+                    identity.set_op("Identity".to_string());
+                    identity.set_input(
+                        vec![
+                            format!("{}:{}", name, index),
+                            format!("^{}", gate.get_name()),
+                        ]
+                        .into(),
+                    );
+                    identity.mut_attr().insert("T".to_string(), dtype);
+                    gated.insert((name, index), (identity.get_name().to_string(), 0));
+                    nodes.push(identity);
+                }
+                nodes.push(gate);
+                for gradient in dy.iter_mut().flatten() {
+                    *gradient = gated[&*gradient].clone();
+                }
+            }
+        }
+
+        // The imported operations refer to the existing operations by name,
+        // which must be mapped to the operations of this graph.
+        let mut options = ImportGraphDefOptions::new();
+        let mut mapped = HashSet::new();
+        for node in &nodes {
+            for input in node.get_input() {
+                let name = input_node_name(input);
+                if !existing.contains(name) || !mapped.insert(input.clone()) {
+                    continue;
+                }
+                let operation = self.operation_by_name_required(name)?;
+                if input.starts_with('^') {
+                    options.remap_control_dependency(name, &operation)?;
+                } else {
+                    let index = match input.rfind(':') {
+                        Some(pos) => input[pos + 1..]
+                            .parse()
+                            .map_err(|_| invalid_arg!("Invalid input {:?}", input))?,
+                        None => 0,
+                    };
+                    options.add_input_mapping(
+                        name,
+                        index,
+                        &Output {
+                            operation,
+                            index: index as c_int,
+                        },
+                    )?;
+                }
+            }
+        }
+        proto.set_node(nodes.into());
+        let graph_def = proto
+            .write_to_bytes()
+            .map_err(|e| invalid_arg!("Unable to serialize GraphDef: {}", e))?;
+        self.import_graph_def(&graph_def, &options)?;
+        let mut gradients = Vec::with_capacity(dy.len());
+        for gradient in dy {
+            gradients.push(match gradient {
+                Some((name, index)) => Some(Output {
+                    operation: self.operation_by_name_required(&name)?,
+                    index,
+                }),
+                None => None,
+            });
+        }
+        Ok(gradients)
+    }
+
+    /// Replaces input `dst_index` of `dst` with `new_src`.
+    ///
+    /// This allows fixing up connections without rebuilding the graph.  The
+    /// type of `new_src` must be compatible with the input, and the shape of
+    /// `new_src` must be compatible with the shapes inferred for `dst`.  The
+    /// edge must be updated before any session is created for the graph,
+    /// since sessions do not see changes to existing operations.
+    pub fn update_edge(
+        &mut self,
+        new_src: Output,
+        dst: &Operation,
+        dst_index: usize,
+    ) -> Result<()> {
+        let num_inputs = dst.num_inputs();
+        if dst_index >= num_inputs {
+            return Err(invalid_arg!(
+                "Input index {} out of range for operation with {} inputs",
+                dst_index,
+                num_inputs
+            ));
+        }
+        let mut status = Status::new();
+        unsafe {
+            TF_UpdateEdge(
+                self.inner(),
+                new_src.to_c(),
+                tf::TF_Input {
+                    oper: dst.inner,
+                    index: dst_index as c_int,
+                },
+                status.inner(),
+            );
+        }
+        status.into_result()
+    }
+
+    /// Returns the prefix for gradients of `y` if all of `y` were produced by
+    /// gradient operations in the same scope, e.g. "gradients/gradients" for
+    /// outputs under "gradients/", made unique within the graph.
+    fn nested_gradients_prefix(&self, y: &[Output]) -> Result<Option<String>> {
+        let mut scope: Option<String> = None;
+        for output in y {
+            let name = output.operation.name()?;
+            let op_scope: Vec<&str> = name
+                .split('/')
+                .take_while(|part| part.starts_with("gradients"))
+                .collect();
+            // The op name itself is not part of its scope.
+            if op_scope.is_empty() || op_scope.len() == name.split('/').count() {
+                return Ok(None);
+            }
+            let op_scope = op_scope.join("/");
+            match &scope {
+                Some(scope) if *scope != op_scope => return Ok(None),
+                _ => scope = Some(op_scope),
+            }
+        }
+        let base = match scope {
+            Some(scope) => format!("{}/gradients", scope),
+            None => return Ok(None),
+        };
+        let mut names = Vec::new();
+        for op in self.operation_iter() {
+            names.push(op.name()?);
+        }
+        let is_used = |prefix: &str| {
+            let scope = format!("{}/", prefix);
+            names.iter().any(|name| name.starts_with(&scope))
+        };
+        let mut prefix = base.clone();
+        let mut i = 0;
+        while is_used(&prefix) {
+            i += 1;
+            prefix = format!("{}_{}", base, i);
+        }
+        Ok(Some(prefix))
+    }
+
+    pub(crate) fn inner(&self) -> *mut tf::TF_Graph {
+        self.gimpl.inner
+    }
+
+    pub(crate) unsafe fn from_c(inner: *mut tf::TF_Graph) -> Self {
+        Graph {
+            gimpl: Arc::new(GraphImpl {
+                inner,
+                owned: false,
+            }),
+            lifetime: GraphLifetime,
+        }
+    }
+}
+
+/// Returns all outputs of `operation`.
+fn operation_outputs(operation: &Operation) -> impl Iterator<Item = Output> + '_ {
+    (0..operation.num_outputs()).map(move |index| Output {
+        operation: operation.clone(),
+        index: index as c_int,
+    })
+}
+
+/// Returns the dimensions of `shape`, or `None` if any of them are unknown.
+fn known_dims(shape: &Shape) -> Option<Vec<u64>> {
+    shape
+        .0
+        .as_ref()?
+        .iter()
+        .map(|dim| match dim {
+            Some(dim) if *dim >= 0 => Some(*dim as u64),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Returns the outputs of `graph` with the same operation names and indices
+/// as `outputs`, which may belong to another graph.
+fn outputs_by_name(graph: &Graph, outputs: &[Output]) -> Result<Vec<Output>> {
+    let mut result = Vec::with_capacity(outputs.len());
+    for output in outputs {
+        result.push(Output {
+            operation: graph.operation_by_name_required(&output.operation.name()?)?,
+            index: output.index,
+        });
+    }
+    Ok(result)
+}
+
+/// Returns the nodes which replace the `AddN` node `add_n` built by
+/// `Graph::add_gradients` to sum gradients with `method`, following Python's
+/// `_AggregatedGrads`.  `shape` is the inferred shape of the sum.  The last
+/// node keeps the name of `add_n`, so its consumers are unchanged.
+fn aggregate_gradients(
+    mut add_n: protos::node_def::NodeDef,
+    method: AggregationMethod,
+    shape: &Shape,
+) -> Vec<protos::node_def::NodeDef> {
+    let (inputs, control_inputs): (Vec<String>, Vec<String>) = add_n
+        .take_input()
+        .into_iter()
+        .partition(|input| !input.starts_with('^'));
+    let mut nodes = Vec::new();
+    let inputs = match method {
+        AggregationMethod::ExperimentalAccumulateN
+            if inputs.len() > 2 && known_dims(shape).is_some() =>
+        {
+            add_n.set_op("AccumulateNV2".to_string());
+            let mut shape_value = protos::attr_value::AttrValue::new();
+            shape_value.set_shape(shape.clone().into_proto());
+            add_n.mut_attr().insert("shape".to_string(), shape_value);
+            inputs
+        }
+        AggregationMethod::ExperimentalTree | AggregationMethod::ExperimentalAccumulateN
+            if inputs.len() > 2 =>
+        {
+            let mut num_inputs = protos::attr_value::AttrValue::new();
+            num_inputs.set_i(2);
+            add_n.mut_attr().insert("N".to_string(), num_inputs);
+            let mut sum = inputs[0].clone();
+            for (i, input) in inputs[1..inputs.len() - 1].iter().enumerate() {
+                let mut node = add_n.clone();
+                node.set_name(format!("{}/add_{}", add_n.get_name(), i + 1));
+                node.set_input(vec![sum, input.clone()].into());
+                sum = node.get_name().to_string();
+                nodes.push(node);
+            }
+            vec![sum, inputs[inputs.len() - 1].clone()]
+        }
+        _ => inputs,
+    };
+    add_n.set_input(inputs.into_iter().chain(control_inputs).collect());
+    nodes.push(add_n);
+    nodes
+}
+
+/// Returns the name scope shared by all of `nodes`, or "gradients" if they
+/// don't share one.
+fn gradients_scope(nodes: &[protos::node_def::NodeDef]) -> String {
+    let mut scope: Option<Vec<&str>> = None;
+    for node in nodes {
+        let parts: Vec<&str> = node.get_name().split('/').collect();
+        let parts = &parts[..parts.len() - 1];
+        scope = Some(match scope {
+            None => parts.to_vec(),
+            Some(scope) => scope
+                .iter()
+                .zip(parts)
+                .take_while(|(a, b)| a == b)
+                .map(|(a, _)| *a)
+                .collect(),
+        });
+    }
+    match scope {
+        Some(scope) if !scope.is_empty() => scope.join("/"),
+        _ => "gradients".to_string(),
+    }
+}
+
+/// Returns `base`, or `base` with the first `_<number>` suffix which is not in
+/// `taken`, and adds it to `taken`.
+fn unique_name(taken: &mut HashSet<String>, base: &str) -> String {
+    let mut name = base.to_string();
+    let mut i = 0;
+    while taken.contains(&name) {
+        i += 1;
+        name = format!("{}_{}", base, i);
+    }
+    taken.insert(name.clone());
+    name
+}
+
+/// Returns the product of `values`, saturating at `u64::MAX` instead of
+/// overflowing.
+fn saturating_product(values: &[u64]) -> u64 {
+    values
+        .iter()
+        .fold(1, |product: u64, value| product.saturating_mul(*value))
+}
+
+/// Returns the name of the node referred to by an input in a `NodeDef`, which
+/// has the form `name`, `name:index` or `^name` for control inputs.
+fn input_node_name(input: &str) -> &str {
+    let input = input.strip_prefix('^').unwrap_or(input);
+    match input.rfind(':') {
+        Some(pos) => &input[..pos],
+        None => input,
+    }
+}
+
+/// Removes a `_<number>` suffix, such as the ones added by
+/// `Scope::get_unique_name_for_op`, from each component of a node name.
+fn strip_name_suffix(name: &str) -> String {
+    name.split('/')
+        .map(|component| match component.rfind('_') {
+            Some(pos)
+                if pos > 0
+                    && pos + 1 < component.len()
+                    && component[pos + 1..].bytes().all(|b| b.is_ascii_digit()) =>
+            {
+                &component[..pos]
+            }
+            _ => component,
+        })
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+/// Returns the content hash of the node named `name`.  Nodes which have not
+/// been hashed yet (i.e. the targets of back edges in loops) are hashed by
+/// name only, which keeps the hash well defined for cyclic graphs.
+fn input_hash(node_hashes: &HashMap<&str, u64>, name: &str) -> u64 {
+    match node_hashes.get(name) {
+        Some(hash) => *hash,
+        None => {
+            let mut hasher = ContentHasher::new();
+            hasher.write_str(&strip_name_suffix(name));
+            hasher.finish()
+        }
+    }
+}
+
+/// 64-bit FNV-1a hasher used by `Graph::content_hash`.  Unlike the hashers
+/// in the standard library, its output is guaranteed not to change between
+/// Rust versions or platforms.
+#[derive(Debug, Clone, Copy)]
+struct ContentHasher(u64);
+
+impl ContentHasher {
+    fn new() -> Self {
+        ContentHasher(0xcbf2_9ce4_8422_2325)
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        for b in bytes {
+            self.0 ^= u64::from(*b);
+            self.0 = self.0.wrapping_mul(0x0100_0000_01b3);
+        }
+    }
+
+    fn write_u64(&mut self, value: u64) {
+        self.write(&value.to_le_bytes());
+    }
+
+    fn write_str(&mut self, value: &str) {
+        // Length-prefix strings so that adjacent strings can't run together.
+        self.write_u64(value.len() as u64);
+        self.write(value.as_bytes());
+    }
+
+    fn write_attr_value(&mut self, value: &protos::attr_value::AttrValue) -> Result<()> {
+        // NameAttrList contains a map, which doesn't serialize
+        // deterministically, so functions are hashed field by field.
+        if value.has_func() {
+            return self.write_name_attr_list(value.get_func());
+        }
+        if value.has_list() && !value.get_list().get_func().is_empty() {
+            for func in value.get_list().get_func() {
+                self.write_name_attr_list(func)?;
+            }
+            let mut value = value.clone();
+            value.mut_list().clear_func();
+            return self.write_attr_value(&value);
+        }
+        let bytes = value
+            .write_to_bytes()
+            .map_err(|e| invalid_arg!("Unable to serialize AttrValue: {}", e))?;
+        self.write(&bytes);
+        Ok(())
+    }
+
+    fn write_name_attr_list(&mut self, value: &protos::attr_value::NameAttrList) -> Result<()> {
+        self.write_str(value.get_name());
+        let mut attrs: Vec<_> = value.get_attr().iter().collect();
+        attrs.sort_by_key(|(name, _)| name.as_str());
+        for (name, value) in attrs {
+            self.write_str(name);
+            self.write_attr_value(value)?;
+        }
+        Ok(())
+    }
+
+    fn finish(&self) -> u64 {
+        self.0
+    }
+}
+
+////////////////////////
+
+/// A rough estimate of the cost of running a graph, returned by
+/// `Graph::estimate_flops`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FlopEstimate {
+    /// Estimated number of floating point operations.
+    pub flops: u64,
+    /// Names of the operations whose cost could not be estimated, because
+    /// their type is not known or their shapes are not fully known.
+    pub unestimated: Vec<String>,
+}
+
+////////////////////////
+
+/// How `Graph::add_gradients_with_aggregation` sums the gradients which flow
+/// to a tensor from several consumers, like Python's `tf.AggregationMethod`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AggregationMethod {
+    /// `ADD_N`: a single `AddN` operation, as used by `Graph::add_gradients`.
+    /// All of the gradients must be computed before they are summed.
+    AddN,
+    /// `EXPERIMENTAL_TREE`: a chain of `AddN` operations with two inputs
+    /// each.  Each partial sum can be computed as soon as its gradients are
+    /// available, which can reduce peak memory, at the cost of more
+    /// operations.
+    ExperimentalTree,
+    /// `EXPERIMENTAL_ACCUMULATE_N`: an `AccumulateNV2` operation, which adds
+    /// each gradient to an accumulator as soon as it is available.  Like in
+    /// Python, this is only used for more than two gradients of a fully
+    /// known shape, and `ExperimentalTree` is used otherwise.
+    ExperimentalAccumulateN,
+}
+
+////////////////////////
+
+/// A problem found by `Graph::validate`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum GraphProblem {
+    /// The operations which are on a cycle, or depend on an operation on a
+    /// cycle, in graph order.  Back edges of while loops are not cycles.
+    Cycle {
+        /// The names of the operations.
+        operations: Vec<String>,
+    },
+    /// An input of an operation refers to an operation or output which
+    /// doesn't exist.
+    UnknownInput {
+        /// The name of the operation with the input.
+        operation: String,
+        /// The input, in `GraphDef` syntax, e.g. `name:1`.
+        input: String,
+    },
+}
+
+impl Display for GraphProblem {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            GraphProblem::Cycle { operations } => write!(
+                f,
+                "operations on or depending on a cycle: {}",
+                operations.join(", ")
+            ),
+            GraphProblem::UnknownInput { operation, input } => {
+                write!(f, "operation {} has unknown input {}", operation, input)
+            }
+        }
+    }
 }
 
 ////////////////////////
@@ -1154,8 +2720,7 @@ impl Operation {
         unsafe {
             let num_consumers = tf::TF_OperationNumControlInputs(self.inner);
             let mut vec = <Vec<*mut tf::TF_Operation>>::with_capacity(num_consumers as usize);
-            let len =
-                tf::TF_OperationGetControlInputs(self.inner, vec.as_mut_ptr(), vec.len() as c_int);
+            let len = tf::TF_OperationGetControlInputs(self.inner, vec.as_mut_ptr(), num_consumers);
             vec.set_len(len as usize);
             vec.into_iter()
                 .map(|operation| Operation {
@@ -1177,7 +2742,7 @@ impl Operation {
             let num_consumers = tf::TF_OperationNumControlOutputs(self.inner);
             let mut vec = <Vec<*mut tf::TF_Operation>>::with_capacity(num_consumers as usize);
             let len =
-                tf::TF_OperationGetControlOutputs(self.inner, vec.as_mut_ptr(), vec.len() as c_int);
+                tf::TF_OperationGetControlOutputs(self.inner, vec.as_mut_ptr(), num_consumers);
             vec.set_len(len as usize);
             vec.into_iter()
                 .map(|operation| Operation {
@@ -1783,6 +3348,78 @@ impl Display for OutputName {
 
 ////////////////////////
 
+/// The value of an operation attribute, for setting attributes generically
+/// with `OperationDescription::set_attr` or `Scope::with_attr`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum AttrValue {
+    /// A string attribute.
+    String(String),
+    /// A list of strings.
+    StringList(Vec<String>),
+    /// An integer attribute.
+    Int(i64),
+    /// A list of integers.
+    IntList(Vec<i64>),
+    /// A float attribute.
+    Float(f32),
+    /// A list of floats.
+    FloatList(Vec<f32>),
+    /// A boolean attribute.
+    Bool(bool),
+    /// A list of booleans.
+    BoolList(Vec<bool>),
+    /// A data type attribute.
+    Type(DataType),
+    /// A list of data types.
+    TypeList(Vec<DataType>),
+    /// A shape attribute.
+    Shape(Shape),
+    /// A list of shapes.
+    ShapeList(Vec<Shape>),
+}
+
+impl From<&str> for AttrValue {
+    fn from(value: &str) -> Self {
+        AttrValue::String(value.to_string())
+    }
+}
+
+impl From<String> for AttrValue {
+    fn from(value: String) -> Self {
+        AttrValue::String(value)
+    }
+}
+
+impl From<i64> for AttrValue {
+    fn from(value: i64) -> Self {
+        AttrValue::Int(value)
+    }
+}
+
+impl From<f32> for AttrValue {
+    fn from(value: f32) -> Self {
+        AttrValue::Float(value)
+    }
+}
+
+impl From<bool> for AttrValue {
+    fn from(value: bool) -> Self {
+        AttrValue::Bool(value)
+    }
+}
+
+impl From<DataType> for AttrValue {
+    fn from(value: DataType) -> Self {
+        AttrValue::Type(value)
+    }
+}
+
+impl From<Shape> for AttrValue {
+    fn from(value: Shape) -> Self {
+        AttrValue::Shape(value)
+    }
+}
+
 /// An `OperationDescription` is an `Operation` in the process of being built
 /// (i.e. the builder pattern).
 ///
@@ -1796,6 +3433,7 @@ pub struct OperationDescription<'a> {
     // the docs on TF_NewOperation.
     graph: &'a Graph,
     finished: bool,
+    attr_names: HashSet<String>,
 }
 
 impl<'a> Drop for OperationDescription<'a> {
@@ -1858,13 +3496,56 @@ impl<'a> OperationDescription<'a> {
         }
     }
 
-    /// Adds a control input.
+    /// Adds a control input, so that the operation will not run until `input`
+    /// has finished running.  Unlike regular inputs, control inputs do not
+    /// carry any data, and may be added in any order.
     pub fn add_control_input(&mut self, input: &Operation) {
         unsafe {
             tf::TF_AddControlInput(self.inner, input.inner);
         }
     }
 
+    /// Sets the value of an attribute of any type.
+    ///
+    /// This is useful for attributes which don't depend on the op, such as
+    /// internal attributes like `_class`:
+    ///
+    /// ```
+    /// # use tensorflow::AttrValue;
+    /// # use tensorflow::DataType;
+    /// # use tensorflow::Graph;
+    /// let mut graph = Graph::new();
+    /// let mut nd = graph.new_operation("Placeholder", "x")?;
+    /// nd.set_attr("dtype", &AttrValue::Type(DataType::Float))?;
+    /// nd.set_attr("_my_attr", &AttrValue::IntList(vec![1, 2]))?;
+    /// let x = nd.finish()?;
+    /// assert_eq!(x.get_attr_int_list("_my_attr")?, &[1, 2]);
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    pub fn set_attr(&mut self, attr_name: &str, value: &AttrValue) -> Result<()> {
+        match value {
+            AttrValue::String(v) => self.set_attr_string(attr_name, v)?,
+            AttrValue::StringList(v) => self.set_attr_string_list(attr_name, v)?,
+            AttrValue::Int(v) => self.set_attr_int(attr_name, *v)?,
+            AttrValue::IntList(v) => self.set_attr_int_list(attr_name, v)?,
+            AttrValue::Float(v) => self.set_attr_float(attr_name, *v)?,
+            AttrValue::FloatList(v) => self.set_attr_float_list(attr_name, v)?,
+            AttrValue::Bool(v) => self.set_attr_bool(attr_name, *v)?,
+            AttrValue::BoolList(v) => self.set_attr_bool_list(attr_name, v)?,
+            AttrValue::Type(v) => self.set_attr_type(attr_name, *v)?,
+            AttrValue::TypeList(v) => self.set_attr_type_list(attr_name, v)?,
+            AttrValue::Shape(v) => self.set_attr_shape(attr_name, v)?,
+            AttrValue::ShapeList(v) => self.set_attr_shape_list(attr_name, v)?,
+        }
+        Ok(())
+    }
+
+    /// Returns true if an attribute named `attr_name` has been set.
+    #[cfg(feature = "experimental_training")]
+    pub(crate) fn has_attr(&self, attr_name: &str) -> bool {
+        self.attr_names.contains(attr_name)
+    }
+
     /// Sets the value of a string attribute.
     #[allow(trivial_numeric_casts)]
     pub fn set_attr_string(
@@ -1873,6 +3554,7 @@ impl<'a> OperationDescription<'a> {
         value: &str,
     ) -> std::result::Result<(), NulError> {
         let c_attr_name = CString::new(attr_name)?;
+        self.attr_names.insert(attr_name.to_string());
         let c_value = value.as_bytes();
         unsafe {
             tf::TF_SetAttrString(
@@ -1893,6 +3575,7 @@ impl<'a> OperationDescription<'a> {
         value: &[S],
     ) -> std::result::Result<(), NulError> {
         let c_attr_name = CString::new(attr_name)?;
+        self.attr_names.insert(attr_name.to_string());
         let bytes: Vec<&[u8]> = value.iter().map(|x| x.as_ref().as_bytes()).collect();
         let ptrs: Vec<*const c_void> = bytes.iter().map(|x| x.as_ptr() as *const c_void).collect();
         let lens: Vec<size_t> = bytes.iter().map(|x| x.len() as size_t).collect();
@@ -1916,6 +3599,7 @@ impl<'a> OperationDescription<'a> {
         value: &str,
     ) -> std::result::Result<(), NulError> {
         let c_attr_name = CString::new(attr_name)?;
+        self.attr_names.insert(attr_name.to_string());
         let c_value = value.as_bytes();
         unsafe {
             tf::TF_SetAttrFuncName(
@@ -1935,6 +3619,7 @@ impl<'a> OperationDescription<'a> {
         value: i64,
     ) -> std::result::Result<(), NulError> {
         let c_attr_name = CString::new(attr_name)?;
+        self.attr_names.insert(attr_name.to_string());
         unsafe {
             tf::TF_SetAttrInt(self.inner, c_attr_name.as_ptr(), value);
         }
@@ -1948,6 +3633,7 @@ impl<'a> OperationDescription<'a> {
         value: &[i64],
     ) -> std::result::Result<(), NulError> {
         let c_attr_name = CString::new(attr_name)?;
+        self.attr_names.insert(attr_name.to_string());
         unsafe {
             tf::TF_SetAttrIntList(
                 self.inner,
@@ -1966,6 +3652,7 @@ impl<'a> OperationDescription<'a> {
         value: f32,
     ) -> std::result::Result<(), NulError> {
         let c_attr_name = CString::new(attr_name)?;
+        self.attr_names.insert(attr_name.to_string());
         unsafe {
             tf::TF_SetAttrFloat(self.inner, c_attr_name.as_ptr(), value);
         }
@@ -1980,6 +3667,7 @@ impl<'a> OperationDescription<'a> {
         value: &[f32],
     ) -> std::result::Result<(), NulError> {
         let c_attr_name = CString::new(attr_name)?;
+        self.attr_names.insert(attr_name.to_string());
         // Allow trivial_numeric_casts here because f32 is not necessarily equal to c_float.
         let c_value: Vec<c_float> = value.iter().map(|x| *x as c_float).collect();
         unsafe {
@@ -2000,6 +3688,7 @@ impl<'a> OperationDescription<'a> {
         value: bool,
     ) -> std::result::Result<(), NulError> {
         let c_attr_name = CString::new(attr_name)?;
+        self.attr_names.insert(attr_name.to_string());
         unsafe {
             tf::TF_SetAttrBool(self.inner, c_attr_name.as_ptr(), if value { 1 } else { 0 });
         }
@@ -2013,6 +3702,7 @@ impl<'a> OperationDescription<'a> {
         value: &[bool],
     ) -> std::result::Result<(), NulError> {
         let c_attr_name = CString::new(attr_name)?;
+        self.attr_names.insert(attr_name.to_string());
         let c_value: Vec<c_uchar> = value.iter().map(|x| if *x { 1 } else { 0 }).collect();
         unsafe {
             tf::TF_SetAttrBoolList(
@@ -2032,6 +3722,7 @@ impl<'a> OperationDescription<'a> {
         value: DataType,
     ) -> std::result::Result<(), NulError> {
         let c_attr_name = CString::new(attr_name)?;
+        self.attr_names.insert(attr_name.to_string());
         unsafe {
             tf::TF_SetAttrType(self.inner, c_attr_name.as_ptr(), value.to_c());
         }
@@ -2045,6 +3736,7 @@ impl<'a> OperationDescription<'a> {
         value: &[DataType],
     ) -> std::result::Result<(), NulError> {
         let c_attr_name = CString::new(attr_name)?;
+        self.attr_names.insert(attr_name.to_string());
         let c_value: Vec<tf::TF_DataType> = value.iter().map(|x| x.to_c()).collect();
         unsafe {
             tf::TF_SetAttrTypeList(
@@ -2064,6 +3756,7 @@ impl<'a> OperationDescription<'a> {
         value: &Shape,
     ) -> std::result::Result<(), NulError> {
         let c_attr_name = CString::new(attr_name)?;
+        self.attr_names.insert(attr_name.to_string());
         unsafe {
             match value.0 {
                 None => tf::TF_SetAttrShape(self.inner, c_attr_name.as_ptr(), ptr::null(), -1),
@@ -2094,6 +3787,7 @@ impl<'a> OperationDescription<'a> {
         value: &[Shape],
     ) -> std::result::Result<(), NulError> {
         let c_attr_name = CString::new(attr_name)?;
+        self.attr_names.insert(attr_name.to_string());
         // Convert Option<i64> in each shape to i64 with None becoming -1.
         let c_dims: Vec<Option<Vec<i64>>> = value
             .iter()
@@ -2139,6 +3833,7 @@ impl<'a> OperationDescription<'a> {
     #[allow(trivial_numeric_casts)]
     pub fn set_attr_tensor_shape_proto(&mut self, attr_name: &str, value: &[u8]) -> Result<()> {
         let c_attr_name = CString::new(attr_name)?;
+        self.attr_names.insert(attr_name.to_string());
         let mut status = Status::new();
         unsafe {
             tf::TF_SetAttrTensorShapeProto(
@@ -2160,6 +3855,7 @@ impl<'a> OperationDescription<'a> {
         value: &[T],
     ) -> Result<()> {
         let c_attr_name = CString::new(attr_name)?;
+        self.attr_names.insert(attr_name.to_string());
         let ptrs: Vec<*const c_void> = value
             .iter()
             .map(|x| x.as_ref().as_ptr() as *const c_void)
@@ -2195,6 +3891,7 @@ impl<'a> OperationDescription<'a> {
         value: &dyn AnyTensor,
     ) -> Result<()> {
         let c_attr_name = CString::new(attr_name)?;
+        self.attr_names.insert(attr_name.to_string());
         let mut status = Status::new();
         unsafe {
             tf::TF_SetAttrTensor(
@@ -2214,6 +3911,7 @@ impl<'a> OperationDescription<'a> {
         T: TensorType,
     {
         let c_attr_name = CString::new(attr_name)?;
+        self.attr_names.insert(attr_name.to_string());
         let mut status = Status::new();
         unsafe {
             // These have to stay alive durng the TF_SetAttrTensorList call.
@@ -2241,6 +3939,7 @@ impl<'a> OperationDescription<'a> {
     #[allow(trivial_numeric_casts)]
     pub fn set_attr_value_proto(&mut self, attr_name: &str, value: &[u8]) -> Result<()> {
         let c_attr_name = CString::new(attr_name)?;
+        self.attr_names.insert(attr_name.to_string());
         let mut status = Status::new();
         unsafe {
             tf::TF_SetAttrValueProto(
@@ -2264,6 +3963,7 @@ impl<'a> OperationDescription<'a> {
 #[allow(missing_copy_implementations)]
 pub struct FunctionOptions {
     inner: *mut tf::TF_FunctionOptions,
+    noinline: bool,
 }
 
 impl Default for FunctionOptions {
@@ -2277,8 +3977,28 @@ impl FunctionOptions {
     pub fn new() -> Self {
         FunctionOptions {
             inner: ptr::null_mut(), // TODO: Use real options when they become available
+            noinline: false,
         }
     }
+
+    /// Sets whether the `_noinline` attribute is set on the function, which
+    /// keeps TensorFlow from inlining calls to it into the calling graph.
+    ///
+    /// This is meant for profiling.  An inlined call disappears, and its body
+    /// is optimized and placed together with the rest of the graph, so the
+    /// `RunMetadata` step stats attribute the time to the individual
+    /// operations of the body.  A call which is not inlined shows up in the
+    /// step stats as a single node whose time covers the whole call, which
+    /// makes the cost of the function as a unit easy to read, at the price of
+    /// the optimizations across the call boundary and some call overhead.
+    pub fn set_noinline(&mut self, noinline: bool) {
+        self.noinline = noinline;
+    }
+
+    /// Returns whether the `_noinline` attribute is set on the function.
+    pub fn noinline(&self) -> bool {
+        self.noinline
+    }
 }
 
 ////////////////////////
@@ -2487,6 +4207,114 @@ mod tests {
         g2.copy_function(&f, None).unwrap();
         assert_eq!(1, g2.num_functions());
         assert_eq!(1, g2.get_functions().unwrap().len());
+        assert!(f.get_attr_value_proto("_noinline").is_err());
+    }
+
+    #[test]
+    fn graph_to_function_noinline() {
+        let mut g = Graph::new();
+        let x = {
+            let mut nd = g.new_operation("Placeholder", "x").unwrap();
+            nd.set_attr_type("dtype", DataType::Float).unwrap();
+            nd.finish().unwrap()
+        };
+        let y = multiply(&mut g, x.clone(), x.clone(), "y").unwrap();
+        let mut opts = FunctionOptions::new();
+        opts.set_noinline(true);
+        assert!(opts.noinline());
+        let f = g
+            .to_function(
+                "square",
+                false,
+                None,
+                &[x.into()],
+                &[y.into()],
+                None::<&[&str]>,
+                &opts,
+                None,
+            )
+            .unwrap();
+        let attr: protos::attr_value::AttrValue =
+            protobuf::parse_from_bytes(&f.get_attr_value_proto("_noinline").unwrap()).unwrap();
+        assert!(attr.get_b());
+    }
+
+    fn times_two_function() -> Function {
+        let mut g = Graph::new();
+        let x = {
+            let mut nd = g.new_operation("Placeholder", "x").unwrap();
+            nd.set_attr_type("dtype", DataType::Float).unwrap();
+            nd.finish().unwrap()
+        };
+        let two = {
+            let mut nd = g.new_operation("Const", "two").unwrap();
+            nd.set_attr_type("dtype", DataType::Float).unwrap();
+            nd.set_attr_tensor("value", Tensor::from(2.0f32)).unwrap();
+            nd.finish().unwrap()
+        };
+        let y = multiply(&mut g, two, x.clone(), "y").unwrap();
+        g.to_function(
+            "times_two",
+            false,
+            None,
+            &[x.into()],
+            &[y.into()],
+            None::<&[&str]>,
+            &FunctionOptions::new(),
+            None,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn import_graph_def_with_function_library() {
+        let mut g = Graph::new();
+        g.copy_function(&times_two_function(), None).unwrap();
+        let x = {
+            let mut nd = g.new_operation("Placeholder", "x").unwrap();
+            nd.set_attr_type("dtype", DataType::Float).unwrap();
+            nd.finish().unwrap()
+        };
+        {
+            let mut nd = g.new_operation("times_two", "y").unwrap();
+            nd.add_input(x);
+            nd.finish().unwrap();
+        }
+        let graph_def = g.graph_def().unwrap();
+
+        let mut g2 = Graph::new();
+        g2.import_graph_def(&graph_def, &ImportGraphDefOptions::new())
+            .unwrap();
+        assert_eq!(1, g2.num_functions());
+        assert_eq!(
+            "times_two",
+            g2.operation_by_name_required("y")
+                .unwrap()
+                .op_type()
+                .unwrap()
+        );
+
+        let proto: protos::graph::GraphDef = protobuf::parse_from_bytes(&graph_def).unwrap();
+        let library = proto.get_library().write_to_bytes().unwrap();
+        let mut g3 = Graph::new();
+        g3.add_function_library(&library).unwrap();
+        assert_eq!(1, g3.num_functions());
+        // Adding the same library again is a no-op.
+        g3.add_function_library(&library).unwrap();
+        assert_eq!(1, g3.num_functions());
+        let x = {
+            let mut nd = g3.new_operation("Placeholder", "x").unwrap();
+            nd.set_attr_type("dtype", DataType::Float).unwrap();
+            nd.finish().unwrap()
+        };
+        let mut nd = g3.new_operation("times_two", "y").unwrap();
+        nd.add_input(x);
+        nd.finish().unwrap();
+
+        assert_eq!(
+            g3.add_function_library(&[0xff]).unwrap_err().code(),
+            Code::InvalidArgument
+        );
     }
 
     // This test checks that Operation::get_attr_* returns the value passed in
@@ -2706,27 +4534,533 @@ mod tests {
     }
 
     #[test]
-    fn import_graph_def_results_return_operations() {
+    fn control_inputs() {
         let mut g = Graph::new();
-        let mut opts = ImportGraphDefOptions::new();
-        assert_eq!(opts.num_return_operations(), 0);
-        opts.add_return_operation("a_times_b").unwrap();
-        assert_eq!(opts.num_return_operations(), 1);
-        let result = g
-            .import_graph_def_with_results(&graph_def(), &opts)
-            .unwrap();
-        let ops = result.return_operations();
-        assert_eq!(ops.len(), 1);
-        assert_eq!(ops[0].name().unwrap(), "a_times_b");
+        let no_op = g.new_operation("NoOp", "no_op").unwrap().finish().unwrap();
+        let c = {
+            let mut nd = g.new_operation("Const", "c").unwrap();
+            nd.set_attr_tensor("value", Tensor::<i32>::new(&[]))
+                .unwrap();
+            nd.set_attr_type("dtype", DataType::Int32).unwrap();
+            nd.add_control_input(&no_op);
+            nd.finish().unwrap()
+        };
+        assert_eq!(c.num_control_inputs(), 1);
+        let control_inputs = c.control_inputs();
+        assert_eq!(control_inputs.len(), 1);
+        assert_eq!(control_inputs[0].name().unwrap(), "no_op");
+        assert_eq!(no_op.num_control_outputs(), 1);
+        let control_outputs = no_op.control_outputs();
+        assert_eq!(control_outputs.len(), 1);
+        assert_eq!(control_outputs[0].name().unwrap(), "c");
     }
 
     #[test]
-    fn import_graph_def_results_missing_unused_input_mappings() {
+    fn topological_order() {
         let mut g = Graph::new();
-        let op = {
-            let mut nd = g.new_operation("Variable", "foo").unwrap();
-            nd.set_attr_type("dtype", DataType::Int32).unwrap();
-            nd.set_attr_shape("shape", &Shape(None)).unwrap();
+        let placeholder = |g: &mut Graph, name: &str| {
+            let mut nd = g.new_operation("Placeholder", name).unwrap();
+            nd.set_attr_type("dtype", DataType::Float).unwrap();
+            nd.finish().unwrap()
+        };
+        let x = placeholder(&mut g, "x");
+        let y = placeholder(&mut g, "y");
+        let no_op = g.new_operation("NoOp", "no_op").unwrap().finish().unwrap();
+        let sum = add(&mut g, x.clone(), y, "sum").unwrap();
+        {
+            let mut nd = g.new_operation("Mul", "product").unwrap();
+            nd.add_input(sum);
+            nd.add_input(x);
+            nd.add_control_input(&no_op);
+            nd.finish().unwrap();
+        }
+        let names: Vec<_> = g
+            .topological_order()
+            .unwrap()
+            .iter()
+            .map(|op| op.name().unwrap())
+            .collect();
+        assert_eq!(names, ["x", "y", "no_op", "sum", "product"]);
+
+        // The back edge of a loop from NextIteration to Merge is ignored.
+        let mut proto = protos::graph::GraphDef::new();
+        let mut node = |name: &str, op: &str, inputs: &[&str], attrs: &[(&str, i64)]| {
+            let mut node = protos::node_def::NodeDef::new();
+            node.set_name(name.to_string());
+            node.set_op(op.to_string());
+            for input in inputs {
+                node.mut_input().push(input.to_string());
+            }
+            let mut dtype = protos::attr_value::AttrValue::new();
+            dtype.set_field_type(DataType::Float.into_proto());
+            let attr_name = if op == "Placeholder" { "dtype" } else { "T" };
+            node.mut_attr().insert(attr_name.to_string(), dtype);
+            for (name, value) in attrs {
+                let mut attr = protos::attr_value::AttrValue::new();
+                attr.set_i(*value);
+                node.mut_attr().insert(name.to_string(), attr);
+            }
+            proto.mut_node().push(node);
+        };
+        node("x", "Placeholder", &[], &[]);
+        node("merge", "Merge", &["x", "next"], &[("N", 2)]);
+        node("next", "NextIteration", &["merge"], &[]);
+        let mut g = Graph::new();
+        g.import_graph_def(
+            &protobuf::Message::write_to_bytes(&proto).unwrap(),
+            &ImportGraphDefOptions::new(),
+        )
+        .unwrap();
+        let names: Vec<_> = g
+            .topological_order()
+            .unwrap()
+            .iter()
+            .map(|op| op.name().unwrap())
+            .collect();
+        assert_eq!(names, ["x", "merge", "next"]);
+    }
+
+    #[test]
+    fn const_value() {
+        let mut g = Graph::new();
+        let c = {
+            let mut nd = g.new_operation("Const", "c").unwrap();
+            nd.set_attr_type("dtype", DataType::Float).unwrap();
+            nd.set_attr_tensor(
+                "value",
+                Tensor::new(&[2]).with_values(&[1.0f32, 2.0]).unwrap(),
+            )
+            .unwrap();
+            nd.finish().unwrap()
+        };
+        add(&mut g, c.clone(), c, "sum").unwrap();
+        let value = g.const_value::<f32>("c").unwrap();
+        assert_eq!(value.dims(), &[2]);
+        assert_eq!(&value[..], &[1.0, 2.0]);
+        assert_eq!(
+            g.const_value::<f32>("sum").unwrap_err().code(),
+            Code::InvalidArgument
+        );
+        assert_eq!(
+            g.const_value::<i32>("c").unwrap_err().code(),
+            Code::InvalidArgument
+        );
+        assert!(g.const_value::<f32>("missing").is_err());
+    }
+
+    #[test]
+    fn without_ops() {
+        let mut g = Graph::new();
+        let x = {
+            let mut nd = g.new_operation("Placeholder", "x").unwrap();
+            nd.set_attr_type("dtype", DataType::Float).unwrap();
+            nd.finish().unwrap()
+        };
+        let c = {
+            let mut nd = g.new_operation("Const", "c").unwrap();
+            nd.set_attr_type("dtype", DataType::Float).unwrap();
+            nd.set_attr_tensor("value", Tensor::from(2.0f32)).unwrap();
+            nd.finish().unwrap()
+        };
+        let aux = multiply(&mut g, x.clone(), x.clone(), "aux").unwrap();
+        let aux_loss = {
+            let mut nd = g.new_operation("Mul", "aux_loss").unwrap();
+            nd.add_input(aux.clone());
+            nd.add_input(c.clone());
+            nd.finish().unwrap()
+        };
+        let y = add(&mut g, x, c, "y").unwrap();
+        {
+            let mut nd = g.new_operation("NoOp", "done").unwrap();
+            nd.add_control_input(&aux_loss);
+            nd.add_control_input(&y);
+            nd.finish().unwrap();
+        }
+        let names = |g: &Graph| {
+            let mut names: Vec<_> = g.operation_iter().map(|op| op.name().unwrap()).collect();
+            names.sort();
+            names
+        };
+
+        // Removing aux removes aux_loss, which consumes it, and the control
+        // dependency of done on aux_loss.
+        let pruned = g.without_ops(&["aux"]).unwrap();
+        assert_eq!(names(&pruned), ["c", "done", "x", "y"]);
+        let done = pruned.operation_by_name_required("done").unwrap();
+        assert_eq!(done.num_control_inputs(), 1);
+
+        // Removing done leaves nothing which needs the rest of the graph.
+        let pruned = g.without_ops(&["done", "y"]).unwrap();
+        assert_eq!(names(&pruned).len(), 0);
+
+        assert_eq!(
+            g.without_ops(&["missing"]).unwrap_err().code(),
+            Code::NotFound
+        );
+    }
+
+    #[test]
+    fn fuse_conv_bias_relu() {
+        let mut g = Graph::new();
+        let x = {
+            let mut nd = g.new_operation("Placeholder", "x").unwrap();
+            nd.set_attr_type("dtype", DataType::Float).unwrap();
+            nd.set_attr_shape("shape", &Shape::from(&[1, 2, 2, 1][..]))
+                .unwrap();
+            nd.finish().unwrap()
+        };
+        let constant = |g: &mut Graph, name: &str, value: Tensor<f32>| {
+            let mut nd = g.new_operation("Const", name).unwrap();
+            nd.set_attr_type("dtype", DataType::Float).unwrap();
+            nd.set_attr_tensor("value", value).unwrap();
+            nd.finish().unwrap()
+        };
+        let filter = constant(
+            &mut g,
+            "filter",
+            Tensor::new(&[1, 1, 1, 1]).with_values(&[2.0f32]).unwrap(),
+        );
+        let bias = constant(&mut g, "bias", Tensor::from(&[-3.0f32][..]));
+        // Builds Conv2D -> BiasAdd -> Relu with the given name prefix.
+        let chain = |g: &mut Graph, prefix: &str| {
+            let mut nd = g
+                .new_operation("Conv2D", &format!("{}/conv", prefix))
+                .unwrap();
+            nd.add_input(x.clone());
+            nd.add_input(filter.clone());
+            nd.set_attr_int_list("strides", &[1, 1, 1, 1]).unwrap();
+            nd.set_attr_string("padding", "VALID").unwrap();
+            let conv = nd.finish().unwrap();
+            let mut nd = g
+                .new_operation("BiasAdd", &format!("{}/bias_add", prefix))
+                .unwrap();
+            nd.add_input(conv);
+            nd.add_input(bias.clone());
+            let bias_add = nd.finish().unwrap();
+            let mut nd = g
+                .new_operation("Relu", &format!("{}/relu", prefix))
+                .unwrap();
+            nd.add_input(bias_add.clone());
+            (bias_add, nd.finish().unwrap())
+        };
+        chain(&mut g, "a");
+        // The BiasAdd of this chain has another consumer, so it isn't fused.
+        let (b_bias_add, _) = chain(&mut g, "b");
+        let mut nd = g.new_operation("Identity", "b/bias_add_copy").unwrap();
+        nd.add_input(b_bias_add);
+        nd.finish().unwrap();
+
+        let fused = g.fuse_conv_bias_relu().unwrap();
+        let a_relu = fused.operation_by_name_required("a/relu").unwrap();
+        assert_eq!(a_relu.op_type().unwrap(), "_FusedConv2D");
+        assert_eq!(a_relu.num_inputs(), 3);
+        assert!(fused.operation_by_name("a/conv").unwrap().is_none());
+        assert!(fused.operation_by_name("a/bias_add").unwrap().is_none());
+        let b_relu = fused.operation_by_name_required("b/relu").unwrap();
+        assert_eq!(b_relu.op_type().unwrap(), "Relu");
+        assert!(fused.operation_by_name("b/conv").unwrap().is_some());
+
+        let session =
+            super::super::Session::new(&super::super::SessionOptions::new(), &fused).unwrap();
+        let x = fused.operation_by_name_required("x").unwrap();
+        let x_value = Tensor::new(&[1, 2, 2, 1])
+            .with_values(&[1.0f32, 2.0, 3.0, 4.0])
+            .unwrap();
+        let mut args = super::super::SessionRunArgs::new();
+        args.add_feed(&x, 0, &x_value);
+        let a_fetch = args.request_fetch(&a_relu, 0);
+        let b_fetch = args.request_fetch(&b_relu, 0);
+        session.run(&mut args).unwrap();
+        let expected = [0.0f32, 1.0, 3.0, 5.0];
+        assert_eq!(&args.fetch::<f32>(a_fetch).unwrap()[..], &expected);
+        assert_eq!(&args.fetch::<f32>(b_fetch).unwrap()[..], &expected);
+    }
+
+    #[test]
+    fn content_hash() {
+        fn build(names: [&str; 3], value: f32, swap_inputs: bool) -> Graph {
+            let mut g = Graph::new();
+            let x = {
+                let mut nd = g.new_operation("Placeholder", names[0]).unwrap();
+                nd.set_attr_type("dtype", DataType::Float).unwrap();
+                nd.finish().unwrap()
+            };
+            let c = {
+                let mut nd = g.new_operation("Const", names[1]).unwrap();
+                nd.set_attr_type("dtype", DataType::Float).unwrap();
+                nd.set_attr_tensor("value", Tensor::from(value)).unwrap();
+                nd.finish().unwrap()
+            };
+            if swap_inputs {
+                add(&mut g, c, x, names[2]).unwrap();
+            } else {
+                add(&mut g, x, c, names[2]).unwrap();
+            }
+            g
+        }
+
+        let hash = build(["x", "c", "add"], 1.0, false).content_hash().unwrap();
+        assert_eq!(
+            build(["x", "c", "add"], 1.0, false).content_hash().unwrap(),
+            hash
+        );
+        assert_eq!(
+            build(["x_1", "c_2", "add_1"], 1.0, false)
+                .content_hash()
+                .unwrap(),
+            hash
+        );
+        assert_ne!(
+            build(["y", "c", "add"], 1.0, false).content_hash().unwrap(),
+            hash
+        );
+        assert_ne!(
+            build(["x", "c", "add"], 2.0, false).content_hash().unwrap(),
+            hash
+        );
+        assert_ne!(
+            build(["x", "c", "add"], 1.0, true).content_hash().unwrap(),
+            hash
+        );
+        assert_eq!(strip_name_suffix("foo_1/bar_2_3"), "foo/bar_2");
+        assert_eq!(strip_name_suffix("_1/a_b"), "_1/a_b");
+    }
+
+    #[test]
+    fn guess_inputs_and_outputs() {
+        let mut g = Graph::new();
+        let x = {
+            let mut nd = g.new_operation("Placeholder", "x").unwrap();
+            nd.set_attr_type("dtype", DataType::Float).unwrap();
+            nd.finish().unwrap()
+        };
+        let y = {
+            let mut nd = g.new_operation("Placeholder", "y").unwrap();
+            nd.set_attr_type("dtype", DataType::Float).unwrap();
+            nd.finish().unwrap()
+        };
+        let x_times_y = multiply(&mut g, x.clone(), y.clone(), "x_times_y").unwrap();
+        multiply(&mut g, x_times_y.clone(), y, "result").unwrap();
+        add(&mut g, x_times_y, x, "other_result").unwrap();
+        g.new_operation("NoOp", "no_op").unwrap().finish().unwrap();
+
+        let names = |outputs: Vec<Output>| {
+            let mut names: Vec<_> = outputs
+                .iter()
+                .map(|output| output.name().unwrap().to_string())
+                .collect();
+            names.sort();
+            names
+        };
+        assert_eq!(names(g.guess_inputs()), vec!["x:0", "y:0"]);
+        assert_eq!(names(g.guess_outputs()), vec!["other_result:0", "result:0"]);
+    }
+
+    #[test]
+    fn input_signature() {
+        let mut g = Graph::new();
+        {
+            let mut nd = g.new_operation("Placeholder", "images").unwrap();
+            nd.set_attr_type("dtype", DataType::Float).unwrap();
+            nd.set_attr_shape("shape", &Shape(Some(vec![None, Some(3)])))
+                .unwrap();
+            nd.finish().unwrap();
+        }
+        let labels = {
+            let mut nd = g.new_operation("Placeholder", "labels").unwrap();
+            nd.set_attr_type("dtype", DataType::Int32).unwrap();
+            nd.finish().unwrap()
+        };
+        {
+            let mut nd = g
+                .new_operation("PlaceholderWithDefault", "training")
+                .unwrap();
+            nd.add_input(labels);
+            nd.set_attr_shape("shape", &Shape(None)).unwrap();
+            nd.finish().unwrap();
+        }
+
+        assert_eq!(
+            g.input_signature().unwrap(),
+            vec![
+                (
+                    "images".to_string(),
+                    DataType::Float,
+                    Shape(Some(vec![None, Some(3)]))
+                ),
+                ("labels".to_string(), DataType::Int32, Shape(None)),
+            ]
+        );
+    }
+
+    #[test]
+    fn graph_def_with_output_shapes() {
+        let mut g = Graph::new();
+        let x = {
+            let mut nd = g.new_operation("Placeholder", "x").unwrap();
+            nd.set_attr_type("dtype", DataType::Float).unwrap();
+            nd.set_attr_shape("shape", &Shape(Some(vec![None, Some(3)])))
+                .unwrap();
+            nd.finish().unwrap()
+        };
+        multiply(&mut g, x.clone(), x, "x_squared").unwrap();
+        g.new_operation("NoOp", "no_op").unwrap().finish().unwrap();
+
+        let graph_def = g.graph_def_with_output_shapes().unwrap();
+        let proto: protos::graph::GraphDef = protobuf::parse_from_bytes(&graph_def).unwrap();
+        let output_shapes = |name: &str| -> Vec<Shape> {
+            let node = proto
+                .get_node()
+                .iter()
+                .find(|node| node.get_name() == name)
+                .unwrap();
+            node.get_attr()["_output_shapes"]
+                .get_list()
+                .get_shape()
+                .iter()
+                .map(Shape::from_proto)
+                .collect()
+        };
+        let expected = Shape(Some(vec![None, Some(3)]));
+        assert_eq!(output_shapes("x"), vec![expected.clone()]);
+        assert_eq!(output_shapes("x_squared"), vec![expected]);
+        assert_eq!(output_shapes("no_op"), vec![]);
+
+        let mut imported = Graph::new();
+        imported
+            .import_graph_def(&graph_def, &ImportGraphDefOptions::new())
+            .unwrap();
+        assert_eq!(imported.operation_iter().count(), 3);
+    }
+
+    #[test]
+    fn validate() {
+        let mut g = Graph::new();
+        let x = {
+            let mut nd = g.new_operation("Placeholder", "x").unwrap();
+            nd.set_attr_type("dtype", DataType::Float).unwrap();
+            nd.finish().unwrap()
+        };
+        let identity = |g: &mut Graph, name: &str, input: Operation| {
+            let mut nd = g.new_operation("Identity", name).unwrap();
+            nd.add_input(input);
+            nd.finish().unwrap()
+        };
+        let a = identity(&mut g, "a", x);
+        let b = identity(&mut g, "b", a.clone());
+        identity(&mut g, "c", b.clone());
+        g.validate().unwrap();
+        assert_eq!(g.problems().unwrap(), vec![]);
+
+        g.update_edge(b.into(), &a, 0).unwrap();
+        assert_eq!(
+            g.problems().unwrap(),
+            vec![GraphProblem::Cycle {
+                operations: vec!["a".to_string(), "b".to_string(), "c".to_string()]
+            }]
+        );
+        let error = g.validate().unwrap_err();
+        assert_eq!(error.code(), Code::InvalidArgument);
+        assert!(error.to_string().contains("cycle: a, b, c"), "{}", error);
+    }
+
+    #[test]
+    fn export_with_outputs() {
+        let mut g = Graph::new();
+        let x = {
+            let mut nd = g.new_operation("Placeholder", "x").unwrap();
+            nd.set_attr_type("dtype", DataType::Float).unwrap();
+            nd.finish().unwrap()
+        };
+        let y = multiply(&mut g, x.clone(), x.clone(), "y").unwrap();
+        let z = add(&mut g, y.clone(), x, "z").unwrap();
+        let graph_def = g
+            .export_with_outputs(&[z.into(), y.clone().into(), y.into()])
+            .unwrap();
+
+        let mut imported = Graph::new();
+        imported
+            .import_graph_def(&graph_def, &ImportGraphDefOptions::new())
+            .unwrap();
+        let names: Vec<_> = imported
+            .marked_outputs()
+            .unwrap()
+            .iter()
+            .map(|output| output.name().unwrap().to_string())
+            .collect();
+        assert_eq!(names, ["z:0", "y:0", "y:0"]);
+        assert!(g.marked_outputs().unwrap().is_empty());
+
+        let other = Graph::new()
+            .new_operation("NoOp", "other")
+            .unwrap()
+            .finish()
+            .unwrap();
+        assert_eq!(
+            g.export_with_outputs(&[other.into()]).unwrap_err().code(),
+            Code::NotFound
+        );
+    }
+
+    #[test]
+    fn import_graph_def_limits() {
+        let mut g = Graph::new();
+        let graph_def = graph_def();
+        let mut opts = ImportGraphDefOptions::new();
+        opts.set_max_graph_def_size(Some(graph_def.len() - 1));
+        let status = g.import_graph_def(&graph_def, &opts).unwrap_err();
+        assert_eq!(status.code(), Code::InvalidArgument);
+        opts.set_max_graph_def_size(None);
+        opts.set_max_nodes(Some(2));
+        let status = g.import_graph_def(&graph_def, &opts).unwrap_err();
+        assert_eq!(status.code(), Code::InvalidArgument);
+        assert_eq!(g.operation_iter().count(), 0);
+        opts.set_max_nodes(Some(3));
+        g.import_graph_def(&graph_def, &opts).unwrap();
+        assert_eq!(g.operation_iter().count(), 3);
+    }
+
+    #[test]
+    fn import_graph_def_reject_unknown_ops() {
+        let mut proto = protos::graph::GraphDef::new();
+        let mut node = protos::node_def::NodeDef::new();
+        node.set_name("x".to_string());
+        node.set_op("NotARegisteredOp".to_string());
+        proto.mut_node().push(node);
+        let graph_def = protobuf::Message::write_to_bytes(&proto).unwrap();
+        let mut g = Graph::new();
+        let mut opts = ImportGraphDefOptions::new();
+        opts.set_reject_unknown_ops(true);
+        let status = g.import_graph_def(&graph_def, &opts).unwrap_err();
+        assert_eq!(status.code(), Code::NotFound);
+        assert!(format!("{}", status).contains("NotARegisteredOp"));
+        g.import_graph_def(&self::graph_def(), &opts).unwrap();
+        let status = g.import_graph_def(b"not a graph", &opts).unwrap_err();
+        assert_eq!(status.code(), Code::InvalidArgument);
+    }
+
+    #[test]
+    fn import_graph_def_results_return_operations() {
+        let mut g = Graph::new();
+        let mut opts = ImportGraphDefOptions::new();
+        assert_eq!(opts.num_return_operations(), 0);
+        opts.add_return_operation("a_times_b").unwrap();
+        assert_eq!(opts.num_return_operations(), 1);
+        let result = g
+            .import_graph_def_with_results(&graph_def(), &opts)
+            .unwrap();
+        let ops = result.return_operations();
+        assert_eq!(ops.len(), 1);
+        assert_eq!(ops[0].name().unwrap(), "a_times_b");
+    }
+
+    #[test]
+    fn import_graph_def_results_missing_unused_input_mappings() {
+        let mut g = Graph::new();
+        let op = {
+            let mut nd = g.new_operation("Variable", "foo").unwrap();
+            nd.set_attr_type("dtype", DataType::Int32).unwrap();
+            nd.set_attr_shape("shape", &Shape(None)).unwrap();
             nd.finish().unwrap()
         };
         let output = op.into();
@@ -2755,6 +5089,45 @@ mod tests {
         assert_eq!(ops[0].index, 0);
     }
 
+    #[test]
+    fn graph_merge_from() {
+        let mut a = Graph::new();
+        let x = {
+            let mut nd = a.new_operation("Placeholder", "x").unwrap();
+            nd.set_attr_type("dtype", DataType::Float).unwrap();
+            nd.finish().unwrap()
+        };
+        let mut b = Graph::new();
+        let x_b = {
+            let mut nd = b.new_operation("Placeholder", "x").unwrap();
+            nd.set_attr_type("dtype", DataType::Float).unwrap();
+            nd.finish().unwrap()
+        };
+        add(&mut b, x_b.clone(), x_b.clone(), "y").unwrap();
+        let merged = a
+            .merge_from(
+                &b,
+                &[(
+                    Output {
+                        operation: x.clone(),
+                        index: 0,
+                    },
+                    Output {
+                        operation: x_b,
+                        index: 0,
+                    },
+                )],
+            )
+            .unwrap();
+        assert_eq!(merged["x:0"].operation.name().unwrap(), "x");
+        let y = &merged["y:0"];
+        assert_eq!(y.operation.name().unwrap(), "y");
+        assert_eq!(y.operation.input(0).0.name().unwrap(), "x");
+        assert_eq!(y.operation.input(1).0.name().unwrap(), "x");
+        // The unused placeholder from `b` was renamed rather than clashing.
+        assert!(a.operation_by_name("x_1").unwrap().is_some());
+    }
+
     #[test]
     fn graph_get_op_def() {
         let g = Graph::new();
@@ -2825,6 +5198,281 @@ mod tests {
         }
     }
 
+    #[test]
+    fn graph_add_gradients_second_order() {
+        let mut g = Graph::new();
+        let x = {
+            let mut nd = g.new_operation("Placeholder", "x").unwrap();
+            nd.set_attr_type("dtype", DataType::Float).unwrap();
+            nd.set_attr_shape("shape", &Shape(Some(vec![]))).unwrap();
+            nd.finish().unwrap()
+        };
+        let one = {
+            let mut nd = g.new_operation("Const", "one").unwrap();
+            nd.set_attr_type("dtype", DataType::Float).unwrap();
+            nd.set_attr_tensor("value", Tensor::<f32>::from(1.0))
+                .unwrap();
+            nd.finish().unwrap()
+        };
+        let x_squared = multiply(&mut g, x.clone(), x.clone(), "x_squared").unwrap();
+        let x_cubed = multiply(&mut g, x_squared, x.clone(), "x_cubed").unwrap();
+        let dy = g
+            .add_gradients(
+                None,
+                &[x_cubed.into()],
+                &[x.clone().into()],
+                Some(&[one.into()]),
+            )
+            .unwrap()[0]
+            .clone()
+            .unwrap();
+        assert!(dy.operation.name().unwrap().starts_with("gradients/"));
+        let d2y = g
+            .add_gradients(None, &[dy.clone()], &[x.clone().into()], None)
+            .unwrap()[0]
+            .clone()
+            .unwrap();
+        let name = d2y.operation.name().unwrap();
+        assert!(name.starts_with("gradients/gradients/"), "name = {}", name);
+        // Computing the same gradient again must not collide with the first.
+        let d2y_again = g
+            .add_gradients(None, &[dy.clone()], &[x.clone().into()], None)
+            .unwrap()[0]
+            .clone()
+            .unwrap();
+        let name = d2y_again.operation.name().unwrap();
+        assert!(
+            name.starts_with("gradients/gradients_1/"),
+            "name = {}",
+            name
+        );
+
+        let session = super::super::Session::new(&super::super::SessionOptions::new(), &g).unwrap();
+        let x_value = Tensor::<f32>::from(2.0);
+        let mut args = super::super::SessionRunArgs::new();
+        args.add_feed(&x, 0, &x_value);
+        let dy_token = args.request_fetch(&dy.operation, dy.index);
+        let d2y_token = args.request_fetch(&d2y.operation, d2y.index);
+        session.run(&mut args).unwrap();
+        // d/dx x^3 = 3x^2, d^2/dx^2 x^3 = 6x
+        assert_eq!(args.fetch::<f32>(dy_token).unwrap()[0], 12.0);
+        assert_eq!(args.fetch::<f32>(d2y_token).unwrap()[0], 12.0);
+    }
+
+    #[test]
+    fn graph_update_edge() {
+        let mut g = Graph::new();
+        let constant = |g: &mut Graph, name: &str, value: Tensor<f32>| {
+            let mut nd = g.new_operation("Const", name).unwrap();
+            nd.set_attr_type("dtype", DataType::Float).unwrap();
+            nd.set_attr_tensor("value", value).unwrap();
+            nd.finish().unwrap()
+        };
+        let a = constant(&mut g, "a", Tensor::from(1.0));
+        let b = constant(&mut g, "b", Tensor::from(2.0));
+        let c = {
+            let mut nd = g.new_operation("Const", "c").unwrap();
+            nd.set_attr_type("dtype", DataType::Int32).unwrap();
+            nd.set_attr_tensor("value", Tensor::<i32>::from(3)).unwrap();
+            nd.finish().unwrap()
+        };
+        let a_plus_a = add(&mut g, a.clone(), a.clone(), "a_plus_a").unwrap();
+        g.update_edge(b.clone().into(), &a_plus_a, 1).unwrap();
+        assert_eq!(a_plus_a.input(0).0.name().unwrap(), "a");
+        assert_eq!(a_plus_a.input(1).0.name().unwrap(), "b");
+        assert!(g.update_edge(c.into(), &a_plus_a, 1).is_err());
+        assert!(g.update_edge(b.into(), &a_plus_a, 2).is_err());
+
+        let session = super::super::Session::new(&super::super::SessionOptions::new(), &g).unwrap();
+        let mut args = super::super::SessionRunArgs::new();
+        let token = args.request_fetch(&a_plus_a, 0);
+        session.run(&mut args).unwrap();
+        assert_eq!(args.fetch::<f32>(token).unwrap()[0], 3.0);
+    }
+
+    #[test]
+    fn estimate_flops() {
+        let mut g = Graph::new();
+        let constant = |g: &mut Graph, name: &str, dims: &[u64]| {
+            let mut nd = g.new_operation("Const", name).unwrap();
+            nd.set_attr_type("dtype", DataType::Float).unwrap();
+            nd.set_attr_tensor("value", Tensor::<f32>::new(dims))
+                .unwrap();
+            nd.finish().unwrap()
+        };
+        let x = {
+            let mut nd = g.new_operation("Placeholder", "x").unwrap();
+            nd.set_attr_type("dtype", DataType::Float).unwrap();
+            nd.set_attr_shape("shape", &Shape(Some(vec![Some(2), Some(3)])))
+                .unwrap();
+            nd.finish().unwrap()
+        };
+        let w = constant(&mut g, "w", &[3, 4]);
+        let b = constant(&mut g, "b", &[4]);
+        constant(&mut g, "scalar", &[]);
+        {
+            let mut nd = g.new_operation("Const", "axes").unwrap();
+            nd.set_attr_type("dtype", DataType::Int32).unwrap();
+            nd.set_attr_tensor("value", Tensor::<i32>::new(&[8]))
+                .unwrap();
+            nd.finish().unwrap();
+        }
+        {
+            let mut nd = g.new_operation("VariableV2", "v").unwrap();
+            nd.set_attr_type("dtype", DataType::Float).unwrap();
+            nd.set_attr_shape("shape", &Shape(Some(vec![Some(5), Some(5)])))
+                .unwrap();
+            nd.finish().unwrap();
+        }
+        let mut nd = g.new_operation("MatMul", "matmul").unwrap();
+        nd.add_input(x);
+        nd.add_input(w);
+        let matmul = nd.finish().unwrap();
+        let mut nd = g.new_operation("BiasAdd", "bias_add").unwrap();
+        nd.add_input(matmul);
+        nd.add_input(b);
+        let bias_add = nd.finish().unwrap();
+        let mut nd = g.new_operation("Softplus", "softplus").unwrap();
+        nd.add_input(bias_add);
+        nd.finish().unwrap();
+
+        let estimate = g.estimate_flops().unwrap();
+        // 2 * 2 * 4 * 3 for the MatMul and 2 * 4 for the BiasAdd.
+        assert_eq!(estimate.flops, 56);
+        assert_eq!(estimate.unestimated, ["softplus"]);
+        // v, w and b.
+        assert_eq!(g.count_parameters().unwrap(), 25 + 12 + 4);
+
+        // Estimates which don't fit in a u64 saturate.
+        let mut g = Graph::new();
+        let huge = Shape(Some(vec![Some(1 << 40), Some(1 << 40)]));
+        let x = {
+            let mut nd = g.new_operation("VariableV2", "x").unwrap();
+            nd.set_attr_type("dtype", DataType::Float).unwrap();
+            nd.set_attr_shape("shape", &huge).unwrap();
+            nd.finish().unwrap()
+        };
+        let mut nd = g.new_operation("Square", "square").unwrap();
+        nd.add_input(x);
+        nd.finish().unwrap();
+        assert_eq!(g.estimate_flops().unwrap().flops, u64::MAX);
+        assert_eq!(g.count_parameters().unwrap(), u64::MAX);
+    }
+
+    #[test]
+    fn dedup_constants() {
+        let mut g = Graph::new();
+        let constant = |g: &mut Graph, name: &str, value: f32| {
+            let mut nd = g.new_operation("Const", name).unwrap();
+            nd.set_attr_type("dtype", DataType::Float).unwrap();
+            nd.set_attr_tensor("value", Tensor::from(value)).unwrap();
+            nd.finish().unwrap()
+        };
+        let a = constant(&mut g, "a", 1.0);
+        let b = constant(&mut g, "b", 1.0);
+        let c = constant(&mut g, "c", 2.0);
+        add(&mut g, a, b.clone(), "a_plus_b").unwrap();
+        add(&mut g, b, c, "b_plus_c").unwrap();
+
+        let deduped = g.dedup_constants().unwrap();
+        assert!(deduped.operation_by_name("b").unwrap().is_none());
+        assert!(deduped.operation_by_name("c").unwrap().is_some());
+        assert_eq!(deduped.operation_iter().count(), 4);
+        let a_plus_b = deduped.operation_by_name_required("a_plus_b").unwrap();
+        assert_eq!(a_plus_b.input(1).0.name().unwrap(), "a");
+        let b_plus_c = deduped.operation_by_name_required("b_plus_c").unwrap();
+        assert_eq!(b_plus_c.input(0).0.name().unwrap(), "a");
+
+        let session =
+            super::super::Session::new(&super::super::SessionOptions::new(), &deduped).unwrap();
+        let mut args = super::super::SessionRunArgs::new();
+        let a_plus_b_token = args.request_fetch(&a_plus_b, 0);
+        let b_plus_c_token = args.request_fetch(&b_plus_c, 0);
+        session.run(&mut args).unwrap();
+        assert_eq!(args.fetch::<f32>(a_plus_b_token).unwrap()[0], 2.0);
+        assert_eq!(args.fetch::<f32>(b_plus_c_token).unwrap()[0], 3.0);
+    }
+
+    #[test]
+    fn graph_add_gradients_with_aggregation() {
+        let methods = [
+            (AggregationMethod::AddN, "AddN"),
+            (AggregationMethod::ExperimentalTree, "AddN"),
+            (AggregationMethod::ExperimentalAccumulateN, "AccumulateNV2"),
+        ];
+        for &(method, op_type) in &methods {
+            for &gate_gradients in &[false, true] {
+                let mut g = Graph::new();
+                let x = {
+                    let mut nd = g.new_operation("Placeholder", "x").unwrap();
+                    nd.set_attr_type("dtype", DataType::Float).unwrap();
+                    nd.set_attr_shape("shape", &Shape(Some(vec![]))).unwrap();
+                    nd.finish().unwrap()
+                };
+                // x has four consumers, so four gradients are summed.
+                let x_squared = multiply(&mut g, x.clone(), x.clone(), "x_squared").unwrap();
+                let y1 = add(&mut g, x_squared, x.clone(), "y1").unwrap();
+                let y = add(&mut g, y1, x.clone(), "y").unwrap();
+                let num_operations = g.operation_iter().count();
+
+                // Errors leave the graph unchanged.
+                assert!(g
+                    .add_gradients_with_aggregation(
+                        None,
+                        &[y.clone().into()],
+                        &[x.clone().into()],
+                        Some(&[]),
+                        method,
+                        gate_gradients,
+                    )
+                    .is_err());
+                assert_eq!(g.operation_iter().count(), num_operations);
+
+                let dy = g
+                    .add_gradients_with_aggregation(
+                        None,
+                        &[y.into()],
+                        &[x.clone().into()],
+                        None,
+                        method,
+                        gate_gradients,
+                    )
+                    .unwrap();
+                let dy = dy[0].clone().unwrap();
+                let sum = if gate_gradients {
+                    assert_eq!(dy.operation.op_type().unwrap(), "Identity");
+                    assert_eq!(dy.operation.num_control_inputs(), 1);
+                    dy.operation.input(0).0
+                } else {
+                    dy.operation.clone()
+                };
+                assert_eq!(sum.op_type().unwrap(), op_type);
+                let expected_inputs = match method {
+                    AggregationMethod::ExperimentalTree => 2,
+                    _ => 4,
+                };
+                assert_eq!(sum.num_inputs(), expected_inputs);
+                // Replaced sums are not left in the graph without consumers.
+                for operation in g.operation_iter() {
+                    let name = operation.name().unwrap();
+                    if operation.op_type().unwrap() == op_type && name != sum.name().unwrap() {
+                        assert!(operation.output_num_consumers(0) > 0, "{}", name);
+                    }
+                }
+
+                let session =
+                    super::super::Session::new(&super::super::SessionOptions::new(), &g).unwrap();
+                let x_value = Tensor::<f32>::from(3.0);
+                let mut args = super::super::SessionRunArgs::new();
+                args.add_feed(&x, 0, &x_value);
+                let token = args.request_fetch(&dy.operation, dy.index);
+                session.run(&mut args).unwrap();
+                // d/dx (x^2 + 2x) = 2x + 2
+                assert_eq!(args.fetch::<f32>(token).unwrap()[0], 8.0);
+            }
+        }
+    }
+
     #[test]
     fn graph_add_gradients_stopped_gradient() {
         // TODO: Add an integration test to verify that the gradient behaves as expected.
@@ -0,0 +1,152 @@
+use crate::Result;
+use std::sync::atomic::AtomicU64;
+use std::sync::atomic::Ordering;
+use std::time::Duration;
+use std::time::Instant;
+
+/// Default upper bounds of the latency histogram buckets, in microseconds.
+const DEFAULT_BUCKETS_MICROS: &[u64] = &[
+    1_000, 2_500, 5_000, 10_000, 25_000, 50_000, 100_000, 250_000, 500_000, 1_000_000, 2_500_000,
+];
+
+/// Counts runs, failures, and a histogram of run latencies, for exporting to
+/// a monitoring system such as Prometheus.
+///
+/// The counters are atomics, so runs can be recorded from any number of
+/// threads without locking.  `InferencePool` records every run in its own
+/// metrics, see `InferencePool::metrics`; other code can record runs with
+/// `time` or `record`.
+///
+/// ```rust,ignore
+/// let metrics = InferenceMetrics::new();
+/// metrics.time(|| session.run(&mut args))?;
+/// let snapshot = metrics.snapshot();
+/// println!("{} runs, {} errors", snapshot.count, snapshot.errors);
+/// ```
+#[derive(Debug)]
+pub struct InferenceMetrics {
+    bounds: Vec<Duration>,
+    // Number of runs in each bucket, not cumulative.  The last bucket counts
+    // the runs slower than every bound.
+    buckets: Vec<AtomicU64>,
+    count: AtomicU64,
+    errors: AtomicU64,
+    latency_sum_micros: AtomicU64,
+}
+
+impl Default for InferenceMetrics {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl InferenceMetrics {
+    /// Creates metrics with latency buckets from 1ms to 2.5s.
+    pub fn new() -> Self {
+        let bounds: Vec<_> = DEFAULT_BUCKETS_MICROS
+            .iter()
+            .map(|micros| Duration::from_micros(*micros))
+            .collect();
+        Self::with_buckets(&bounds)
+    }
+
+    /// Creates metrics with the given upper bounds for the latency buckets.
+    /// The bounds are sorted, and an unbounded bucket is always added.
+    pub fn with_buckets(bounds: &[Duration]) -> Self {
+        let mut bounds = bounds.to_vec();
+        bounds.sort();
+        bounds.dedup();
+        InferenceMetrics {
+            buckets: (0..=bounds.len()).map(|_| AtomicU64::new(0)).collect(),
+            bounds,
+            count: AtomicU64::new(0),
+            errors: AtomicU64::new(0),
+            latency_sum_micros: AtomicU64::new(0),
+        }
+    }
+
+    /// Records a run which took `latency` and succeeded if `success` is true.
+    pub fn record(&self, latency: Duration, success: bool) {
+        let bucket = self
+            .bounds
+            .iter()
+            .position(|bound| latency <= *bound)
+            .unwrap_or(self.bounds.len());
+        self.buckets[bucket].fetch_add(1, Ordering::Relaxed);
+        self.count.fetch_add(1, Ordering::Relaxed);
+        if !success {
+            self.errors.fetch_add(1, Ordering::Relaxed);
+        }
+        self.latency_sum_micros
+            .fetch_add(latency.as_micros() as u64, Ordering::Relaxed);
+    }
+
+    /// Calls `f` and records its latency, and whether it returned an error.
+    pub fn time<R, F: FnOnce() -> Result<R>>(&self, f: F) -> Result<R> {
+        let start = Instant::now();
+        let result = f();
+        self.record(start.elapsed(), result.is_ok());
+        result
+    }
+
+    /// Returns the current values of the counters.  Runs recorded while this
+    /// is called may be partially included.
+    pub fn snapshot(&self) -> MetricsSnapshot {
+        let mut cumulative = 0;
+        let mut buckets = Vec::with_capacity(self.bounds.len());
+        for (bound, bucket) in self.bounds.iter().zip(&self.buckets) {
+            cumulative += bucket.load(Ordering::Relaxed);
+            buckets.push((*bound, cumulative));
+        }
+        MetricsSnapshot {
+            count: self.count.load(Ordering::Relaxed),
+            errors: self.errors.load(Ordering::Relaxed),
+            latency_sum: Duration::from_micros(self.latency_sum_micros.load(Ordering::Relaxed)),
+            buckets,
+        }
+    }
+}
+
+/// The values of the counters of `InferenceMetrics` at some point in time.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MetricsSnapshot {
+    /// Number of runs, including failed ones.
+    pub count: u64,
+    /// Number of failed runs.
+    pub errors: u64,
+    /// Total latency of all runs.
+    pub latency_sum: Duration,
+    /// Upper bound of each latency bucket, and the number of runs which took
+    /// at most that long.  As in Prometheus, the counts are cumulative, and
+    /// the count of the implicit unbounded bucket is `count`.
+    pub buckets: Vec<(Duration, u64)>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn inference_metrics() {
+        let metrics =
+            InferenceMetrics::with_buckets(&[Duration::from_millis(10), Duration::from_millis(1)]);
+        metrics.record(Duration::from_micros(500), true);
+        metrics.record(Duration::from_millis(1), true);
+        metrics.record(Duration::from_millis(5), false);
+        metrics.record(Duration::from_secs(1), true);
+        assert!(metrics
+            .time(|| Err::<(), _>(invalid_arg!("failed")))
+            .is_err());
+        assert_eq!(metrics.time(|| Ok(3)).unwrap(), 3);
+        let snapshot = metrics.snapshot();
+        assert_eq!(snapshot.count, 6);
+        assert_eq!(snapshot.errors, 2);
+        assert!(snapshot.latency_sum >= Duration::from_micros(1_006_500));
+        assert_eq!(snapshot.buckets.len(), 2);
+        assert_eq!(snapshot.buckets[0].0, Duration::from_millis(1));
+        assert_eq!(snapshot.buckets[1].0, Duration::from_millis(10));
+        // The two timed runs are almost certainly faster than 1ms.
+        assert!(snapshot.buckets[0].1 >= 2 && snapshot.buckets[0].1 <= 4);
+        assert_eq!(snapshot.buckets[1].1, 5);
+    }
+}
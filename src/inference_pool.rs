@@ -0,0 +1,192 @@
+use crate::Graph;
+use crate::InferenceMetrics;
+use crate::Result;
+use crate::Session;
+use crate::SessionOptions;
+use crate::SessionRunArgs;
+use std::ops::Deref;
+use std::sync::Condvar;
+use std::sync::Mutex;
+use std::sync::MutexGuard;
+
+/// A pool of identical sessions which dispatches each run to a free session.
+///
+/// A single `Session` can already be run concurrently from multiple threads,
+/// and TensorFlow executes each run on its own inter-op and intra-op thread
+/// pools, so for most servers sharing one `Session` is enough and uses less
+/// memory, since every session holds its own copy of the variables.  A pool
+/// helps when runs are small and latency-sensitive, so that contention
+/// inside a single session dominates.  In that case, give each session
+/// fewer threads in `SessionOptions` so the sessions don't oversubscribe the
+/// CPU.  The `inference_pool` example measures both setups.
+///
+/// All sessions must run the same graph, since the operations in the
+/// `SessionRunArgs` passed to `run` belong to a single graph.
+///
+/// Every call to `run` is recorded in the pool's `metrics`, including the
+/// time spent waiting for a free session.
+///
+/// ```rust,ignore
+/// let pool = InferencePool::from_graph(&graph, &options, 4)?;
+/// // From any number of threads:
+/// let mut args = SessionRunArgs::new();
+/// args.add_feed(&x, 0, &input);
+/// let y = args.request_fetch(&output, 0);
+/// pool.run(&mut args)?;
+/// ```
+#[derive(Debug)]
+pub struct InferencePool {
+    sessions: Vec<Session>,
+    // Indices of the free sessions.  The most recently used session is
+    // handed out first, since its caches are more likely to be warm.
+    free: Mutex<Vec<usize>>,
+    available: Condvar,
+    metrics: InferenceMetrics,
+}
+
+impl InferencePool {
+    /// Creates a pool from sessions over the same graph.  Returns an error if
+    /// `sessions` is empty.
+    pub fn new(sessions: Vec<Session>) -> Result<Self> {
+        if sessions.is_empty() {
+            return Err(invalid_arg!("An InferencePool needs at least one session"));
+        }
+        Ok(InferencePool {
+            free: Mutex::new((0..sessions.len()).rev().collect()),
+            sessions,
+            available: Condvar::new(),
+            metrics: InferenceMetrics::new(),
+        })
+    }
+
+    /// Creates a pool of `size` sessions over `graph`.
+    pub fn from_graph(graph: &Graph, options: &SessionOptions, size: usize) -> Result<Self> {
+        let sessions = (0..size)
+            .map(|_| Session::new(options, graph))
+            .collect::<Result<Vec<_>>>()?;
+        Self::new(sessions)
+    }
+
+    /// Returns the number of sessions in the pool.
+    pub fn size(&self) -> usize {
+        self.sessions.len()
+    }
+
+    /// Waits until a session is free and takes it out of the pool until the
+    /// returned guard is dropped.
+    pub fn acquire(&self) -> PooledSession<'_> {
+        let mut free = self.lock_free();
+        loop {
+            if let Some(index) = free.pop() {
+                return PooledSession { pool: self, index };
+            }
+            free = self.available.wait(free).unwrap_or_else(|e| e.into_inner());
+        }
+    }
+
+    /// Runs the graph on the first free session, waiting for one if all of
+    /// them are busy.  See `Session::run`.
+    pub fn run(&self, args: &mut SessionRunArgs<'_>) -> Result<()> {
+        self.metrics.time(|| self.acquire().run(args))
+    }
+
+    /// Returns the metrics of the runs made with `run`.
+    pub fn metrics(&self) -> &InferenceMetrics {
+        &self.metrics
+    }
+
+    fn lock_free(&self) -> MutexGuard<'_, Vec<usize>> {
+        // The lock is never held while running user code, so a poisoned lock
+        // still holds a consistent free list.
+        self.free.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+/// A session taken out of an `InferencePool`, which is returned to the pool
+/// when this is dropped.
+#[derive(Debug)]
+pub struct PooledSession<'a> {
+    pool: &'a InferencePool,
+    index: usize,
+}
+
+impl<'a> Deref for PooledSession<'a> {
+    type Target = Session;
+
+    fn deref(&self) -> &Session {
+        &self.pool.sessions[self.index]
+    }
+}
+
+impl<'a> Drop for PooledSession<'a> {
+    fn drop(&mut self) {
+        self.pool.lock_free().push(self.index);
+        self.pool.available.notify_one();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::DataType;
+    use crate::Operation;
+    use crate::Shape;
+    use crate::Tensor;
+    use std::sync::Arc;
+    use std::thread;
+
+    fn create_graph() -> (Graph, Operation, Operation) {
+        let mut g = Graph::new();
+        let x = {
+            let mut nd = g.new_operation("Placeholder", "x").unwrap();
+            nd.set_attr_type("dtype", DataType::Float).unwrap();
+            nd.set_attr_shape("shape", &Shape(None)).unwrap();
+            nd.finish().unwrap()
+        };
+        let y = {
+            let mut nd = g.new_operation("Add", "y").unwrap();
+            nd.add_input(x.clone());
+            nd.add_input(x.clone());
+            nd.finish().unwrap()
+        };
+        (g, x, y)
+    }
+
+    #[test]
+    fn inference_pool() {
+        let (graph, x, y) = create_graph();
+        let pool = Arc::new(InferencePool::from_graph(&graph, &SessionOptions::new(), 2).unwrap());
+        assert_eq!(pool.size(), 2);
+        let threads: Vec<_> = (0..8)
+            .map(|i| {
+                let pool = pool.clone();
+                let x = x.clone();
+                let y = y.clone();
+                thread::spawn(move || {
+                    let input = Tensor::from(i as f32);
+                    let mut args = SessionRunArgs::new();
+                    args.add_feed(&x, 0, &input);
+                    let fetch = args.request_fetch(&y, 0);
+                    pool.run(&mut args).unwrap();
+                    let output: Tensor<f32> = args.fetch(fetch).unwrap();
+                    output[0]
+                })
+            })
+            .collect();
+        for (i, thread) in threads.into_iter().enumerate() {
+            assert_eq!(thread.join().unwrap(), 2.0 * i as f32);
+        }
+        let metrics = pool.metrics().snapshot();
+        assert_eq!(metrics.count, 8);
+        assert_eq!(metrics.errors, 0);
+
+        // Held sessions are not handed out again until they are returned.
+        let first = pool.acquire();
+        let second = pool.acquire();
+        assert_ne!(first.index, second.index);
+        drop(first);
+        assert_eq!(pool.acquire().index, 1 - second.index);
+
+        assert!(InferencePool::new(vec![]).is_err());
+    }
+}
@@ -18,8 +18,9 @@
 use half::f16;
 use libc::{c_int, c_uint};
 use num_complex::Complex;
-#[cfg(feature = "experimental_training")]
+use protobuf::Message;
 use protobuf::ProtobufEnum;
+use std::any::Any;
 use std::borrow::Borrow;
 use std::cell::Cell;
 use std::cell::RefCell;
@@ -43,6 +44,7 @@ use std::ops::Index;
 use std::os::raw::c_char;
 use std::ptr;
 use std::slice;
+use std::str::FromStr;
 use std::str::Utf8Error;
 use tensorflow_sys as tf;
 
@@ -187,10 +189,41 @@ pub use crate::scope::*;
 mod session;
 pub use crate::session::*;
 
+mod inference_metrics;
+pub use crate::inference_metrics::*;
+
+mod inference_pool;
+pub use crate::inference_pool::*;
+
+mod fallback_session;
+pub use crate::fallback_session::*;
+
+mod routing_model;
+pub use crate::routing_model::*;
+
+mod ragged_tensor;
+pub use crate::ragged_tensor::*;
+
+mod run_stats;
+pub use crate::run_stats::*;
+
+mod dyn_tensor;
+pub use crate::dyn_tensor::*;
+
+mod sparse_tensor;
+pub use crate::sparse_tensor::*;
+
+#[cfg(all(feature = "tensor_mmap", unix))]
+mod tensor_mmap;
+
 pub mod expr;
 
 pub mod io;
 
+pub mod onnx;
+
+pub mod profiler;
+
 #[cfg(feature = "experimental_training")]
 pub mod ops;
 
@@ -410,7 +443,6 @@ impl Default for DataType {
 }
 
 impl DataType {
-    #[cfg(feature = "experimental_training")]
     // We don't use Into, because we don't want this to be public API.
     fn into_proto(self) -> protos::types::DataType {
         if let Some(d) = protos::types::DataType::from_i32(self.to_int() as i32) {
@@ -421,7 +453,6 @@ impl DataType {
         }
     }
 
-    #[cfg(feature = "experimental_training")]
     // We don't use From, because we don't want this to be public API.
     fn from_proto(proto: protos::types::DataType) -> Self {
         Self::from_int(proto.value() as c_uint)
@@ -935,6 +966,8 @@ pub(crate) trait AnyTensor: Debug {
     fn inner(&self) -> Result<*mut tf::TF_Tensor>;
 
     fn data_type(&self) -> DataType;
+
+    fn as_any(&self) -> &dyn Any;
 }
 
 impl AnyTensor for Box<dyn AnyTensor> {
@@ -947,6 +980,11 @@ impl AnyTensor for Box<dyn AnyTensor> {
         let borrowed: &dyn AnyTensor = self.borrow();
         borrowed.data_type()
     }
+
+    fn as_any(&self) -> &dyn Any {
+        let borrowed: &dyn AnyTensor = self.borrow();
+        borrowed.as_any()
+    }
 }
 
 ////////////////////////
@@ -1304,6 +1342,42 @@ impl<T: TensorType> Tensor<T> {
         Ok(self)
     }
 
+    /// Creates a tensor with the given shape, filled with the values of
+    /// `iter` in row-major order, without collecting them first.  Returns an
+    /// error if `iter` doesn't yield exactly as many values as the tensor has
+    /// elements.
+    ///
+    /// ```
+    /// # use tensorflow::Tensor;
+    /// let a = Tensor::from_iter(&[2, 3], (0..6).map(|i| i as f32 * 0.5))?;
+    /// assert_eq!(&a[..], &[0.0, 0.5, 1.0, 1.5, 2.0, 2.5]);
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    pub fn from_iter<I: IntoIterator<Item = T>>(dims: &[u64], iter: I) -> Result<Self> {
+        let mut tensor = Tensor::new(dims);
+        let mut iter = iter.into_iter();
+        let len = tensor.len();
+        for (i, e) in tensor.iter_mut().enumerate() {
+            match iter.next() {
+                Some(v) => *e = v,
+                None => {
+                    return Err(invalid_arg!(
+                        "iterator yielded {} values, but the tensor has {} elements",
+                        i,
+                        len
+                    ))
+                }
+            }
+        }
+        if iter.next().is_some() {
+            return Err(invalid_arg!(
+                "iterator yielded more than the tensor's {} elements",
+                len
+            ));
+        }
+        Ok(tensor)
+    }
+
     /// Set one single value on the tensor.
     ///
     /// ```
@@ -1368,6 +1442,291 @@ impl<T: TensorType> Tensor<T> {
         Shape(Some(self.dims.iter().map(|d| Some(*d as i64)).collect()))
     }
 
+    /// Returns a copy of the tensor with shape `[dims[0], n]`, where `n` is
+    /// the product of the remaining dimensions.  A scalar becomes a `[1, 1]`
+    /// matrix and a vector `[n]` becomes an `[n, 1]` matrix.
+    ///
+    /// ```
+    /// # use tensorflow::Tensor;
+    /// let a = Tensor::<i32>::new(&[2, 3, 4]);
+    /// assert_eq!(a.flatten_to_2d().dims(), &[2, 12]);
+    /// ```
+    pub fn flatten_to_2d(&self) -> Tensor<T> {
+        let dims = match self.dims.split_first() {
+            Some((first, rest)) => [*first, product(rest)],
+            None => [1, 1],
+        };
+        self.with_dims(&dims)
+    }
+
+    /// Returns a copy of the tensor with all dimensions collapsed into one.
+    ///
+    /// ```
+    /// # use tensorflow::Tensor;
+    /// let a = Tensor::<i32>::new(&[2, 3, 4]);
+    /// assert_eq!(a.flatten_to_1d().dims(), &[24]);
+    /// ```
+    pub fn flatten_to_1d(&self) -> Tensor<T> {
+        self.with_dims(&[self.len() as u64])
+    }
+
+    /// Returns a transposed copy of the tensor, where dimension `i` of the
+    /// result is dimension `perm[i]` of this tensor.  This is the host-side
+    /// equivalent of the `Transpose` op, e.g. `&[0, 3, 1, 2]` converts NHWC
+    /// to NCHW.
+    ///
+    /// Returns an error if `perm` is not a permutation of `0..rank`.
+    ///
+    /// ```
+    /// # use tensorflow::Tensor;
+    /// let a = Tensor::new(&[2, 3]).with_values(&[1, 2, 3, 4, 5, 6])?;
+    /// let b = a.permute_axes(&[1, 0])?;
+    /// assert_eq!(b.dims(), &[3, 2]);
+    /// assert_eq!(&b[..], &[1, 4, 2, 5, 3, 6]);
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    pub fn permute_axes(&self, perm: &[usize]) -> Result<Tensor<T>> {
+        let rank = self.dims.len();
+        let mut seen = vec![false; rank];
+        for &axis in perm {
+            if axis >= rank || seen[axis] {
+                return Err(invalid_arg!(
+                    "{:?} is not a permutation of the axes of a rank {} tensor",
+                    perm,
+                    rank
+                ));
+            }
+            seen[axis] = true;
+        }
+        if perm.len() != rank {
+            return Err(invalid_arg!(
+                "{:?} is not a permutation of the axes of a rank {} tensor",
+                perm,
+                rank
+            ));
+        }
+        let mut strides = vec![1; rank];
+        for i in (1..rank).rev() {
+            strides[i - 1] = strides[i] * self.dims[i] as usize;
+        }
+        let dims: Vec<u64> = perm.iter().map(|&axis| self.dims[axis]).collect();
+        // Strides of the input, in the order of the output's dimensions.
+        let strides: Vec<usize> = perm.iter().map(|&axis| strides[axis]).collect();
+        let mut result = Tensor::<T>::new(&dims);
+        let mut index = vec![0; rank];
+        let mut offset = 0;
+        for value in result.iter_mut() {
+            value.clone_from(&self[offset]);
+            // Advance the output index, updating the input offset to match.
+            for i in (0..rank).rev() {
+                index[i] += 1;
+                offset += strides[i];
+                if (index[i] as u64) < dims[i] {
+                    break;
+                }
+                offset -= strides[i] * index[i];
+                index[i] = 0;
+            }
+        }
+        Ok(result)
+    }
+
+    /// Returns a tensor of the same shape with the bytes of each element
+    /// reinterpreted as a `U`, like `f32::to_bits` but for a whole tensor.
+    ///
+    /// This is a checked version of `mem::transmute`: both types must have
+    /// the same representation in C and Rust and the same size, so the
+    /// element counts line up.  Reinterpreting as `bool` additionally
+    /// requires every byte to be 0 or 1.  Otherwise an error is returned.
+    /// The data is copied, so the original tensor is left untouched.
+    pub fn reinterpret<U: TensorType>(&self) -> Result<Tensor<U>> {
+        if !T::is_repr_c() || !U::is_repr_c() {
+            return Err(invalid_arg!(
+                "Cannot reinterpret {} as {}; both types must have the same representation in C \
+                 and Rust",
+                T::data_type(),
+                U::data_type()
+            ));
+        }
+        if mem::size_of::<T>() != mem::size_of::<U>() {
+            return Err(invalid_arg!(
+                "Cannot reinterpret {} ({} bytes) as {} ({} bytes)",
+                T::data_type(),
+                mem::size_of::<T>(),
+                U::data_type(),
+                mem::size_of::<U>()
+            ));
+        }
+        let num_bytes = self.len() * mem::size_of::<T>();
+        // Safe because T is a plain C type, so its bytes can be read as u8.
+        let bytes = unsafe { slice::from_raw_parts(self.as_ptr() as *const u8, num_bytes) };
+        if U::data_type() == DataType::Bool && bytes.iter().any(|&b| b > 1) {
+            return Err(invalid_arg!(
+                "Cannot reinterpret {} as bool; bytes must be 0 or 1",
+                T::data_type()
+            ));
+        }
+        let mut result = Tensor::<U>::new(&self.dims);
+        // Safe because the sizes match and every bit pattern is valid for U,
+        // which is a plain C type (checked above for bool).
+        unsafe {
+            ptr::copy_nonoverlapping(bytes.as_ptr(), result.as_mut_ptr() as *mut u8, num_bytes);
+        }
+        Ok(result)
+    }
+
+    /// Returns an iterator over the elements in row-major order, along with
+    /// their multi-dimensional indices.
+    ///
+    /// The iterator updates a single index buffer in place, but since items
+    /// can't borrow from the iterator, each index is returned as a copy.  Use
+    /// `for_each_indexed` to avoid that allocation.
+    pub fn indexed_iter(&self) -> IndexedIter<'_, T> {
+        IndexedIter {
+            dims: &self.dims,
+            values: self.iter(),
+            index: vec![0; self.dims.len()],
+        }
+    }
+
+    /// Calls `f` with the index and value of each element in row-major order,
+    /// reusing the same index buffer for every element.
+    pub fn for_each_indexed<F: FnMut(&[u64], &T)>(&self, mut f: F) {
+        let mut index = vec![0; self.dims.len()];
+        for value in self.iter() {
+            f(&index, value);
+            advance_index(&mut index, &self.dims);
+        }
+    }
+
+    /// Randomly permutes the slices of the tensor along its first dimension
+    /// in place, e.g. to shuffle the rows of a small dataset before batching.
+    ///
+    /// `rng` must return uniformly distributed random numbers.  Returns an
+    /// error if the tensor is a scalar.
+    pub fn shuffle_rows<R: FnMut() -> u64>(&mut self, mut rng: R) -> Result<()> {
+        let num_rows = match self.dims.first() {
+            Some(n) => *n as usize,
+            None => return Err(invalid_arg!("Cannot shuffle the rows of a scalar")),
+        };
+        let row_size = product(&self.dims[1..]) as usize;
+        // Fisher-Yates shuffle.
+        for i in (1..num_rows).rev() {
+            let j = (rng() % (i as u64 + 1)) as usize;
+            if i != j {
+                let (head, tail) = self.split_at_mut(i * row_size);
+                head[j * row_size..(j + 1) * row_size].swap_with_slice(&mut tail[..row_size]);
+            }
+        }
+        Ok(())
+    }
+
+    /// Splits the tensor into `num` tensors of equal size along its first
+    /// dimension, e.g. to scatter the result for a batch back to the
+    /// individual requests.
+    ///
+    /// Returns an error if the tensor is a scalar, `num` is zero, or the
+    /// first dimension is not divisible by `num`.
+    pub fn split(&self, num: usize) -> Result<Vec<Tensor<T>>> {
+        let num_rows = match self.dims.first() {
+            Some(n) => *n,
+            None => return Err(invalid_arg!("Cannot split a scalar")),
+        };
+        if num == 0 || num_rows % num as u64 != 0 {
+            return Err(invalid_arg!(
+                "Cannot split a tensor with shape {:?} into {} parts along its first dimension",
+                self.dims,
+                num
+            ));
+        }
+        let mut dims = self.dims.clone();
+        dims[0] = num_rows / num as u64;
+        let chunk_size = product(&dims) as usize;
+        Ok((0..num)
+            .map(|i| {
+                let mut part = Tensor::new(&dims);
+                part.clone_from_slice(&self[i * chunk_size..(i + 1) * chunk_size]);
+                part
+            })
+            .collect())
+    }
+
+    /// Returns a copy of the part of the tensor selected by `indices`, with
+    /// one index per dimension, e.g. to post-process a fetched result.
+    /// Dimensions without an index are taken in full, and dimensions indexed
+    /// with `SliceIndex::At` are removed.  This is the host-side counterpart
+    /// of `ops::strided_slice_ranges`.
+    ///
+    /// ```
+    /// # use tensorflow::SliceIndex;
+    /// # use tensorflow::Tensor;
+    /// let x = Tensor::new(&[2, 3]).with_values(&[1, 2, 3, 4, 5, 6])?;
+    /// let column = x.slice(&[SliceIndex::Full, SliceIndex::At(1)])?;
+    /// assert_eq!(&column[..], &[2, 5]);
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    ///
+    /// Returns an error if there are more indices than dimensions, or an
+    /// index is out of bounds.
+    pub fn slice(&self, indices: &[SliceIndex]) -> Result<Tensor<T>> {
+        if indices.len() > self.dims.len() {
+            return Err(invalid_arg!(
+                "Cannot slice a tensor with shape {:?} with {} indices",
+                self.dims,
+                indices.len()
+            ));
+        }
+        let mut starts = Vec::with_capacity(self.dims.len());
+        let mut lens = Vec::with_capacity(self.dims.len());
+        let mut dims = Vec::new();
+        for (i, size) in self.dims.iter().cloned().enumerate() {
+            let (start, end, keep) = match indices.get(i) {
+                None | Some(SliceIndex::Full) => (0, size, true),
+                Some(SliceIndex::Range(range)) => (range.start, range.end, true),
+                Some(SliceIndex::At(index)) => (*index, index.saturating_add(1), false),
+            };
+            if start > end || end > size {
+                return Err(invalid_arg!(
+                    "Index {:?} is out of bounds for dimension {} of size {}",
+                    indices[i],
+                    i,
+                    size
+                ));
+            }
+            starts.push(start);
+            lens.push(end - start);
+            if keep {
+                dims.push(end - start);
+            }
+        }
+        let mut strides = vec![1; self.dims.len()];
+        for i in (1..self.dims.len()).rev() {
+            strides[i - 1] = strides[i] * self.dims[i];
+        }
+        let mut result = Tensor::new(&dims);
+        let mut position = vec![0; lens.len()];
+        for element in result.iter_mut() {
+            let offset: u64 = position
+                .iter()
+                .zip(&starts)
+                .zip(&strides)
+                .map(|((p, start), stride)| (p + start) * stride)
+                .sum();
+            *element = self[offset as usize].clone();
+            advance_index(&mut position, &lens);
+        }
+        Ok(result)
+    }
+
+    /// Returns a copy of the tensor with the given dimensions, which must have
+    /// the same number of elements.
+    fn with_dims(&self, dims: &[u64]) -> Tensor<T> {
+        debug_assert_eq!(product(dims), self.len() as u64);
+        let mut tensor = Tensor::new(dims);
+        tensor.clone_from_slice(self);
+        tensor
+    }
+
     // Wraps a TF_Tensor. Returns None if types don't match.
     unsafe fn from_tf_tensor(tensor: *mut tf::TF_Tensor) -> Option<Self> {
         let mut dims = Vec::with_capacity(tf::TF_NumDims(tensor) as usize);
@@ -1382,6 +1741,667 @@ impl<T: TensorType> Tensor<T> {
     }
 }
 
+/// One index of the slice taken by `Tensor::slice`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SliceIndex {
+    /// Takes the whole dimension, like `..`.
+    Full,
+    /// Takes the indices from `start` (inclusive) to `end` (exclusive).
+    Range(std::ops::Range<u64>),
+    /// Takes a single index and removes the dimension.
+    At(u64),
+}
+
+impl From<std::ops::RangeFull> for SliceIndex {
+    fn from(_: std::ops::RangeFull) -> Self {
+        SliceIndex::Full
+    }
+}
+
+impl From<std::ops::Range<u64>> for SliceIndex {
+    fn from(range: std::ops::Range<u64>) -> Self {
+        SliceIndex::Range(range)
+    }
+}
+
+impl From<u64> for SliceIndex {
+    fn from(index: u64) -> Self {
+        SliceIndex::At(index)
+    }
+}
+
+/// Returns the size in bytes of the scalars that make up a single element of
+/// type `T`.  This is the unit that has to be byte swapped when converting
+/// between little-endian and big-endian data.
+fn scalar_size<T: TensorType>() -> usize {
+    match T::data_type() {
+        DataType::Complex64 => mem::size_of::<f32>(),
+        DataType::Complex128 => mem::size_of::<f64>(),
+        _ => mem::size_of::<T>(),
+    }
+}
+
+/// Swaps the byte order of each `scalar_size`-byte scalar in `data` if the
+/// host is big-endian, converting between host and little-endian byte order.
+fn swap_to_little_endian(data: &mut [u8], scalar_size: usize) {
+    if cfg!(target_endian = "big") && scalar_size > 1 {
+        for scalar in data.chunks_mut(scalar_size) {
+            scalar.reverse();
+        }
+    }
+}
+
+/// Returns the values stored in the typed fields of a `TensorProto`, such as
+/// `float_val`, as little-endian bytes, or `None` if the values of `dtype`
+/// can't be stored that way.
+fn typed_proto_values(proto: &protos::tensor::TensorProto, dtype: DataType) -> Option<Vec<u8>> {
+    let mut bytes = Vec::new();
+    match dtype {
+        DataType::Float => {
+            for v in proto.get_float_val() {
+                bytes.extend_from_slice(&v.to_bits().to_le_bytes());
+            }
+        }
+        DataType::Double => {
+            for v in proto.get_double_val() {
+                bytes.extend_from_slice(&v.to_bits().to_le_bytes());
+            }
+        }
+        DataType::Complex64 => {
+            for v in proto.get_scomplex_val() {
+                bytes.extend_from_slice(&v.to_bits().to_le_bytes());
+            }
+        }
+        DataType::Complex128 => {
+            for v in proto.get_dcomplex_val() {
+                bytes.extend_from_slice(&v.to_bits().to_le_bytes());
+            }
+        }
+        // Narrower integers are stored in int_val, and 16-bit floats as
+        // their bits in half_val.
+        DataType::Int32 | DataType::QInt32 => {
+            for v in proto.get_int_val() {
+                bytes.extend_from_slice(&v.to_le_bytes());
+            }
+        }
+        DataType::Int16 | DataType::UInt16 | DataType::QInt16 | DataType::QUInt16 => {
+            for v in proto.get_int_val() {
+                bytes.extend_from_slice(&v.to_le_bytes()[..2]);
+            }
+        }
+        DataType::Int8 | DataType::UInt8 | DataType::QInt8 | DataType::QUInt8 => {
+            for v in proto.get_int_val() {
+                bytes.push(v.to_le_bytes()[0]);
+            }
+        }
+        DataType::Half | DataType::BFloat16 => {
+            for v in proto.get_half_val() {
+                bytes.extend_from_slice(&v.to_le_bytes()[..2]);
+            }
+        }
+        DataType::Int64 => {
+            for v in proto.get_int64_val() {
+                bytes.extend_from_slice(&v.to_le_bytes());
+            }
+        }
+        DataType::UInt32 => {
+            for v in proto.get_uint32_val() {
+                bytes.extend_from_slice(&v.to_le_bytes());
+            }
+        }
+        DataType::UInt64 => {
+            for v in proto.get_uint64_val() {
+                bytes.extend_from_slice(&v.to_le_bytes());
+            }
+        }
+        DataType::Bool => bytes.extend(proto.get_bool_val().iter().map(|v| *v as u8)),
+        _ => return None,
+    }
+    Some(bytes)
+}
+
+impl<T: TensorType> Tensor<T> {
+    /// Serializes the tensor as a `TensorProto`.
+    ///
+    /// The values are stored in the proto's `tensor_content` field in
+    /// little-endian byte order regardless of the host's byte order, so the
+    /// result can be read on any platform.  Returns an error for types which
+    /// do not have the same representation in C and Rust, such as `String`.
+    pub fn to_proto_bytes(&self) -> Result<Vec<u8>> {
+        self.to_tensor_proto()?.write_to_bytes().map_err(|e| {
+            Status::new_set_lossy(
+                Code::Internal,
+                &format!("Unable to serialize TensorProto: {}", e),
+            )
+        })
+    }
+
+    /// Converts the tensor to a `TensorProto`, e.g. for the `value` of a
+    /// `Const` node in a `GraphDef`.  See `to_proto_bytes`.
+    pub(crate) fn to_tensor_proto(&self) -> Result<protos::tensor::TensorProto> {
+        if !T::is_repr_c() {
+            return Err(Status::new_set_lossy(
+                Code::Unimplemented,
+                &format!(
+                    "Serializing {} tensors as TensorProto is not supported",
+                    T::data_type()
+                ),
+            ));
+        }
+        let mut content = unsafe {
+            slice::from_raw_parts(self.as_ptr() as *const u8, mem::size_of_val(&self[..]))
+        }
+        .to_vec();
+        swap_to_little_endian(&mut content, scalar_size::<T>());
+        let mut proto = protos::tensor::TensorProto::new();
+        proto.set_dtype(T::data_type().into_proto());
+        proto.set_tensor_shape(self.shape().into_proto());
+        proto.set_tensor_content(content);
+        Ok(proto)
+    }
+
+    /// Deserializes a tensor from a serialized `TensorProto`.
+    ///
+    /// The values may be stored either in the proto's `tensor_content` field,
+    /// which is assumed to be little-endian, which is what TensorFlow writes
+    /// on all supported platforms, or in the field for the type, such as
+    /// `float_val`.  As in TensorFlow, if the typed field has fewer values
+    /// than the tensor, the last value is repeated, and if it is empty, the
+    /// tensor is filled with zeros.  Returns an error if the proto's data type
+    /// does not match `T`, its shape is not fully known, or a `bool` tensor
+    /// has bytes other than 0 and 1.
+    pub fn from_proto_bytes(data: &[u8]) -> Result<Self> {
+        let proto: protos::tensor::TensorProto = protobuf::parse_from_bytes(data)
+            .map_err(|e| invalid_arg!("Invalid serialized TensorProto: {}", e))?;
+        Self::from_tensor_proto(&proto)
+    }
+
+    /// Converts a `TensorProto`, e.g. the `value` of a `Const` node in a
+    /// `GraphDef`, to a tensor.  See `from_proto_bytes`.
+    pub(crate) fn from_tensor_proto(proto: &protos::tensor::TensorProto) -> Result<Self> {
+        let dtype = DataType::from_proto(proto.get_dtype());
+        if dtype != T::data_type() {
+            return Err(invalid_arg!(
+                "TensorProto has type {}, but {} was requested",
+                dtype,
+                T::data_type()
+            ));
+        }
+        if !T::is_repr_c() {
+            return Err(Status::new_set_lossy(
+                Code::Unimplemented,
+                &format!(
+                    "Deserializing {} tensors from TensorProto is not supported",
+                    dtype
+                ),
+            ));
+        }
+        let shape = Shape::from_proto(proto.get_tensor_shape());
+        let mut dims = Vec::new();
+        for dim in shape
+            .0
+            .as_ref()
+            .ok_or_else(|| invalid_arg!("TensorProto has unknown rank"))?
+        {
+            match dim {
+                Some(d) if *d >= 0 => dims.push(*d as u64),
+                _ => return Err(invalid_arg!("TensorProto has unknown shape {}", shape)),
+            }
+        }
+        let mut tensor = Tensor::new(&dims);
+        let size = mem::size_of_val(&tensor[..]);
+        let mut content = proto.get_tensor_content().to_vec();
+        if content.is_empty() && !tensor.is_empty() {
+            content = typed_proto_values(proto, dtype).ok_or_else(|| {
+                Status::new_set_lossy(
+                    Code::Unimplemented,
+                    &format!(
+                        "TensorProto values of type {} must be stored in tensor_content",
+                        dtype
+                    ),
+                )
+            })?;
+            let element_size = mem::size_of::<T>();
+            // Complex values are stored as (real, imaginary) pairs, so the
+            // values must make up whole elements.
+            if !content.chunks_exact(element_size).remainder().is_empty() {
+                return Err(invalid_arg!(
+                    "TensorProto values of type {} do not make up whole elements",
+                    dtype
+                ));
+            }
+            if content.len() < size {
+                let last = if content.is_empty() {
+                    vec![0; element_size]
+                } else {
+                    content[content.len() - element_size..].to_vec()
+                };
+                while content.len() < size {
+                    content.extend_from_slice(&last);
+                }
+            }
+        }
+        if content.len() != size {
+            return Err(invalid_arg!(
+                "TensorProto content has {} bytes, but {} bytes were expected for shape {}",
+                content.len(),
+                size,
+                shape
+            ));
+        }
+        if dtype == DataType::Bool && content.iter().any(|&b| b > 1) {
+            return Err(invalid_arg!(
+                "TensorProto content is not a valid bool tensor; bytes must be 0 or 1"
+            ));
+        }
+        let bytes = unsafe { slice::from_raw_parts_mut(tensor.as_mut_ptr() as *mut u8, size) };
+        bytes.copy_from_slice(&content);
+        swap_to_little_endian(bytes, scalar_size::<T>());
+        Ok(tensor)
+    }
+}
+
+/// Advances a multi-dimensional index to the next element in row-major order.
+fn advance_index(index: &mut [u64], dims: &[u64]) {
+    for i in (0..index.len()).rev() {
+        index[i] += 1;
+        if index[i] < dims[i] {
+            return;
+        }
+        index[i] = 0;
+    }
+}
+
+/// An iterator over the elements of a tensor and their indices, returned by
+/// `Tensor::indexed_iter`.
+#[derive(Debug)]
+pub struct IndexedIter<'a, T: TensorType> {
+    dims: &'a [u64],
+    values: slice::Iter<'a, T>,
+    index: Vec<u64>,
+}
+
+impl<'a, T: TensorType> Iterator for IndexedIter<'a, T> {
+    type Item = (Vec<u64>, &'a T);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let value = self.values.next()?;
+        let index = self.index.clone();
+        advance_index(&mut self.index, self.dims);
+        Some((index, value))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.values.size_hint()
+    }
+}
+
+impl<'a, T: TensorType> ExactSizeIterator for IndexedIter<'a, T> {}
+
+/// The differences between two tensors, returned by `Tensor::diff_report`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DiffReport {
+    /// Number of elements which differ by more than the tolerance.
+    pub num_mismatches: usize,
+    /// Largest absolute difference between two elements.
+    pub max_abs_diff: f64,
+    /// Largest absolute difference relative to the magnitude of the element
+    /// of the reference tensor.
+    pub max_rel_diff: f64,
+    /// Index of the first element, in row-major order, which differs by more
+    /// than the tolerance.
+    pub first_mismatch_index: Option<Vec<u64>>,
+}
+
+impl Display for DiffReport {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} mismatched elements, max abs diff {}, max rel diff {}",
+            self.num_mismatches, self.max_abs_diff, self.max_rel_diff
+        )?;
+        if let Some(index) = &self.first_mismatch_index {
+            write!(f, ", first mismatch at {:?}", index)?;
+        }
+        Ok(())
+    }
+}
+
+impl<T: TensorType> Tensor<T> {
+    /// Formats a 2-D tensor as comma-separated values with one line per row,
+    /// e.g. to inspect a small weight matrix in a spreadsheet.  Values are
+    /// written with `Display` and are not quoted, so this is meant for
+    /// numbers.  Returns an error if the tensor doesn't have rank 2.
+    pub fn to_csv(&self) -> Result<String> {
+        if self.dims.len() != 2 {
+            return Err(invalid_arg!(
+                "Only tensors of rank 2 can be converted to CSV, got dims {:?}",
+                self.dims
+            ));
+        }
+        let mut csv = String::new();
+        for row in 0..self.dims[0] as usize {
+            let columns = self.dims[1] as usize;
+            let values: Vec<_> = self[row * columns..(row + 1) * columns]
+                .iter()
+                .map(ToString::to_string)
+                .collect();
+            csv.push_str(&values.join(","));
+            csv.push('\n');
+        }
+        Ok(csv)
+    }
+
+    /// Creates a 2-D tensor from nested rows, e.g. a small feature matrix
+    /// built without flat indexing.  Returns an error if the rows have
+    /// different lengths.
+    pub fn from_rows(rows: &[Vec<T>]) -> Result<Tensor<T>> {
+        let columns = rows.first().map_or(0, Vec::len);
+        let mut result = Tensor::new(&[rows.len() as u64, columns as u64]);
+        for (i, row) in rows.iter().enumerate() {
+            if row.len() != columns {
+                return Err(invalid_arg!(
+                    "Row {} has {} values, but row 0 has {}",
+                    i,
+                    row.len(),
+                    columns
+                ));
+            }
+            result[i * columns..(i + 1) * columns].clone_from_slice(row);
+        }
+        Ok(result)
+    }
+
+    /// Copies a 2-D tensor into nested rows, the inverse of `from_rows`.
+    /// Returns an error if the tensor doesn't have rank 2.
+    pub fn to_rows(&self) -> Result<Vec<Vec<T>>> {
+        if self.dims.len() != 2 {
+            return Err(invalid_arg!(
+                "Only tensors of rank 2 can be converted to rows, got dims {:?}",
+                self.dims
+            ));
+        }
+        let columns = self.dims[1] as usize;
+        if columns == 0 {
+            return Ok(vec![vec![]; self.dims[0] as usize]);
+        }
+        Ok(self.chunks(columns).map(<[T]>::to_vec).collect())
+    }
+}
+
+impl<T: TensorType + FromStr> Tensor<T> {
+    /// Parses comma-separated values with one line per row into a 2-D
+    /// tensor, the inverse of `to_csv`.  Whitespace around values and empty
+    /// lines are ignored.  Returns an error if a value can't be parsed or the
+    /// rows have different lengths.
+    pub fn from_csv(csv: &str) -> Result<Tensor<T>> {
+        let mut values = Vec::new();
+        let mut rows = 0;
+        let mut columns = None;
+        for (line_number, line) in csv.lines().enumerate() {
+            if line.trim().is_empty() {
+                continue;
+            }
+            let mut row_len = 0;
+            for field in line.split(',') {
+                let value = field.trim().parse::<T>().map_err(|_| {
+                    invalid_arg!(
+                        "Unable to parse {:?} on line {} of CSV as {}",
+                        field.trim(),
+                        line_number + 1,
+                        T::data_type()
+                    )
+                })?;
+                values.push(value);
+                row_len += 1;
+            }
+            match columns {
+                Some(columns) if columns != row_len => {
+                    return Err(invalid_arg!(
+                        "Line {} of CSV has {} values, but previous lines have {}",
+                        line_number + 1,
+                        row_len,
+                        columns
+                    ));
+                }
+                _ => columns = Some(row_len),
+            }
+            rows += 1;
+        }
+        Tensor::new(&[rows, columns.unwrap_or(0)]).with_values(&values)
+    }
+}
+
+impl<T: TensorType + Copy + Into<f64>> Tensor<T> {
+    /// Compares the tensor element-wise with the reference tensor `other`,
+    /// e.g. the output of the same model in Python, and reports the elements
+    /// whose absolute difference is greater than `tol`.
+    ///
+    /// NaNs compare equal to each other, and a NaN compared with a number has
+    /// an infinite difference.  Returns an error if the shapes differ.
+    pub fn diff_report(&self, other: &Tensor<T>, tol: f64) -> Result<DiffReport> {
+        if self.dims != other.dims {
+            return Err(invalid_arg!(
+                "Cannot compare tensors with shapes {:?} and {:?}",
+                self.dims,
+                other.dims
+            ));
+        }
+        let mut report = DiffReport {
+            num_mismatches: 0,
+            max_abs_diff: 0.0,
+            max_rel_diff: 0.0,
+            first_mismatch_index: None,
+        };
+        for (i, (a, b)) in self.iter().zip(other.iter()).enumerate() {
+            let (a, b): (f64, f64) = ((*a).into(), (*b).into());
+            if a == b || (a.is_nan() && b.is_nan()) {
+                continue;
+            }
+            let abs_diff = (a - b).abs();
+            let abs_diff = if abs_diff.is_nan() {
+                f64::INFINITY
+            } else {
+                abs_diff
+            };
+            let rel_diff = if b == 0.0 {
+                f64::INFINITY
+            } else {
+                abs_diff / b.abs()
+            };
+            report.max_abs_diff = report.max_abs_diff.max(abs_diff);
+            report.max_rel_diff = report.max_rel_diff.max(rel_diff);
+            if abs_diff > tol {
+                report.num_mismatches += 1;
+                if report.first_mismatch_index.is_none() {
+                    let mut index = vec![0; self.dims.len()];
+                    let mut rest = i as u64;
+                    for (j, dim) in self.dims.iter().enumerate().rev() {
+                        index[j] = rest % dim;
+                        rest /= dim;
+                    }
+                    report.first_mismatch_index = Some(index);
+                }
+            }
+        }
+        Ok(report)
+    }
+}
+
+impl<T: TensorType + Copy + Into<i64>> Tensor<T> {
+    /// Counts the occurrences of each value in `[0, num_bins)` on the host,
+    /// e.g. to check the class balance of a batch of labels.  Other values
+    /// are ignored.  Use `ops::bincount` to count in the graph instead.
+    pub fn bincount(&self, num_bins: usize) -> Tensor<i64> {
+        let mut counts = Tensor::new(&[num_bins as u64]);
+        for value in self.iter() {
+            let value: i64 = (*value).into();
+            if value >= 0 && (value as u64) < num_bins as u64 {
+                counts[value as usize] += 1;
+            }
+        }
+        counts
+    }
+}
+
+/// Checks that `mean` and `std` each have either one value, or one value per
+/// channel, i.e. per element of the last dimension of `dims`.  Returns the
+/// number of channels the values are cycled over.
+fn check_channel_params(dims: &[u64], mean: &[f32], std: &[f32]) -> Result<usize> {
+    let channels = dims.last().cloned().unwrap_or(1) as usize;
+    for (name, values) in &[("mean", mean), ("std", std)] {
+        if values.len() != 1 && values.len() != channels {
+            return Err(invalid_arg!(
+                "{} must have 1 or {} values, but has {}",
+                name,
+                channels,
+                values.len()
+            ));
+        }
+    }
+    if std.contains(&0.0) {
+        return Err(invalid_arg!("std must not contain zeros"));
+    }
+    Ok(channels.max(1))
+}
+
+impl Tensor<u8> {
+    /// Converts pixel values in `[0, 255]` to `f32`, computing
+    /// `(x / 255 - mean) / std` for each element.
+    ///
+    /// `mean` and `std` have either a single value, or one value per channel,
+    /// which is the last dimension, as in NHWC images.  For example, the
+    /// ImageNet statistics are `&[0.485, 0.456, 0.406]` and
+    /// `&[0.229, 0.224, 0.225]`.
+    pub fn to_normalized_f32(&self, mean: &[f32], std: &[f32]) -> Result<Tensor<f32>> {
+        let channels = check_channel_params(&self.dims, mean, std)?;
+        let mut result = Tensor::new(&self.dims);
+        for (i, (out, x)) in result.iter_mut().zip(self.iter()).enumerate() {
+            let c = i % channels;
+            let mean = mean[c % mean.len()];
+            let std = std[c % std.len()];
+            *out = (f32::from(*x) / 255.0 - mean) / std;
+        }
+        Ok(result)
+    }
+}
+
+impl Tensor<f32> {
+    /// The inverse of `Tensor::<u8>::to_normalized_f32`, computing
+    /// `(x * std + mean) * 255` for each element, rounded to the nearest
+    /// integer and clamped to `[0, 255]`.  NaNs become 0.
+    pub fn to_u8_clamped(&self, mean: &[f32], std: &[f32]) -> Result<Tensor<u8>> {
+        let channels = check_channel_params(&self.dims, mean, std)?;
+        let mut result = Tensor::new(&self.dims);
+        for (i, (out, x)) in result.iter_mut().zip(self.iter()).enumerate() {
+            let c = i % channels;
+            let mean = mean[c % mean.len()];
+            let std = std[c % std.len()];
+            let pixel = ((x * std + mean) * 255.0).round();
+            // Comparisons with NaN are false, so NaN maps to 0.
+            *out = if pixel >= 255.0 {
+                255
+            } else if pixel > 0.0 {
+                pixel as u8
+            } else {
+                0
+            };
+        }
+        Ok(result)
+    }
+
+    /// Computes the softmax over the last dimension on the host, e.g. to turn
+    /// a few fetched logits into probabilities without adding a `Softmax` to
+    /// the graph.  Each row along the last dimension sums to 1, and a 1-D
+    /// tensor is a single row.  The maximum of each row is subtracted before
+    /// exponentiating, so large logits don't overflow.
+    pub fn softmax_rows(&self) -> Tensor<f32> {
+        let mut result = Tensor::new(&self.dims);
+        let row_len = self.dims.last().cloned().unwrap_or(1) as usize;
+        if row_len == 0 {
+            return result;
+        }
+        for (out, row) in result.chunks_mut(row_len).zip(self.chunks(row_len)) {
+            let max = row.iter().cloned().fold(f32::NEG_INFINITY, f32::max);
+            let mut sum = 0.0;
+            for (o, x) in out.iter_mut().zip(row) {
+                *o = (x - max).exp();
+                sum += *o;
+            }
+            for o in out.iter_mut() {
+                *o /= sum;
+            }
+        }
+        result
+    }
+}
+
+impl Tensor<bool> {
+    /// Creates a tensor from bits packed into 64-bit words, e.g. a large mask
+    /// kept as a bitset on the host.  Element `i`, in row-major order, is bit
+    /// `i % 64` of `bits[i / 64]`, counting from the least significant bit.
+    ///
+    /// Returns an error if `bits` has fewer words than needed for `dims`.
+    /// Extra words and the unused high bits of the last word are ignored.
+    pub fn from_bits(dims: &[u64], bits: &[u64]) -> Result<Tensor<bool>> {
+        let mut result = Tensor::new(dims);
+        let words = result.chunks(64).len();
+        if bits.len() < words {
+            return Err(invalid_arg!(
+                "{} elements need {} words of bits, but only {} were given",
+                result.len(),
+                words,
+                bits.len()
+            ));
+        }
+        for (chunk, word) in result.chunks_mut(64).zip(bits) {
+            for (i, element) in chunk.iter_mut().enumerate() {
+                *element = (word >> i) & 1 != 0;
+            }
+        }
+        Ok(result)
+    }
+
+    /// Packs the elements into 64-bit words, in the layout accepted by
+    /// `from_bits`.  The unused high bits of the last word are zero.
+    pub fn to_bits(&self) -> Vec<u64> {
+        self.chunks(64)
+            .map(|chunk| {
+                chunk
+                    .iter()
+                    .enumerate()
+                    .fold(0, |word, (i, element)| word | (u64::from(*element) << i))
+            })
+            .collect()
+    }
+}
+
+macro_rules! float_tensor_impl {
+    ($float_type:ty, $x:ident => $is_finite:expr) => {
+        impl Tensor<$float_type> {
+            /// Replaces every NaN and infinite element with `value`, e.g. to
+            /// sanitize user-provided data before feeding it to a model.
+            /// Returns the number of elements which were replaced.
+            pub fn replace_non_finite(&mut self, value: $float_type) -> usize {
+                let mut replaced = 0;
+                for element in self.iter_mut() {
+                    let $x = *element;
+                    if !$is_finite {
+                        *element = value;
+                        replaced += 1;
+                    }
+                }
+                replaced
+            }
+        }
+    };
+}
+
+float_tensor_impl!(f16, x => x.is_finite());
+float_tensor_impl!(f32, x => x.is_finite());
+float_tensor_impl!(f64, x => x.is_finite());
+float_tensor_impl!(BFloat16, x => Into::<f32>::into(x).is_finite());
+
 impl<T: TensorType> AnyTensor for Tensor<T> {
     fn inner(&self) -> Result<*mut tf::TF_Tensor> {
         self.inner.as_mut_ptr(&self.dims)
@@ -1390,6 +2410,10 @@ impl<T: TensorType> AnyTensor for Tensor<T> {
     fn data_type(&self) -> DataType {
         T::data_type()
     }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
 }
 
 impl<T: TensorType> Deref for Tensor<T> {
@@ -1560,7 +2584,30 @@ impl Shape {
         }
     }
 
-    #[cfg(feature = "experimental_training")]
+    /// Returns true if some tensor could have both this shape and `other`,
+    /// i.e. if the ranks are equal or unknown and each pair of dimensions is
+    /// equal or has an unknown member.
+    ///
+    /// ```
+    /// # use tensorflow::Shape;
+    /// let batch = Shape::new(Some(vec![None, Some(128)]));
+    /// assert!(batch.is_compatible_with(&Shape::from(&[32i64, 128][..])));
+    /// assert!(!batch.is_compatible_with(&Shape::from(&[32i64, 256][..])));
+    /// assert!(batch.is_compatible_with(&Shape::new(None)));
+    /// ```
+    pub fn is_compatible_with(&self, other: &Shape) -> bool {
+        match (&self.0, &other.0) {
+            (Some(a), Some(b)) => {
+                a.len() == b.len()
+                    && a.iter().zip(b).all(|(a, b)| match (a, b) {
+                        (Some(a), Some(b)) => a == b,
+                        _ => true,
+                    })
+            }
+            _ => true,
+        }
+    }
+
     // We don't use Into, because we don't want this to be public API.
     fn into_proto(self) -> protos::tensor_shape::TensorShapeProto {
         match self.0 {
@@ -1586,7 +2633,6 @@ impl Shape {
         }
     }
 
-    #[cfg(feature = "experimental_training")]
     // We don't use Into, because we don't want this to be public API.
     fn from_proto(proto: &protos::tensor_shape::TensorShapeProto) -> Self {
         Shape(if proto.get_unknown_rank() {
@@ -1927,6 +2973,551 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_indexed_iter() {
+        let t = Tensor::new(&[2, 1, 3])
+            .with_values(&[0i32, 1, 2, 3, 4, 5])
+            .unwrap();
+        let items: Vec<_> = t.indexed_iter().collect();
+        assert_eq!(items.len(), 6);
+        assert_eq!(items[0], (vec![0, 0, 0], &0));
+        assert_eq!(items[2], (vec![0, 0, 2], &2));
+        assert_eq!(items[3], (vec![1, 0, 0], &3));
+        assert_eq!(items[5], (vec![1, 0, 2], &5));
+        assert_eq!(t.indexed_iter().len(), 6);
+
+        let mut visited = vec![];
+        t.for_each_indexed(|index, value| visited.push((index.to_vec(), *value)));
+        let expected: Vec<_> = items
+            .iter()
+            .map(|(index, value)| (index.clone(), **value))
+            .collect();
+        assert_eq!(visited, expected);
+
+        let scalar = Tensor::from(7i32);
+        assert_eq!(scalar.indexed_iter().collect::<Vec<_>>(), [(vec![], &7)]);
+        assert_eq!(Tensor::<i32>::new(&[2, 0]).indexed_iter().count(), 0);
+    }
+
+    #[test]
+    fn test_replace_non_finite() {
+        let mut t = Tensor::new(&[5])
+            .with_values(&[1.0f32, f32::NAN, f32::INFINITY, -2.0, f32::NEG_INFINITY])
+            .unwrap();
+        assert_eq!(t.replace_non_finite(0.0), 3);
+        assert_eq!(&t[..], &[1.0, 0.0, 0.0, -2.0, 0.0]);
+        assert_eq!(t.replace_non_finite(0.0), 0);
+
+        let mut t = Tensor::new(&[2]).with_values(&[f64::NAN, 3.0]).unwrap();
+        assert_eq!(t.replace_non_finite(-1.0), 1);
+        assert_eq!(&t[..], &[-1.0, 3.0]);
+
+        let mut t = Tensor::new(&[2])
+            .with_values(&[BFloat16::from(f32::INFINITY), BFloat16::from(0.5)])
+            .unwrap();
+        assert_eq!(t.replace_non_finite(BFloat16::from(1.0)), 1);
+        assert_eq!(&t[..], &[BFloat16::from(1.0), BFloat16::from(0.5)]);
+    }
+
+    #[test]
+    fn test_reinterpret() {
+        let t = Tensor::new(&[2, 1]).with_values(&[1.0f32, -2.0]).unwrap();
+        let bits = t.reinterpret::<u32>().unwrap();
+        assert_eq!(bits.dims(), &[2, 1]);
+        assert_eq!(&bits[..], &[1.0f32.to_bits(), (-2.0f32).to_bits()]);
+        assert_eq!(bits.reinterpret::<f32>().unwrap(), t);
+
+        let bytes = Tensor::new(&[3]).with_values(&[0u8, 1, 1]).unwrap();
+        assert_eq!(
+            &bytes.reinterpret::<bool>().unwrap()[..],
+            &[false, true, true]
+        );
+        let bytes = Tensor::new(&[1]).with_values(&[2u8]).unwrap();
+        assert_eq!(
+            bytes.reinterpret::<bool>().unwrap_err().code(),
+            Code::InvalidArgument
+        );
+        assert_eq!(
+            t.reinterpret::<u64>().unwrap_err().code(),
+            Code::InvalidArgument
+        );
+        assert_eq!(
+            t.reinterpret::<String>().unwrap_err().code(),
+            Code::InvalidArgument
+        );
+    }
+
+    #[test]
+    fn test_from_iter() {
+        let t = Tensor::from_iter(&[2, 2], (1..5).map(|i| i * 10)).unwrap();
+        assert_eq!(t.dims(), &[2, 2]);
+        assert_eq!(&t[..], &[10, 20, 30, 40]);
+        let t = Tensor::from_iter(&[2], vec!["a".to_string(), "b".to_string()]).unwrap();
+        assert_eq!(&t[..], &["a", "b"]);
+        assert_eq!(
+            Tensor::from_iter(&[2, 2], 0..3).unwrap_err().code(),
+            Code::InvalidArgument
+        );
+        assert_eq!(
+            Tensor::from_iter(&[2, 2], 0..5).unwrap_err().code(),
+            Code::InvalidArgument
+        );
+    }
+
+    #[test]
+    fn test_shuffle_rows() {
+        use random::Source;
+
+        let mut source = random::default().seed([1, 2]);
+        let mut t = Tensor::new(&[5, 2])
+            .with_values(&[0i32, 1, 2, 3, 4, 5, 6, 7, 8, 9])
+            .unwrap();
+        t.shuffle_rows(|| source.read_u64()).unwrap();
+        assert_eq!(t.dims(), &[5, 2]);
+        let mut rows: Vec<_> = t.chunks(2).map(|row| row.to_vec()).collect();
+        rows.sort();
+        assert_eq!(rows, [[0, 1], [2, 3], [4, 5], [6, 7], [8, 9]]);
+
+        // A generator which always returns 0 makes the shuffle deterministic.
+        let mut t = Tensor::new(&[3]).with_values(&[1i32, 2, 3]).unwrap();
+        let mut n = 0;
+        t.shuffle_rows(|| {
+            n += 1;
+            0
+        })
+        .unwrap();
+        assert_eq!(n, 2);
+        assert_eq!(&t[..], &[2, 3, 1]);
+
+        assert_eq!(
+            Tensor::from(1i32).shuffle_rows(|| 0).unwrap_err().code(),
+            Code::InvalidArgument
+        );
+    }
+
+    #[test]
+    fn test_split() {
+        let t = Tensor::new(&[4, 2])
+            .with_values(&[0i32, 1, 2, 3, 4, 5, 6, 7])
+            .unwrap();
+        let parts = t.split(2).unwrap();
+        assert_eq!(parts.len(), 2);
+        assert_eq!(parts[0].dims(), &[2, 2]);
+        assert_eq!(&parts[0][..], &[0, 1, 2, 3]);
+        assert_eq!(&parts[1][..], &[4, 5, 6, 7]);
+        assert_eq!(t.split(4).unwrap()[3].dims(), &[1, 2]);
+
+        assert_eq!(t.split(3).unwrap_err().code(), Code::InvalidArgument);
+        assert_eq!(t.split(0).unwrap_err().code(), Code::InvalidArgument);
+        assert_eq!(
+            Tensor::from(1i32).split(1).unwrap_err().code(),
+            Code::InvalidArgument
+        );
+        let empty = Tensor::<f32>::new(&[0, 3]).split(2).unwrap();
+        assert_eq!(empty[1].dims(), &[0, 3]);
+    }
+
+    #[test]
+    fn test_slice() {
+        let values: Vec<i32> = (0..12).collect();
+        let x = Tensor::new(&[3, 4]).with_values(&values).unwrap();
+        let rows = x.slice(&[(1..3).into(), 2.into()]).unwrap();
+        assert_eq!(rows.dims(), &[2]);
+        assert_eq!(&rows[..], &[6, 10]);
+        let columns = x.slice(&[(..).into(), (1..3).into()]).unwrap();
+        assert_eq!(columns.dims(), &[3, 2]);
+        assert_eq!(&columns[..], &[1, 2, 5, 6, 9, 10]);
+        let row = x.slice(&[SliceIndex::At(1)]).unwrap();
+        assert_eq!(row.dims(), &[4]);
+        assert_eq!(&row[..], &[4, 5, 6, 7]);
+        assert_eq!(x.slice(&[]).unwrap(), x);
+        let element = x.slice(&[2.into(), 3.into()]).unwrap();
+        assert_eq!(element.dims(), &[] as &[u64]);
+        assert_eq!(element[0], 11);
+        assert_eq!(x.slice(&[(1..1).into()]).unwrap().dims(), &[0, 4]);
+        for indices in &[
+            vec![SliceIndex::Range(2..5)],
+            vec![SliceIndex::At(3)],
+            vec![SliceIndex::Full, SliceIndex::Full, SliceIndex::Full],
+        ] {
+            assert_eq!(x.slice(indices).unwrap_err().code(), Code::InvalidArgument);
+        }
+    }
+
+    #[test]
+    fn test_softmax_rows() {
+        let logits = Tensor::new(&[2, 3])
+            .with_values(&[1.0f32, 2.0, 3.0, 1000.0, 1000.0, 1000.0])
+            .unwrap();
+        let probs = logits.softmax_rows();
+        assert_eq!(probs.dims(), &[2, 3]);
+        let total: f32 = [1.0f32, 2.0, 3.0].iter().map(|x| x.exp()).sum();
+        for (p, x) in probs[..3].iter().zip(&[1.0f32, 2.0, 3.0]) {
+            assert!((p - x.exp() / total).abs() < 1e-6);
+        }
+        for p in &probs[3..] {
+            assert!((p - 1.0 / 3.0).abs() < 1e-6);
+        }
+
+        let vector = Tensor::new(&[2]).with_values(&[0.0f32, 0.0]).unwrap();
+        assert_eq!(&vector.softmax_rows()[..], &[0.5, 0.5]);
+        assert_eq!(Tensor::<f32>::new(&[2, 0]).softmax_rows().dims(), &[2, 0]);
+    }
+
+    #[test]
+    fn test_bincount() {
+        let labels = Tensor::new(&[2, 3])
+            .with_values(&[1i64, 3, 1, -1, 7, 0])
+            .unwrap();
+        let counts = labels.bincount(4);
+        assert_eq!(counts.dims(), &[4]);
+        assert_eq!(&counts[..], &[1, 2, 0, 1]);
+        assert_eq!(&Tensor::from(2u8).bincount(3)[..], &[0, 0, 1]);
+        assert_eq!(labels.bincount(0).len(), 0);
+    }
+
+    #[test]
+    fn test_bool_bits() {
+        let bits = [0x8000_0000_0000_0005, 0b10];
+        let mask = Tensor::<bool>::from_bits(&[2, 33], &bits).unwrap();
+        assert_eq!(mask.dims(), &[2, 33]);
+        let set: Vec<usize> = (0..mask.len()).filter(|i| mask[*i]).collect();
+        assert_eq!(set, [0, 2, 63, 65]);
+        assert_eq!(mask.to_bits(), bits);
+        assert_eq!(
+            Tensor::<bool>::from_bits(&[65], &bits[..1])
+                .unwrap_err()
+                .code(),
+            Code::InvalidArgument
+        );
+        // Bits beyond the last element are ignored when packing back.
+        let short = Tensor::<bool>::from_bits(&[3], &[0xff]).unwrap();
+        assert_eq!(&short[..], &[true, true, true]);
+        assert_eq!(short.to_bits(), [0b111]);
+        assert!(Tensor::<bool>::new(&[0]).to_bits().is_empty());
+    }
+
+    #[test]
+    fn test_csv() {
+        let x = Tensor::new(&[2, 3])
+            .with_values(&[1.5f32, -2.0, 3.25, 0.0, 5.0, 6.0])
+            .unwrap();
+        let csv = x.to_csv().unwrap();
+        assert_eq!(csv, "1.5,-2,3.25\n0,5,6\n");
+        assert_eq!(Tensor::<f32>::from_csv(&csv).unwrap(), x);
+        assert_eq!(
+            Tensor::<i32>::from_csv(" 1, 2\r\n\n3 ,4\n").unwrap(),
+            Tensor::new(&[2, 2]).with_values(&[1, 2, 3, 4]).unwrap()
+        );
+        assert_eq!(Tensor::<i32>::from_csv("").unwrap().dims(), &[0, 0]);
+        assert_eq!(
+            Tensor::<f32>::new(&[3]).to_csv().unwrap_err().code(),
+            Code::InvalidArgument
+        );
+        assert_eq!(
+            Tensor::<i32>::from_csv("1,2\n3\n").unwrap_err().code(),
+            Code::InvalidArgument
+        );
+        assert_eq!(
+            Tensor::<i32>::from_csv("1,x\n").unwrap_err().code(),
+            Code::InvalidArgument
+        );
+    }
+
+    #[test]
+    fn test_rows() {
+        let rows = vec![vec![1, 2, 3], vec![4, 5, 6]];
+        let x = Tensor::<i32>::from_rows(&rows).unwrap();
+        assert_eq!(
+            x,
+            Tensor::new(&[2, 3])
+                .with_values(&[1, 2, 3, 4, 5, 6])
+                .unwrap()
+        );
+        assert_eq!(x.to_rows().unwrap(), rows);
+        assert_eq!(Tensor::<i32>::from_rows(&[]).unwrap().dims(), &[0, 0]);
+        let empty_rows = vec![vec![], vec![]];
+        assert_eq!(
+            Tensor::<i32>::from_rows(&empty_rows)
+                .unwrap()
+                .to_rows()
+                .unwrap(),
+            empty_rows
+        );
+        assert_eq!(
+            Tensor::<i32>::from_rows(&[vec![1, 2], vec![3]])
+                .unwrap_err()
+                .code(),
+            Code::InvalidArgument
+        );
+        assert_eq!(
+            Tensor::<i32>::new(&[4]).to_rows().unwrap_err().code(),
+            Code::InvalidArgument
+        );
+    }
+
+    #[test]
+    fn test_normalized_f32() {
+        let pixels = Tensor::new(&[1, 2, 2])
+            .with_values(&[0u8, 255, 51, 102])
+            .unwrap();
+        let assert_close = |actual: &Tensor<f32>, expected: &[f32]| {
+            assert_eq!(actual.len(), expected.len());
+            for (a, e) in actual.iter().zip(expected) {
+                assert!((a - e).abs() < 1e-5, "{:?} vs {:?}", actual, expected);
+            }
+        };
+        let normalized = pixels.to_normalized_f32(&[0.5], &[0.5]).unwrap();
+        assert_eq!(normalized.dims(), &[1, 2, 2]);
+        assert_close(&normalized, &[-1.0, 1.0, -0.6, -0.2]);
+        assert_eq!(normalized.to_u8_clamped(&[0.5], &[0.5]).unwrap(), pixels);
+
+        let per_channel = pixels.to_normalized_f32(&[0.0, 0.2], &[1.0, 0.4]).unwrap();
+        assert_close(&per_channel, &[0.0, 2.0, 0.2, 0.5]);
+        assert_eq!(
+            per_channel.to_u8_clamped(&[0.0, 0.2], &[1.0, 0.4]).unwrap(),
+            pixels
+        );
+
+        let out_of_range = Tensor::new(&[4])
+            .with_values(&[-0.5f32, 1.5, f32::NAN, 0.5])
+            .unwrap();
+        assert_eq!(
+            &out_of_range.to_u8_clamped(&[0.0], &[1.0]).unwrap()[..],
+            &[0, 255, 0, 128]
+        );
+        assert_eq!(
+            pixels
+                .to_normalized_f32(&[0.0; 3], &[1.0])
+                .unwrap_err()
+                .code(),
+            Code::InvalidArgument
+        );
+        assert!(pixels.to_normalized_f32(&[0.0], &[0.0]).is_err());
+    }
+
+    #[test]
+    fn test_diff_report() {
+        let reference = Tensor::new(&[2, 3])
+            .with_values(&[1.0f32, 2.0, 0.0, 4.0, f32::NAN, 6.0])
+            .unwrap();
+        let actual = Tensor::new(&[2, 3])
+            .with_values(&[1.0f32, 2.5, 0.0, 4.0, f32::NAN, 6.001])
+            .unwrap();
+        let report = actual.diff_report(&reference, 0.01).unwrap();
+        assert_eq!(report.num_mismatches, 1);
+        assert_eq!(report.max_abs_diff, 0.5);
+        assert_eq!(report.max_rel_diff, 0.25);
+        assert_eq!(report.first_mismatch_index, Some(vec![0, 1]));
+        assert!(report.to_string().contains("first mismatch at [0, 1]"));
+
+        let report = actual.diff_report(&reference, 1.0).unwrap();
+        assert_eq!(report.num_mismatches, 0);
+        assert_eq!(report.first_mismatch_index, None);
+
+        let nan = Tensor::new(&[2, 3]).with_values(&[f32::NAN; 6]).unwrap();
+        let report = nan.diff_report(&reference, 1.0).unwrap();
+        assert_eq!(report.num_mismatches, 5);
+        assert_eq!(report.max_abs_diff, f64::INFINITY);
+        assert_eq!(report.first_mismatch_index, Some(vec![0, 0]));
+
+        let ints = Tensor::new(&[2]).with_values(&[1i32, 2]).unwrap();
+        assert_eq!(ints.diff_report(&ints, 0.0).unwrap().num_mismatches, 0);
+        assert_eq!(
+            ints.diff_report(&Tensor::new(&[3]), 0.0)
+                .unwrap_err()
+                .code(),
+            Code::InvalidArgument
+        );
+    }
+
+    #[test]
+    fn test_permute_axes() {
+        let values: Vec<i32> = (0..24).collect();
+        let a = Tensor::new(&[2, 3, 4]).with_values(&values).unwrap();
+        let b = a.permute_axes(&[2, 0, 1]).unwrap();
+        assert_eq!(b.dims(), &[4, 2, 3]);
+        for i in 0..2 {
+            for j in 0..3 {
+                for k in 0..4 {
+                    assert_eq!(b.get(&[k, i, j]), a.get(&[i, j, k]));
+                }
+            }
+        }
+        assert_eq!(b.permute_axes(&[1, 2, 0]).unwrap(), a);
+        assert_eq!(a.permute_axes(&[0, 1, 2]).unwrap(), a);
+        assert_eq!(
+            Tensor::from(1i32).permute_axes(&[]).unwrap(),
+            Tensor::from(1i32)
+        );
+        assert_eq!(
+            Tensor::<i32>::new(&[2, 0])
+                .permute_axes(&[1, 0])
+                .unwrap()
+                .dims(),
+            &[0, 2]
+        );
+        for perm in &[&[0, 1][..], &[0, 1, 1], &[0, 1, 3], &[0, 1, 2, 3]] {
+            assert_eq!(
+                a.permute_axes(perm).unwrap_err().code(),
+                Code::InvalidArgument
+            );
+        }
+    }
+
+    #[test]
+    fn test_flatten() {
+        let values: Vec<i32> = (0..24).collect();
+        let a = Tensor::new(&[2, 3, 4]).with_values(&values).unwrap();
+        let b = a.flatten_to_2d();
+        assert_eq!(b.dims(), &[2, 12]);
+        assert_eq!(&b[..], &values[..]);
+        assert_eq!(b.get(&[1, 0]), 12);
+        let c = a.flatten_to_1d();
+        assert_eq!(c.dims(), &[24]);
+        assert_eq!(&c[..], &values[..]);
+        assert_eq!(Tensor::from(5i32).flatten_to_2d().dims(), &[1, 1]);
+        assert_eq!(Tensor::from(5i32).flatten_to_1d().dims(), &[1]);
+        assert_eq!(Tensor::<i32>::new(&[3]).flatten_to_2d().dims(), &[3, 1]);
+        assert_eq!(Tensor::<i32>::new(&[0, 3]).flatten_to_1d().dims(), &[0]);
+        let strings = Tensor::new(&[1, 2])
+            .with_values(&["a".to_string(), "b".to_string()])
+            .unwrap();
+        assert_eq!(&strings.flatten_to_1d()[..], &strings[..]);
+    }
+
+    #[test]
+    fn test_tensor_from_proto_bytes() {
+        let mut content = Vec::new();
+        for x in &[1.5f32, -2.0, 3.25, 1e10] {
+            content.extend_from_slice(&x.to_bits().to_le_bytes());
+        }
+        let mut proto = protos::tensor::TensorProto::new();
+        proto.set_dtype(protos::types::DataType::DT_FLOAT);
+        proto.set_tensor_shape(Shape::from(&[2, 2]).into_proto());
+        proto.set_tensor_content(content.clone());
+        let data = proto.write_to_bytes().unwrap();
+        let tensor = Tensor::<f32>::from_proto_bytes(&data).unwrap();
+        assert_eq!(tensor.dims(), &[2, 2]);
+        assert_eq!(&tensor[..], &[1.5f32, -2.0, 3.25, 1e10]);
+        let roundtrip: protos::tensor::TensorProto =
+            protobuf::parse_from_bytes(&tensor.to_proto_bytes().unwrap()).unwrap();
+        assert_eq!(roundtrip.get_tensor_content(), &content[..]);
+
+        assert_eq!(
+            Tensor::<i32>::from_proto_bytes(&data).unwrap_err().code(),
+            Code::InvalidArgument
+        );
+        proto.set_tensor_content(content[..12].to_vec());
+        let data = proto.write_to_bytes().unwrap();
+        assert_eq!(
+            Tensor::<f32>::from_proto_bytes(&data).unwrap_err().code(),
+            Code::InvalidArgument
+        );
+
+        let mut proto = protos::tensor::TensorProto::new();
+        proto.set_dtype(protos::types::DataType::DT_BOOL);
+        proto.set_tensor_shape(Shape::from(&[3]).into_proto());
+        proto.set_tensor_content(vec![0, 1, 1]);
+        let data = proto.write_to_bytes().unwrap();
+        let tensor = Tensor::<bool>::from_proto_bytes(&data).unwrap();
+        assert_eq!(&tensor[..], &[false, true, true]);
+        proto.set_tensor_content(vec![0, 2, 1]);
+        let data = proto.write_to_bytes().unwrap();
+        assert_eq!(
+            Tensor::<bool>::from_proto_bytes(&data).unwrap_err().code(),
+            Code::InvalidArgument
+        );
+    }
+
+    #[test]
+    fn test_tensor_from_typed_proto_values() {
+        let mut proto = protos::tensor::TensorProto::new();
+        proto.set_dtype(protos::types::DataType::DT_FLOAT);
+        proto.set_tensor_shape(Shape::from(&[2, 2]).into_proto());
+        proto.set_float_val(vec![1.5, -2.0, 3.25, 1e10]);
+        let tensor = Tensor::<f32>::from_tensor_proto(&proto).unwrap();
+        assert_eq!(&tensor[..], &[1.5f32, -2.0, 3.25, 1e10]);
+        assert_eq!(
+            Tensor::<f32>::from_tensor_proto(&tensor.to_tensor_proto().unwrap()).unwrap(),
+            tensor
+        );
+
+        // The last value is repeated, and no values means zeros.
+        proto.set_float_val(vec![1.0, 2.0]);
+        let tensor = Tensor::<f32>::from_tensor_proto(&proto).unwrap();
+        assert_eq!(&tensor[..], &[1.0f32, 2.0, 2.0, 2.0]);
+        proto.set_float_val(vec![]);
+        let tensor = Tensor::<f32>::from_tensor_proto(&proto).unwrap();
+        assert_eq!(&tensor[..], &[0.0f32; 4]);
+        proto.set_float_val(vec![0.0; 5]);
+        assert_eq!(
+            Tensor::<f32>::from_tensor_proto(&proto).unwrap_err().code(),
+            Code::InvalidArgument
+        );
+
+        let mut proto = protos::tensor::TensorProto::new();
+        proto.set_dtype(protos::types::DataType::DT_INT16);
+        proto.set_tensor_shape(Shape::from(&[3]).into_proto());
+        proto.set_int_val(vec![-1, 300, 7]);
+        let data = proto.write_to_bytes().unwrap();
+        let tensor = Tensor::<i16>::from_proto_bytes(&data).unwrap();
+        assert_eq!(&tensor[..], &[-1i16, 300, 7]);
+
+        let mut proto = protos::tensor::TensorProto::new();
+        proto.set_dtype(protos::types::DataType::DT_BOOL);
+        proto.set_tensor_shape(Shape::from(&[2]).into_proto());
+        proto.set_bool_val(vec![false, true]);
+        let tensor = Tensor::<bool>::from_tensor_proto(&proto).unwrap();
+        assert_eq!(&tensor[..], &[false, true]);
+
+        let mut proto = protos::tensor::TensorProto::new();
+        proto.set_dtype(protos::types::DataType::DT_COMPLEX64);
+        proto.set_tensor_shape(Shape::from(&[3]).into_proto());
+        proto.set_scomplex_val(vec![1.0, -2.0, 3.0, 4.0]);
+        let tensor = Tensor::<Complex<f32>>::from_tensor_proto(&proto).unwrap();
+        assert_eq!(
+            &tensor[..],
+            &[
+                Complex::new(1.0, -2.0),
+                Complex::new(3.0, 4.0),
+                Complex::new(3.0, 4.0)
+            ]
+        );
+        proto.set_scomplex_val(vec![1.0]);
+        assert_eq!(
+            Tensor::<Complex<f32>>::from_tensor_proto(&proto)
+                .unwrap_err()
+                .code(),
+            Code::InvalidArgument
+        );
+        proto.set_scomplex_val(vec![1.0, 2.0, 3.0]);
+        assert_eq!(
+            Tensor::<Complex<f32>>::from_tensor_proto(&proto)
+                .unwrap_err()
+                .code(),
+            Code::InvalidArgument
+        );
+    }
+
+    #[test]
+    fn test_tensor_proto_bytes_roundtrip() {
+        let values = [Complex::new(1.0f64, -2.0), Complex::new(0.5, 1e-10)];
+        let tensor = Tensor::new(&[2]).with_values(&values).unwrap();
+        let data = tensor.to_proto_bytes().unwrap();
+        let proto: protos::tensor::TensorProto = protobuf::parse_from_bytes(&data).unwrap();
+        assert_eq!(
+            &proto.get_tensor_content()[..8],
+            &1.0f64.to_bits().to_le_bytes()
+        );
+        assert_eq!(
+            &proto.get_tensor_content()[8..16],
+            &(-2.0f64).to_bits().to_le_bytes()
+        );
+        let tensor = Tensor::<Complex<f64>>::from_proto_bytes(&data).unwrap();
+        assert_eq!(&tensor[..], &values);
+    }
+
     #[test]
     fn test_get_all_registered_kernels() {
         assert!(get_all_registered_kernels().unwrap().len() > 0);
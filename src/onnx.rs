@@ -0,0 +1,763 @@
+//! A basic exporter from TensorFlow graphs to the
+//! [ONNX](https://github.com/onnx/onnx/blob/master/docs/IR.md) format.
+//!
+//! Only a small subset of ops is supported: `Const`, `Identity`, `MatMul`,
+//! `Add`, `AddV2`, `BiasAdd`, `Sub`, `Mul`, `RealDiv`, `Relu`, `Relu6`,
+//! `Sigmoid`, `Tanh`, `Softmax`, `Reshape`, `Conv2D`, `MaxPool` and
+//! `AvgPool`.  Images in TensorFlow's default `NHWC` layout are transposed to
+//! and from ONNX's `NCHW` layout around convolutions and pooling.
+use crate::Code;
+use crate::DataType;
+use crate::Graph;
+use crate::Operation;
+use crate::Output;
+use crate::Result;
+use crate::Shape;
+use crate::Status;
+use crate::Tensor;
+use crate::TensorType;
+use protobuf::CodedOutputStream;
+use protobuf::ProtobufResult;
+use std::collections::HashSet;
+
+// Field numbers and enum values from onnx/onnx.proto.  The ONNX protos are not
+// part of the generated protos, so the messages are encoded by hand.
+const MODEL_IR_VERSION: u32 = 1;
+const MODEL_PRODUCER_NAME: u32 = 2;
+const MODEL_GRAPH: u32 = 7;
+const MODEL_OPSET_IMPORT: u32 = 8;
+const OPERATOR_SET_ID_VERSION: u32 = 2;
+const GRAPH_NODE: u32 = 1;
+const GRAPH_NAME: u32 = 2;
+const GRAPH_INITIALIZER: u32 = 5;
+const GRAPH_INPUT: u32 = 11;
+const GRAPH_OUTPUT: u32 = 12;
+const NODE_INPUT: u32 = 1;
+const NODE_OUTPUT: u32 = 2;
+const NODE_NAME: u32 = 3;
+const NODE_OP_TYPE: u32 = 4;
+const NODE_ATTRIBUTE: u32 = 5;
+const ATTRIBUTE_NAME: u32 = 1;
+const ATTRIBUTE_I: u32 = 3;
+const ATTRIBUTE_S: u32 = 4;
+const ATTRIBUTE_INTS: u32 = 8;
+const ATTRIBUTE_TYPE: u32 = 20;
+const ATTRIBUTE_TYPE_INT: i32 = 2;
+const ATTRIBUTE_TYPE_STRING: i32 = 3;
+const ATTRIBUTE_TYPE_INTS: i32 = 7;
+const TENSOR_DIMS: u32 = 1;
+const TENSOR_DATA_TYPE: u32 = 2;
+const TENSOR_NAME: u32 = 8;
+const TENSOR_RAW_DATA: u32 = 9;
+const VALUE_INFO_NAME: u32 = 1;
+const VALUE_INFO_TYPE: u32 = 2;
+const TYPE_TENSOR_TYPE: u32 = 1;
+const TYPE_TENSOR_ELEM_TYPE: u32 = 1;
+const TYPE_TENSOR_SHAPE: u32 = 2;
+const TENSOR_SHAPE_DIM: u32 = 1;
+const DIMENSION_DIM_VALUE: u32 = 1;
+
+/// The IR version and opset written to exported models.
+const IR_VERSION: i64 = 7;
+const OPSET_VERSION: i64 = 13;
+
+const NHWC_TO_NCHW: [i64; 4] = [0, 3, 1, 2];
+const NCHW_TO_NHWC: [i64; 4] = [0, 2, 3, 1];
+const HWIO_TO_OIHW: [i64; 4] = [3, 2, 0, 1];
+
+/// Returns the ONNX `TensorProto.DataType` for a TensorFlow type.
+fn onnx_type(data_type: DataType) -> Option<i32> {
+    Some(match data_type {
+        DataType::Float => 1,
+        DataType::UInt8 => 2,
+        DataType::Int8 => 3,
+        DataType::UInt16 => 4,
+        DataType::Int16 => 5,
+        DataType::Int32 => 6,
+        DataType::Int64 => 7,
+        DataType::Bool => 9,
+        DataType::Half => 10,
+        DataType::Double => 11,
+        DataType::UInt32 => 12,
+        DataType::UInt64 => 13,
+        _ => return None,
+    })
+}
+
+/// Returns the name of the ONNX value for a TensorFlow output.
+fn value_name(operation: &Operation, index: usize) -> Result<String> {
+    let name = operation.name()?;
+    Ok(if index == 0 {
+        name
+    } else {
+        format!("{}:{}", name, index)
+    })
+}
+
+fn encode<F>(f: F) -> ProtobufResult<Vec<u8>>
+where
+    F: FnOnce(&mut CodedOutputStream<'_>) -> ProtobufResult<()>,
+{
+    let mut data = Vec::new();
+    {
+        let mut output = CodedOutputStream::vec(&mut data);
+        f(&mut output)?;
+        output.flush()?;
+    }
+    Ok(data)
+}
+
+#[derive(Debug)]
+enum Attribute {
+    Int(&'static str, i64),
+    Ints(&'static str, Vec<i64>),
+    String(&'static str, String),
+}
+
+impl Attribute {
+    fn encode(&self) -> ProtobufResult<Vec<u8>> {
+        encode(|o| match self {
+            Attribute::Int(name, value) => {
+                o.write_string(ATTRIBUTE_NAME, name)?;
+                o.write_int32(ATTRIBUTE_TYPE, ATTRIBUTE_TYPE_INT)?;
+                o.write_int64(ATTRIBUTE_I, *value)
+            }
+            Attribute::Ints(name, values) => {
+                o.write_string(ATTRIBUTE_NAME, name)?;
+                o.write_int32(ATTRIBUTE_TYPE, ATTRIBUTE_TYPE_INTS)?;
+                for value in values {
+                    o.write_int64(ATTRIBUTE_INTS, *value)?;
+                }
+                Ok(())
+            }
+            Attribute::String(name, value) => {
+                o.write_string(ATTRIBUTE_NAME, name)?;
+                o.write_int32(ATTRIBUTE_TYPE, ATTRIBUTE_TYPE_STRING)?;
+                o.write_bytes(ATTRIBUTE_S, value.as_bytes())
+            }
+        })
+    }
+}
+
+#[derive(Debug)]
+struct Node {
+    op_type: &'static str,
+    name: String,
+    inputs: Vec<String>,
+    outputs: Vec<String>,
+    attributes: Vec<Attribute>,
+}
+
+impl Node {
+    fn encode(&self) -> ProtobufResult<Vec<u8>> {
+        let attributes = self
+            .attributes
+            .iter()
+            .map(Attribute::encode)
+            .collect::<ProtobufResult<Vec<_>>>()?;
+        encode(|o| {
+            for input in &self.inputs {
+                o.write_string(NODE_INPUT, input)?;
+            }
+            for output in &self.outputs {
+                o.write_string(NODE_OUTPUT, output)?;
+            }
+            o.write_string(NODE_NAME, &self.name)?;
+            o.write_string(NODE_OP_TYPE, self.op_type)?;
+            for attribute in &attributes {
+                o.write_bytes(NODE_ATTRIBUTE, attribute)?;
+            }
+            Ok(())
+        })
+    }
+}
+
+#[derive(Debug)]
+struct Initializer {
+    name: String,
+    data_type: i32,
+    dims: Vec<i64>,
+    raw_data: Vec<u8>,
+}
+
+impl Initializer {
+    fn encode(&self) -> ProtobufResult<Vec<u8>> {
+        encode(|o| {
+            for dim in &self.dims {
+                o.write_int64(TENSOR_DIMS, *dim)?;
+            }
+            o.write_int32(TENSOR_DATA_TYPE, self.data_type)?;
+            o.write_string(TENSOR_NAME, &self.name)?;
+            o.write_bytes(TENSOR_RAW_DATA, &self.raw_data)
+        })
+    }
+}
+
+/// Encodes a `ValueInfoProto` for a graph input or output.
+fn encode_value_info(name: &str, elem_type: i32, shape: &Shape) -> ProtobufResult<Vec<u8>> {
+    let tensor_type = encode(|o| {
+        o.write_int32(TYPE_TENSOR_ELEM_TYPE, elem_type)?;
+        if let Some(dims) = &shape.0 {
+            let mut dim_protos = Vec::with_capacity(dims.len());
+            for dim in dims {
+                // Unknown dimensions are left empty.
+                dim_protos.push(encode(|o| match dim {
+                    Some(size) => o.write_int64(DIMENSION_DIM_VALUE, *size),
+                    None => Ok(()),
+                })?);
+            }
+            let shape = encode(|o| {
+                for dim in &dim_protos {
+                    o.write_bytes(TENSOR_SHAPE_DIM, dim)?;
+                }
+                Ok(())
+            })?;
+            o.write_bytes(TYPE_TENSOR_SHAPE, &shape)?;
+        }
+        Ok(())
+    })?;
+    let type_proto = encode(|o| o.write_bytes(TYPE_TENSOR_TYPE, &tensor_type))?;
+    encode(|o| {
+        o.write_string(VALUE_INFO_NAME, name)?;
+        o.write_bytes(VALUE_INFO_TYPE, &type_proto)
+    })
+}
+
+/// Returns the raw little-endian data of a `Const` operation's value.
+fn const_data<T: TensorType, F: Fn(&T, &mut Vec<u8>)>(
+    operation: &Operation,
+    write: F,
+) -> Result<(Vec<i64>, Vec<u8>)> {
+    let value: Tensor<T> = operation.get_attr_tensor("value")?;
+    let mut data = Vec::new();
+    for element in value.iter() {
+        write(element, &mut data);
+    }
+    Ok((value.dims().iter().map(|d| *d as i64).collect(), data))
+}
+
+/// Returns the height and width entries of an `NHWC` or `NCHW` attribute.
+fn spatial(values: &[i64], nchw: bool) -> Vec<i64> {
+    if nchw {
+        values[2..].to_vec()
+    } else {
+        values[1..3].to_vec()
+    }
+}
+
+#[derive(Debug, Default)]
+struct Exporter {
+    nodes: Vec<Node>,
+    initializers: Vec<Initializer>,
+    unsupported: Vec<String>,
+}
+
+impl Exporter {
+    fn node(
+        &mut self,
+        op_type: &'static str,
+        name: String,
+        inputs: Vec<String>,
+        output: String,
+        attributes: Vec<Attribute>,
+    ) {
+        self.nodes.push(Node {
+            op_type,
+            name,
+            inputs,
+            outputs: vec![output],
+            attributes,
+        });
+    }
+
+    /// Adds a `Transpose` node and returns the name of its output.
+    fn transpose(&mut self, input: String, name: String, perm: &[i64]) -> String {
+        self.node(
+            "Transpose",
+            name.clone(),
+            vec![input],
+            name.clone(),
+            vec![Attribute::Ints("perm", perm.to_vec())],
+        );
+        name
+    }
+
+    /// Adds the ONNX nodes for a convolution or pooling op, transposing from
+    /// and to `NHWC` if necessary.
+    fn image_op(
+        &mut self,
+        operation: &Operation,
+        op_type: &'static str,
+        mut inputs: Vec<String>,
+        mut attributes: Vec<Attribute>,
+    ) -> Result<()> {
+        let name = operation.name()?;
+        let output = value_name(operation, 0)?;
+        let nchw = operation.get_attr_string("data_format")? == "NCHW";
+        let strides = operation.get_attr_int_list("strides")?;
+        attributes.push(Attribute::Ints("strides", spatial(&strides, nchw)));
+        let auto_pad = match operation.get_attr_string("padding")?.as_str() {
+            "SAME" => "SAME_UPPER",
+            "VALID" => "VALID",
+            padding => {
+                self.unsupported
+                    .push(format!("{} ({} with padding {})", name, op_type, padding));
+                return Ok(());
+            }
+        };
+        attributes.push(Attribute::String("auto_pad", auto_pad.to_string()));
+        if nchw {
+            self.node(op_type, name, inputs, output, attributes);
+        } else {
+            inputs[0] = self.transpose(
+                inputs[0].clone(),
+                format!("{}/onnx_to_nchw", name),
+                &NHWC_TO_NCHW,
+            );
+            let nchw_output = format!("{}/onnx_nchw", name);
+            self.node(
+                op_type,
+                nchw_output.clone(),
+                inputs,
+                nchw_output.clone(),
+                attributes,
+            );
+            self.node(
+                "Transpose",
+                format!("{}/onnx_to_nhwc", name),
+                vec![nchw_output],
+                output,
+                vec![Attribute::Ints("perm", NCHW_TO_NHWC.to_vec())],
+            );
+        }
+        Ok(())
+    }
+
+    fn convert(&mut self, operation: &Operation) -> Result<()> {
+        let op_type = operation.op_type()?;
+        let name = operation.name()?;
+        let output = value_name(operation, 0)?;
+        let mut inputs = Vec::with_capacity(operation.num_inputs());
+        for i in 0..operation.num_inputs() {
+            let (input, index) = operation.input(i);
+            inputs.push(value_name(&input, index)?);
+        }
+        let elementwise = match op_type.as_str() {
+            "Identity" => Some("Identity"),
+            "Add" | "AddV2" => Some("Add"),
+            "Sub" => Some("Sub"),
+            "Mul" => Some("Mul"),
+            "RealDiv" => Some("Div"),
+            "Relu" => Some("Relu"),
+            "Sigmoid" => Some("Sigmoid"),
+            "Tanh" => Some("Tanh"),
+            "Softmax" => Some("Softmax"),
+            _ => None,
+        };
+        if let Some(onnx_op_type) = elementwise {
+            self.node(onnx_op_type, name, inputs, output, vec![]);
+            return Ok(());
+        }
+        match op_type.as_str() {
+            "Const" => {
+                let data_type = operation.get_attr_type("dtype")?;
+                let data = match data_type {
+                    DataType::Float => const_data::<f32, _>(operation, |v, d| {
+                        d.extend_from_slice(&v.to_le_bytes())
+                    }),
+                    DataType::Double => const_data::<f64, _>(operation, |v, d| {
+                        d.extend_from_slice(&v.to_le_bytes())
+                    }),
+                    DataType::Int32 => const_data::<i32, _>(operation, |v, d| {
+                        d.extend_from_slice(&v.to_le_bytes())
+                    }),
+                    DataType::Int64 => const_data::<i64, _>(operation, |v, d| {
+                        d.extend_from_slice(&v.to_le_bytes())
+                    }),
+                    _ => {
+                        self.unsupported
+                            .push(format!("{} (Const of type {})", name, data_type));
+                        return Ok(());
+                    }
+                };
+                let (dims, raw_data) = data?;
+                self.initializers.push(Initializer {
+                    name: output,
+                    data_type: onnx_type(data_type).unwrap(),
+                    dims,
+                    raw_data,
+                });
+            }
+            "MatMul" => {
+                let transpose_a = operation.get_attr_bool("transpose_a")?;
+                let transpose_b = operation.get_attr_bool("transpose_b")?;
+                if transpose_a || transpose_b {
+                    let attributes = vec![
+                        Attribute::Int("transA", transpose_a as i64),
+                        Attribute::Int("transB", transpose_b as i64),
+                    ];
+                    self.node("Gemm", name, inputs, output, attributes);
+                } else {
+                    self.node("MatMul", name, inputs, output, vec![]);
+                }
+            }
+            "BiasAdd" => {
+                if operation.get_attr_string("data_format")? == "NCHW" {
+                    self.unsupported
+                        .push(format!("{} (BiasAdd with data_format NCHW)", name));
+                } else {
+                    // The bias broadcasts along the last dimension.
+                    self.node("Add", name, inputs, output, vec![]);
+                }
+            }
+            "Relu6" => {
+                // Since opset 11, Clip takes its bounds as inputs.
+                let min = format!("{}/onnx_min", name);
+                let max = format!("{}/onnx_max", name);
+                for (bound_name, bound) in &[(&min, 0.0f32), (&max, 6.0f32)] {
+                    self.initializers.push(Initializer {
+                        name: bound_name.to_string(),
+                        data_type: onnx_type(DataType::Float).unwrap(),
+                        dims: vec![],
+                        raw_data: bound.to_le_bytes().to_vec(),
+                    });
+                }
+                self.node(
+                    "Clip",
+                    name,
+                    vec![inputs[0].clone(), min, max],
+                    output,
+                    vec![],
+                );
+            }
+            "Reshape" => {
+                // ONNX requires an int64 shape.
+                let shape = format!("{}/onnx_shape", name);
+                self.node(
+                    "Cast",
+                    shape.clone(),
+                    vec![inputs[1].clone()],
+                    shape.clone(),
+                    vec![Attribute::Int(
+                        "to",
+                        onnx_type(DataType::Int64).unwrap() as i64,
+                    )],
+                );
+                self.node(
+                    "Reshape",
+                    name,
+                    vec![inputs[0].clone(), shape],
+                    output,
+                    vec![],
+                );
+            }
+            "Conv2D" => {
+                let nchw = operation.get_attr_string("data_format")? == "NCHW";
+                let dilations = operation.get_attr_int_list("dilations")?;
+                let filter = self.transpose(
+                    inputs[1].clone(),
+                    format!("{}/onnx_filter", name),
+                    &HWIO_TO_OIHW,
+                );
+                let attributes = vec![Attribute::Ints("dilations", spatial(&dilations, nchw))];
+                self.image_op(
+                    operation,
+                    "Conv",
+                    vec![inputs[0].clone(), filter],
+                    attributes,
+                )?;
+            }
+            "MaxPool" | "AvgPool" => {
+                let nchw = operation.get_attr_string("data_format")? == "NCHW";
+                let ksize = operation.get_attr_int_list("ksize")?;
+                let attributes = vec![Attribute::Ints("kernel_shape", spatial(&ksize, nchw))];
+                let onnx_op_type = if op_type == "MaxPool" {
+                    "MaxPool"
+                } else {
+                    "AveragePool"
+                };
+                self.image_op(operation, onnx_op_type, inputs, attributes)?;
+            }
+            _ => self.unsupported.push(format!("{} ({})", name, op_type)),
+        }
+        Ok(())
+    }
+}
+
+/// Returns the operations needed to compute `outputs` from `inputs`, with
+/// every operation after the operations it depends on.
+fn operations_to_export(inputs: &[Output], outputs: &[Output]) -> Vec<Operation> {
+    let is_input = |operation: &Operation, index: usize| {
+        inputs.iter().any(|input| {
+            input.operation.inner() == operation.inner() && input.index as usize == index
+        })
+    };
+    let mut visited = HashSet::new();
+    let mut order = Vec::new();
+    // Depth-first search, where `true` marks operations whose inputs have
+    // already been pushed.
+    let mut stack: Vec<(Operation, bool)> = outputs
+        .iter()
+        .rev()
+        .filter(|output| !is_input(&output.operation, output.index as usize))
+        .map(|output| (output.operation.clone(), false))
+        .collect();
+    while let Some((operation, expanded)) = stack.pop() {
+        if expanded {
+            order.push(operation);
+            continue;
+        }
+        if !visited.insert(operation.inner()) {
+            continue;
+        }
+        stack.push((operation.clone(), true));
+        for i in (0..operation.num_inputs()).rev() {
+            let (input, index) = operation.input(i);
+            if !is_input(&input, index) && !visited.contains(&input.inner()) {
+                stack.push((input, false));
+            }
+        }
+    }
+    order
+}
+
+/// Exports the part of `graph` which computes `outputs` from `inputs` as a
+/// serialized ONNX `ModelProto`.
+///
+/// `inputs` become the inputs of the ONNX graph, so any placeholders needed
+/// to compute the outputs must be listed.  Returns an `Unimplemented` error
+/// listing every operation which can't be exported, such as ops outside the
+/// supported subset (see the module documentation).
+pub fn export(graph: &Graph, inputs: &[Output], outputs: &[Output]) -> Result<Vec<u8>> {
+    let mut exporter = Exporter::default();
+    for operation in operations_to_export(inputs, outputs) {
+        if operation.op_type()? == "Placeholder" {
+            exporter.unsupported.push(format!(
+                "{} (Placeholder which is not one of the inputs)",
+                operation.name()?
+            ));
+        } else {
+            exporter.convert(&operation)?;
+        }
+    }
+    if !exporter.unsupported.is_empty() {
+        return Err(Status::new_set_lossy(
+            Code::Unimplemented,
+            &format!(
+                "Unable to export operations to ONNX: {}",
+                exporter.unsupported.join(", ")
+            ),
+        ));
+    }
+    let value_infos = |values: &[Output]| -> Result<Vec<Vec<u8>>> {
+        let mut encoded = Vec::with_capacity(values.len());
+        let mut names = HashSet::new();
+        for value in values {
+            let name = value_name(&value.operation, value.index as usize)?;
+            if !names.insert(name.clone()) {
+                continue;
+            }
+            let data_type = value.operation.output_type(value.index as usize);
+            let elem_type = onnx_type(data_type).ok_or_else(|| {
+                Status::new_set_lossy(
+                    Code::Unimplemented,
+                    &format!(
+                        "{} has type {}, which ONNX does not support",
+                        name, data_type
+                    ),
+                )
+            })?;
+            let shape = graph.tensor_shape(value.clone())?;
+            encoded.push(encode_value_info(&name, elem_type, &shape).map_err(invalid_proto)?);
+        }
+        Ok(encoded)
+    };
+    let input_infos = value_infos(inputs)?;
+    let output_infos = value_infos(outputs)?;
+    let nodes = exporter
+        .nodes
+        .iter()
+        .map(Node::encode)
+        .collect::<ProtobufResult<Vec<_>>>()
+        .map_err(invalid_proto)?;
+    let initializers = exporter
+        .initializers
+        .iter()
+        .map(Initializer::encode)
+        .collect::<ProtobufResult<Vec<_>>>()
+        .map_err(invalid_proto)?;
+    let graph_proto = encode(|o| {
+        for node in &nodes {
+            o.write_bytes(GRAPH_NODE, node)?;
+        }
+        o.write_string(GRAPH_NAME, "tensorflow")?;
+        for initializer in &initializers {
+            o.write_bytes(GRAPH_INITIALIZER, initializer)?;
+        }
+        for input in &input_infos {
+            o.write_bytes(GRAPH_INPUT, input)?;
+        }
+        for output in &output_infos {
+            o.write_bytes(GRAPH_OUTPUT, output)?;
+        }
+        Ok(())
+    })
+    .map_err(invalid_proto)?;
+    let opset =
+        encode(|o| o.write_int64(OPERATOR_SET_ID_VERSION, OPSET_VERSION)).map_err(invalid_proto)?;
+    encode(|o| {
+        o.write_int64(MODEL_IR_VERSION, IR_VERSION)?;
+        o.write_string(MODEL_PRODUCER_NAME, "tensorflow-rust")?;
+        o.write_bytes(MODEL_GRAPH, &graph_proto)?;
+        o.write_bytes(MODEL_OPSET_IMPORT, &opset)
+    })
+    .map_err(invalid_proto)
+}
+
+fn invalid_proto(e: protobuf::ProtobufError) -> Status {
+    invalid_arg!("Unable to encode ONNX proto: {}", e)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use protobuf::wire_format::WireType;
+    use protobuf::CodedInputStream;
+
+    /// Returns the length-delimited fields with the given number.
+    fn fields(data: &[u8], field: u32) -> Vec<Vec<u8>> {
+        let mut input = CodedInputStream::from_bytes(data);
+        let mut result = Vec::new();
+        while !input.eof().unwrap() {
+            let (field_number, wire_type) = input.read_tag_unpack().unwrap();
+            if field_number == field && wire_type == WireType::WireTypeLengthDelimited {
+                result.push(input.read_bytes().unwrap());
+            } else {
+                input.skip_field(wire_type).unwrap();
+            }
+        }
+        result
+    }
+
+    fn strings(data: &[u8], field: u32) -> Vec<String> {
+        fields(data, field)
+            .into_iter()
+            .map(|s| String::from_utf8(s).unwrap())
+            .collect()
+    }
+
+    fn placeholder(g: &mut Graph, name: &str, dims: &[Option<i64>]) -> Operation {
+        let mut nd = g.new_operation("Placeholder", name).unwrap();
+        nd.set_attr_type("dtype", DataType::Float).unwrap();
+        nd.set_attr_shape("shape", &Shape(Some(dims.to_vec())))
+            .unwrap();
+        nd.finish().unwrap()
+    }
+
+    fn constant(g: &mut Graph, name: &str, value: Tensor<f32>) -> Operation {
+        let mut nd = g.new_operation("Const", name).unwrap();
+        nd.set_attr_type("dtype", DataType::Float).unwrap();
+        nd.set_attr_tensor("value", value).unwrap();
+        nd.finish().unwrap()
+    }
+
+    fn unary(g: &mut Graph, op_type: &str, name: &str, input: &Operation) -> Operation {
+        let mut nd = g.new_operation(op_type, name).unwrap();
+        nd.add_input(input.clone());
+        nd.finish().unwrap()
+    }
+
+    fn binary(g: &mut Graph, op_type: &str, name: &str, a: &Operation, b: &Operation) -> Operation {
+        let mut nd = g.new_operation(op_type, name).unwrap();
+        nd.add_input(a.clone());
+        nd.add_input(b.clone());
+        nd.finish().unwrap()
+    }
+
+    #[test]
+    fn export_dense_layer() {
+        let mut g = Graph::new();
+        let x = placeholder(&mut g, "x", &[None, Some(2)]);
+        let w = constant(
+            &mut g,
+            "w",
+            Tensor::new(&[2, 2])
+                .with_values(&[1.0, 2.0, 3.0, 4.0])
+                .unwrap(),
+        );
+        let b = constant(&mut g, "b", Tensor::from(&[0.5f32, 1.5][..]));
+        let mat_mul = binary(&mut g, "MatMul", "mat_mul", &x, &w);
+        let bias_add = binary(&mut g, "BiasAdd", "bias_add", &mat_mul, &b);
+        let y = unary(&mut g, "Relu", "y", &bias_add);
+        // Not needed for the outputs, so not exported.
+        unary(&mut g, "Floor", "unused", &x);
+
+        let model = export(&g, &[x.into()], &[y.into()]).unwrap();
+        let graphs = fields(&model, MODEL_GRAPH);
+        assert_eq!(graphs.len(), 1);
+        let graph = &graphs[0];
+        let nodes = fields(graph, GRAPH_NODE);
+        let op_types: Vec<_> = nodes
+            .iter()
+            .flat_map(|node| strings(node, NODE_OP_TYPE))
+            .collect();
+        assert_eq!(op_types, ["MatMul", "Add", "Relu"]);
+        assert_eq!(strings(&nodes[0], NODE_INPUT), ["x", "w"]);
+        assert_eq!(strings(&nodes[2], NODE_OUTPUT), ["y"]);
+        let initializers: Vec<_> = fields(graph, GRAPH_INITIALIZER)
+            .iter()
+            .flat_map(|initializer| strings(initializer, TENSOR_NAME))
+            .collect();
+        assert_eq!(initializers, ["w", "b"]);
+        let input_names: Vec<_> = fields(graph, GRAPH_INPUT)
+            .iter()
+            .flat_map(|input| strings(input, VALUE_INFO_NAME))
+            .collect();
+        assert_eq!(input_names, ["x"]);
+        let output_names: Vec<_> = fields(graph, GRAPH_OUTPUT)
+            .iter()
+            .flat_map(|output| strings(output, VALUE_INFO_NAME))
+            .collect();
+        assert_eq!(output_names, ["y"]);
+    }
+
+    #[test]
+    fn export_conv2d() {
+        let mut g = Graph::new();
+        let x = placeholder(&mut g, "x", &[Some(1), Some(4), Some(4), Some(1)]);
+        let filter = constant(
+            &mut g,
+            "filter",
+            Tensor::new(&[2, 2, 1, 1]).with_values(&[1.0; 4]).unwrap(),
+        );
+        let conv = {
+            let mut nd = g.new_operation("Conv2D", "conv").unwrap();
+            nd.add_input(x.clone());
+            nd.add_input(filter);
+            nd.set_attr_int_list("strides", &[1, 1, 1, 1]).unwrap();
+            nd.set_attr_string("padding", "SAME").unwrap();
+            nd.finish().unwrap()
+        };
+        let model = export(&g, &[x.into()], &[conv.into()]).unwrap();
+        let graph = &fields(&model, MODEL_GRAPH)[0];
+        let op_types: Vec<_> = fields(graph, GRAPH_NODE)
+            .iter()
+            .flat_map(|node| strings(node, NODE_OP_TYPE))
+            .collect();
+        assert_eq!(op_types, ["Transpose", "Transpose", "Conv", "Transpose"]);
+    }
+
+    #[test]
+    fn export_unsupported() {
+        let mut g = Graph::new();
+        let x = placeholder(&mut g, "x", &[Some(2)]);
+        let unfed = placeholder(&mut g, "unfed", &[Some(2)]);
+        let floor = unary(&mut g, "Floor", "floor", &x);
+        let y = binary(&mut g, "Add", "y", &floor, &unfed);
+        let error = export(&g, &[x.into()], &[y.into()]).unwrap_err();
+        assert_eq!(error.code(), Code::Unimplemented);
+        let message = error.to_string();
+        assert!(message.contains("floor (Floor)"), "{}", message);
+        assert!(message.contains("unfed (Placeholder"), "{}", message);
+    }
+}
@@ -18,12 +18,33 @@
 //! Note that for some ops, the builder may always be required, because
 //! the op has required attributes with no default specified.
 
+mod array_ops;
+pub use array_ops::*;
+
+mod control_flow_ops;
+pub use control_flow_ops::*;
+
+mod dataset_ops;
+pub use dataset_ops::*;
+
+mod image_ops;
+pub use image_ops::*;
+
 mod math_ops;
 pub use math_ops::*;
 
+mod nn_ops;
+pub use nn_ops::*;
+
 mod random_ops;
 pub use random_ops::*;
 
+mod sparse_ops;
+pub use sparse_ops::*;
+
+mod state_ops;
+pub use state_ops::*;
+
 #[allow(
     clippy::double_parens,
     clippy::too_many_arguments,
@@ -31,3 +52,15 @@ pub use random_ops::*;
 )]
 mod ops_impl;
 pub use ops_impl::*;
+
+/// Runs the graph of `scope` and fetches output 0 of `op`, for the tests of
+/// the ops.
+#[cfg(test)]
+fn run_f32(scope: &crate::Scope, op: &crate::Operation) -> crate::Tensor<f32> {
+    let options = crate::SessionOptions::new();
+    let session = crate::Session::new(&options, &scope.graph()).unwrap();
+    let mut run_args = crate::SessionRunArgs::new();
+    let fetch = run_args.request_fetch(op, 0);
+    session.run(&mut run_args).unwrap();
+    run_args.fetch(fetch).unwrap()
+}
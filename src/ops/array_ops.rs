@@ -0,0 +1,990 @@
+use super::DepthToSpace;
+use super::DynamicPartition;
+use super::SpaceToDepth;
+use super::Squeeze;
+use super::StridedSlice;
+use super::Unpack;
+use crate::ops::int_constant;
+use crate::DataType;
+use crate::Operation;
+use crate::Output;
+use crate::Result;
+use crate::Scope;
+use crate::Tensor;
+use libc::c_int;
+
+/// Returns the outputs of `operation` with indices `0..num`.
+pub(crate) fn outputs(operation: Operation, num: i64) -> Vec<Output> {
+    (0..num)
+        .map(|index| Output {
+            operation: operation.clone(),
+            index: index as c_int,
+        })
+        .collect()
+}
+
+/// Unpacks `value` along `axis` into `num` tensors, each with one dimension
+/// fewer than `value`.  This is the inverse of `pack`.
+///
+/// A negative `axis` counts from the last dimension.  If the size of the
+/// dimension is known when building the graph, it must equal `num`, otherwise
+/// an `InvalidArgument` error is returned.
+pub fn unstack<O: Into<Output>>(
+    value: O,
+    axis: i64,
+    num: i64,
+    scope: &mut Scope,
+) -> Result<Vec<Output>> {
+    let value = value.into();
+    if num < 0 {
+        return Err(invalid_arg!(
+            "unstack: num must be non-negative, got {}",
+            num
+        ));
+    }
+    let shape = scope.graph().tensor_shape(value.clone())?;
+    if let Some(dims) = &shape.0 {
+        let rank = dims.len() as i64;
+        if axis < -rank || axis >= rank {
+            return Err(invalid_arg!(
+                "unstack: axis {} is out of range for a tensor of rank {}",
+                axis,
+                rank
+            ));
+        }
+        let axis_index = if axis < 0 { axis + rank } else { axis };
+        if let Some(size) = dims[axis_index as usize] {
+            if size != num {
+                return Err(invalid_arg!(
+                    "unstack: dimension {} of shape {} has size {}, but num is {}",
+                    axis_index,
+                    shape,
+                    size,
+                    num
+                ));
+            }
+        }
+    }
+    let operation = Unpack::new().num(num).axis(axis).build(value, scope)?;
+    Ok(outputs(operation, num))
+}
+
+/// Inserts a dimension of size 1 at index `axis` of the shape of `input`.
+///
+/// A negative `axis` counts from the end, so -1 appends a dimension.  This is
+/// `expand_dims` with the axis given as a value rather than as a tensor.
+pub fn expand_dims_at<O: Into<Output>>(
+    input: O,
+    axis: i64,
+    scope: &mut Scope,
+) -> Result<Operation> {
+    let axis = int_constant(axis, scope)?;
+    super::expand_dims(input, axis, scope)
+}
+
+/// Removes the dimensions of size 1 at the indices `axes` from the shape of
+/// `input`.  If `axes` is empty, all dimensions of size 1 are removed.
+///
+/// This is `squeeze` with the `squeeze_dims` attribute set.
+pub fn squeeze_axes<O: Into<Output>>(
+    input: O,
+    axes: &[i64],
+    scope: &mut Scope,
+) -> Result<Operation> {
+    Squeeze::new()
+        .squeeze_dims(axes.to_vec())
+        .build(input, scope)
+}
+
+/// Repeats `input` `multiples[i]` times along dimension `i`, e.g. to repeat
+/// a context vector across time steps.  This is `tile` with the multiples
+/// given as values rather than as a tensor.
+///
+/// Returns an `InvalidArgument` error if a multiple is negative, or if the
+/// rank of `input` is known and differs from the length of `multiples`.
+pub fn tile_multiples<O: Into<Output>>(
+    input: O,
+    multiples: &[i64],
+    scope: &mut Scope,
+) -> Result<Operation> {
+    let input = input.into();
+    if let Some(multiple) = multiples.iter().find(|m| **m < 0) {
+        return Err(invalid_arg!(
+            "tile_multiples: multiples must be non-negative, got {}",
+            multiple
+        ));
+    }
+    if let Some(rank) = scope.graph().tensor_shape(input.clone())?.dims() {
+        if rank != multiples.len() {
+            return Err(invalid_arg!(
+                "tile_multiples: got {} multiples for a tensor of rank {}",
+                multiples.len(),
+                rank
+            ));
+        }
+    }
+    let multiples = int_constant(multiples, scope)?;
+    super::tile(input, multiples, scope)
+}
+
+/// Partitions `data` into `num_partitions` tensors, where `partitions[i]`
+/// selects the output that the slice `data[i, ...]` goes to, e.g. to route
+/// the examples of a batch to different experts.
+///
+/// `partitions` must have type `int32`, with values in
+/// `[0, num_partitions)`.  The slices keep their relative order within each
+/// output.  Use `dynamic_stitch_list` to merge the outputs back together.
+pub fn dynamic_partition_list<O1: Into<Output>, O2: Into<Output>>(
+    data: O1,
+    partitions: O2,
+    num_partitions: i64,
+    scope: &mut Scope,
+) -> Result<Vec<Output>> {
+    if num_partitions < 1 {
+        return Err(invalid_arg!(
+            "dynamic_partition_list: num_partitions must be positive, got {}",
+            num_partitions
+        ));
+    }
+    let operation = DynamicPartition::new()
+        .num_partitions(num_partitions)
+        .build(data, partitions, scope)?;
+    Ok(outputs(operation, num_partitions))
+}
+
+/// Interleaves the values from the `data` tensors into a single tensor, so
+/// that `result[indices[m][i], ...] = data[m][i, ...]`.
+///
+/// `indices` and `data` must have the same length, and each `indices[m]`
+/// must be an `int32` tensor whose shape is a prefix of the shape of
+/// `data[m]`.  This is the inverse of `dynamic_partition_list` when given
+/// the partitioned positions of the original slices.
+pub fn dynamic_stitch_list(
+    indices: &[Output],
+    data: &[Output],
+    scope: &mut Scope,
+) -> Result<Operation> {
+    if indices.is_empty() || indices.len() != data.len() {
+        return Err(invalid_arg!(
+            "dynamic_stitch_list: got {} indices and {} data tensors",
+            indices.len(),
+            data.len()
+        ));
+    }
+    scope.new_operation("DynamicStitch", |nd| {
+        nd.add_input_list(indices);
+        nd.add_input_list(data);
+        Ok(())
+    })
+}
+
+/// Gathers slices of `params` at the multi-dimensional `indices`, e.g. to
+/// pick the elements a pointer network points to, or the boxes selected for
+/// each image of a batch.
+///
+/// The last dimension of `indices`, of size `K`, indexes the first `K`
+/// dimensions of `params`, and the other dimensions of `indices` are batch
+/// dimensions.  The output has shape
+/// `indices.shape[..-1] + params.shape[K..]`: for example, `params` of shape
+/// `[4, 5, 6]` and `indices` of shape `[2, 3, 2]` give an output of shape
+/// `[2, 3, 6]`, where `output[i, j, :] = params[indices[i, j, 0], indices[i, j, 1], :]`.
+///
+/// This is `gather_nd` with static checks.  Returns an `InvalidArgument`
+/// error if `indices` is not `int32` or `int64`, is a scalar, or if `K` and
+/// the rank of `params` are known and `K` is larger.  Out of range indices
+/// fail when the graph is run on the CPU.
+pub fn gather_nd_checked<O1: Into<Output>, O2: Into<Output>>(
+    params: O1,
+    indices: O2,
+    scope: &mut Scope,
+) -> Result<Operation> {
+    let params = params.into();
+    let indices = indices.into();
+    let indices_type = indices.operation.output_type(indices.index as usize);
+    if indices_type != DataType::Int32 && indices_type != DataType::Int64 {
+        return Err(invalid_arg!(
+            "gather_nd_checked: indices must be Int32 or Int64, got {}",
+            indices_type
+        ));
+    }
+    let (indices_shape, params_shape) = {
+        let graph = scope.graph();
+        (
+            graph.tensor_shape(indices.clone())?,
+            graph.tensor_shape(params.clone())?,
+        )
+    };
+    if let Some(dims) = &indices_shape.0 {
+        let index_depth = match dims.last() {
+            Some(index_depth) => *index_depth,
+            None => {
+                return Err(invalid_arg!(
+                    "gather_nd_checked: indices must have rank at least 1"
+                ))
+            }
+        };
+        if let (Some(index_depth), Some(rank)) = (index_depth, params_shape.dims()) {
+            if index_depth > rank as i64 {
+                return Err(invalid_arg!(
+                    "gather_nd_checked: indices of shape {} index {} dimensions, but params \
+                     of shape {} only has {}",
+                    indices_shape,
+                    index_depth,
+                    params_shape,
+                    rank
+                ));
+            }
+        }
+    }
+    super::gather_nd(params, indices, scope)
+}
+
+/// Checks the block shape and the paddings or crops of
+/// `space_to_batch_nd_blocks` and `batch_to_space_nd_blocks`, and returns
+/// the known shape of `input`, if any.
+fn check_blocks(
+    op_name: &str,
+    input: &Output,
+    block_shape: &[i64],
+    pads: &[[i64; 2]],
+    scope: &Scope,
+) -> Result<Option<Vec<Option<i64>>>> {
+    if block_shape.is_empty() || block_shape.iter().any(|b| *b < 1) {
+        return Err(invalid_arg!(
+            "{}: block_shape must be non-empty and positive, got {:?}",
+            op_name,
+            block_shape
+        ));
+    }
+    if pads.len() != block_shape.len() || pads.iter().flatten().any(|p| *p < 0) {
+        return Err(invalid_arg!(
+            "{}: expected {} non-negative [start, end] pairs, got {:?}",
+            op_name,
+            block_shape.len(),
+            pads
+        ));
+    }
+    let shape = scope.graph().tensor_shape(input.clone())?;
+    if let Some(dims) = &shape.0 {
+        if dims.len() < block_shape.len() + 1 {
+            return Err(invalid_arg!(
+                "{}: input of shape {} must have rank at least {}",
+                op_name,
+                shape,
+                block_shape.len() + 1
+            ));
+        }
+    }
+    Ok(shape.0)
+}
+
+/// Converts a list of `[start, end]` pairs to an `[M, 2]` constant.
+fn pairs_constant(pairs: &[[i64; 2]], scope: &mut Scope) -> Result<Operation> {
+    let values: Vec<i64> = pairs.iter().flatten().cloned().collect();
+    int_constant(
+        Tensor::new(&[pairs.len() as u64, 2]).with_values(&values)?,
+        scope,
+    )
+}
+
+/// Zero-pads the `M` spatial dimensions of `input` (those after the batch
+/// dimension) by `paddings[i] = [start, end]`, then moves blocks of
+/// `block_shape` from them into the batch dimension.  This is
+/// `space_to_batch_nd` with the block shape and paddings given as values
+/// rather than as tensors, and is used e.g. to implement dilated
+/// convolutions.
+///
+/// Returns an `InvalidArgument` error if `block_shape` is empty or not
+/// positive, if `paddings` does not have one non-negative pair per block
+/// dimension, or if a known spatial dimension plus its padding is not
+/// divisible by its block size.
+pub fn space_to_batch_nd_blocks<O: Into<Output>>(
+    input: O,
+    block_shape: &[i64],
+    paddings: &[[i64; 2]],
+    scope: &mut Scope,
+) -> Result<Operation> {
+    let input = input.into();
+    let dims = check_blocks(
+        "space_to_batch_nd_blocks",
+        &input,
+        block_shape,
+        paddings,
+        scope,
+    )?;
+    if let Some(dims) = dims {
+        for (i, (block, pad)) in block_shape.iter().zip(paddings).enumerate() {
+            if let Some(size) = dims[i + 1] {
+                if (size + pad[0] + pad[1]) % block != 0 {
+                    return Err(invalid_arg!(
+                        "space_to_batch_nd_blocks: padded size {} of dimension {} is not \
+                         divisible by block size {}",
+                        size + pad[0] + pad[1],
+                        i + 1,
+                        block
+                    ));
+                }
+            }
+        }
+    }
+    let block_shape = int_constant(block_shape, scope)?;
+    let paddings = pairs_constant(paddings, scope)?;
+    super::space_to_batch_nd(input, block_shape, paddings, scope)
+}
+
+/// Moves blocks of `block_shape` from the batch dimension of `input` back
+/// into its `M` spatial dimensions, then removes `crops[i] = [start, end]`
+/// from them.  This is the inverse of `space_to_batch_nd_blocks`, and is
+/// `batch_to_space_nd` with the block shape and crops given as values rather
+/// than as tensors.
+///
+/// Returns an `InvalidArgument` error if `block_shape` is empty or not
+/// positive, if `crops` does not have one non-negative pair per block
+/// dimension, or if a known batch dimension is not divisible by the product
+/// of the block sizes.
+pub fn batch_to_space_nd_blocks<O: Into<Output>>(
+    input: O,
+    block_shape: &[i64],
+    crops: &[[i64; 2]],
+    scope: &mut Scope,
+) -> Result<Operation> {
+    let input = input.into();
+    let dims = check_blocks(
+        "batch_to_space_nd_blocks",
+        &input,
+        block_shape,
+        crops,
+        scope,
+    )?;
+    let num_blocks: i64 = block_shape.iter().product();
+    if let Some(Some(batch)) = dims.as_ref().map(|dims| dims[0]) {
+        if batch % num_blocks != 0 {
+            return Err(invalid_arg!(
+                "batch_to_space_nd_blocks: batch size {} is not divisible by {}, the \
+                 product of the block sizes",
+                batch,
+                num_blocks
+            ));
+        }
+    }
+    let block_shape = int_constant(block_shape, scope)?;
+    let crops = pairs_constant(crops, scope)?;
+    super::batch_to_space_nd(input, block_shape, crops, scope)
+}
+
+/// Checks the block size and data format of `depth_to_space_blocks` and
+/// `space_to_depth_blocks`, and returns the known `[height, width, channels]`
+/// of `input`, if its rank is known.
+fn check_depth_blocks(
+    op_name: &str,
+    input: &Output,
+    block_size: i64,
+    data_format: &str,
+    scope: &Scope,
+) -> Result<Option<[Option<i64>; 3]>> {
+    if block_size < 2 {
+        return Err(invalid_arg!(
+            "{}: block_size must be at least 2, got {}",
+            op_name,
+            block_size
+        ));
+    }
+    let (height, width, channels) = match data_format {
+        "NHWC" => (1, 2, 3),
+        "NCHW" => (2, 3, 1),
+        _ => {
+            return Err(invalid_arg!(
+                "{}: data_format must be \"NHWC\" or \"NCHW\", got {:?}",
+                op_name,
+                data_format
+            ))
+        }
+    };
+    let shape = scope.graph().tensor_shape(input.clone())?;
+    match &shape.0 {
+        None => Ok(None),
+        Some(dims) if dims.len() == 4 => Ok(Some([dims[height], dims[width], dims[channels]])),
+        Some(_) => Err(invalid_arg!(
+            "{}: input of shape {} must have rank 4",
+            op_name,
+            shape
+        )),
+    }
+}
+
+/// Moves blocks of `block_size * block_size` values from the channel
+/// dimension of `input` into the spatial dimensions, so that the output has
+/// `block_size` times the height and width and `block_size * block_size`
+/// times fewer channels.  This is the "pixel shuffle" used for upsampling
+/// e.g. in super-resolution models, and the inverse of
+/// `space_to_depth_blocks`.
+///
+/// `data_format` is either `"NHWC"` or `"NCHW"`.  Returns an
+/// `InvalidArgument` error if `block_size` is less than 2, if the rank of
+/// `input` is known and is not 4, or if a known number of channels is not
+/// divisible by `block_size * block_size`.
+pub fn depth_to_space_blocks<O: Into<Output>>(
+    input: O,
+    block_size: i64,
+    data_format: &str,
+    scope: &mut Scope,
+) -> Result<Operation> {
+    let input = input.into();
+    let dims = check_depth_blocks(
+        "depth_to_space_blocks",
+        &input,
+        block_size,
+        data_format,
+        scope,
+    )?;
+    if let Some([_, _, Some(channels)]) = dims {
+        if channels % (block_size * block_size) != 0 {
+            return Err(invalid_arg!(
+                "depth_to_space_blocks: {} channels are not divisible by block_size^2 = {}",
+                channels,
+                block_size * block_size
+            ));
+        }
+    }
+    DepthToSpace::new()
+        .block_size(block_size)
+        .data_format(data_format)
+        .build(input, scope)
+}
+
+/// Moves blocks of `block_size * block_size` values from the spatial
+/// dimensions of `input` into the channel dimension, so that the output has
+/// `block_size` times smaller height and width and `block_size * block_size`
+/// times more channels.  This is the inverse of `depth_to_space_blocks`.
+///
+/// `data_format` is either `"NHWC"` or `"NCHW"`.  Returns an
+/// `InvalidArgument` error if `block_size` is less than 2, if the rank of
+/// `input` is known and is not 4, or if a known height or width is not
+/// divisible by `block_size`.
+pub fn space_to_depth_blocks<O: Into<Output>>(
+    input: O,
+    block_size: i64,
+    data_format: &str,
+    scope: &mut Scope,
+) -> Result<Operation> {
+    let input = input.into();
+    let dims = check_depth_blocks(
+        "space_to_depth_blocks",
+        &input,
+        block_size,
+        data_format,
+        scope,
+    )?;
+    if let Some([height, width, _]) = dims {
+        for size in height.iter().chain(width.iter()) {
+            if size % block_size != 0 {
+                return Err(invalid_arg!(
+                    "space_to_depth_blocks: spatial size {} is not divisible by block_size {}",
+                    size,
+                    block_size
+                ));
+            }
+        }
+    }
+    SpaceToDepth::new()
+        .block_size(block_size)
+        .data_format(data_format)
+        .build(input, scope)
+}
+
+/// One component of the slice taken by `strided_slice_ranges`, like the
+/// components of a Python slice expression such as `x[1, ::2, tf.newaxis]`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SliceRange {
+    /// Takes the whole dimension, like `:`.
+    All,
+    /// Takes a single index and removes the dimension, like `i`.  A negative
+    /// index counts from the end.
+    Index(i64),
+    /// Takes every `step`th index from `start` (inclusive) to `end`
+    /// (exclusive), like `start:end:step`.  `None` means the start or end of
+    /// the dimension, depending on the direction of `step`, which must not be
+    /// zero.
+    Range {
+        /// First index, or `None` for the start of the dimension.
+        start: Option<i64>,
+        /// Index after the last one, or `None` for the end of the dimension.
+        end: Option<i64>,
+        /// Distance between the indices taken.
+        step: i64,
+    },
+    /// Takes all of the dimensions not covered by the other ranges, like
+    /// `...`.  At most one ellipsis is allowed.
+    Ellipsis,
+    /// Inserts a dimension of size 1, like `tf.newaxis`.
+    NewAxis,
+}
+
+/// Takes the slice of `input` described by `ranges`, e.g.
+/// `[Index(1), Range { start: None, end: None, step: 2 }, NewAxis]` for the
+/// Python expression `input[1, ::2, tf.newaxis]`.  Dimensions not covered by
+/// `ranges` are taken whole.
+///
+/// This is `strided_slice` with the begin, end and strides tensors and the
+/// masks computed from `ranges`.  Returns an `InvalidArgument` error if
+/// `ranges` is empty or has more than 32 entries, if a step is zero, or if
+/// there is more than one ellipsis.
+pub fn strided_slice_ranges<O: Into<Output>>(
+    input: O,
+    ranges: &[SliceRange],
+    scope: &mut Scope,
+) -> Result<Operation> {
+    if ranges.is_empty() || ranges.len() > 32 {
+        return Err(invalid_arg!(
+            "strided_slice_ranges: expected 1 to 32 ranges, got {}",
+            ranges.len()
+        ));
+    }
+    let mut begin = Vec::with_capacity(ranges.len());
+    let mut end = Vec::with_capacity(ranges.len());
+    let mut strides = Vec::with_capacity(ranges.len());
+    let mut begin_mask = 0i64;
+    let mut end_mask = 0i64;
+    let mut ellipsis_mask = 0i64;
+    let mut new_axis_mask = 0i64;
+    let mut shrink_axis_mask = 0i64;
+    for (i, range) in ranges.iter().enumerate() {
+        let bit = 1 << i;
+        let (b, e, s) = match *range {
+            SliceRange::All => {
+                begin_mask |= bit;
+                end_mask |= bit;
+                (0, 0, 1)
+            }
+            SliceRange::Index(index) => {
+                shrink_axis_mask |= bit;
+                (index, index.wrapping_add(1), 1)
+            }
+            SliceRange::Range { start, end, step } => {
+                if step == 0 {
+                    return Err(invalid_arg!(
+                        "strided_slice_ranges: step of range {} is zero",
+                        i
+                    ));
+                }
+                if start.is_none() {
+                    begin_mask |= bit;
+                }
+                if end.is_none() {
+                    end_mask |= bit;
+                }
+                (start.unwrap_or(0), end.unwrap_or(0), step)
+            }
+            SliceRange::Ellipsis => {
+                if ellipsis_mask != 0 {
+                    return Err(invalid_arg!(
+                        "strided_slice_ranges: only one ellipsis is allowed"
+                    ));
+                }
+                ellipsis_mask |= bit;
+                (0, 0, 1)
+            }
+            SliceRange::NewAxis => {
+                new_axis_mask |= bit;
+                (0, 0, 1)
+            }
+        };
+        begin.push(b);
+        end.push(e);
+        strides.push(s);
+    }
+    let begin = int_constant(&begin[..], scope)?;
+    let end = int_constant(&end[..], scope)?;
+    let strides = int_constant(&strides[..], scope)?;
+    StridedSlice::new()
+        .begin_mask(begin_mask)
+        .end_mask(end_mask)
+        .ellipsis_mask(ellipsis_mask)
+        .new_axis_mask(new_axis_mask)
+        .shrink_axis_mask(shrink_axis_mask)
+        .build(input, begin, end, strides, scope)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ops::constant;
+    use crate::Code;
+    use crate::Session;
+    use crate::SessionOptions;
+    use crate::SessionRunArgs;
+    use crate::Shape;
+    use crate::Tensor;
+
+    #[test]
+    fn test_unstack() {
+        let mut scope = Scope::new_root_scope();
+        let x = constant(
+            Tensor::new(&[3, 2])
+                .with_values(&[1i32, 2, 3, 4, 5, 6])
+                .unwrap(),
+            &mut scope,
+        )
+        .unwrap();
+        assert_eq!(
+            unstack(x.clone(), 0, 2, &mut scope).unwrap_err().code(),
+            Code::InvalidArgument
+        );
+        assert_eq!(
+            unstack(x.clone(), 2, 3, &mut scope).unwrap_err().code(),
+            Code::InvalidArgument
+        );
+        let rows = unstack(x.clone(), 0, 3, &mut scope).unwrap();
+        let columns = unstack(x, -1, 2, &mut scope).unwrap();
+        assert_eq!(rows.len(), 3);
+        assert_eq!(columns.len(), 2);
+
+        let options = SessionOptions::new();
+        let session = Session::new(&options, &scope.graph()).unwrap();
+        let mut run_args = SessionRunArgs::new();
+        let row_fetch = run_args.request_fetch(&rows[2].operation, rows[2].index);
+        let column_fetch = run_args.request_fetch(&columns[1].operation, columns[1].index);
+        session.run(&mut run_args).unwrap();
+        assert_eq!(&run_args.fetch::<i32>(row_fetch).unwrap()[..], &[5, 6]);
+        assert_eq!(
+            &run_args.fetch::<i32>(column_fetch).unwrap()[..],
+            &[2, 4, 6]
+        );
+    }
+
+    #[test]
+    fn test_expand_dims_at_and_squeeze_axes() {
+        let mut scope = Scope::new_root_scope();
+        let x = constant(
+            Tensor::new(&[2, 3]).with_values(&[0f32; 6]).unwrap(),
+            &mut scope,
+        )
+        .unwrap();
+        let expanded = expand_dims_at(x.clone(), -1, &mut scope).unwrap();
+        let expanded = expand_dims_at(expanded, 0, &mut scope).unwrap();
+        assert_eq!(
+            scope.graph().tensor_shape(expanded.clone()).unwrap(),
+            Shape::from(&[1, 2, 3, 1][..])
+        );
+        let squeezed = squeeze_axes(expanded.clone(), &[0], &mut scope).unwrap();
+        assert_eq!(
+            scope.graph().tensor_shape(squeezed).unwrap(),
+            Shape::from(&[2, 3, 1][..])
+        );
+        let squeezed = squeeze_axes(expanded, &[], &mut scope).unwrap();
+        assert_eq!(
+            scope.graph().tensor_shape(squeezed).unwrap(),
+            Shape::from(&[2, 3][..])
+        );
+    }
+
+    #[test]
+    fn test_tile_multiples() {
+        let mut scope = Scope::new_root_scope();
+        let x = constant(
+            Tensor::new(&[2, 1]).with_values(&[1i32, 2]).unwrap(),
+            &mut scope,
+        )
+        .unwrap();
+        assert_eq!(
+            tile_multiples(x.clone(), &[3], &mut scope)
+                .unwrap_err()
+                .code(),
+            Code::InvalidArgument
+        );
+        assert_eq!(
+            tile_multiples(x.clone(), &[1, -1], &mut scope)
+                .unwrap_err()
+                .code(),
+            Code::InvalidArgument
+        );
+        let tiled = tile_multiples(x, &[2, 3], &mut scope).unwrap();
+        assert_eq!(
+            scope.graph().tensor_shape(tiled.clone()).unwrap(),
+            Shape::from(&[4, 3][..])
+        );
+
+        let session = Session::new(&SessionOptions::new(), &scope.graph()).unwrap();
+        let mut run_args = SessionRunArgs::new();
+        let fetch = run_args.request_fetch(&tiled, 0);
+        session.run(&mut run_args).unwrap();
+        assert_eq!(
+            &run_args.fetch::<i32>(fetch).unwrap()[..],
+            &[1, 1, 1, 2, 2, 2, 1, 1, 1, 2, 2, 2]
+        );
+    }
+
+    #[test]
+    fn test_gather_nd_checked() {
+        let mut scope = Scope::new_root_scope();
+        let values: Vec<f32> = (0..24).map(|x| x as f32).collect();
+        let params = constant(
+            Tensor::new(&[2, 3, 4]).with_values(&values).unwrap(),
+            &mut scope,
+        )
+        .unwrap();
+        let indices = constant(
+            Tensor::new(&[2, 2, 2])
+                .with_values(&[0i32, 0, 1, 2, 1, 1, 0, 2])
+                .unwrap(),
+            &mut scope,
+        )
+        .unwrap();
+        let float_indices = constant(&[0.0f32, 1.0][..], &mut scope).unwrap();
+        let deep_indices = constant(&[0i64, 0, 0, 0][..], &mut scope).unwrap();
+        let scalar_index = constant(0i32, &mut scope).unwrap();
+        for bad_indices in &[float_indices, deep_indices, scalar_index] {
+            assert_eq!(
+                gather_nd_checked(params.clone(), bad_indices.clone(), &mut scope)
+                    .unwrap_err()
+                    .code(),
+                Code::InvalidArgument
+            );
+        }
+        let rows = gather_nd_checked(params.clone(), indices, &mut scope).unwrap();
+        assert_eq!(
+            scope.graph().tensor_shape(rows.clone()).unwrap(),
+            Shape::from(&[2, 2, 4][..])
+        );
+        let element_indices = constant(&[1i64, 2, 3][..], &mut scope).unwrap();
+        let element = gather_nd_checked(params, element_indices, &mut scope).unwrap();
+
+        let session = Session::new(&SessionOptions::new(), &scope.graph()).unwrap();
+        let mut run_args = SessionRunArgs::new();
+        let rows_fetch = run_args.request_fetch(&rows, 0);
+        let element_fetch = run_args.request_fetch(&element, 0);
+        session.run(&mut run_args).unwrap();
+        assert_eq!(
+            &run_args.fetch::<f32>(rows_fetch).unwrap()[..],
+            &[
+                0.0, 1.0, 2.0, 3.0, 20.0, 21.0, 22.0, 23.0, 16.0, 17.0, 18.0, 19.0, 8.0, 9.0, 10.0,
+                11.0
+            ]
+        );
+        let element = run_args.fetch::<f32>(element_fetch).unwrap();
+        assert_eq!(element.dims(), &[] as &[u64]);
+        assert_eq!(element[0], 23.0);
+    }
+
+    #[test]
+    fn test_dynamic_partition_and_stitch() {
+        let mut scope = Scope::new_root_scope();
+        let data = constant(&[10.0f32, 20.0, 30.0, 40.0, 50.0][..], &mut scope).unwrap();
+        let partitions = constant(&[1i32, 0, 1, 1, 0][..], &mut scope).unwrap();
+        let positions = constant(&[0i32, 1, 2, 3, 4][..], &mut scope).unwrap();
+        let parts = dynamic_partition_list(data, partitions.clone(), 2, &mut scope).unwrap();
+        let indices = dynamic_partition_list(positions, partitions.clone(), 2, &mut scope).unwrap();
+        assert_eq!(parts.len(), 2);
+        let stitched = dynamic_stitch_list(&indices, &parts, &mut scope).unwrap();
+        assert_eq!(
+            dynamic_partition_list(partitions.clone(), partitions, 0, &mut scope)
+                .unwrap_err()
+                .code(),
+            Code::InvalidArgument
+        );
+        assert_eq!(
+            dynamic_stitch_list(&indices[..1], &parts, &mut scope)
+                .unwrap_err()
+                .code(),
+            Code::InvalidArgument
+        );
+
+        let options = SessionOptions::new();
+        let session = Session::new(&options, &scope.graph()).unwrap();
+        let mut run_args = SessionRunArgs::new();
+        let part_fetch = run_args.request_fetch(&parts[1].operation, parts[1].index);
+        let stitched_fetch = run_args.request_fetch(&stitched, 0);
+        session.run(&mut run_args).unwrap();
+        assert_eq!(
+            &run_args.fetch::<f32>(part_fetch).unwrap()[..],
+            &[10.0, 30.0, 40.0]
+        );
+        assert_eq!(
+            &run_args.fetch::<f32>(stitched_fetch).unwrap()[..],
+            &[10.0, 20.0, 30.0, 40.0, 50.0]
+        );
+    }
+
+    #[test]
+    fn test_space_to_batch_nd_blocks() {
+        let mut scope = Scope::new_root_scope();
+        let values: Vec<f32> = (0..6).map(|x| x as f32).collect();
+        let x = constant(
+            Tensor::new(&[1, 2, 3, 1]).with_values(&values).unwrap(),
+            &mut scope,
+        )
+        .unwrap();
+        assert_eq!(
+            space_to_batch_nd_blocks(x.clone(), &[2, 2], &[[0, 0], [0, 0]], &mut scope)
+                .unwrap_err()
+                .code(),
+            Code::InvalidArgument
+        );
+        assert_eq!(
+            space_to_batch_nd_blocks(x.clone(), &[2, 2], &[[0, 0]], &mut scope)
+                .unwrap_err()
+                .code(),
+            Code::InvalidArgument
+        );
+        assert_eq!(
+            space_to_batch_nd_blocks(x.clone(), &[0], &[[0, 0]], &mut scope)
+                .unwrap_err()
+                .code(),
+            Code::InvalidArgument
+        );
+        let batched = space_to_batch_nd_blocks(x, &[2, 2], &[[0, 0], [0, 1]], &mut scope).unwrap();
+        assert_eq!(
+            scope.graph().tensor_shape(batched.clone()).unwrap(),
+            Shape::from(&[4, 1, 2, 1][..])
+        );
+        assert_eq!(
+            batch_to_space_nd_blocks(batched.clone(), &[3], &[[0, 0]], &mut scope)
+                .unwrap_err()
+                .code(),
+            Code::InvalidArgument
+        );
+        let restored =
+            batch_to_space_nd_blocks(batched, &[2, 2], &[[0, 0], [0, 1]], &mut scope).unwrap();
+
+        let options = SessionOptions::new();
+        let session = Session::new(&options, &scope.graph()).unwrap();
+        let mut run_args = SessionRunArgs::new();
+        let restored_fetch = run_args.request_fetch(&restored, 0);
+        session.run(&mut run_args).unwrap();
+        let restored = run_args.fetch::<f32>(restored_fetch).unwrap();
+        assert_eq!(restored.dims(), &[1, 2, 3, 1]);
+        assert_eq!(&restored[..], &values[..]);
+    }
+
+    #[test]
+    fn test_depth_to_space_blocks() {
+        let mut scope = Scope::new_root_scope();
+        let values: Vec<f32> = (0..16).map(|x| x as f32).collect();
+        let x = constant(
+            Tensor::new(&[1, 2, 2, 4]).with_values(&values).unwrap(),
+            &mut scope,
+        )
+        .unwrap();
+        for (block_size, data_format) in &[(1, "NHWC"), (2, "NWHC"), (3, "NHWC"), (4, "NCHW")] {
+            assert_eq!(
+                depth_to_space_blocks(x.clone(), *block_size, data_format, &mut scope)
+                    .unwrap_err()
+                    .code(),
+                Code::InvalidArgument
+            );
+        }
+        assert_eq!(
+            space_to_depth_blocks(x.clone(), 4, "NHWC", &mut scope)
+                .unwrap_err()
+                .code(),
+            Code::InvalidArgument
+        );
+        let shuffled = depth_to_space_blocks(x, 2, "NHWC", &mut scope).unwrap();
+        assert_eq!(
+            scope.graph().tensor_shape(shuffled.clone()).unwrap(),
+            Shape::from(&[1, 4, 4, 1][..])
+        );
+        let restored = space_to_depth_blocks(shuffled.clone(), 2, "NHWC", &mut scope).unwrap();
+
+        let options = SessionOptions::new();
+        let session = Session::new(&options, &scope.graph()).unwrap();
+        let mut run_args = SessionRunArgs::new();
+        let shuffled_fetch = run_args.request_fetch(&shuffled, 0);
+        let restored_fetch = run_args.request_fetch(&restored, 0);
+        session.run(&mut run_args).unwrap();
+        let shuffled = run_args.fetch::<f32>(shuffled_fetch).unwrap();
+        assert_eq!(&shuffled[..4], &[0.0, 1.0, 4.0, 5.0]);
+        let restored = run_args.fetch::<f32>(restored_fetch).unwrap();
+        assert_eq!(restored.dims(), &[1, 2, 2, 4]);
+        assert_eq!(&restored[..], &values[..]);
+    }
+
+    #[test]
+    fn test_strided_slice_ranges() {
+        let mut scope = Scope::new_root_scope();
+        let values: Vec<i32> = (0..24).collect();
+        let x = constant(
+            Tensor::new(&[2, 3, 4]).with_values(&values).unwrap(),
+            &mut scope,
+        )
+        .unwrap();
+        let every_other = SliceRange::Range {
+            start: Some(0),
+            end: Some(3),
+            step: 2,
+        };
+        let a = strided_slice_ranges(
+            x.clone(),
+            &[SliceRange::Index(1), every_other, SliceRange::NewAxis],
+            &mut scope,
+        )
+        .unwrap();
+        assert_eq!(
+            scope.graph().tensor_shape(a.clone()).unwrap(),
+            Shape::from(&[2, 1, 4][..])
+        );
+        let b = strided_slice_ranges(
+            x.clone(),
+            &[SliceRange::Ellipsis, SliceRange::Index(-1)],
+            &mut scope,
+        )
+        .unwrap();
+        let reversed = SliceRange::Range {
+            start: None,
+            end: None,
+            step: -1,
+        };
+        let c = strided_slice_ranges(
+            x.clone(),
+            &[SliceRange::All, reversed, SliceRange::Index(0)],
+            &mut scope,
+        )
+        .unwrap();
+        assert_eq!(
+            strided_slice_ranges(x.clone(), &[], &mut scope)
+                .unwrap_err()
+                .code(),
+            Code::InvalidArgument
+        );
+        assert_eq!(
+            strided_slice_ranges(
+                x.clone(),
+                &[SliceRange::Ellipsis, SliceRange::Ellipsis],
+                &mut scope
+            )
+            .unwrap_err()
+            .code(),
+            Code::InvalidArgument
+        );
+        let zero_step = SliceRange::Range {
+            start: None,
+            end: None,
+            step: 0,
+        };
+        assert_eq!(
+            strided_slice_ranges(x, &[zero_step], &mut scope)
+                .unwrap_err()
+                .code(),
+            Code::InvalidArgument
+        );
+
+        let options = SessionOptions::new();
+        let session = Session::new(&options, &scope.graph()).unwrap();
+        let mut run_args = SessionRunArgs::new();
+        let a_fetch = run_args.request_fetch(&a, 0);
+        let b_fetch = run_args.request_fetch(&b, 0);
+        let c_fetch = run_args.request_fetch(&c, 0);
+        session.run(&mut run_args).unwrap();
+        assert_eq!(
+            &run_args.fetch::<i32>(a_fetch).unwrap()[..],
+            &[12, 13, 14, 15, 20, 21, 22, 23]
+        );
+        let b = run_args.fetch::<i32>(b_fetch).unwrap();
+        assert_eq!(b.dims(), &[2, 3]);
+        assert_eq!(&b[..], &[3, 7, 11, 15, 19, 23]);
+        assert_eq!(
+            &run_args.fetch::<i32>(c_fetch).unwrap()[..],
+            &[8, 4, 0, 20, 16, 12]
+        );
+    }
+}
@@ -0,0 +1,107 @@
+//! Wrappers for the low-level control flow ops of TensorFlow 1.x.
+//!
+//! Conditionals can be built from `switch_branches` and `merge_list`.  Loops
+//! additionally need `enter_frame`, `exit` and `next_iteration`, but a loop
+//! is a cycle in the graph, which the C API can only create with
+//! `Graph::while_loop` or by importing a `GraphDef`.
+use super::array_ops::outputs;
+use super::Enter;
+use crate::Operation;
+use crate::Output;
+use crate::Result;
+use crate::Scope;
+
+/// Forwards `data` to one of two outputs depending on the boolean scalar
+/// `pred`, returning `(output_false, output_true)`.  Ops depending on the
+/// output which isn't taken are not executed.
+pub fn switch_branches<O1: Into<Output>, O2: Into<Output>>(
+    data: O1,
+    pred: O2,
+    scope: &mut Scope,
+) -> Result<(Output, Output)> {
+    let outputs = outputs(super::switch(data, pred, scope)?, 2);
+    Ok((outputs[0].clone(), outputs[1].clone()))
+}
+
+/// Forwards the value of the first of `inputs` to become available,
+/// returning `(output, value_index)`, where `value_index` is the `int32`
+/// index of the input which was forwarded.  This joins the branches of a
+/// `switch_branches`.
+pub fn merge_list(inputs: &[Output], scope: &mut Scope) -> Result<(Output, Output)> {
+    if inputs.is_empty() {
+        return Err(invalid_arg!("merge_list: inputs must not be empty"));
+    }
+    let operation = scope.new_operation("Merge", |nd| {
+        nd.add_input_list(inputs);
+        Ok(())
+    })?;
+    let outputs = outputs(operation, 2);
+    Ok((outputs[0].clone(), outputs[1].clone()))
+}
+
+/// Makes `data` available in the child frame `frame_name`, i.e. the body of
+/// a loop.  If `is_constant` is true, the value is the same in every
+/// iteration.
+pub fn enter_frame<O: Into<Output>>(
+    data: O,
+    frame_name: &str,
+    is_constant: bool,
+    scope: &mut Scope,
+) -> Result<Operation> {
+    Enter::new()
+        .frame_name(frame_name)
+        .is_constant(is_constant)
+        .build(data, scope)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ops::constant;
+    use crate::Code;
+    use crate::Session;
+    use crate::SessionOptions;
+    use crate::SessionRunArgs;
+
+    #[test]
+    fn test_cond() {
+        for &(pred, expected, expected_index) in &[(true, 6.0f32, 1), (false, -3.0, 0)] {
+            let mut scope = Scope::new_root_scope();
+            let x = constant(3.0f32, &mut scope).unwrap();
+            let pred = constant(pred, &mut scope).unwrap();
+            let (output_false, output_true) = switch_branches(x, pred, &mut scope).unwrap();
+            let two = constant(2.0f32, &mut scope).unwrap();
+            let output_true = super::super::mul(output_true, two, &mut scope).unwrap();
+            let output_false = super::super::neg(output_false, &mut scope).unwrap();
+            let (output, value_index) =
+                merge_list(&[output_false.into(), output_true.into()], &mut scope).unwrap();
+
+            let session = Session::new(&SessionOptions::new(), &scope.graph()).unwrap();
+            let mut run_args = SessionRunArgs::new();
+            let output = run_args.request_fetch(&output.operation, output.index);
+            let value_index = run_args.request_fetch(&value_index.operation, value_index.index);
+            session.run(&mut run_args).unwrap();
+            assert_eq!(run_args.fetch::<f32>(output).unwrap()[0], expected);
+            assert_eq!(
+                run_args.fetch::<i32>(value_index).unwrap()[0],
+                expected_index
+            );
+        }
+        let mut scope = Scope::new_root_scope();
+        assert_eq!(
+            merge_list(&[], &mut scope).unwrap_err().code(),
+            Code::InvalidArgument
+        );
+    }
+
+    #[test]
+    fn test_enter_frame() {
+        let mut scope = Scope::new_root_scope();
+        let x = constant(1.0f32, &mut scope).unwrap();
+        let entered = enter_frame(x, "loop", true, &mut scope).unwrap();
+        assert_eq!(entered.get_attr_string("frame_name").unwrap(), "loop");
+        assert!(entered.get_attr_bool("is_constant").unwrap());
+        let exited = super::super::exit(entered, &mut scope).unwrap();
+        assert_eq!(exited.op_type().unwrap(), "Exit");
+    }
+}
@@ -0,0 +1,81 @@
+use crate::DataType;
+use crate::Operation;
+use crate::Output;
+use crate::Result;
+use crate::Scope;
+use crate::Shape;
+
+/// Shuffles the elements of `input_dataset` using a buffer of `buffer_size`
+/// elements.
+///
+/// Unlike `shuffle_dataset`, the `seed` and `seed2` inputs are derived from
+/// the graph seed of the scope, see `Scope::set_global_seed`, so the order of
+/// the elements is reproducible.  Without a graph seed, the order is
+/// nondeterministic.
+pub fn shuffle_dataset_seeded<O0: Into<Output>, O1: Into<Output>>(
+    input_dataset: O0,
+    buffer_size: O1,
+    output_types: &[DataType],
+    output_shapes: &[Shape],
+    scope: &mut Scope,
+) -> Result<Operation> {
+    let (seed, seed2) = scope.next_op_seeds();
+    let seed = super::constant(seed, scope)?;
+    let seed2 = super::constant(seed2, scope)?;
+    super::ShuffleDataset::new()
+        .output_types(output_types)
+        .output_shapes(output_shapes)
+        .build(input_dataset, buffer_size, seed, seed2, scope)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ops::constant;
+
+    #[test]
+    fn test_shuffle_dataset_seeded() {
+        let mut scope = Scope::new_root_scope();
+        let output_types = [DataType::Int64];
+        let output_shapes = [Shape(Some(vec![]))];
+        let start = constant(0i64, &mut scope).unwrap();
+        let stop = constant(10i64, &mut scope).unwrap();
+        let step = constant(1i64, &mut scope).unwrap();
+        let range = crate::ops::RangeDataset::new()
+            .output_types(&output_types[..])
+            .output_shapes(&output_shapes[..])
+            .build(start, stop, step, &mut scope)
+            .unwrap();
+        let buffer_size = constant(10i64, &mut scope).unwrap();
+        let seeds = |op: &Operation| {
+            let seed = op.input(2).0.get_attr_tensor::<i64>("value").unwrap();
+            let seed2 = op.input(3).0.get_attr_tensor::<i64>("value").unwrap();
+            (seed[0], seed2[0])
+        };
+
+        let unseeded = shuffle_dataset_seeded(
+            range.clone(),
+            buffer_size.clone(),
+            &output_types,
+            &output_shapes,
+            &mut scope,
+        )
+        .unwrap();
+        assert_eq!(seeds(&unseeded), (0, 0));
+
+        scope.set_global_seed(5);
+        let shuffled = shuffle_dataset_seeded(
+            range,
+            buffer_size,
+            &output_types,
+            &output_shapes,
+            &mut scope,
+        )
+        .unwrap();
+        assert_eq!(seeds(&shuffled), (5, 1));
+        assert_eq!(
+            shuffled.get_attr_type_list("output_types").unwrap(),
+            output_types
+        );
+    }
+}
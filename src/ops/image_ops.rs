@@ -0,0 +1,204 @@
+use super::CropAndResize;
+use super::NonMaxSuppressionV5;
+use super::ResizeNearestNeighbor;
+use crate::ops::constant;
+use crate::ops::int_constant;
+use crate::Operation;
+use crate::Output;
+use crate::Result;
+use crate::Scope;
+
+fn size_constant(size: [i32; 2], scope: &mut Scope) -> Result<Operation> {
+    if size[0] < 1 || size[1] < 1 {
+        return Err(invalid_arg!(
+            "Size must be positive, got {}x{}",
+            size[0],
+            size[1]
+        ));
+    }
+    int_constant(&size[..], scope)
+}
+
+/// Resizes `images` of shape `[batch, height, width, channels]` to
+/// `size = [new_height, new_width]` using nearest neighbor interpolation.
+///
+/// This is `resize_nearest_neighbor` with the size given as a constant.
+pub fn resize_nearest_neighbor_to_size<O: Into<Output>>(
+    images: O,
+    size: [i32; 2],
+    scope: &mut Scope,
+) -> Result<Operation> {
+    let size = size_constant(size, scope)?;
+    ResizeNearestNeighbor::new().build(images, size, scope)
+}
+
+/// Extracts crops from `image` of shape `[batch, height, width, channels]`
+/// and resizes each of them to `crop_size = [crop_height, crop_width]` with
+/// bilinear interpolation, as used by two-stage detectors.
+///
+/// `boxes` has shape `[num_boxes, 4]`, where each row is
+/// `[y1, x1, y2, x2]` in normalized coordinates, and `box_ind` has shape
+/// `[num_boxes]` and gives the index of the image in the batch that each box
+/// refers to.  The result has shape
+/// `[num_boxes, crop_height, crop_width, channels]`.
+///
+/// This is `crop_and_resize` with the crop size given as a constant.
+pub fn crop_and_resize_to_size<O1: Into<Output>, O2: Into<Output>, O3: Into<Output>>(
+    image: O1,
+    boxes: O2,
+    box_ind: O3,
+    crop_size: [i32; 2],
+    scope: &mut Scope,
+) -> Result<Operation> {
+    let crop_size = size_constant(crop_size, scope)?;
+    CropAndResize::new().build(image, boxes, box_ind, crop_size, scope)
+}
+
+/// Greedily selects boxes in descending order of score, skipping boxes
+/// which overlap a previously selected box by more than `iou_threshold`, and
+/// returns the `int32` indices of at most `max_output_size` selected boxes.
+///
+/// `boxes` has shape `[num_boxes, 4]`, where each row is `[y1, x1, y2, x2]`,
+/// and `scores` has shape `[num_boxes]`; both must be `float32`.  Boxes with
+/// a score of at most `score_threshold` are never selected.  Use
+/// `gather` with the indices to get the selected boxes and scores.
+///
+/// This is `non_max_suppression_v5` with the scalar inputs given as
+/// constants and soft NMS disabled.
+pub fn non_max_suppression_with_thresholds<O1: Into<Output>, O2: Into<Output>>(
+    boxes: O1,
+    scores: O2,
+    max_output_size: i32,
+    iou_threshold: f32,
+    score_threshold: f32,
+    scope: &mut Scope,
+) -> Result<Output> {
+    if max_output_size < 0 {
+        return Err(invalid_arg!(
+            "max_output_size must not be negative, got {}",
+            max_output_size
+        ));
+    }
+    if !(0.0..=1.0).contains(&iou_threshold) {
+        return Err(invalid_arg!(
+            "iou_threshold must be in [0, 1], got {}",
+            iou_threshold
+        ));
+    }
+    let max_output_size = int_constant(max_output_size, scope)?;
+    let iou_threshold = constant(iou_threshold, scope)?;
+    let score_threshold = constant(score_threshold, scope)?;
+    let soft_nms_sigma = constant(0.0f32, scope)?;
+    let nms = NonMaxSuppressionV5::new().build(
+        boxes,
+        scores,
+        max_output_size,
+        iou_threshold,
+        score_threshold,
+        soft_nms_sigma,
+        scope,
+    )?;
+    Ok(Output {
+        operation: nms,
+        index: 0,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::run_f32;
+    use super::*;
+    use crate::Code;
+    use crate::Session;
+    use crate::SessionOptions;
+    use crate::SessionRunArgs;
+    use crate::Tensor;
+
+    #[test]
+    fn test_resize_nearest_neighbor_to_size() {
+        let mut scope = Scope::new_root_scope();
+        let images = constant(
+            Tensor::new(&[1, 2, 2, 1])
+                .with_values(&[1.0f32, 2.0, 3.0, 4.0])
+                .unwrap(),
+            &mut scope,
+        )
+        .unwrap();
+        let resized = resize_nearest_neighbor_to_size(images.clone(), [2, 4], &mut scope).unwrap();
+        let result = run_f32(&scope, &resized);
+        assert_eq!(result.dims(), &[1, 2, 4, 1]);
+        assert_eq!(&result[..], &[1.0, 1.0, 2.0, 2.0, 3.0, 3.0, 4.0, 4.0]);
+        assert_eq!(
+            resize_nearest_neighbor_to_size(images, [0, 4], &mut scope)
+                .unwrap_err()
+                .code(),
+            Code::InvalidArgument
+        );
+    }
+
+    #[test]
+    fn test_crop_and_resize_to_size() {
+        let mut scope = Scope::new_root_scope();
+        let image = constant(
+            Tensor::new(&[1, 2, 2, 1])
+                .with_values(&[1.0f32, 2.0, 3.0, 4.0])
+                .unwrap(),
+            &mut scope,
+        )
+        .unwrap();
+        // The whole image, and its top-left pixel.
+        let boxes = constant(
+            Tensor::new(&[2, 4])
+                .with_values(&[0.0f32, 0.0, 1.0, 1.0, 0.0, 0.0, 0.0, 0.0])
+                .unwrap(),
+            &mut scope,
+        )
+        .unwrap();
+        let box_ind = constant(&[0i32, 0][..], &mut scope).unwrap();
+        let crops = crop_and_resize_to_size(image, boxes, box_ind, [2, 2], &mut scope).unwrap();
+        let result = run_f32(&scope, &crops);
+        assert_eq!(result.dims(), &[2, 2, 2, 1]);
+        assert_eq!(&result[..], &[1.0, 2.0, 3.0, 4.0, 1.0, 1.0, 1.0, 1.0]);
+    }
+
+    #[test]
+    fn test_non_max_suppression_with_thresholds() {
+        let mut scope = Scope::new_root_scope();
+        // The second box overlaps the first one almost completely, the third
+        // one is disjoint, and the fourth one has a low score.
+        let boxes = constant(
+            Tensor::new(&[4, 4])
+                .with_values(&[
+                    0.0f32, 0.0, 1.0, 1.0, 0.0, 0.1, 1.0, 1.1, 0.0, 2.0, 1.0, 3.0, 0.0, 4.0, 1.0,
+                    5.0,
+                ])
+                .unwrap(),
+            &mut scope,
+        )
+        .unwrap();
+        let scores = constant(&[0.8f32, 0.9, 0.7, 0.1][..], &mut scope).unwrap();
+        let selected = non_max_suppression_with_thresholds(
+            boxes.clone(),
+            scores.clone(),
+            10,
+            0.5,
+            0.2,
+            &mut scope,
+        )
+        .unwrap();
+
+        let options = SessionOptions::new();
+        let session = Session::new(&options, &scope.graph()).unwrap();
+        let mut run_args = SessionRunArgs::new();
+        let fetch = run_args.request_fetch(&selected.operation, selected.index);
+        session.run(&mut run_args).unwrap();
+        let result: Tensor<i32> = run_args.fetch(fetch).unwrap();
+        assert_eq!(&result[..], &[1, 2]);
+        assert_eq!(
+            non_max_suppression_with_thresholds(boxes, scores, 10, 1.5, 0.2, &mut scope)
+                .unwrap_err()
+                .code(),
+            Code::InvalidArgument
+        );
+    }
+}
@@ -1,5 +1,15 @@
+use super::All;
+use super::Any;
+use super::Cumprod;
+use super::Cumsum;
+use super::MatMul;
+use super::MatrixInverse;
+use super::Max;
+use super::Sum;
+use super::Svd;
 use crate::AnyTensor;
 use crate::Operation;
+use crate::Output;
 use crate::Result;
 use crate::Scope;
 use crate::Tensor;
@@ -31,21 +41,42 @@ pub fn constant<T: TensorType, TT: Into<Tensor<T>>>(
     value: TT,
     scope: &mut Scope,
 ) -> Result<Operation> {
-    let name = scope.get_unique_name_for_op("Const");
-    let mut graph = scope.graph_mut();
-    let mut c = graph.new_operation("Const", &name)?;
-    c.set_attr_tensor("value", value.into())?;
-    c.set_attr_type("dtype", T::data_type())?;
-    c.finish()
+    scope.new_operation("Const", |nd| {
+        nd.set_attr_tensor("value", value.into())?;
+        nd.set_attr_type("dtype", T::data_type())?;
+        Ok(())
+    })
+}
+
+/// Like `constant`, but reuses the `Const` built earlier with this scope, or
+/// any scope sharing its root, for the same values, data type and device, so
+/// that e.g. repeated reductions over the same axes share one constant.
+/// This is used by the wrappers which build small axis and shape tensors.
+pub(crate) fn int_constant<T: TensorType + Copy + Into<i64>, TT: Into<Tensor<T>>>(
+    value: TT,
+    scope: &mut Scope,
+) -> Result<Operation> {
+    let value = value.into();
+    let key = (
+        scope.device().to_string(),
+        T::data_type().to_int(),
+        value.dims().to_vec(),
+        value.iter().map(|v| (*v).into()).collect(),
+    );
+    if let Some(constant) = scope.cached_int_constant(&key) {
+        return Ok(constant);
+    }
+    let constant = constant(value, scope)?;
+    scope.cache_int_constant(key, constant.clone());
+    Ok(constant)
 }
 
 pub(crate) fn any_constant(value: &dyn AnyTensor, scope: &mut Scope) -> Result<Operation> {
-    let name = scope.get_unique_name_for_op("Const");
-    let mut graph = scope.graph_mut();
-    let mut c = graph.new_operation("Const", &name)?;
-    c.set_attr_any_tensor("value", value)?;
-    c.set_attr_type("dtype", value.data_type())?;
-    c.finish()
+    scope.new_operation("Const", |nd| {
+        nd.set_attr_any_tensor("value", value)?;
+        nd.set_attr_type("dtype", value.data_type())?;
+        Ok(())
+    })
 }
 
 #[deprecated(note = "Use mul instead.", since = "0.15.0")]
@@ -53,3 +84,514 @@ define_op!(multiply, Multiply, "Mul", args { a, b });
 
 #[deprecated(note = "Use sub instead.", since = "0.15.0")]
 define_op!(subtract, Subtract, "Sub", args { a, b });
+
+/// Multiplies matrices `a` and `b`, optionally transposing either of them
+/// first.
+///
+/// The transposes are fused into the multiplication, which is cheaper than
+/// adding separate `Transpose` ops, e.g. `mat_mul_t(a, b, true, false, scope)`
+/// computes `a^T b`.
+pub fn mat_mul_t<O1: Into<Output>, O2: Into<Output>>(
+    a: O1,
+    b: O2,
+    transpose_a: bool,
+    transpose_b: bool,
+    scope: &mut Scope,
+) -> Result<Operation> {
+    MatMul::new()
+        .transpose_a(transpose_a)
+        .transpose_b(transpose_b)
+        .build(a, b, scope)
+}
+
+/// Computes the inverse of each of the square matrices in the innermost two
+/// dimensions of `input`, or of their adjoints (conjugate transposes) if
+/// `adjoint` is true.
+///
+/// Like for `mat_mul_t`, the adjoint is fused into the operation, which is
+/// cheaper than adding a separate `Transpose`.  The Cholesky decomposition is
+/// available as `cholesky`.
+pub fn matrix_inverse_t<O: Into<Output>>(
+    input: O,
+    adjoint: bool,
+    scope: &mut Scope,
+) -> Result<Operation> {
+    MatrixInverse::new().adjoint(adjoint).build(input, scope)
+}
+
+/// Computes the singular value decomposition of each of the matrices in the
+/// innermost two dimensions of `input`, returning `(s, u, v)` such that
+/// `input = u * diag(s) * adjoint(v)`.
+///
+/// For `[M, N]` matrices with `P = min(M, N)`, `s` has the `P` singular values
+/// in descending order.  If `full_matrices` is true, `u` is `[M, M]` and `v`
+/// is `[N, N]`; otherwise they are `[M, P]` and `[N, P]`.  If `compute_uv` is
+/// false, only `s` is computed and `u` and `v` are empty.
+pub fn svd_uv<O: Into<Output>>(
+    input: O,
+    compute_uv: bool,
+    full_matrices: bool,
+    scope: &mut Scope,
+) -> Result<(Output, Output, Output)> {
+    let svd = Svd::new()
+        .compute_uv(compute_uv)
+        .full_matrices(full_matrices)
+        .build(input, scope)?;
+    let output = |index| Output {
+        operation: svd.clone(),
+        index,
+    };
+    Ok((output(0), output(1), output(2)))
+}
+
+/// Computes `log(sum(exp(input)))` across the dimensions given by `axes`.
+///
+/// This is computed in a numerically stable way by subtracting the maximum
+/// value along `axes` before exponentiating, so large inputs don't overflow
+/// and small inputs don't underflow to `log(0)`.  If `keep_dims` is true, the
+/// reduced dimensions are retained with length 1.
+pub fn reduce_logsumexp<O: Into<Output>>(
+    input: O,
+    axes: &[i64],
+    keep_dims: bool,
+    scope: &mut Scope,
+) -> Result<Operation> {
+    let input = input.into();
+    let axes = int_constant(axes, scope)?;
+    let raw_max = Max::new()
+        .keep_dims(true)
+        .build(input.clone(), axes.clone(), scope)?;
+    // If the maximum is infinite (e.g. every input is -inf), subtracting it
+    // would produce NaN, so subtract zero instead.
+    let finite = super::is_finite(raw_max.clone(), scope)?;
+    let zeros = super::zeros_like(raw_max.clone(), scope)?;
+    let max = super::select(finite, raw_max, zeros, scope)?;
+    let max = super::stop_gradient(max, scope)?;
+    let shifted = super::sub(input, max.clone(), scope)?;
+    let exp = super::exp(shifted, scope)?;
+    let sum = Sum::new().keep_dims(keep_dims).build(exp, axes, scope)?;
+    let log = super::log(sum.clone(), scope)?;
+    let max = if keep_dims {
+        max
+    } else {
+        let shape = super::shape(sum, scope)?;
+        super::reshape(max, shape, scope)?
+    };
+    super::add(log, max, scope)
+}
+
+/// Computes the logical and of the `bool` tensor `input` across the
+/// dimensions given by `axes`, e.g. to check that every sample of a batch is
+/// valid.  An empty `axes` leaves `input` unchanged.  If `keep_dims` is true,
+/// the reduced dimensions are retained with length 1.
+pub fn reduce_all<O: Into<Output>>(
+    input: O,
+    axes: &[i64],
+    keep_dims: bool,
+    scope: &mut Scope,
+) -> Result<Operation> {
+    let axes = int_constant(axes, scope)?;
+    All::new().keep_dims(keep_dims).build(input, axes, scope)
+}
+
+/// Computes the logical or of the `bool` tensor `input` across the
+/// dimensions given by `axes`, e.g. to check whether any sample of a batch is
+/// invalid.  An empty `axes` leaves `input` unchanged.  If `keep_dims` is
+/// true, the reduced dimensions are retained with length 1.
+pub fn reduce_any<O: Into<Output>>(
+    input: O,
+    axes: &[i64],
+    keep_dims: bool,
+    scope: &mut Scope,
+) -> Result<Operation> {
+    let axes = int_constant(axes, scope)?;
+    Any::new().keep_dims(keep_dims).build(input, axes, scope)
+}
+
+/// Computes the sum along segments of `data`, where `segment_ids` gives the
+/// segment of each slice of `data` along its leading dimensions.
+///
+/// Unlike `segment_sum`, the ids don't need to be sorted and some segments
+/// may be empty, which makes this suitable for aggregating the messages of a
+/// graph neural network by destination node.  Segments with no slices are
+/// zero, and slices with ids outside `[0, num_segments)` are dropped.
+pub fn unsorted_segment_sum_n<O1: Into<Output>, O2: Into<Output>>(
+    data: O1,
+    segment_ids: O2,
+    num_segments: i64,
+    scope: &mut Scope,
+) -> Result<Operation> {
+    let num_segments = int_constant(num_segments, scope)?;
+    super::unsorted_segment_sum(data, segment_ids, num_segments, scope)
+}
+
+/// Counts the occurrences of each value in `arr`, which must be an `int32`
+/// tensor of non-negative values, returning an `int32` tensor of `size`
+/// counts.  Values greater than or equal to `size` are ignored.
+///
+/// This is `bincount` without weights.  To sum weights instead of counting,
+/// use `bincount` directly.
+pub fn bincount_n<O: Into<Output>>(arr: O, size: i32, scope: &mut Scope) -> Result<Operation> {
+    let size = int_constant(size, scope)?;
+    // Empty weights count each value once.
+    let weights = constant(Tensor::<i32>::new(&[0]), scope)?;
+    super::bincount(arr, size, weights, scope)
+}
+
+/// Computes the cumulative sum of `x` along `axis`, which counts from the
+/// end if negative.
+///
+/// If `exclusive` is true, each element is the sum of the elements before
+/// it, e.g. `[a, b, c]` becomes `[0, a, a + b]`.  If `reverse` is true, the
+/// sum runs from the end, e.g. `[a + b + c, b + c, c]`.  This is `cumsum`
+/// with the axis given as a value rather than as a tensor.
+pub fn cumsum_at<O: Into<Output>>(
+    x: O,
+    axis: i64,
+    exclusive: bool,
+    reverse: bool,
+    scope: &mut Scope,
+) -> Result<Operation> {
+    let axis = int_constant(axis, scope)?;
+    Cumsum::new()
+        .exclusive(exclusive)
+        .reverse(reverse)
+        .build(x, axis, scope)
+}
+
+/// Computes the cumulative product of `x` along `axis`, which counts from
+/// the end if negative.
+///
+/// `exclusive` and `reverse` work as for `cumsum_at`, with an exclusive
+/// product starting at 1.  This is `cumprod` with the axis given as a value
+/// rather than as a tensor.
+pub fn cumprod_at<O: Into<Output>>(
+    x: O,
+    axis: i64,
+    exclusive: bool,
+    reverse: bool,
+    scope: &mut Scope,
+) -> Result<Operation> {
+    let axis = int_constant(axis, scope)?;
+    Cumprod::new()
+        .exclusive(exclusive)
+        .reverse(reverse)
+        .build(x, axis, scope)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Session;
+    use crate::SessionOptions;
+    use crate::SessionRunArgs;
+
+    #[test]
+    fn test_mat_mul_t() {
+        let mut scope = Scope::new_root_scope();
+        let a = constant(
+            Tensor::new(&[2, 3])
+                .with_values(&[1.0f32, 2.0, 3.0, 4.0, 5.0, 6.0])
+                .unwrap(),
+            &mut scope,
+        )
+        .unwrap();
+        let b = constant(
+            Tensor::new(&[2, 2])
+                .with_values(&[1.0f32, 0.0, 0.0, 2.0])
+                .unwrap(),
+            &mut scope,
+        )
+        .unwrap();
+        let at_b = mat_mul_t(a.clone(), b.clone(), true, false, &mut scope).unwrap();
+        let at_bt = mat_mul_t(a.clone(), b.clone(), true, true, &mut scope).unwrap();
+        let b_a = mat_mul_t(b, a, false, false, &mut scope).unwrap();
+
+        let options = SessionOptions::new();
+        let session = Session::new(&options, &scope.graph()).unwrap();
+        let mut run_args = SessionRunArgs::new();
+        let at_b_fetch = run_args.request_fetch(&at_b, 0);
+        let at_bt_fetch = run_args.request_fetch(&at_bt, 0);
+        let b_a_fetch = run_args.request_fetch(&b_a, 0);
+        session.run(&mut run_args).unwrap();
+        let at_b = run_args.fetch::<f32>(at_b_fetch).unwrap();
+        assert_eq!(at_b.dims(), &[3, 2]);
+        assert_eq!(&at_b[..], &[1.0, 8.0, 2.0, 10.0, 3.0, 12.0]);
+        // b is symmetric, so transposing it doesn't change the result.
+        assert_eq!(run_args.fetch::<f32>(at_bt_fetch).unwrap(), at_b);
+        let b_a = run_args.fetch::<f32>(b_a_fetch).unwrap();
+        assert_eq!(&b_a[..], &[1.0, 2.0, 3.0, 8.0, 10.0, 12.0]);
+    }
+
+    #[test]
+    fn test_linalg() {
+        let mut scope = Scope::new_root_scope();
+        let a = constant(
+            Tensor::new(&[2, 2])
+                .with_values(&[4.0f32, 2.0, 2.0, 3.0])
+                .unwrap(),
+            &mut scope,
+        )
+        .unwrap();
+        let b = constant(
+            Tensor::new(&[3, 2])
+                .with_values(&[0.0f32, 2.0, 3.0, 0.0, 0.0, 0.0])
+                .unwrap(),
+            &mut scope,
+        )
+        .unwrap();
+        let upper = constant(
+            Tensor::new(&[2, 2])
+                .with_values(&[1.0f32, 2.0, 0.0, 4.0])
+                .unwrap(),
+            &mut scope,
+        )
+        .unwrap();
+        let cholesky = super::super::cholesky(a, &mut scope).unwrap();
+        let inverse = matrix_inverse_t(upper.clone(), false, &mut scope).unwrap();
+        let adjoint_inverse = matrix_inverse_t(upper, true, &mut scope).unwrap();
+        let (s, u, v) = svd_uv(b.clone(), true, false, &mut scope).unwrap();
+        let (_, full_u, full_v) = svd_uv(b.clone(), true, true, &mut scope).unwrap();
+        let (s_only, empty_u, _) = svd_uv(b, false, false, &mut scope).unwrap();
+
+        let options = SessionOptions::new();
+        let session = Session::new(&options, &scope.graph()).unwrap();
+        let mut run_args = SessionRunArgs::new();
+        let cholesky_fetch = run_args.request_fetch(&cholesky, 0);
+        let inverse_fetch = run_args.request_fetch(&inverse, 0);
+        let adjoint_inverse_fetch = run_args.request_fetch(&adjoint_inverse, 0);
+        let svd_fetches: Vec<_> = [s, u, v, full_u, full_v, s_only, empty_u]
+            .iter()
+            .map(|output| run_args.request_fetch(&output.operation, output.index))
+            .collect();
+        session.run(&mut run_args).unwrap();
+        let assert_close = |actual: &[f32], expected: &[f32]| {
+            assert_eq!(actual.len(), expected.len());
+            for (a, e) in actual.iter().zip(expected) {
+                assert!((a - e).abs() < 1e-5, "{:?} vs {:?}", actual, expected);
+            }
+        };
+        assert_close(
+            &run_args.fetch::<f32>(cholesky_fetch).unwrap(),
+            &[2.0, 0.0, 1.0, 2.0f32.sqrt()],
+        );
+        assert_close(
+            &run_args.fetch::<f32>(inverse_fetch).unwrap(),
+            &[1.0, -0.5, 0.0, 0.25],
+        );
+        assert_close(
+            &run_args.fetch::<f32>(adjoint_inverse_fetch).unwrap(),
+            &[1.0, 0.0, -0.5, 0.25],
+        );
+        let svd: Vec<Tensor<f32>> = svd_fetches
+            .into_iter()
+            .map(|token| run_args.fetch::<f32>(token).unwrap())
+            .collect();
+        assert_close(&svd[0], &[3.0, 2.0]);
+        assert_eq!(svd[1].dims(), &[3, 2]);
+        assert_eq!(svd[2].dims(), &[2, 2]);
+        assert_eq!(svd[3].dims(), &[3, 3]);
+        assert_eq!(svd[4].dims(), &[2, 2]);
+        assert_close(&svd[5], &[3.0, 2.0]);
+        assert_eq!(svd[6].len(), 0);
+        // Check that u * diag(s) * v^T reconstructs the input.
+        let (s, u, v) = (&svd[0], &svd[1], &svd[2]);
+        let mut reconstructed = vec![0.0f32; 6];
+        for i in 0..3 {
+            for j in 0..2 {
+                for k in 0..2 {
+                    reconstructed[i * 2 + j] += u[i * 2 + k] * s[k] * v[j * 2 + k];
+                }
+            }
+        }
+        assert_close(&reconstructed, &[0.0, 2.0, 3.0, 0.0, 0.0, 0.0]);
+    }
+
+    #[test]
+    fn test_reduce_logsumexp() {
+        let mut scope = Scope::new_root_scope();
+        let x = constant(
+            Tensor::new(&[2, 2])
+                .with_values(&[1000.0f32, 1000.0, -1000.0, f32::NEG_INFINITY])
+                .unwrap(),
+            &mut scope,
+        )
+        .unwrap();
+        let y = reduce_logsumexp(x.clone(), &[1], false, &mut scope).unwrap();
+        let y_keep = reduce_logsumexp(x, &[1], true, &mut scope).unwrap();
+
+        let options = SessionOptions::new();
+        let session = Session::new(&options, &scope.graph()).unwrap();
+        let mut run_args = SessionRunArgs::new();
+        let y_fetch = run_args.request_fetch(&y, 0);
+        let y_keep_fetch = run_args.request_fetch(&y_keep, 0);
+        session.run(&mut run_args).unwrap();
+        let y = run_args.fetch::<f32>(y_fetch).unwrap();
+        let y_keep = run_args.fetch::<f32>(y_keep_fetch).unwrap();
+        assert_eq!(y.dims(), &[2]);
+        assert_eq!(y_keep.dims(), &[2, 1]);
+        for output in &[y, y_keep] {
+            assert!((output[0] - (1000.0 + 2.0f32.ln())).abs() < 1e-3);
+            assert!((output[1] - -1000.0).abs() < 1e-3);
+        }
+    }
+
+    #[test]
+    fn test_reduce_all_and_any() {
+        let mut scope = Scope::new_root_scope();
+        let x = constant(
+            Tensor::new(&[2, 3])
+                .with_values(&[true, true, true, false, true, false])
+                .unwrap(),
+            &mut scope,
+        )
+        .unwrap();
+        let all_rows = reduce_all(x.clone(), &[1], false, &mut scope).unwrap();
+        let any_columns = reduce_any(x.clone(), &[0], true, &mut scope).unwrap();
+        let all = reduce_all(x.clone(), &[0, 1], false, &mut scope).unwrap();
+        let any = reduce_any(x, &[0, 1], false, &mut scope).unwrap();
+
+        let options = SessionOptions::new();
+        let session = Session::new(&options, &scope.graph()).unwrap();
+        let mut run_args = SessionRunArgs::new();
+        let all_rows_fetch = run_args.request_fetch(&all_rows, 0);
+        let any_columns_fetch = run_args.request_fetch(&any_columns, 0);
+        let all_fetch = run_args.request_fetch(&all, 0);
+        let any_fetch = run_args.request_fetch(&any, 0);
+        session.run(&mut run_args).unwrap();
+        assert_eq!(
+            &run_args.fetch::<bool>(all_rows_fetch).unwrap()[..],
+            &[true, false]
+        );
+        let any_columns = run_args.fetch::<bool>(any_columns_fetch).unwrap();
+        assert_eq!(any_columns.dims(), &[1, 3]);
+        assert_eq!(&any_columns[..], &[true, true, true]);
+        assert!(!run_args.fetch::<bool>(all_fetch).unwrap()[0]);
+        assert!(run_args.fetch::<bool>(any_fetch).unwrap()[0]);
+    }
+
+    #[test]
+    fn test_segment_sum() {
+        let mut scope = Scope::new_root_scope();
+        let data = constant(
+            Tensor::new(&[4, 2])
+                .with_values(&[1.0f32, 2.0, 3.0, 4.0, 5.0, 6.0, 7.0, 8.0])
+                .unwrap(),
+            &mut scope,
+        )
+        .unwrap();
+        let sorted_ids = constant(&[0i32, 0, 1, 1][..], &mut scope).unwrap();
+        let unsorted_ids = constant(&[2i32, 0, 2, 0][..], &mut scope).unwrap();
+        let sorted = super::super::segment_sum(data.clone(), sorted_ids, &mut scope).unwrap();
+        let unsorted = unsorted_segment_sum_n(data, unsorted_ids, 4, &mut scope).unwrap();
+
+        let options = SessionOptions::new();
+        let session = Session::new(&options, &scope.graph()).unwrap();
+        let mut run_args = SessionRunArgs::new();
+        let sorted_fetch = run_args.request_fetch(&sorted, 0);
+        let unsorted_fetch = run_args.request_fetch(&unsorted, 0);
+        session.run(&mut run_args).unwrap();
+        let sorted = run_args.fetch::<f32>(sorted_fetch).unwrap();
+        assert_eq!(sorted.dims(), &[2, 2]);
+        assert_eq!(&sorted[..], &[4.0, 6.0, 12.0, 14.0]);
+        let unsorted = run_args.fetch::<f32>(unsorted_fetch).unwrap();
+        assert_eq!(unsorted.dims(), &[4, 2]);
+        assert_eq!(&unsorted[..], &[10.0, 12.0, 0.0, 0.0, 6.0, 8.0, 0.0, 0.0]);
+    }
+
+    #[test]
+    fn test_bincount_n() {
+        let mut scope = Scope::new_root_scope();
+        let labels = constant(&[1i32, 3, 1, 0, 7][..], &mut scope).unwrap();
+        let counts = bincount_n(labels, 4, &mut scope).unwrap();
+
+        let options = SessionOptions::new();
+        let session = Session::new(&options, &scope.graph()).unwrap();
+        let mut run_args = SessionRunArgs::new();
+        let counts_fetch = run_args.request_fetch(&counts, 0);
+        session.run(&mut run_args).unwrap();
+        let counts = run_args.fetch::<i32>(counts_fetch).unwrap();
+        assert_eq!(&counts[..], &[1, 2, 0, 1]);
+    }
+
+    #[test]
+    fn test_cumulative() {
+        let mut scope = Scope::new_root_scope();
+        let x = constant(
+            Tensor::new(&[2, 3])
+                .with_values(&[1.0f32, 2.0, 3.0, 4.0, 5.0, 6.0])
+                .unwrap(),
+            &mut scope,
+        )
+        .unwrap();
+        let sum = cumsum_at(x.clone(), 1, false, false, &mut scope).unwrap();
+        let exclusive_sum = cumsum_at(x.clone(), -1, true, false, &mut scope).unwrap();
+        let reverse_sum = cumsum_at(x.clone(), 0, false, true, &mut scope).unwrap();
+        let product = cumprod_at(x.clone(), 1, false, false, &mut scope).unwrap();
+        let exclusive_reverse_product = cumprod_at(x, 1, true, true, &mut scope).unwrap();
+
+        let options = SessionOptions::new();
+        let session = Session::new(&options, &scope.graph()).unwrap();
+        let mut run_args = SessionRunArgs::new();
+        let fetches: Vec<_> = [
+            &sum,
+            &exclusive_sum,
+            &reverse_sum,
+            &product,
+            &exclusive_reverse_product,
+        ]
+        .iter()
+        .map(|op| run_args.request_fetch(op, 0))
+        .collect();
+        session.run(&mut run_args).unwrap();
+        let results: Vec<Vec<f32>> = fetches
+            .into_iter()
+            .map(|fetch| run_args.fetch::<f32>(fetch).unwrap().to_vec())
+            .collect();
+        assert_eq!(results[0], [1.0, 3.0, 6.0, 4.0, 9.0, 15.0]);
+        assert_eq!(results[1], [0.0, 1.0, 3.0, 0.0, 4.0, 9.0]);
+        assert_eq!(results[2], [5.0, 7.0, 9.0, 4.0, 5.0, 6.0]);
+        assert_eq!(results[3], [1.0, 2.0, 6.0, 4.0, 20.0, 120.0]);
+        assert_eq!(results[4], [6.0, 3.0, 1.0, 30.0, 6.0, 1.0]);
+    }
+
+    #[test]
+    fn test_int_constant() {
+        let name = |operation: Operation| operation.name().unwrap();
+        let mut scope = Scope::new_root_scope();
+        let axes = int_constant(&[1i64][..], &mut scope).unwrap();
+        assert_eq!(
+            name(int_constant(&[1i64][..], &mut scope).unwrap()),
+            "Const"
+        );
+        assert_eq!(
+            name(int_constant(&[1i64][..], &mut scope.new_sub_scope("sub")).unwrap()),
+            "Const"
+        );
+        // The data type, shape and device distinguish constants.
+        assert_eq!(
+            name(int_constant(&[1i32][..], &mut scope).unwrap()),
+            "Const_1"
+        );
+        assert_eq!(name(int_constant(1i64, &mut scope).unwrap()), "Const_2");
+        let mut cpu_scope = scope.with_device("/device:CPU:0");
+        assert_eq!(
+            name(int_constant(&[1i64][..], &mut cpu_scope).unwrap()),
+            "Const_3"
+        );
+        assert_eq!(
+            name(int_constant(&[1i64][..], &mut cpu_scope).unwrap()),
+            "Const_3"
+        );
+        // Constants with control dependencies are not shared.
+        let mut dependent_scope = scope.with_control_dependencies(&[axes]);
+        assert_eq!(
+            name(int_constant(&[1i64][..], &mut dependent_scope).unwrap()),
+            "Const_4"
+        );
+        assert_eq!(
+            name(int_constant(&[1i64][..], &mut dependent_scope).unwrap()),
+            "Const_5"
+        );
+    }
+}
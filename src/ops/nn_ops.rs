@@ -0,0 +1,714 @@
+use super::softmax;
+use super::top_kv2;
+use super::Cast;
+use super::Conv2D;
+use super::DepthwiseConv2dNative;
+use super::Mean;
+use super::SoftmaxCrossEntropyWithLogits;
+use super::Sum;
+use super::TopKV2;
+use crate::ops::constant;
+use crate::ops::int_constant;
+use crate::DataType;
+use crate::Operation;
+use crate::Output;
+use crate::Result;
+use crate::Scope;
+use crate::Tensor;
+
+/// Maps classifier logits to the `k` most likely labels.
+///
+/// `Classifier::new` builds a subgraph which applies softmax to the logits
+/// and picks the top `k` classes.  After fetching `scores()` and `indices()`,
+/// `decode` turns the results into `(label, probability)` pairs.
+///
+/// ```
+/// # use tensorflow::ops;
+/// # use tensorflow::ops::Classifier;
+/// # use tensorflow::Scope;
+/// # use tensorflow::Tensor;
+/// let mut scope = Scope::new_root_scope();
+/// let logits = ops::constant(Tensor::new(&[1, 3]).with_values(&[1.0f32, 3.0, 2.0])?, &mut scope)?;
+/// let classifier = Classifier::new(logits, &["cat", "dog", "bird"], 2, &mut scope)?;
+/// // Fetch classifier.scores() and classifier.indices(), then:
+/// // let predictions = classifier.decode(&scores, &indices)?;
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+#[derive(Debug, Clone)]
+pub struct Classifier {
+    labels: Vec<String>,
+    scores: Output,
+    indices: Output,
+}
+
+impl Classifier {
+    /// Builds the classification subgraph for `logits`, which must have shape
+    /// `[num_labels]` or `[batch_size, num_labels]`.  `labels[i]` is the label
+    /// of class `i`.
+    pub fn new<O: Into<Output>, S: AsRef<str>>(
+        logits: O,
+        labels: &[S],
+        k: i64,
+        scope: &mut Scope,
+    ) -> Result<Self> {
+        let logits = logits.into();
+        if k < 1 || k as usize > labels.len() {
+            return Err(invalid_arg!(
+                "Classifier: k must be between 1 and the number of labels ({}), got {}",
+                labels.len(),
+                k
+            ));
+        }
+        let shape = scope.graph().tensor_shape(logits.clone())?;
+        if let Some(dims) = &shape.0 {
+            if dims.is_empty() || dims.len() > 2 {
+                return Err(invalid_arg!(
+                    "Classifier: logits must have rank 1 or 2, got shape {}",
+                    shape
+                ));
+            }
+            if let Some(num_classes) = dims[dims.len() - 1] {
+                if num_classes != labels.len() as i64 {
+                    return Err(invalid_arg!(
+                        "Classifier: logits have {} classes, but {} labels were given",
+                        num_classes,
+                        labels.len()
+                    ));
+                }
+            }
+        }
+        let probabilities = softmax(logits, scope)?;
+        let k = int_constant(k as i32, scope)?;
+        let top_k = top_kv2(probabilities, k, scope)?;
+        Ok(Classifier {
+            labels: labels
+                .iter()
+                .map(|label| label.as_ref().to_string())
+                .collect(),
+            scores: Output {
+                operation: top_k.clone(),
+                index: 0,
+            },
+            indices: Output {
+                operation: top_k,
+                index: 1,
+            },
+        })
+    }
+
+    /// Returns the labels, indexed by class.
+    pub fn labels(&self) -> &[String] {
+        &self.labels
+    }
+
+    /// Returns the probabilities of the top `k` classes, in descending order.
+    pub fn scores(&self) -> &Output {
+        &self.scores
+    }
+
+    /// Returns the `int32` class indices of the top `k` classes, in the same
+    /// order as `scores()`.
+    pub fn indices(&self) -> &Output {
+        &self.indices
+    }
+
+    /// Decodes the fetched values of `scores()` and `indices()` into
+    /// `(label, probability)` pairs, with one list per row of the logits.
+    pub fn decode(
+        &self,
+        scores: &Tensor<f32>,
+        indices: &Tensor<i32>,
+    ) -> Result<Vec<Vec<(String, f32)>>> {
+        if scores.dims() != indices.dims() || scores.dims().is_empty() {
+            return Err(invalid_arg!(
+                "Classifier: scores and indices must have the same non-scalar shape, \
+                 got {:?} and {:?}",
+                scores.dims(),
+                indices.dims()
+            ));
+        }
+        let k = scores.dims()[scores.dims().len() - 1] as usize;
+        if k == 0 {
+            return Ok(vec![]);
+        }
+        scores
+            .chunks(k)
+            .zip(indices.chunks(k))
+            .map(|(row_scores, row_indices)| {
+                row_scores
+                    .iter()
+                    .zip(row_indices)
+                    .map(|(score, index)| match self.labels.get(*index as usize) {
+                        Some(label) if *index >= 0 => Ok((label.clone(), *score)),
+                        _ => Err(invalid_arg!(
+                            "Classifier: class index {} is out of range for {} labels",
+                            index,
+                            self.labels.len()
+                        )),
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+}
+
+/// Checks that `padding` is one of the padding algorithms supported by the
+/// convolution wrappers.
+fn check_padding(op: &str, padding: &str) -> Result<()> {
+    match padding {
+        "SAME" | "VALID" => Ok(()),
+        _ => Err(invalid_arg!(
+            "{}: padding must be \"SAME\" or \"VALID\", got {:?}",
+            op,
+            padding
+        )),
+    }
+}
+
+/// Computes a depthwise 2-D convolution of an NHWC `input` with a `filter` of
+/// shape `[height, width, in_channels, channel_multiplier]`.
+///
+/// Each input channel is convolved with its own `channel_multiplier` filters,
+/// so the output has `in_channels * channel_multiplier` channels.  `strides`
+/// are the strides along the height and width dimensions, and `padding` is
+/// either `"SAME"` or `"VALID"`.
+pub fn depthwise_conv2d<O1: Into<Output>, O2: Into<Output>>(
+    input: O1,
+    filter: O2,
+    strides: [i64; 2],
+    padding: &str,
+    scope: &mut Scope,
+) -> Result<Operation> {
+    check_padding("depthwise_conv2d", padding)?;
+    DepthwiseConv2dNative::new()
+        .strides(vec![1, strides[0], strides[1], 1])
+        .padding(padding)
+        .build(input, filter, scope)
+}
+
+/// Computes a depthwise separable 2-D convolution: a depthwise convolution
+/// with `depthwise_filter` (see `depthwise_conv2d`), followed by a 1x1
+/// convolution with `pointwise_filter`, which has shape
+/// `[1, 1, in_channels * channel_multiplier, out_channels]`.
+///
+/// `strides` and `padding` apply to the depthwise convolution.
+pub fn separable_conv2d<O1: Into<Output>, O2: Into<Output>, O3: Into<Output>>(
+    input: O1,
+    depthwise_filter: O2,
+    pointwise_filter: O3,
+    strides: [i64; 2],
+    padding: &str,
+    scope: &mut Scope,
+) -> Result<Operation> {
+    check_padding("separable_conv2d", padding)?;
+    let depthwise = depthwise_conv2d(input, depthwise_filter, strides, padding, scope)?;
+    Conv2D::new()
+        .strides(vec![1, 1, 1, 1])
+        .padding("VALID")
+        .build(depthwise, pointwise_filter, scope)
+}
+
+/// Computes the softmax cross entropy between `labels` and `logits`, both of
+/// shape `[batch_size, num_classes]`, returning `(loss, backprop)`.
+///
+/// `loss` has shape `[batch_size]`, and `backprop` is the gradient of the
+/// loss with respect to the logits, with shape `[batch_size, num_classes]`.
+/// Each row of `labels` must be a valid probability distribution.
+///
+/// Note that the arguments are in the opposite order from the generated
+/// `softmax_cross_entropy_with_logits`, which returns the raw operation.
+pub fn softmax_cross_entropy<O1: Into<Output>, O2: Into<Output>>(
+    labels: O1,
+    logits: O2,
+    scope: &mut Scope,
+) -> Result<(Output, Output)> {
+    let op = SoftmaxCrossEntropyWithLogits::new().build(logits, labels, scope)?;
+    Ok((
+        Output {
+            operation: op.clone(),
+            index: 0,
+        },
+        Output {
+            operation: op,
+            index: 1,
+        },
+    ))
+}
+
+/// Builds a scalar constant with the same data type as `like`.
+fn scalar_like(value: f32, like: &Output, scope: &mut Scope) -> Result<Output> {
+    let value = constant(value, scope)?;
+    let dtype = like.operation.output_type(like.index as usize);
+    if dtype == DataType::Float {
+        return Ok(value.into());
+    }
+    Ok(Cast::new().DstT(dtype).build(value, scope)?.into())
+}
+
+/// Normalizes `x` along `axes` so that the L2 norm along those axes is 1,
+/// i.e. computes `x / sqrt(max(sum(x^2), epsilon))`.
+///
+/// `epsilon` is a lower bound on the squared norm which avoids dividing by
+/// zero.  A negative axis counts from the last dimension.
+pub fn l2_normalize<O: Into<Output>>(
+    x: O,
+    axes: &[i64],
+    epsilon: f32,
+    scope: &mut Scope,
+) -> Result<Operation> {
+    let x = x.into();
+    let axes = int_constant(axes, scope)?;
+    let epsilon = scalar_like(epsilon, &x, scope)?;
+    let square = super::square(x.clone(), scope)?;
+    let square_sum = Sum::new().keep_dims(true).build(square, axes, scope)?;
+    let square_sum = super::maximum(square_sum, epsilon, scope)?;
+    let inv_norm = super::rsqrt(square_sum, scope)?;
+    super::mul(x, inv_norm, scope)
+}
+
+/// Computes the mean and variance of `x` across `axes`, returning
+/// `(mean, variance)`.  The reduced dimensions are removed.
+///
+/// This is the basis for batch and layer normalization.  The variance is the
+/// population variance, i.e. the mean of the squared differences from the
+/// mean.
+pub fn moments<O: Into<Output>>(
+    x: O,
+    axes: &[i64],
+    scope: &mut Scope,
+) -> Result<(Operation, Operation)> {
+    let x = x.into();
+    let axes = int_constant(axes, scope)?;
+    let mean = Mean::new()
+        .keep_dims(true)
+        .build(x.clone(), axes.clone(), scope)?;
+    // The gradient of the variance doesn't need to flow through the mean,
+    // since the derivative of the sum of squared differences with respect to
+    // the mean is zero.
+    let centered_mean = super::stop_gradient(mean.clone(), scope)?;
+    let squared_difference = super::squared_difference(x, centered_mean, scope)?;
+    let variance = Mean::new().build(squared_difference, axes, scope)?;
+    let shape = super::shape(variance.clone(), scope)?;
+    let mean = super::reshape(mean, shape, scope)?;
+    Ok((mean, variance))
+}
+
+/// Computes the Gaussian Error Linear Unit activation `x * Φ(x)`, where `Φ`
+/// is the cumulative distribution function of the standard normal
+/// distribution.
+///
+/// If `approximate` is false, this is computed exactly as
+/// `0.5 * x * (1 + erf(x / sqrt(2)))`.  Otherwise the tanh approximation
+/// `0.5 * x * (1 + tanh(sqrt(2 / π) * (x + 0.044715 * x^3)))` is used, which
+/// is what e.g. the original BERT models were trained with.
+///
+/// The other common activations are available as `relu`, `relu6`,
+/// `sigmoid` and `tanh`.
+pub fn gelu<O: Into<Output>>(x: O, approximate: bool, scope: &mut Scope) -> Result<Operation> {
+    let x = x.into();
+    let half = scalar_like(0.5, &x, scope)?;
+    let one = scalar_like(1.0, &x, scope)?;
+    let cdf = if approximate {
+        let coefficient = scalar_like(0.044715, &x, scope)?;
+        let scale = scalar_like((2.0 / std::f32::consts::PI).sqrt(), &x, scope)?;
+        let cube = super::mul(super::square(x.clone(), scope)?, x.clone(), scope)?;
+        let inner = super::add(x.clone(), super::mul(coefficient, cube, scope)?, scope)?;
+        super::tanh(super::mul(scale, inner, scope)?, scope)?
+    } else {
+        let scale = scalar_like(std::f32::consts::FRAC_1_SQRT_2, &x, scope)?;
+        super::erf(super::mul(x.clone(), scale, scope)?, scope)?
+    };
+    let cdf = super::mul(half, super::add(one, cdf, scope)?, scope)?;
+    super::mul(x, cdf, scope)
+}
+
+/// Finds the `k` largest entries along the last dimension of `input`,
+/// returning `(values, indices)`.  The indices are cast to `int64`, so the
+/// fetched results can be passed to `decode_top_k`.
+///
+/// If `sorted` is true, the entries of each row are in descending order of
+/// value.  Otherwise their order is unspecified, which can be faster when
+/// only the set of top entries matters.
+pub fn top_k_n<O: Into<Output>>(
+    input: O,
+    k: i32,
+    sorted: bool,
+    scope: &mut Scope,
+) -> Result<(Output, Output)> {
+    if k < 0 {
+        return Err(invalid_arg!("top_k_n: k must not be negative, got {}", k));
+    }
+    let k = int_constant(k, scope)?;
+    let top_k = TopKV2::new().sorted(sorted).build(input, k, scope)?;
+    let values = Output {
+        operation: top_k.clone(),
+        index: 0,
+    };
+    let indices = Cast::new().DstT(DataType::Int64).build(
+        Output {
+            operation: top_k,
+            index: 1,
+        },
+        scope,
+    )?;
+    Ok((values, indices.into()))
+}
+
+/// Turns the fetched `values` and `indices` of `top_k_n` into one list of
+/// `(index, value)` pairs per row, ranked by descending value.
+///
+/// The lists are ranked even if `top_k_n` was built with `sorted` set to
+/// false.
+///
+/// # Panics
+///
+/// Panics if `values` and `indices` have different shapes.
+pub fn decode_top_k(values: &Tensor<f32>, indices: &Tensor<i64>) -> Vec<Vec<(i64, f32)>> {
+    assert_eq!(
+        values.dims(),
+        indices.dims(),
+        "decode_top_k: values and indices must have the same shape"
+    );
+    let k = values.dims().last().cloned().unwrap_or(1) as usize;
+    if k == 0 {
+        return vec![];
+    }
+    values
+        .chunks(k)
+        .zip(indices.chunks(k))
+        .map(|(row_values, row_indices)| {
+            let mut row: Vec<(i64, f32)> = row_indices
+                .iter()
+                .cloned()
+                .zip(row_values.iter().cloned())
+                .collect();
+            row.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap_or(std::cmp::Ordering::Equal));
+            row
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::run_f32;
+    use super::*;
+    use crate::Code;
+    use crate::Session;
+    use crate::SessionOptions;
+    use crate::SessionRunArgs;
+
+    #[test]
+    fn test_depthwise_and_separable_conv2d() {
+        let mut scope = Scope::new_root_scope();
+        let input = constant(
+            Tensor::new(&[1, 3, 3, 2])
+                .with_values(&[1.0f32; 18])
+                .unwrap(),
+            &mut scope,
+        )
+        .unwrap();
+        let depthwise_filter = constant(
+            Tensor::new(&[2, 2, 2, 1])
+                .with_values(&[1.0f32, 2.0, 1.0, 2.0, 1.0, 2.0, 1.0, 2.0])
+                .unwrap(),
+            &mut scope,
+        )
+        .unwrap();
+        let pointwise_filter = constant(
+            Tensor::new(&[1, 1, 2, 3])
+                .with_values(&[1.0f32; 6])
+                .unwrap(),
+            &mut scope,
+        )
+        .unwrap();
+        assert_eq!(
+            depthwise_conv2d(
+                input.clone(),
+                depthwise_filter.clone(),
+                [1, 1],
+                "FULL",
+                &mut scope
+            )
+            .unwrap_err()
+            .code(),
+            Code::InvalidArgument
+        );
+        let valid = depthwise_conv2d(
+            input.clone(),
+            depthwise_filter.clone(),
+            [1, 1],
+            "VALID",
+            &mut scope,
+        )
+        .unwrap();
+        let strided = depthwise_conv2d(
+            input.clone(),
+            depthwise_filter.clone(),
+            [2, 2],
+            "SAME",
+            &mut scope,
+        )
+        .unwrap();
+        let separable = separable_conv2d(
+            input,
+            depthwise_filter,
+            pointwise_filter,
+            [1, 1],
+            "VALID",
+            &mut scope,
+        )
+        .unwrap();
+
+        let valid = run_f32(&scope, &valid);
+        assert_eq!(valid.dims(), &[1, 2, 2, 2]);
+        assert_eq!(&valid[..], &[4.0, 8.0, 4.0, 8.0, 4.0, 8.0, 4.0, 8.0]);
+        let strided = run_f32(&scope, &strided);
+        assert_eq!(strided.dims(), &[1, 2, 2, 2]);
+        assert_eq!(&strided[..2], &[4.0, 8.0]);
+        let separable = run_f32(&scope, &separable);
+        assert_eq!(separable.dims(), &[1, 2, 2, 3]);
+        assert_eq!(&separable[..], &[12.0f32; 12][..]);
+    }
+
+    #[test]
+    fn test_softmax_cross_entropy() {
+        let mut scope = Scope::new_root_scope();
+        let labels = constant(
+            Tensor::new(&[2, 3])
+                .with_values(&[0.0f32, 1.0, 0.0, 0.5, 0.5, 0.0])
+                .unwrap(),
+            &mut scope,
+        )
+        .unwrap();
+        let logits = constant(
+            Tensor::new(&[2, 3])
+                .with_values(&[0.0f32, 0.0, 0.0, 1.0, 2.0, 3.0])
+                .unwrap(),
+            &mut scope,
+        )
+        .unwrap();
+        let (loss, backprop) = softmax_cross_entropy(labels, logits, &mut scope).unwrap();
+        assert_eq!(
+            scope.graph().tensor_shape(loss.clone()).unwrap(),
+            crate::Shape::from(Some(vec![Some(2)]))
+        );
+        assert_eq!(
+            scope.graph().tensor_shape(backprop.clone()).unwrap(),
+            crate::Shape::from(Some(vec![Some(2), Some(3)]))
+        );
+
+        let options = SessionOptions::new();
+        let session = Session::new(&options, &scope.graph()).unwrap();
+        let mut run_args = SessionRunArgs::new();
+        let loss_fetch = run_args.request_fetch(&loss.operation, loss.index);
+        let backprop_fetch = run_args.request_fetch(&backprop.operation, backprop.index);
+        session.run(&mut run_args).unwrap();
+        let loss = run_args.fetch::<f32>(loss_fetch).unwrap();
+        let backprop = run_args.fetch::<f32>(backprop_fetch).unwrap();
+        assert_eq!(loss.dims(), &[2]);
+        assert!((loss[0] - 3.0f32.ln()).abs() < 1e-5);
+        assert_eq!(backprop.dims(), &[2, 3]);
+        // The gradient is softmax(logits) - labels.
+        assert!((backprop[0] - 1.0 / 3.0).abs() < 1e-5);
+        assert!((backprop[1] - (1.0 / 3.0 - 1.0)).abs() < 1e-5);
+    }
+
+    #[test]
+    fn test_l2_normalize() {
+        let mut scope = Scope::new_root_scope();
+        let x = constant(
+            Tensor::new(&[2, 2])
+                .with_values(&[3.0f32, 4.0, 0.0, 0.0])
+                .unwrap(),
+            &mut scope,
+        )
+        .unwrap();
+        let y = l2_normalize(x.clone(), &[-1], 1e-12, &mut scope).unwrap();
+        let x64 = Cast::new()
+            .DstT(DataType::Double)
+            .build(x, &mut scope)
+            .unwrap();
+        let y64 = l2_normalize(x64, &[0, 1], 1e-12, &mut scope).unwrap();
+        let y = run_f32(&scope, &y);
+
+        let options = SessionOptions::new();
+        let session = Session::new(&options, &scope.graph()).unwrap();
+        let mut run_args = SessionRunArgs::new();
+        let fetch = run_args.request_fetch(&y64, 0);
+        session.run(&mut run_args).unwrap();
+        let y64 = run_args.fetch::<f64>(fetch).unwrap();
+        let expected = [0.6, 0.8, 0.0, 0.0];
+        for i in 0..4 {
+            assert!((f64::from(y[i]) - expected[i]).abs() < 1e-6);
+            assert!((y64[i] - expected[i]).abs() < 1e-12);
+        }
+    }
+
+    #[test]
+    fn test_moments() {
+        let mut scope = Scope::new_root_scope();
+        let x = constant(
+            Tensor::new(&[2, 3])
+                .with_values(&[1.0f32, 2.0, 3.0, 2.0, 4.0, 6.0])
+                .unwrap(),
+            &mut scope,
+        )
+        .unwrap();
+        let (mean, variance) = moments(x.clone(), &[1], &mut scope).unwrap();
+        let (all_mean, all_variance) = moments(x, &[0, 1], &mut scope).unwrap();
+        let mean = run_f32(&scope, &mean);
+        let variance = run_f32(&scope, &variance);
+        assert_eq!(mean.dims(), &[2]);
+        assert_eq!(&mean[..], &[2.0, 4.0]);
+        assert_eq!(variance.dims(), &[2]);
+        assert!((variance[0] - 2.0 / 3.0).abs() < 1e-6);
+        assert!((variance[1] - 8.0 / 3.0).abs() < 1e-6);
+        let all_mean = run_f32(&scope, &all_mean);
+        assert_eq!(all_mean.dims(), &[] as &[u64]);
+        assert_eq!(all_mean[0], 3.0);
+        assert!((run_f32(&scope, &all_variance)[0] - 8.0 / 3.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_classifier() {
+        let mut scope = Scope::new_root_scope();
+        let logits = constant(
+            Tensor::new(&[2, 3])
+                .with_values(&[1.0f32, 3.0, 2.0, 0.0, 0.0, 5.0])
+                .unwrap(),
+            &mut scope,
+        )
+        .unwrap();
+        let labels = ["cat", "dog", "bird"];
+        assert_eq!(
+            Classifier::new(logits.clone(), &labels, 4, &mut scope)
+                .unwrap_err()
+                .code(),
+            Code::InvalidArgument
+        );
+        assert_eq!(
+            Classifier::new(logits.clone(), &labels[..2], 1, &mut scope)
+                .unwrap_err()
+                .code(),
+            Code::InvalidArgument
+        );
+        let classifier = Classifier::new(logits, &labels, 2, &mut scope).unwrap();
+
+        let options = SessionOptions::new();
+        let session = Session::new(&options, &scope.graph()).unwrap();
+        let mut run_args = SessionRunArgs::new();
+        let scores_fetch =
+            run_args.request_fetch(&classifier.scores().operation, classifier.scores().index);
+        let indices_fetch =
+            run_args.request_fetch(&classifier.indices().operation, classifier.indices().index);
+        session.run(&mut run_args).unwrap();
+        let scores = run_args.fetch::<f32>(scores_fetch).unwrap();
+        let indices = run_args.fetch::<i32>(indices_fetch).unwrap();
+        let predictions = classifier.decode(&scores, &indices).unwrap();
+        assert_eq!(predictions.len(), 2);
+        let labels: Vec<Vec<&str>> = predictions
+            .iter()
+            .map(|row| row.iter().map(|(label, _)| label.as_str()).collect())
+            .collect();
+        assert_eq!(labels, [["dog", "bird"], ["bird", "cat"]]);
+        let expected = 2.0f32.exp() / (1.0f32.exp() + 3.0f32.exp() + 2.0f32.exp());
+        assert!((predictions[0][1].1 - expected).abs() < 1e-5);
+        assert!(predictions[1][0].1 > 0.98);
+
+        let bad_indices = Tensor::new(&[1, 2]).with_values(&[0, 3]).unwrap();
+        let bad_scores = Tensor::new(&[1, 2]).with_values(&[0.5, 0.5]).unwrap();
+        assert_eq!(
+            classifier
+                .decode(&bad_scores, &bad_indices)
+                .unwrap_err()
+                .code(),
+            Code::InvalidArgument
+        );
+    }
+
+    #[test]
+    fn test_top_k_n() {
+        let mut scope = Scope::new_root_scope();
+        let input = constant(
+            Tensor::new(&[2, 4])
+                .with_values(&[1.0f32, 4.0, 3.0, 2.0, 0.5, 0.0, 2.5, 1.5])
+                .unwrap(),
+            &mut scope,
+        )
+        .unwrap();
+        assert_eq!(
+            top_k_n(input.clone(), -1, true, &mut scope)
+                .unwrap_err()
+                .code(),
+            Code::InvalidArgument
+        );
+        let (sorted_values, sorted_indices) = top_k_n(input.clone(), 2, true, &mut scope).unwrap();
+        let (values, indices) = top_k_n(input, 3, false, &mut scope).unwrap();
+
+        let options = SessionOptions::new();
+        let session = Session::new(&options, &scope.graph()).unwrap();
+        let mut run_args = SessionRunArgs::new();
+        let sorted_values_fetch =
+            run_args.request_fetch(&sorted_values.operation, sorted_values.index);
+        let sorted_indices_fetch =
+            run_args.request_fetch(&sorted_indices.operation, sorted_indices.index);
+        let values_fetch = run_args.request_fetch(&values.operation, values.index);
+        let indices_fetch = run_args.request_fetch(&indices.operation, indices.index);
+        session.run(&mut run_args).unwrap();
+        let sorted_values = run_args.fetch::<f32>(sorted_values_fetch).unwrap();
+        let sorted_indices = run_args.fetch::<i64>(sorted_indices_fetch).unwrap();
+        assert_eq!(&sorted_values[..], &[4.0, 3.0, 2.5, 1.5]);
+        assert_eq!(&sorted_indices[..], &[1, 2, 2, 3]);
+        assert_eq!(
+            decode_top_k(&sorted_values, &sorted_indices),
+            vec![vec![(1, 4.0), (2, 3.0)], vec![(2, 2.5), (3, 1.5)]]
+        );
+        let values = run_args.fetch::<f32>(values_fetch).unwrap();
+        let indices = run_args.fetch::<i64>(indices_fetch).unwrap();
+        assert_eq!(
+            decode_top_k(&values, &indices),
+            vec![
+                vec![(1, 4.0), (2, 3.0), (3, 2.0)],
+                vec![(2, 2.5), (3, 1.5), (0, 0.5)]
+            ]
+        );
+    }
+
+    #[test]
+    fn test_activations() {
+        let mut scope = Scope::new_root_scope();
+        let x = constant(&[-1.0f32, 0.0, 1.0, 2.0, 7.0][..], &mut scope).unwrap();
+        let relu = super::super::relu(x.clone(), &mut scope).unwrap();
+        let relu6 = super::super::relu6(x.clone(), &mut scope).unwrap();
+        let sigmoid = super::super::sigmoid(x.clone(), &mut scope).unwrap();
+        let tanh = super::super::tanh(x.clone(), &mut scope).unwrap();
+        let gelu_exact = gelu(x.clone(), false, &mut scope).unwrap();
+        let gelu_approximate = gelu(x, true, &mut scope).unwrap();
+        let assert_close = |op: &Operation, expected: &[f32]| {
+            let result = run_f32(&scope, op);
+            for (actual, expected) in result.iter().zip(expected) {
+                assert!(
+                    (actual - expected).abs() < 1e-5,
+                    "{} != {}",
+                    actual,
+                    expected
+                );
+            }
+        };
+        assert_close(&relu, &[0.0, 0.0, 1.0, 2.0, 7.0]);
+        assert_close(&relu6, &[0.0, 0.0, 1.0, 2.0, 6.0]);
+        assert_close(
+            &sigmoid,
+            &[0.268_941_4, 0.5, 0.731_058_6, 0.880_797, 0.999_089],
+        );
+        assert_close(
+            &tanh,
+            &[-0.761_594_2, 0.0, 0.761_594_2, 0.964_027_6, 0.999_998_4],
+        );
+        assert_close(&gelu_exact, &[-0.158_655_3, 0.0, 0.841_344_7, 1.954_5, 7.0]);
+        assert_close(
+            &gelu_approximate,
+            &[-0.158_808, 0.0, 0.841_192, 1.954_597_7, 7.0],
+        );
+    }
+}
@@ -1,4 +1,8 @@
 use crate::DataType;
+use crate::Operation;
+use crate::Output;
+use crate::Result;
+use crate::Scope;
 use tensorflow_internal_macros::define_op;
 
 #[deprecated(note = "Use random_standard_normal instead.", since = "0.15.0")]
@@ -7,3 +11,46 @@ define_op!(random_normal, RandomNormal, "RandomStandardNormal", args{x}, attrs {
     seed?: i64 => "seed",
     seed2?: i64 => "seed2",
 });
+
+/// Randomly shuffles `value` along its first dimension, using `seed` as the
+/// op-level seed so the shuffle is reproducible for a given graph seed.
+///
+/// Each slice `value[i, ...]` is moved as a whole, so this can be used to
+/// shuffle the rows of a batch.  Use `Tensor::shuffle_rows` to shuffle data
+/// on the host instead.
+pub fn random_shuffle_with_seed<O: Into<Output>>(
+    value: O,
+    seed: i64,
+    scope: &mut Scope,
+) -> Result<Operation> {
+    super::RandomShuffle::new().seed(seed).build(value, scope)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ops::constant;
+    use crate::Session;
+    use crate::SessionOptions;
+    use crate::SessionRunArgs;
+    use crate::Tensor;
+
+    #[test]
+    fn test_random_shuffle_with_seed() {
+        let mut scope = Scope::new_root_scope();
+        let value = Tensor::new(&[4, 2])
+            .with_values(&[0i32, 1, 2, 3, 4, 5, 6, 7])
+            .unwrap();
+        let value = constant(value, &mut scope).unwrap();
+        let shuffled = random_shuffle_with_seed(value, 7, &mut scope).unwrap();
+        let session = Session::new(&SessionOptions::new(), &scope.graph()).unwrap();
+        let mut run_args = SessionRunArgs::new();
+        let fetch = run_args.request_fetch(&shuffled, 0);
+        session.run(&mut run_args).unwrap();
+        let result: Tensor<i32> = run_args.fetch(fetch).unwrap();
+        assert_eq!(result.dims(), &[4, 2]);
+        let mut rows: Vec<_> = result.chunks(2).map(|row| row.to_vec()).collect();
+        rows.sort();
+        assert_eq!(rows, [[0, 1], [2, 3], [4, 5], [6, 7]]);
+    }
+}
@@ -0,0 +1,114 @@
+use super::Placeholder;
+use super::SparseToDense;
+use crate::ops::constant;
+use crate::DataType;
+use crate::Operation;
+use crate::Output;
+use crate::Result;
+use crate::Scope;
+use crate::SessionRunArgs;
+use crate::Shape;
+use crate::SparseTensor;
+use crate::TensorType;
+
+/// The three component tensors of a sparse tensor in a graph, such as a
+/// sparse input.  See `SparseTensor` for the meaning of the components.
+#[derive(Debug, Clone)]
+pub struct SparseInput {
+    /// The `int64` indices of the values, with shape `[num_values, rank]`.
+    pub indices: Output,
+    /// The values, with shape `[num_values]`.
+    pub values: Output,
+    /// The `int64` dense shape, with shape `[rank]`.
+    pub dense_shape: Output,
+}
+
+impl SparseInput {
+    /// Creates placeholders for a sparse input with values of type `dtype`,
+    /// named `indices`, `values` and `dense_shape` within the scope.
+    pub fn placeholder(dtype: DataType, scope: &mut Scope) -> Result<Self> {
+        let indices = Placeholder::new()
+            .dtype(DataType::Int64)
+            .shape(Shape(Some(vec![None, None])))
+            .build(&mut scope.with_op_name("indices"))?;
+        let values = Placeholder::new()
+            .dtype(dtype)
+            .shape(Shape(Some(vec![None])))
+            .build(&mut scope.with_op_name("values"))?;
+        let dense_shape = Placeholder::new()
+            .dtype(DataType::Int64)
+            .shape(Shape(Some(vec![None])))
+            .build(&mut scope.with_op_name("dense_shape"))?;
+        Ok(SparseInput {
+            indices: indices.into(),
+            values: values.into(),
+            dense_shape: dense_shape.into(),
+        })
+    }
+
+    /// Feeds the components of `value` to the components of the input.
+    pub fn feed<'l, T: TensorType>(
+        &self,
+        args: &mut SessionRunArgs<'l>,
+        value: &'l SparseTensor<T>,
+    ) {
+        args.add_feed(&self.indices.operation, self.indices.index, value.indices());
+        args.add_feed(&self.values.operation, self.values.index, value.values());
+        args.add_feed(
+            &self.dense_shape.operation,
+            self.dense_shape.index,
+            value.dense_shape(),
+        );
+    }
+}
+
+/// Converts a sparse tensor in the graph to a dense tensor, with `default`
+/// for every element which has no value.
+///
+/// This is `sparse_to_dense` with the components taken from a
+/// `SparseInput`.  The indices must be in row-major order without repeats.
+pub fn sparse_input_to_dense<T: TensorType>(
+    sparse: &SparseInput,
+    default: T,
+    scope: &mut Scope,
+) -> Result<Operation> {
+    let default = constant(default, scope)?;
+    SparseToDense::new().build(
+        sparse.indices.clone(),
+        sparse.dense_shape.clone(),
+        sparse.values.clone(),
+        default,
+        scope,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Session;
+    use crate::SessionOptions;
+    use crate::Tensor;
+
+    #[test]
+    fn test_sparse_input_to_dense() {
+        let mut scope = Scope::new_root_scope();
+        let input =
+            SparseInput::placeholder(DataType::Float, &mut scope.new_sub_scope("x")).unwrap();
+        assert_eq!(input.indices.operation.name().unwrap(), "x/indices");
+        let dense = sparse_input_to_dense(&input, 0.0f32, &mut scope).unwrap();
+
+        let sparse = SparseTensor::new(
+            Tensor::new(&[2, 2]).with_values(&[0i64, 1, 1, 0]).unwrap(),
+            Tensor::new(&[2]).with_values(&[5.0f32, 7.0]).unwrap(),
+            vec![2, 2],
+        )
+        .unwrap();
+        let session = Session::new(&SessionOptions::new(), &scope.graph()).unwrap();
+        let mut run_args = SessionRunArgs::new();
+        input.feed(&mut run_args, &sparse);
+        let fetch = run_args.request_fetch(&dense, 0);
+        session.run(&mut run_args).unwrap();
+        let result: Tensor<f32> = run_args.fetch(fetch).unwrap();
+        assert_eq!(result, sparse.to_dense(0.0));
+    }
+}
@@ -0,0 +1,72 @@
+use crate::Operation;
+use crate::Output;
+use crate::Result;
+use crate::Scope;
+use crate::Variable;
+
+/// Assigns `new_values` to the elements of `var` where `mask` is true, and
+/// leaves the other elements unchanged.  Returns the assign operation, whose
+/// output is the updated value.
+///
+/// `mask` and `new_values` are broadcast against the variable, so e.g. in
+/// Q-learning, the Q-values of the actions taken can be updated with a
+/// one-hot mask of shape `[batch, num_actions]` and targets of shape
+/// `[batch, 1]`.  The whole variable is rewritten, so this is meant for
+/// variables of moderate size.
+pub fn masked_assign<O1: Into<Output>, O2: Into<Output>>(
+    var: &Variable,
+    mask: O1,
+    new_values: O2,
+    scope: &mut Scope,
+) -> Result<Operation> {
+    let updated = super::select_v2(mask, new_values, var.output().clone(), scope)?;
+    super::assign(var.output().clone(), updated, scope)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ops::constant;
+    use crate::Session;
+    use crate::SessionOptions;
+    use crate::SessionRunArgs;
+    use crate::Tensor;
+
+    #[test]
+    fn test_masked_assign() {
+        let mut scope = Scope::new_root_scope();
+        let q_values = Tensor::new(&[2, 2])
+            .with_values(&[1.0f32, 2.0, 3.0, 4.0])
+            .unwrap();
+        let var = Variable::builder()
+            .const_initial_value(q_values)
+            .build(&mut scope)
+            .unwrap();
+        let mask = constant(
+            Tensor::new(&[2, 2])
+                .with_values(&[true, false, false, true])
+                .unwrap(),
+            &mut scope,
+        )
+        .unwrap();
+        let targets = constant(
+            Tensor::new(&[2, 1]).with_values(&[10.0f32, 20.0]).unwrap(),
+            &mut scope,
+        )
+        .unwrap();
+        let update = masked_assign(&var, mask, targets, &mut scope).unwrap();
+
+        let session = Session::new(&SessionOptions::new(), &scope.graph()).unwrap();
+        let mut run_args = SessionRunArgs::new();
+        run_args.add_target(var.initializer());
+        session.run(&mut run_args).unwrap();
+        let mut run_args = SessionRunArgs::new();
+        run_args.add_target(&update);
+        session.run(&mut run_args).unwrap();
+        let mut run_args = SessionRunArgs::new();
+        let fetch = run_args.request_fetch(&var.output().operation, 0);
+        session.run(&mut run_args).unwrap();
+        let result = run_args.fetch::<f32>(fetch).unwrap();
+        assert_eq!(&result[..], &[10.0, 2.0, 3.0, 20.0]);
+    }
+}
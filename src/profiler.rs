@@ -0,0 +1,207 @@
+//! Tracing of session runs, which can be viewed as a Chrome trace.
+//!
+//! The C API does not expose TensorFlow's profiler, so the trace is built
+//! from the step statistics which TensorFlow collects for runs with
+//! `FULL_TRACE` run options.  Only runs made through
+//! `ProfilerSession::run` are traced.
+//!
+//! ```ignore
+//! let mut profiler = ProfilerSession::start();
+//! profiler.run(&session, &mut args)?;
+//! let trace = profiler.stop();
+//! std::fs::write("trace.json", trace.to_chrome_trace_json())?;
+//! ```
+use crate::run_stats::parse_node_exec_stats_from_run_metadata;
+use crate::run_stats::NodeExecStats;
+use crate::session::with_run_option;
+use crate::Result;
+use crate::Session;
+use crate::SessionRunArgs;
+use std::collections::HashMap;
+use std::fmt::Write;
+
+/// Field number of `trace_level` in tensorflow/core/protobuf/config.proto's
+/// `RunOptions`.
+const RUN_OPTIONS_TRACE_LEVEL: u32 = 1;
+
+/// The `FULL_TRACE` value of `RunOptions.TraceLevel`.
+const FULL_TRACE: i64 = 3;
+
+/// Collects the node executions of the runs made while it is active.
+#[derive(Debug, Default)]
+pub struct ProfilerSession {
+    events: Vec<NodeExecStats>,
+}
+
+impl ProfilerSession {
+    /// Starts collecting a trace.
+    pub fn start() -> Self {
+        Self::default()
+    }
+
+    /// Runs `args` with `session` like `Session::run`, recording the node
+    /// executions in the trace.  This sets the trace level of the run
+    /// options, replacing any trace level they already have, and requests run
+    /// metadata, so the metadata of `args` is
+    /// available afterwards as well.
+    ///
+    /// Tracing makes runs slower, so this shouldn't be left on in production.
+    pub fn run(&mut self, session: &Session, args: &mut SessionRunArgs<'_>) -> Result<()> {
+        let run_options = with_run_option(
+            args.get_run_options().unwrap_or(&[]),
+            RUN_OPTIONS_TRACE_LEVEL,
+            FULL_TRACE,
+        )?;
+        args.set_run_options(&run_options);
+        args.set_request_metadata(true);
+        session.run(args)?;
+        if let Some(run_metadata) = args.get_metadata() {
+            self.events
+                .extend(parse_node_exec_stats_from_run_metadata(run_metadata)?);
+        }
+        Ok(())
+    }
+
+    /// Stops collecting and returns the trace.
+    pub fn stop(self) -> TraceData {
+        TraceData {
+            events: self.events,
+        }
+    }
+}
+
+/// The node executions collected by a `ProfilerSession`.
+#[derive(Debug, Clone, Default)]
+pub struct TraceData {
+    events: Vec<NodeExecStats>,
+}
+
+impl TraceData {
+    /// Returns the number of node executions in the trace.
+    pub fn num_events(&self) -> usize {
+        self.events.len()
+    }
+
+    /// Returns the trace in the Chrome trace event format, which can be
+    /// loaded in `chrome://tracing` or Perfetto.  Each device is shown as a
+    /// thread, and each node execution as a complete event.
+    pub fn to_chrome_trace_json(&self) -> String {
+        let mut devices: HashMap<&str, usize> = HashMap::new();
+        let mut json = String::from("{\"traceEvents\":[");
+        for event in &self.events {
+            let num_devices = devices.len();
+            let tid = *devices.entry(event.device.as_str()).or_insert_with(|| {
+                if num_devices > 0 {
+                    json.push(',');
+                }
+                json.push_str("{\"name\":\"thread_name\",\"ph\":\"M\",\"pid\":0,\"tid\":");
+                write!(json, "{},\"args\":{{\"name\":", num_devices).unwrap();
+                push_json_string(&mut json, &event.device);
+                json.push_str("}}");
+                num_devices
+            });
+            json.push_str(",{\"name\":");
+            push_json_string(&mut json, &event.node_name);
+            write!(
+                json,
+                ",\"ph\":\"X\",\"pid\":0,\"tid\":{},\"ts\":{},\"dur\":{}}}",
+                tid,
+                event.all_start_micros,
+                event.all_end_rel_micros.max(0)
+            )
+            .unwrap();
+        }
+        json.push_str("]}");
+        json
+    }
+}
+
+/// Appends `s` to `json` as a JSON string literal.
+pub(crate) fn push_json_string(json: &mut String, s: &str) {
+    json.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => json.push_str("\\\""),
+            '\\' => json.push_str("\\\\"),
+            '\n' => json.push_str("\\n"),
+            c if (c as u32) < 0x20 => write!(json, "\\u{:04x}", c as u32).unwrap(),
+            c => json.push(c),
+        }
+    }
+    json.push('"');
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::DataType;
+    use crate::Graph;
+    use crate::SessionOptions;
+    use crate::Tensor;
+
+    #[test]
+    fn chrome_trace_json() {
+        let event = |device: &str, node_name: &str, start: i64, end_rel: i64| NodeExecStats {
+            device: device.to_string(),
+            node_name: node_name.to_string(),
+            all_start_micros: start,
+            all_end_rel_micros: end_rel,
+            memory: vec![],
+        };
+        let trace = TraceData {
+            events: vec![
+                event("/device:CPU:0", "a", 100, 10),
+                event("/device:GPU:0", "b\"c", 105, 20),
+                event("/device:CPU:0", "d", 110, 5),
+            ],
+        };
+        assert_eq!(trace.num_events(), 3);
+        assert_eq!(
+            trace.to_chrome_trace_json(),
+            concat!(
+                r#"{"traceEvents":["#,
+                r#"{"name":"thread_name","ph":"M","pid":0,"tid":0,"args":{"name":"/device:CPU:0"}},"#,
+                r#"{"name":"a","ph":"X","pid":0,"tid":0,"ts":100,"dur":10},"#,
+                r#"{"name":"thread_name","ph":"M","pid":0,"tid":1,"args":{"name":"/device:GPU:0"}},"#,
+                r#"{"name":"b\"c","ph":"X","pid":0,"tid":1,"ts":105,"dur":20},"#,
+                r#"{"name":"d","ph":"X","pid":0,"tid":0,"ts":110,"dur":5}"#,
+                r#"]}"#
+            )
+        );
+        assert_eq!(
+            TraceData::default().to_chrome_trace_json(),
+            r#"{"traceEvents":[]}"#
+        );
+    }
+
+    #[test]
+    fn profile_run() {
+        let mut g = Graph::new();
+        let x = {
+            let mut nd = g.new_operation("Placeholder", "x").unwrap();
+            nd.set_attr_type("dtype", DataType::Float).unwrap();
+            nd.finish().unwrap()
+        };
+        let y = {
+            let mut nd = g.new_operation("Square", "y").unwrap();
+            nd.add_input(x.clone());
+            nd.finish().unwrap()
+        };
+        let session = Session::new(&SessionOptions::new(), &g).unwrap();
+        let x_value = Tensor::from(3.0f32);
+        let mut profiler = ProfilerSession::start();
+        let mut args = SessionRunArgs::new();
+        args.add_feed(&x, 0, &x_value);
+        let token = args.request_fetch(&y, 0);
+        for _ in 0..2 {
+            profiler.run(&session, &mut args).unwrap();
+            assert_eq!(args.fetch::<f32>(token).unwrap()[0], 9.0);
+            // Reusing the args replaces the trace level rather than growing
+            // the run options.
+            assert_eq!(args.get_run_options(), Some(&[0x08, 0x03][..]));
+        }
+        let trace = profiler.stop();
+        assert!(trace.num_events() >= 2);
+        assert!(trace.to_chrome_trace_json().contains(r#""name":"y""#));
+    }
+}
@@ -0,0 +1,221 @@
+use crate::Result;
+use crate::Tensor;
+use crate::TensorType;
+
+/// A batch of variable-length rows, stored as the concatenation of the rows
+/// and the length of each row.
+///
+/// The first dimension of `values` indexes the elements of all rows, so row
+/// `i` consists of `row_lengths()[i]` consecutive entries of `values`.  Any
+/// remaining dimensions of `values` are the shape of each element.
+///
+/// This is a host-side container for variable-length data, such as token
+/// sequences, which is usually padded into a dense batch with `to_dense`
+/// before being fed to a graph.
+///
+/// ```
+/// # use tensorflow::RaggedTensor;
+/// let ragged = RaggedTensor::from_rows(&[&[1, 2, 3][..], &[4]]);
+/// let dense = ragged.to_dense(0);
+/// assert_eq!(dense.dims(), &[2, 3]);
+/// assert_eq!(&dense[..], &[1, 2, 3, 4, 0, 0]);
+/// assert_eq!(RaggedTensor::from_dense(&dense, ragged.row_lengths())?, ragged);
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct RaggedTensor<T: TensorType> {
+    values: Tensor<T>,
+    row_lengths: Vec<u64>,
+}
+
+impl<T: TensorType> RaggedTensor<T> {
+    /// Creates a ragged tensor from the concatenated rows and the length of
+    /// each row.  Returns an error if `values` is a scalar or the row lengths
+    /// don't add up to the first dimension of `values`.
+    pub fn new(values: Tensor<T>, row_lengths: Vec<u64>) -> Result<Self> {
+        let num_values = match values.dims().first() {
+            Some(n) => *n,
+            None => return Err(invalid_arg!("RaggedTensor values must not be a scalar")),
+        };
+        let total: u64 = row_lengths.iter().sum();
+        if total != num_values {
+            return Err(invalid_arg!(
+                "RaggedTensor row lengths add up to {}, but there are {} values",
+                total,
+                num_values
+            ));
+        }
+        Ok(RaggedTensor {
+            values,
+            row_lengths,
+        })
+    }
+
+    /// Creates a ragged tensor with scalar elements from a list of rows.
+    pub fn from_rows<R: AsRef<[T]>>(rows: &[R]) -> Self {
+        let row_lengths: Vec<u64> = rows.iter().map(|row| row.as_ref().len() as u64).collect();
+        let mut values = Tensor::new(&[row_lengths.iter().sum()]);
+        let mut offset = 0;
+        for row in rows {
+            let row = row.as_ref();
+            values[offset..offset + row.len()].clone_from_slice(row);
+            offset += row.len();
+        }
+        RaggedTensor {
+            values,
+            row_lengths,
+        }
+    }
+
+    /// Pads the rows with `padding` to the length of the longest row,
+    /// returning a dense tensor of shape `[num_rows, max_row_length, ...]`.
+    pub fn to_dense(&self, padding: T) -> Tensor<T> {
+        let max_length = self.row_lengths.iter().cloned().max().unwrap_or(0);
+        let element_size = self.element_size();
+        let mut dims = vec![self.row_lengths.len() as u64, max_length];
+        dims.extend_from_slice(&self.values.dims()[1..]);
+        let mut dense = Tensor::new(&dims);
+        let dense_row_size = max_length as usize * element_size;
+        let mut offset = 0;
+        for (i, &length) in self.row_lengths.iter().enumerate() {
+            let size = length as usize * element_size;
+            let dense_row = &mut dense[i * dense_row_size..(i + 1) * dense_row_size];
+            dense_row[..size].clone_from_slice(&self.values[offset..offset + size]);
+            for value in &mut dense_row[size..] {
+                value.clone_from(&padding);
+            }
+            offset += size;
+        }
+        dense
+    }
+
+    /// Extracts the first `row_lengths[i]` entries of each row `i` of a dense
+    /// tensor of shape `[num_rows, max_row_length, ...]`, such as one
+    /// returned by `to_dense`.
+    pub fn from_dense(dense: &Tensor<T>, row_lengths: &[u64]) -> Result<Self> {
+        let dims = dense.dims();
+        if dims.len() < 2 || dims[0] != row_lengths.len() as u64 {
+            return Err(invalid_arg!(
+                "Dense tensor of shape {:?} does not have {} rows",
+                dims,
+                row_lengths.len()
+            ));
+        }
+        let max_length = dims[1];
+        if let Some(length) = row_lengths.iter().find(|length| **length > max_length) {
+            return Err(invalid_arg!(
+                "Row length {} exceeds the dense row length {}",
+                length,
+                max_length
+            ));
+        }
+        let element_size = product(&dims[2..]);
+        let mut values_dims = vec![row_lengths.iter().sum()];
+        values_dims.extend_from_slice(&dims[2..]);
+        let mut values = Tensor::new(&values_dims);
+        let dense_row_size = max_length as usize * element_size;
+        let mut offset = 0;
+        for (i, &length) in row_lengths.iter().enumerate() {
+            let size = length as usize * element_size;
+            let start = i * dense_row_size;
+            values[offset..offset + size].clone_from_slice(&dense[start..start + size]);
+            offset += size;
+        }
+        Ok(RaggedTensor {
+            values,
+            row_lengths: row_lengths.to_vec(),
+        })
+    }
+
+    /// Returns the concatenated rows.
+    pub fn values(&self) -> &Tensor<T> {
+        &self.values
+    }
+
+    /// Returns the length of each row.
+    pub fn row_lengths(&self) -> &[u64] {
+        &self.row_lengths
+    }
+
+    /// Returns the number of rows.
+    pub fn num_rows(&self) -> usize {
+        self.row_lengths.len()
+    }
+
+    /// Returns the flattened elements of row `index`.
+    pub fn row(&self, index: usize) -> &[T] {
+        let element_size = self.element_size();
+        let start: u64 = self.row_lengths[..index].iter().sum();
+        let start = start as usize * element_size;
+        &self.values[start..start + self.row_lengths[index] as usize * element_size]
+    }
+
+    /// Returns the number of scalars in each element.
+    fn element_size(&self) -> usize {
+        product(&self.values.dims()[1..])
+    }
+}
+
+fn product(dims: &[u64]) -> usize {
+    dims.iter().product::<u64>() as usize
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Code;
+
+    #[test]
+    fn ragged_tensor() {
+        let ragged = RaggedTensor::from_rows(&[vec![1i32, 2, 3], vec![], vec![4]]);
+        assert_eq!(ragged.num_rows(), 3);
+        assert_eq!(ragged.row_lengths(), &[3, 0, 1]);
+        assert_eq!(&ragged.values()[..], &[1, 2, 3, 4]);
+        assert_eq!(ragged.row(1), &[] as &[i32]);
+        assert_eq!(ragged.row(2), &[4]);
+        let dense = ragged.to_dense(-1);
+        assert_eq!(dense.dims(), &[3, 3]);
+        assert_eq!(&dense[..], &[1, 2, 3, -1, -1, -1, 4, -1, -1]);
+        assert_eq!(
+            RaggedTensor::from_dense(&dense, ragged.row_lengths()).unwrap(),
+            ragged
+        );
+
+        // Rows of vectors.
+        let values = Tensor::new(&[3, 2])
+            .with_values(&[1.0f32, 2.0, 3.0, 4.0, 5.0, 6.0])
+            .unwrap();
+        let ragged = RaggedTensor::new(values, vec![1, 2]).unwrap();
+        assert_eq!(ragged.row(1), &[3.0, 4.0, 5.0, 6.0]);
+        let dense = ragged.to_dense(0.0);
+        assert_eq!(dense.dims(), &[2, 2, 2]);
+        assert_eq!(&dense[..], &[1.0, 2.0, 0.0, 0.0, 3.0, 4.0, 5.0, 6.0]);
+        assert_eq!(RaggedTensor::from_dense(&dense, &[1, 2]).unwrap(), ragged);
+
+        let empty = RaggedTensor::<i32>::from_rows::<&[i32]>(&[]);
+        assert_eq!(empty.to_dense(0).dims(), &[0, 0]);
+
+        assert_eq!(
+            RaggedTensor::new(Tensor::<i32>::new(&[3]), vec![1, 1])
+                .unwrap_err()
+                .code(),
+            Code::InvalidArgument
+        );
+        assert_eq!(
+            RaggedTensor::new(Tensor::from(1i32), vec![])
+                .unwrap_err()
+                .code(),
+            Code::InvalidArgument
+        );
+        assert_eq!(
+            RaggedTensor::from_dense(&dense, &[1, 3])
+                .unwrap_err()
+                .code(),
+            Code::InvalidArgument
+        );
+        assert_eq!(
+            RaggedTensor::from_dense(&dense, &[1]).unwrap_err().code(),
+            Code::InvalidArgument
+        );
+    }
+}
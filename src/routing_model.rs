@@ -0,0 +1,246 @@
+use crate::Code;
+use crate::Graph;
+use crate::InferencePool;
+use crate::Result;
+use crate::SessionRunArgs;
+use crate::Status;
+use std::collections::hash_map::RandomState;
+use std::hash::BuildHasher;
+use std::hash::Hasher;
+use std::sync::atomic::AtomicU64;
+use std::sync::atomic::Ordering;
+
+/// Serves several versions of a model and splits the requests between them,
+/// e.g. for A/B experiments or for rolling out a new version gradually.
+///
+/// Each version has a weight, and receives that fraction of the total
+/// weight of the requests.  A request with a key, such as a user ID, is
+/// always routed to the same version as long as the versions and weights
+/// don't change, so a user sees consistent results during an experiment.
+/// A request without a key is routed randomly.
+///
+/// The operations in the `SessionRunArgs` passed to `predict` are mapped to
+/// the chosen version's graph by name, so the versions must use the same
+/// names for their inputs and outputs.  The args can be built with the
+/// operations of any of the graphs, see `graph`.
+///
+/// `predict` returns the name of the version which served the request, and
+/// the number of requests served by each version is counted, see `served`.
+/// The latencies of each version are recorded in the metrics of its pool.
+///
+/// ```rust,ignore
+/// let mut model = RoutingModel::new();
+/// model.add_version("v1", graph_v1, InferencePool::from_graph(&graph_v1, &options, 4)?, 90)?;
+/// model.add_version("v2", graph_v2, InferencePool::from_graph(&graph_v2, &options, 4)?, 10)?;
+/// let version = model.predict(Some(&user_id), &mut args)?;
+/// ```
+#[derive(Debug, Default)]
+pub struct RoutingModel {
+    versions: Vec<ModelVersion>,
+    random_state: RandomState,
+    requests: AtomicU64,
+}
+
+#[derive(Debug)]
+struct ModelVersion {
+    name: String,
+    graph: Graph,
+    pool: InferencePool,
+    weight: u32,
+    served: AtomicU64,
+}
+
+impl RoutingModel {
+    /// Creates a model without any versions.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a version of the model, served by `pool` over `graph`, which
+    /// receives `weight` out of the total weight of the requests.  Returns
+    /// an error if a version with the same name already exists.
+    pub fn add_version(
+        &mut self,
+        name: &str,
+        graph: Graph,
+        pool: InferencePool,
+        weight: u32,
+    ) -> Result<()> {
+        if self.version(name).is_some() {
+            return Err(invalid_arg!("Model version {} already exists", name));
+        }
+        self.versions.push(ModelVersion {
+            name: name.to_string(),
+            graph,
+            pool,
+            weight,
+            served: AtomicU64::new(0),
+        });
+        Ok(())
+    }
+
+    /// Changes the weight of a version.  A version with weight zero receives
+    /// no requests.  Changing a weight reassigns some of the request keys to
+    /// other versions.
+    pub fn set_weight(&mut self, name: &str, weight: u32) -> Result<()> {
+        match self.versions.iter_mut().find(|v| v.name == name) {
+            Some(version) => {
+                version.weight = weight;
+                Ok(())
+            }
+            None => Err(invalid_arg!("Unknown model version {}", name)),
+        }
+    }
+
+    /// Returns the names of the versions, in the order they were added.
+    pub fn versions(&self) -> Vec<&str> {
+        self.versions.iter().map(|v| v.name.as_str()).collect()
+    }
+
+    /// Returns the graph of a version.
+    pub fn graph(&self, name: &str) -> Option<&Graph> {
+        self.version(name).map(|v| &v.graph)
+    }
+
+    /// Returns the pool which serves a version.
+    pub fn pool(&self, name: &str) -> Option<&InferencePool> {
+        self.version(name).map(|v| &v.pool)
+    }
+
+    /// Returns the number of requests routed to a version by `predict`,
+    /// including the failed ones.
+    pub fn served(&self, name: &str) -> Option<u64> {
+        self.version(name).map(|v| v.served.load(Ordering::Relaxed))
+    }
+
+    /// Returns the name of the version which serves requests with `key`, or
+    /// a random version if `key` is `None`.  Returns an error if no version
+    /// has a positive weight.
+    pub fn route(&self, key: Option<&str>) -> Result<&str> {
+        self.choose(key).map(|v| v.name.as_str())
+    }
+
+    /// Routes a request like `route` and runs `args` on the chosen version,
+    /// like `InferencePool::run`.  Returns the name of the version which
+    /// served the request.
+    pub fn predict(&self, key: Option<&str>, args: &mut SessionRunArgs<'_>) -> Result<&str> {
+        let version = self.choose(key)?;
+        version.served.fetch_add(1, Ordering::Relaxed);
+        let mut version_args = args.remapped_to(&version.graph)?;
+        version.pool.run(&mut version_args)?;
+        args.take_results_from(&mut version_args);
+        Ok(&version.name)
+    }
+
+    fn version(&self, name: &str) -> Option<&ModelVersion> {
+        self.versions.iter().find(|v| v.name == name)
+    }
+
+    fn choose(&self, key: Option<&str>) -> Result<&ModelVersion> {
+        let total: u64 = self.versions.iter().map(|v| u64::from(v.weight)).sum();
+        if total == 0 {
+            return Err(Status::new_set_lossy(
+                Code::FailedPrecondition,
+                "No model version has a positive weight",
+            ));
+        }
+        let hash = match key {
+            Some(key) => fnv1a(key.as_bytes()),
+            None => {
+                let mut hasher = self.random_state.build_hasher();
+                hasher.write_u64(self.requests.fetch_add(1, Ordering::Relaxed));
+                hasher.finish()
+            }
+        };
+        let mut point = hash % total;
+        for version in &self.versions {
+            let weight = u64::from(version.weight);
+            if point < weight {
+                return Ok(version);
+            }
+            point -= weight;
+        }
+        unreachable!("point is less than the total weight")
+    }
+}
+
+/// The 64-bit FNV-1a hash, which unlike `DefaultHasher` is stable across
+/// Rust releases, so that keys keep their versions across restarts.
+fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf2_9ce4_8422_2325, |hash, byte| {
+        (hash ^ u64::from(*byte)).wrapping_mul(0x0100_0000_01b3)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::DataType;
+    use crate::SessionOptions;
+    use crate::Tensor;
+
+    fn create_version(factor: f32) -> (Graph, InferencePool) {
+        let mut g = Graph::new();
+        let x = {
+            let mut nd = g.new_operation("Placeholder", "x").unwrap();
+            nd.set_attr_type("dtype", DataType::Float).unwrap();
+            nd.finish().unwrap()
+        };
+        let factor = {
+            let mut nd = g.new_operation("Const", "factor").unwrap();
+            nd.set_attr_type("dtype", DataType::Float).unwrap();
+            nd.set_attr_tensor("value", Tensor::from(factor)).unwrap();
+            nd.finish().unwrap()
+        };
+        {
+            let mut nd = g.new_operation("Mul", "y").unwrap();
+            nd.add_input(x);
+            nd.add_input(factor);
+            nd.finish().unwrap();
+        }
+        let pool = InferencePool::from_graph(&g, &SessionOptions::new(), 1).unwrap();
+        (g, pool)
+    }
+
+    #[test]
+    fn routing_model() {
+        let mut model = RoutingModel::new();
+        assert!(model.route(None).is_err());
+        for (name, factor, weight) in &[("a", 1.0, 1), ("b", 2.0, 3), ("off", 3.0, 0)] {
+            let (graph, pool) = create_version(*factor);
+            model.add_version(name, graph, pool, *weight).unwrap();
+        }
+        let (graph, pool) = create_version(1.0);
+        assert!(model.add_version("a", graph, pool, 1).is_err());
+        assert_eq!(model.versions(), vec!["a", "b", "off"]);
+
+        // The args are built with the first graph and run on the chosen one.
+        let graph = model.graph("a").unwrap();
+        let x = graph.operation_by_name_required("x").unwrap();
+        let y = graph.operation_by_name_required("y").unwrap();
+        let input = Tensor::from(5.0f32);
+        for i in 0..200 {
+            let key = format!("user{}", i);
+            let mut args = SessionRunArgs::new();
+            args.add_feed(&x, 0, &input);
+            let token = args.request_fetch(&y, 0);
+            let version = model.predict(Some(&key), &mut args).unwrap();
+            assert_eq!(version, model.route(Some(&key)).unwrap());
+            let expected = if version == "a" { 5.0 } else { 10.0 };
+            assert_eq!(args.fetch::<f32>(token).unwrap()[0], expected);
+        }
+        for _ in 0..200 {
+            assert_ne!(model.route(None).unwrap(), "off");
+        }
+        let a = model.served("a").unwrap();
+        let b = model.served("b").unwrap();
+        assert_eq!(a + b, 200);
+        assert!(a > 0 && b > a);
+        assert_eq!(model.served("off"), Some(0));
+        assert_eq!(model.pool("b").unwrap().metrics().snapshot().count, b);
+
+        model.set_weight("a", 0).unwrap();
+        assert_eq!(model.route(Some("user0")).unwrap(), "b");
+        assert!(model.set_weight("c", 1).is_err());
+    }
+}
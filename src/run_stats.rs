@@ -0,0 +1,384 @@
+use crate::Code;
+use crate::Result;
+use crate::Status;
+use protobuf::wire_format::WireType;
+use protobuf::CodedInputStream;
+use protobuf::ProtobufResult;
+use std::collections::HashMap;
+
+// Field numbers from tensorflow/core/protobuf/config.proto and
+// tensorflow/core/framework/step_stats.proto.  These protos are not part of
+// the generated protos, so the fields we need are decoded by hand.
+const RUN_METADATA_STEP_STATS: u32 = 1;
+const STEP_STATS_DEV_STATS: u32 = 1;
+const DEVICE_STEP_STATS_DEVICE: u32 = 1;
+const DEVICE_STEP_STATS_NODE_STATS: u32 = 2;
+const NODE_EXEC_STATS_NODE_NAME: u32 = 1;
+const NODE_EXEC_STATS_ALL_START_MICROS: u32 = 2;
+const NODE_EXEC_STATS_ALL_END_REL_MICROS: u32 = 5;
+const NODE_EXEC_STATS_MEMORY: u32 = 6;
+const ALLOCATOR_MEMORY_USED_ALLOCATOR_NAME: u32 = 1;
+const ALLOCATOR_MEMORY_USED_PEAK_BYTES: u32 = 3;
+
+/// Memory used by a node from a single allocator.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub(crate) struct AllocatorMemoryUsed {
+    pub(crate) allocator_name: String,
+    pub(crate) peak_bytes: i64,
+}
+
+/// Execution statistics for a single node, decoded from a `NodeExecStats`
+/// proto.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub(crate) struct NodeExecStats {
+    pub(crate) device: String,
+    pub(crate) node_name: String,
+    pub(crate) all_start_micros: i64,
+    pub(crate) all_end_rel_micros: i64,
+    pub(crate) memory: Vec<AllocatorMemoryUsed>,
+}
+
+/// Calls `f` for each field in the serialized message `data`.  `f` returns
+/// false if it did not consume the field, in which case the field is skipped.
+fn for_each_field<F>(data: &[u8], mut f: F) -> ProtobufResult<()>
+where
+    F: FnMut(u32, WireType, &mut CodedInputStream<'_>) -> ProtobufResult<bool>,
+{
+    let mut input = CodedInputStream::from_bytes(data);
+    while !input.eof()? {
+        let (field_number, wire_type) = input.read_tag_unpack()?;
+        if !f(field_number, wire_type, &mut input)? {
+            input.skip_field(wire_type)?;
+        }
+    }
+    Ok(())
+}
+
+fn parse_allocator_memory_used(data: &[u8]) -> ProtobufResult<AllocatorMemoryUsed> {
+    let mut memory = AllocatorMemoryUsed::default();
+    for_each_field(data, |field_number, wire_type, input| {
+        match (field_number, wire_type) {
+            (ALLOCATOR_MEMORY_USED_ALLOCATOR_NAME, WireType::WireTypeLengthDelimited) => {
+                memory.allocator_name = input.read_string()?;
+            }
+            (ALLOCATOR_MEMORY_USED_PEAK_BYTES, WireType::WireTypeVarint) => {
+                memory.peak_bytes = input.read_int64()?;
+            }
+            _ => return Ok(false),
+        }
+        Ok(true)
+    })?;
+    Ok(memory)
+}
+
+fn parse_node_exec_stats(device: &str, data: &[u8]) -> ProtobufResult<NodeExecStats> {
+    let mut stats = NodeExecStats {
+        device: device.to_string(),
+        ..NodeExecStats::default()
+    };
+    for_each_field(data, |field_number, wire_type, input| {
+        match (field_number, wire_type) {
+            (NODE_EXEC_STATS_NODE_NAME, WireType::WireTypeLengthDelimited) => {
+                stats.node_name = input.read_string()?;
+            }
+            (NODE_EXEC_STATS_ALL_START_MICROS, WireType::WireTypeVarint) => {
+                stats.all_start_micros = input.read_int64()?;
+            }
+            (NODE_EXEC_STATS_ALL_END_REL_MICROS, WireType::WireTypeVarint) => {
+                stats.all_end_rel_micros = input.read_int64()?;
+            }
+            (NODE_EXEC_STATS_MEMORY, WireType::WireTypeLengthDelimited) => {
+                let memory = input.read_bytes()?;
+                stats.memory.push(parse_allocator_memory_used(&memory)?);
+            }
+            _ => return Ok(false),
+        }
+        Ok(true)
+    })?;
+    Ok(stats)
+}
+
+fn parse_device_step_stats(data: &[u8], output: &mut Vec<NodeExecStats>) -> ProtobufResult<()> {
+    // The device name may come after the node stats, so collect the raw node
+    // stats first.
+    let mut device = String::new();
+    let mut node_stats = Vec::new();
+    for_each_field(data, |field_number, wire_type, input| {
+        match (field_number, wire_type) {
+            (DEVICE_STEP_STATS_DEVICE, WireType::WireTypeLengthDelimited) => {
+                device = input.read_string()?;
+            }
+            (DEVICE_STEP_STATS_NODE_STATS, WireType::WireTypeLengthDelimited) => {
+                node_stats.push(input.read_bytes()?);
+            }
+            _ => return Ok(false),
+        }
+        Ok(true)
+    })?;
+    for data in node_stats {
+        output.push(parse_node_exec_stats(&device, &data)?);
+    }
+    Ok(())
+}
+
+/// Decodes the per-node execution statistics from a serialized `RunMetadata`
+/// proto.
+pub(crate) fn parse_node_exec_stats_from_run_metadata(
+    run_metadata: &[u8],
+) -> Result<Vec<NodeExecStats>> {
+    let mut output = Vec::new();
+    let result = for_each_field(run_metadata, |field_number, wire_type, input| {
+        if (field_number, wire_type) != (RUN_METADATA_STEP_STATS, WireType::WireTypeLengthDelimited)
+        {
+            return Ok(false);
+        }
+        let step_stats = input.read_bytes()?;
+        for_each_field(&step_stats, |field_number, wire_type, input| {
+            if (field_number, wire_type)
+                != (STEP_STATS_DEV_STATS, WireType::WireTypeLengthDelimited)
+            {
+                return Ok(false);
+            }
+            parse_device_step_stats(&input.read_bytes()?, &mut output)?;
+            Ok(true)
+        })?;
+        Ok(true)
+    });
+    result.map_err(|e| {
+        Status::new_set_lossy(
+            Code::InvalidArgument,
+            &format!("Invalid serialized RunMetadata: {}", e),
+        )
+    })?;
+    Ok(output)
+}
+
+/// A summary of the step statistics collected during a traced run.
+///
+/// The step statistics are only collected if the run options set a trace
+/// level (e.g. `FULL_TRACE`) and run metadata was requested with
+/// `SessionRunArgs::set_request_metadata`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct RunStats {
+    /// Sum over all allocators of the peak number of bytes allocated by any
+    /// single node.  This is a lower bound for the true peak memory usage.
+    pub peak_bytes: u64,
+    /// Number of node executions recorded.
+    pub num_ops: usize,
+    /// Wall time in microseconds from the start of the first node execution
+    /// to the end of the last one.
+    pub total_micros: u64,
+}
+
+impl RunStats {
+    /// Summarizes the step statistics in a serialized [`RunMetadata`
+    /// proto](https://github.com/tensorflow/tensorflow/blob/master/tensorflow/core/protobuf/config.proto),
+    /// such as the one returned by `SessionRunArgs::get_metadata`.
+    pub fn from_run_metadata(run_metadata: &[u8]) -> Result<Self> {
+        Ok(Self::from_node_exec_stats(
+            &parse_node_exec_stats_from_run_metadata(run_metadata)?,
+        ))
+    }
+
+    fn from_node_exec_stats(node_stats: &[NodeExecStats]) -> Self {
+        let mut peak_bytes_by_allocator = HashMap::new();
+        let mut start = None;
+        let mut end = None;
+        for stats in node_stats {
+            for memory in &stats.memory {
+                let peak = peak_bytes_by_allocator
+                    .entry(memory.allocator_name.as_str())
+                    .or_insert(0);
+                *peak = (*peak).max(memory.peak_bytes.max(0) as u64);
+            }
+            let node_end = stats.all_start_micros + stats.all_end_rel_micros;
+            start = Some(start.map_or(stats.all_start_micros, |s: i64| {
+                s.min(stats.all_start_micros)
+            }));
+            end = Some(end.map_or(node_end, |e: i64| e.max(node_end)));
+        }
+        RunStats {
+            peak_bytes: peak_bytes_by_allocator.values().sum(),
+            num_ops: node_stats.len(),
+            total_micros: match (start, end) {
+                (Some(start), Some(end)) => (end - start).max(0) as u64,
+                _ => 0,
+            },
+        }
+    }
+}
+
+/// Memory used on a single device, see `MemoryStats`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct DeviceMemory {
+    /// Full name of the device, as in `Device::name`.
+    pub device: String,
+    /// Sum over the device's allocators of the peak number of bytes
+    /// allocated by any single node, like `RunStats::peak_bytes`.
+    pub peak_bytes: u64,
+    /// Amount of memory on the device, as in `Device::memory_bytes`, if it
+    /// is known.
+    pub memory_bytes: Option<i64>,
+}
+
+/// The memory used by a traced run on each device, e.g. to estimate how many
+/// models fit on a GPU.  See `Session::memory_usage`.
+///
+/// TensorFlow's C API doesn't report how much memory a session holds, so
+/// this is derived from the step statistics of a run, which have the same
+/// accuracy limits as `RunStats::peak_bytes`: the peak of each allocator is
+/// the largest peak seen by a single node, which is a lower bound for the
+/// memory the allocator really held.  Memory which is not allocated while
+/// running, such as variables allocated by an earlier run, or memory which
+/// the GPU allocator reserves up front, is not included.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct MemoryStats {
+    /// The devices, sorted by name.
+    pub devices: Vec<DeviceMemory>,
+}
+
+impl MemoryStats {
+    /// Aggregates the memory statistics in a serialized `RunMetadata` proto
+    /// per device.  Devices without memory statistics are left out, and
+    /// `memory_bytes` is `None` for every device.
+    pub fn from_run_metadata(run_metadata: &[u8]) -> Result<Self> {
+        let node_stats = parse_node_exec_stats_from_run_metadata(run_metadata)?;
+        let mut peak_bytes: HashMap<&str, HashMap<&str, u64>> = HashMap::new();
+        for stats in &node_stats {
+            for memory in &stats.memory {
+                let peak = peak_bytes
+                    .entry(stats.device.as_str())
+                    .or_default()
+                    .entry(memory.allocator_name.as_str())
+                    .or_insert(0);
+                *peak = (*peak).max(memory.peak_bytes.max(0) as u64);
+            }
+        }
+        let mut devices: Vec<_> = peak_bytes
+            .into_iter()
+            .map(|(device, allocators)| DeviceMemory {
+                device: device.to_string(),
+                peak_bytes: allocators.values().sum(),
+                memory_bytes: None,
+            })
+            .collect();
+        devices.sort_by(|a, b| a.device.cmp(&b.device));
+        Ok(MemoryStats { devices })
+    }
+
+    /// Returns the sum of the peak bytes over all devices.
+    pub fn total_peak_bytes(&self) -> u64 {
+        self.devices.iter().map(|d| d.peak_bytes).sum()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use protobuf::CodedOutputStream;
+
+    fn encode<F: FnOnce(&mut CodedOutputStream<'_>) -> ProtobufResult<()>>(f: F) -> Vec<u8> {
+        let mut data = Vec::new();
+        {
+            let mut output = CodedOutputStream::vec(&mut data);
+            f(&mut output).unwrap();
+            output.flush().unwrap();
+        }
+        data
+    }
+
+    fn node_stats(name: &str, start: i64, end_rel: i64, allocator: &str, peak: i64) -> Vec<u8> {
+        let memory = encode(|o| {
+            o.write_string(ALLOCATOR_MEMORY_USED_ALLOCATOR_NAME, allocator)?;
+            o.write_int64(2, 12345)?; // total_bytes, which is ignored
+            o.write_int64(ALLOCATOR_MEMORY_USED_PEAK_BYTES, peak)
+        });
+        encode(|o| {
+            o.write_string(NODE_EXEC_STATS_NODE_NAME, name)?;
+            o.write_int64(NODE_EXEC_STATS_ALL_START_MICROS, start)?;
+            o.write_int64(NODE_EXEC_STATS_ALL_END_REL_MICROS, end_rel)?;
+            o.write_bytes(NODE_EXEC_STATS_MEMORY, &memory)
+        })
+    }
+
+    /// Run metadata with three nodes, two on the CPU and one on the GPU.
+    fn run_metadata() -> Vec<u8> {
+        let cpu = encode(|o| {
+            o.write_string(DEVICE_STEP_STATS_DEVICE, "/device:CPU:0")?;
+            o.write_bytes(
+                DEVICE_STEP_STATS_NODE_STATS,
+                &node_stats("a", 100, 10, "cpu", 64),
+            )?;
+            o.write_bytes(
+                DEVICE_STEP_STATS_NODE_STATS,
+                &node_stats("b", 105, 20, "cpu", 256),
+            )
+        });
+        let gpu = encode(|o| {
+            o.write_bytes(
+                DEVICE_STEP_STATS_NODE_STATS,
+                &node_stats("c", 110, 40, "gpu", 1024),
+            )?;
+            o.write_string(DEVICE_STEP_STATS_DEVICE, "/device:GPU:0")
+        });
+        let step_stats = encode(|o| {
+            o.write_bytes(STEP_STATS_DEV_STATS, &cpu)?;
+            o.write_bytes(STEP_STATS_DEV_STATS, &gpu)
+        });
+        encode(|o| {
+            o.write_bytes(RUN_METADATA_STEP_STATS, &step_stats)?;
+            o.write_bytes(2, b"ignored cost graph")
+        })
+    }
+
+    #[test]
+    fn run_stats_from_run_metadata() {
+        let run_metadata = run_metadata();
+        let node_stats = parse_node_exec_stats_from_run_metadata(&run_metadata).unwrap();
+        assert_eq!(node_stats.len(), 3);
+        assert_eq!(node_stats[1].device, "/device:CPU:0");
+        assert_eq!(node_stats[1].node_name, "b");
+        assert_eq!(node_stats[2].device, "/device:GPU:0");
+        assert_eq!(
+            RunStats::from_run_metadata(&run_metadata).unwrap(),
+            RunStats {
+                peak_bytes: 256 + 1024,
+                num_ops: 3,
+                total_micros: 50,
+            }
+        );
+        assert_eq!(
+            RunStats::from_run_metadata(&[]).unwrap(),
+            RunStats::default()
+        );
+        assert_eq!(
+            RunStats::from_run_metadata(&[0x0a, 0xff])
+                .unwrap_err()
+                .code(),
+            Code::InvalidArgument
+        );
+    }
+
+    #[test]
+    fn memory_stats_from_run_metadata() {
+        let run_metadata = run_metadata();
+        let memory = MemoryStats::from_run_metadata(&run_metadata).unwrap();
+        assert_eq!(
+            memory.devices,
+            vec![
+                DeviceMemory {
+                    device: "/device:CPU:0".to_string(),
+                    peak_bytes: 256,
+                    memory_bytes: None,
+                },
+                DeviceMemory {
+                    device: "/device:GPU:0".to_string(),
+                    peak_bytes: 1024,
+                    memory_bytes: None,
+                },
+            ]
+        );
+        assert_eq!(memory.total_peak_bytes(), 256 + 1024);
+        assert_eq!(MemoryStats::from_run_metadata(&[]).unwrap().devices, vec![]);
+    }
+}
@@ -13,6 +13,7 @@ use super::SessionRunArgs;
 use super::Shape;
 use super::Status;
 use super::Tensor;
+use super::TensorType;
 use super::Variable;
 use protobuf::Message;
 use protobuf::ProtobufError;
@@ -125,6 +126,16 @@ impl TensorInfo {
         &self.name
     }
 
+    /// Returns the data type of the tensor.
+    pub fn dtype(&self) -> DataType {
+        self.dtype
+    }
+
+    /// Returns the shape of the tensor, which may be partially known.
+    pub fn shape(&self) -> &Shape {
+        &self.shape
+    }
+
     // We don't use Into, because we don't want this to be public API.
     fn into_proto(self) -> protos::meta_graph::TensorInfo {
         let mut info = protos::meta_graph::TensorInfo::new();
@@ -193,6 +204,36 @@ impl SignatureDef {
         })
     }
 
+    /// Checks that `tensor` can be fed to the input parameter `name`, i.e.
+    /// that it has the declared data type and that its dimensions match the
+    /// declared shape.  Unknown dimensions, or an unknown rank, match
+    /// anything.
+    ///
+    /// Checking the feeds before running turns the errors TensorFlow reports
+    /// deep inside the graph into an `InvalidArgument` error naming the input
+    /// and showing the expected and actual shapes.
+    pub fn check_input<T: TensorType>(&self, name: &str, tensor: &Tensor<T>) -> Result<()> {
+        let info = self.get_input(name)?;
+        if info.dtype != T::data_type() {
+            return Err(invalid_arg!(
+                "Input '{}' expects type {}, but got {}",
+                name,
+                info.dtype,
+                T::data_type()
+            ));
+        }
+        let dims: Vec<i64> = tensor.dims().iter().map(|d| *d as i64).collect();
+        if !info.shape.is_compatible_with(&Shape::from(&dims[..])) {
+            return Err(invalid_arg!(
+                "Input '{}' expects shape {}, but got shape {:?}",
+                name,
+                info.shape,
+                dims
+            ));
+        }
+        Ok(())
+    }
+
     /// Returns the given output parameter.
     pub fn get_output(&self, name: &str) -> Result<&TensorInfo> {
         self.outputs.get(name).ok_or_else(|| {
@@ -518,3 +559,51 @@ impl SavedModelSaver {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn signature_check_input() {
+        let mut signature = SignatureDef::new("predict".to_string());
+        signature.add_input_info(
+            "x".to_string(),
+            TensorInfo::new(
+                DataType::Float,
+                Shape::from(Some(vec![None, Some(3)])),
+                "x:0".parse().unwrap(),
+            ),
+        );
+        signature.add_input_info(
+            "any".to_string(),
+            TensorInfo::new(DataType::Int32, Shape::from(None), "any:0".parse().unwrap()),
+        );
+        signature
+            .check_input("x", &Tensor::<f32>::new(&[5, 3]))
+            .unwrap();
+        signature
+            .check_input("any", &Tensor::<i32>::new(&[2, 2, 2]))
+            .unwrap();
+        for error in &[
+            signature
+                .check_input("x", &Tensor::<f32>::new(&[5, 4]))
+                .unwrap_err(),
+            signature
+                .check_input("x", &Tensor::<f32>::new(&[3]))
+                .unwrap_err(),
+            signature
+                .check_input("x", &Tensor::<i32>::new(&[5, 3]))
+                .unwrap_err(),
+            signature
+                .check_input("y", &Tensor::<f32>::new(&[5, 3]))
+                .unwrap_err(),
+        ] {
+            assert_eq!(error.code(), Code::InvalidArgument);
+        }
+        let error = signature
+            .check_input("x", &Tensor::<f32>::new(&[5, 4]))
+            .unwrap_err();
+        assert!(error.to_string().contains("'x'"));
+    }
+}
@@ -1,12 +1,24 @@
+use crate::profiler::push_json_string;
+use crate::protos;
+use crate::tf;
+use crate::AttrValue;
+use crate::DataType;
 use crate::Graph;
+use crate::ImportGraphDefOptions;
+use crate::Operation;
+use crate::OperationDescription;
+use crate::Output;
+use crate::Result;
+use libc::c_uint;
 use std::borrow::Borrow;
 use std::cell::RefCell;
-use std::collections::hash_map::Entry;
+use std::collections::BTreeMap;
 use std::collections::HashMap;
 use std::collections::HashSet;
 use std::ops::Deref;
 use std::ops::DerefMut;
 use std::rc::Rc;
+use std::rc::Weak;
 
 /// Joins left and right using the separator.  If either left or right is the
 /// empty string, the separator is left out.
@@ -18,6 +30,76 @@ fn join(sep: &str, left: &str, right: &str) -> String {
     }
 }
 
+/// Returns true if `c` may appear in a TensorFlow node name.
+fn is_valid_name_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || c == '_' || c == '.' || c == '-' || c == '/'
+}
+
+/// Replaces every character that may not appear in a TensorFlow node name
+/// with an underscore, and drops the leading characters which may not start
+/// one, so that a non-empty result is a valid name.
+fn sanitize_name(name: &str) -> String {
+    name.chars()
+        .map(|c| if is_valid_name_char(c) { c } else { '_' })
+        .skip_while(|c| !c.is_ascii_alphanumeric() && *c != '.')
+        .collect()
+}
+
+/// Options for `Scope::new_root_scope_with_options`.
+#[derive(Debug, Clone, Default)]
+pub struct ScopeOptions {
+    default_device: String,
+    graph_seed: Option<i64>,
+}
+
+impl ScopeOptions {
+    /// Sets the device that ops are placed on by default, e.g.
+    /// `/device:CPU:0`.  The empty string, which is the default, means
+    /// unconstrained.
+    pub fn with_default_device(self, default_device: &str) -> Self {
+        Self {
+            default_device: default_device.to_string(),
+            ..self
+        }
+    }
+
+    /// Sets the graph-level random seed.  Random ops whose `seed` and `seed2`
+    /// attributes are not set explicitly get `seed` set to the graph seed and
+    /// `seed2` set to a value unique to the op, so graphs built in the same
+    /// order produce the same random numbers.
+    pub fn with_graph_seed(self, graph_seed: i64) -> Self {
+        Self {
+            graph_seed: Some(graph_seed),
+            ..self
+        }
+    }
+}
+
+/// Identifies an integer constant cached by `Scope::cached_int_constant`: the
+/// device, data type, dimensions and values of the constant.  The data type
+/// is stored as its C value, since `DataType` doesn't implement `Hash`.
+pub(crate) type IntConstantKey = (String, c_uint, Vec<u64>, Vec<i64>);
+
+/// State shared by a root scope and all scopes derived from it.
+#[derive(Debug, Default)]
+struct ScopeState {
+    graph_seed: Option<i64>,
+    /// Number of ops which have been given seeds derived from the graph seed.
+    seeded_ops: i64,
+    /// Whether each op type has `seed` and `seed2` attributes.
+    op_types_with_seed: HashMap<String, bool>,
+    /// Operations recorded by each live `GradientTape`.
+    tapes: Vec<Weak<RefCell<HashSet<*mut tf::TF_Operation>>>>,
+    /// Initializers of the variables built with `VariableBuilder::build`.
+    variable_initializers: Vec<Operation>,
+    /// Operations pushed by live `ColocationGuard`s, outermost first.
+    colocation_stack: Vec<Operation>,
+    /// Small integer constants, such as axes, built by the op wrappers.
+    int_constants: HashMap<IntConstantKey, Operation>,
+    /// Human-readable labels attached with `Scope::label`, by op name.
+    labels: BTreeMap<String, String>,
+}
+
 // TODO: Include other with_* functions
 /// A `Scope` object represents a set of related TensorFlow ops that have the
 /// same properties such as a common name prefix.
@@ -99,6 +181,10 @@ pub struct Scope {
     children_names: Rc<RefCell<HashSet<String>>>,
     op_name: String,
     op_names: Rc<RefCell<HashMap<String, i32>>>,
+    device: String,
+    attrs: Vec<(String, AttrValue)>,
+    control_deps: Vec<Operation>,
+    state: Rc<RefCell<ScopeState>>,
 }
 
 impl Scope {
@@ -106,12 +192,57 @@ impl Scope {
     /// This creates a new graph and all operations constructed in this graph
     /// should use the returned object as the "root" scope.
     pub fn new_root_scope() -> Scope {
+        Scope::new_root_scope_with_options(ScopeOptions::default())
+    }
+
+    /// Return a new scope with the given options, which apply to all
+    /// operations created with the scope or its children.
+    /// This creates a new graph and all operations constructed in this graph
+    /// should use the returned object as the "root" scope.
+    pub fn new_root_scope_with_options(options: ScopeOptions) -> Scope {
+        Scope::root_scope_for_graph(Graph::new(), options)
+    }
+
+    /// Return a new root scope which adds operations to an existing graph,
+    /// e.g. one imported from a `GraphDef`.  Names of operations and sub-scopes
+    /// created with the scope do not collide with the operations already in
+    /// the graph.
+    pub fn from_graph(graph: Graph) -> Result<Scope> {
+        Scope::from_graph_with_options(graph, ScopeOptions::default())
+    }
+
+    /// Like `from_graph`, but with the given options, which apply to all
+    /// operations created with the scope or its children.
+    pub fn from_graph_with_options(graph: Graph, options: ScopeOptions) -> Result<Scope> {
+        let mut children_names = HashSet::new();
+        let mut op_names = HashMap::new();
+        for operation in graph.operation_iter() {
+            let name = operation.name()?;
+            if let Some(i) = name.find('/') {
+                children_names.insert(name[..i].to_string());
+            }
+            op_names.insert(name, 0);
+        }
+        let scope = Scope::root_scope_for_graph(graph, options);
+        *scope.children_names.borrow_mut() = children_names;
+        *scope.op_names.borrow_mut() = op_names;
+        Ok(scope)
+    }
+
+    fn root_scope_for_graph(graph: Graph, options: ScopeOptions) -> Scope {
         Scope {
-            graph: Rc::new(RefCell::new(Graph::new())),
+            graph: Rc::new(RefCell::new(graph)),
             name: "".to_string(),
             children_names: Rc::new(RefCell::new(HashSet::new())),
             op_name: "".to_string(),
             op_names: Rc::new(RefCell::new(HashMap::new())),
+            device: options.default_device,
+            attrs: Vec::new(),
+            control_deps: Vec::new(),
+            state: Rc::new(RefCell::new(ScopeState {
+                graph_seed: options.graph_seed,
+                ..ScopeState::default()
+            })),
         }
     }
 
@@ -132,11 +263,28 @@ impl Scope {
         }
     }
 
+    /// Returns true if `name` is a valid TensorFlow node name, i.e. it matches
+    /// `[A-Za-z0-9.][A-Za-z0-9_.\-/]*`.
+    pub fn is_valid_name(name: &str) -> bool {
+        let mut chars = name.chars();
+        match chars.next() {
+            Some(c) if c.is_ascii_alphanumeric() || c == '.' => chars.all(is_valid_name_char),
+            _ => false,
+        }
+    }
+
     /// Return a new scope. Ops created with this scope will have
     /// `name/child_scope_name` as the prefix. The actual name will be unique
     /// in the current scope. All other properties are inherited from the current
     /// scope. If `child_scope_name` is empty, the `/` is elided.
+    ///
+    /// Characters which are not allowed in TensorFlow node names (anything
+    /// other than `[A-Za-z0-9_.\-/]`) are replaced with `_`, and leading
+    /// characters which may not start a name (anything other than
+    /// `[A-Za-z0-9.]`) are dropped.  A name with nothing left is treated as
+    /// empty.
     pub fn new_sub_scope(&self, name: &str) -> Scope {
+        let name: &str = &sanitize_name(name);
         let self_name: &str = &self.name;
         let (new_name, copy_names) = match (self_name, name) {
             (_, "") => (self.name.clone(), true),
@@ -153,23 +301,101 @@ impl Scope {
             } else {
                 Rc::new(RefCell::new(HashMap::new()))
             },
+            device: self.device.clone(),
+            attrs: self.attrs.clone(),
+            control_deps: self.control_deps.clone(),
+            state: self.state.clone(),
         }
     }
 
     /// Return a new scope. All ops created within the returned scope will have
     /// names of the form `scope_name/name[_suffix]`
+    ///
+    /// Characters which are not allowed in TensorFlow node names (anything
+    /// other than `[A-Za-z0-9_.\-/]`) are replaced with `_`, and leading
+    /// characters which may not start a name (anything other than
+    /// `[A-Za-z0-9.]`) are dropped.  A name with nothing left falls back to
+    /// the type of the op.
     pub fn with_op_name(&self, name: &str) -> Scope {
         Scope {
             graph: self.graph.clone(),
             name: self.name.clone(),
             children_names: self.children_names.clone(),
-            op_name: name.to_string(),
+            op_name: sanitize_name(name),
             op_names: self.op_names.clone(),
+            device: self.device.clone(),
+            attrs: self.attrs.clone(),
+            control_deps: self.control_deps.clone(),
+            state: self.state.clone(),
+        }
+    }
+
+    /// Return a new scope. All ops created within the returned scope will be
+    /// placed on `device`, e.g. `/device:GPU:0`.  The empty string clears the
+    /// device, so placement is left to TensorFlow.
+    pub fn with_device(&self, device: &str) -> Scope {
+        Scope {
+            graph: self.graph.clone(),
+            name: self.name.clone(),
+            children_names: self.children_names.clone(),
+            op_name: self.op_name.clone(),
+            op_names: self.op_names.clone(),
+            device: device.to_string(),
+            attrs: self.attrs.clone(),
+            control_deps: self.control_deps.clone(),
+            state: self.state.clone(),
+        }
+    }
+
+    /// Return a new scope. All ops created within the returned scope will have
+    /// the attribute `name` set to `value`, unless the op sets the attribute
+    /// itself.  This is mostly useful for internal attributes such as
+    /// `_class`, and for attributes shared by a group of ops.
+    ///
+    /// Attributes which an op doesn't declare are rejected when the op is
+    /// built, unless their names start with an underscore.
+    pub fn with_attr<V: Into<AttrValue>>(&self, name: &str, value: V) -> Scope {
+        let mut attrs = self.attrs.clone();
+        attrs.retain(|(n, _)| n != name);
+        attrs.push((name.to_string(), value.into()));
+        Scope {
+            graph: self.graph.clone(),
+            name: self.name.clone(),
+            children_names: self.children_names.clone(),
+            op_name: self.op_name.clone(),
+            op_names: self.op_names.clone(),
+            device: self.device.clone(),
+            attrs,
+            control_deps: self.control_deps.clone(),
+            state: self.state.clone(),
+        }
+    }
+
+    /// Return a new scope. All ops created within the returned scope will have
+    /// control dependencies on `control_deps`, in addition to the control
+    /// dependencies of the current scope, so they only run after all of
+    /// `control_deps` have run.
+    pub fn with_control_dependencies(&self, control_deps: &[Operation]) -> Scope {
+        let mut deps = self.control_deps.clone();
+        deps.extend(control_deps.iter().cloned());
+        Scope {
+            graph: self.graph.clone(),
+            name: self.name.clone(),
+            children_names: self.children_names.clone(),
+            op_name: self.op_name.clone(),
+            op_names: self.op_names.clone(),
+            device: self.device.clone(),
+            attrs: self.attrs.clone(),
+            control_deps: deps,
+            state: self.state.clone(),
         }
     }
 
     /// Return a unique name, using default_name if an op name has not been
     /// specified.
+    ///
+    /// Names which are already used by operations in the graph, even ones not
+    /// created with a scope, are skipped.
     pub fn get_unique_name_for_op(&self, default_name: &str) -> String {
         let name = if self.op_name == "" {
             default_name
@@ -178,21 +404,507 @@ impl Scope {
         };
         let map: &RefCell<_> = self.op_names.borrow();
         let mut map = map.borrow_mut();
-        let mut name_string = name.to_string();
-        loop {
-            match map.entry(name_string.clone()) {
-                Entry::Vacant(e) => {
-                    e.insert(0);
-                    return join("/", &self.name, &name_string);
-                }
-                Entry::Occupied(mut e) => {
-                    *e.get_mut() += 1;
-                    name_string = format!("{}_{}", name, *e.get());
+        let graph = self.graph();
+        let in_graph = |full_name: &str| match graph.operation_by_name(full_name) {
+            Ok(operation) => operation.is_some(),
+            Err(_) => false,
+        };
+        // The count for a name is the last suffix handed out for it, so
+        // probing resumes where it left off instead of starting over.
+        let mut suffix = match map.get(name) {
+            Some(count) => count + 1,
+            None => {
+                let full_name = join("/", &self.name, name);
+                if !in_graph(&full_name) {
+                    map.insert(name.to_string(), 0);
+                    return full_name;
                 }
+                1
+            }
+        };
+        loop {
+            let suffixed = format!("{}_{}", name, suffix);
+            let full_name = join("/", &self.name, &suffixed);
+            if !map.contains_key(&suffixed) && !in_graph(&full_name) {
+                map.insert(name.to_string(), suffix);
+                // Record the suffixed name too, so it isn't handed out again
+                // if it is later requested explicitly.
+                map.insert(suffixed, 0);
+                return full_name;
             }
+            suffix += 1;
         }
     }
 
+    /// Creates a new operation of type `op_type` with a unique name and the
+    /// properties of the scope, such as the device.  `f` adds the inputs and
+    /// sets the attributes of the operation.
+    ///
+    /// This is used by the op constructors in the `ops` module.
+    pub(crate) fn new_operation<F>(&mut self, op_type: &str, f: F) -> Result<Operation>
+    where
+        F: FnOnce(&mut OperationDescription<'_>) -> Result<()>,
+    {
+        let name = self.get_unique_name_for_op(op_type);
+        let graph: &RefCell<Graph> = self.graph.borrow();
+        let mut graph = graph.borrow_mut();
+        let has_graph_seed = (*self.state).borrow().graph_seed.is_some();
+        let use_graph_seed = has_graph_seed && self.has_seed_attrs(&graph, op_type)?;
+        let mut nd = graph.new_operation(op_type, &name)?;
+        if !self.device.is_empty() {
+            nd.set_device(&self.device)?;
+        }
+        f(&mut nd)?;
+        let colocation_groups = self.colocation_groups()?;
+        if !colocation_groups.is_empty() && !nd.has_attr("_class") {
+            nd.set_attr_string_list("_class", &colocation_groups)?;
+        }
+        for control_dep in &self.control_deps {
+            nd.add_control_input(control_dep);
+        }
+        for (attr_name, value) in &self.attrs {
+            if !nd.has_attr(attr_name) {
+                nd.set_attr(attr_name, value)?;
+            }
+        }
+        if use_graph_seed && !nd.has_attr("seed") && !nd.has_attr("seed2") {
+            let (seed, seed2) = self.next_op_seeds();
+            nd.set_attr_int("seed", seed)?;
+            nd.set_attr_int("seed2", seed2)?;
+        }
+        let operation = nd.finish()?;
+        let mut state = self.state.borrow_mut();
+        state.tapes.retain(|tape| match tape.upgrade() {
+            Some(tape) => {
+                tape.borrow_mut().insert(operation.inner());
+                true
+            }
+            None => false,
+        });
+        Ok(operation)
+    }
+
+    /// Calls the function `func_name`, which must be registered in the graph,
+    /// with a `PartitionedCall`, or a `StatefulPartitionedCall` if the
+    /// function is stateful, and returns its outputs.  Unlike an op whose type
+    /// is the function name, the call is not inlined, which is how TF2
+    /// SavedModels invoke their concrete functions.
+    ///
+    /// The function's outputs must have fixed types, i.e. not be given by
+    /// attributes.
+    pub fn call_function(&mut self, func_name: &str, inputs: &[Output]) -> Result<Vec<Output>> {
+        let mut signature = None;
+        for function in self.graph().get_functions()? {
+            if function.get_name()? == func_name {
+                let function_def: protos::function::FunctionDef =
+                    protobuf::parse_from_bytes(&function.to_function_def()?).map_err(|e| {
+                        invalid_arg!("Unable to parse FunctionDef for {}: {}", func_name, e)
+                    })?;
+                signature = Some(function_def.get_signature().clone());
+                break;
+            }
+        }
+        let signature =
+            signature.ok_or_else(|| invalid_arg!("Function {} is not in the graph", func_name))?;
+        if signature.get_input_arg().len() != inputs.len() {
+            return Err(invalid_arg!(
+                "Function {} takes {} inputs, but {} were given",
+                func_name,
+                signature.get_input_arg().len(),
+                inputs.len()
+            ));
+        }
+        let input_types: Vec<DataType> = inputs
+            .iter()
+            .map(|input| input.operation.output_type(input.index as usize))
+            .collect();
+        let mut output_types = Vec::new();
+        for arg in signature.get_output_arg() {
+            if !arg.get_type_attr().is_empty()
+                || !arg.get_number_attr().is_empty()
+                || !arg.get_type_list_attr().is_empty()
+            {
+                return Err(invalid_arg!(
+                    "Output {} of function {} does not have a fixed type",
+                    arg.get_name(),
+                    func_name
+                ));
+            }
+            output_types.push(DataType::from_proto(arg.get_field_type()));
+        }
+        let op_type = if signature.get_is_stateful() {
+            "StatefulPartitionedCall"
+        } else {
+            "PartitionedCall"
+        };
+        let operation = self.new_operation(op_type, |nd| {
+            nd.add_input_list(inputs);
+            nd.set_attr_type_list("Tin", &input_types)?;
+            nd.set_attr_type_list("Tout", &output_types)?;
+            nd.set_attr_func_name("f", func_name)?;
+            Ok(())
+        })?;
+        Ok((0..output_types.len())
+            .map(|index| Output {
+                operation: operation.clone(),
+                index: index as i32,
+            })
+            .collect())
+    }
+
+    /// Sets the graph-level random seed of this scope and all scopes sharing
+    /// its root, as with `ScopeOptions::with_graph_seed`.  The op-level seeds
+    /// of the random and dataset ops built afterwards are derived from `seed`
+    /// and the order in which the ops are built, so building the same ops
+    /// after calling this again yields the same seeds.
+    pub fn set_global_seed(&mut self, seed: u64) {
+        let mut state = self.state.borrow_mut();
+        state.graph_seed = Some(seed as i64);
+        state.seeded_ops = 0;
+    }
+
+    /// Returns the `seed` and `seed2` values for the next op which takes
+    /// seeds, or `(0, 0)`, which means nondeterministic, if no graph seed is
+    /// set.
+    ///
+    /// This is used by op constructors whose seeds are inputs rather than
+    /// attributes, such as `ops::shuffle_dataset_seeded`.
+    pub(crate) fn next_op_seeds(&self) -> (i64, i64) {
+        let mut state = self.state.borrow_mut();
+        match state.graph_seed {
+            Some(graph_seed) => {
+                state.seeded_ops += 1;
+                (graph_seed, state.seeded_ops)
+            }
+            None => (0, 0),
+        }
+    }
+
+    /// Returns true if the op type has `seed` and `seed2` attributes.
+    fn has_seed_attrs(&self, graph: &Graph, op_type: &str) -> Result<bool> {
+        let mut state = self.state.borrow_mut();
+        if let Some(has_seed) = state.op_types_with_seed.get(op_type) {
+            return Ok(*has_seed);
+        }
+        let op_def: protos::op_def::OpDef = protobuf::parse_from_bytes(&graph.get_op_def(op_type)?)
+            .map_err(|e| invalid_arg!("Unable to parse OpDef for {}: {}", op_type, e))?;
+        let attrs: HashSet<&str> = op_def
+            .get_attr()
+            .iter()
+            .map(|attr| attr.get_name())
+            .collect();
+        let has_seed = attrs.contains("seed") && attrs.contains("seed2");
+        state
+            .op_types_with_seed
+            .insert(op_type.to_string(), has_seed);
+        Ok(has_seed)
+    }
+
+    /// Starts recording the operations created with this scope, or any scope
+    /// sharing its root, until the returned tape is dropped.
+    pub fn gradient_tape(&self) -> GradientTape {
+        let recorded = Rc::new(RefCell::new(HashSet::new()));
+        self.state.borrow_mut().tapes.push(Rc::downgrade(&recorded));
+        GradientTape { recorded }
+    }
+
+    /// Colocates all operations created with this scope, or any scope sharing
+    /// its root, with `operation` until the returned guard is dropped, by
+    /// setting their `_class` attribute.  This is the equivalent of Python's
+    /// `tf.colocate_with`, e.g. to place optimizer slots with their variables.
+    ///
+    /// Guards nest: while several are alive, operations are colocated with
+    /// all of their operations.  Dropping a guard also ends the colocation of
+    /// any guards created after it.
+    pub fn colocation(&self, operation: &Operation) -> ColocationGuard {
+        let mut state = self.state.borrow_mut();
+        let depth = state.colocation_stack.len();
+        state.colocation_stack.push(operation.clone());
+        ColocationGuard {
+            state: self.state.clone(),
+            depth,
+        }
+    }
+
+    /// Returns the `_class` values of the operations on the colocation stack.
+    /// An operation which is itself colocated with others contributes their
+    /// groups, so colocation is transitive.
+    fn colocation_groups(&self) -> Result<Vec<String>> {
+        let state = (*self.state).borrow();
+        let mut groups = Vec::new();
+        for operation in &state.colocation_stack {
+            match operation.get_attr_string_list("_class") {
+                Ok(classes) => groups.extend(classes),
+                Err(_) => groups.push(format!("loc:@{}", operation.name()?)),
+            }
+        }
+        groups.sort();
+        groups.dedup();
+        Ok(groups)
+    }
+
+    /// Returns the integer constant cached for `key`, if any.  Nothing is
+    /// cached while the scope adds control dependencies, attributes or
+    /// colocation constraints to the operations it creates, since the
+    /// constant would not be shared correctly with scopes which don't.
+    pub(crate) fn cached_int_constant(&self, key: &IntConstantKey) -> Option<Operation> {
+        if !self.shares_constants() {
+            return None;
+        }
+        (*self.state).borrow().int_constants.get(key).cloned()
+    }
+
+    /// Caches the integer constant `constant` for `key`, so that the op
+    /// wrappers reuse it instead of building identical constants.
+    pub(crate) fn cache_int_constant(&self, key: IntConstantKey, constant: Operation) {
+        if self.shares_constants() {
+            self.state.borrow_mut().int_constants.insert(key, constant);
+        }
+    }
+
+    fn shares_constants(&self) -> bool {
+        self.control_deps.is_empty()
+            && self.attrs.is_empty()
+            && (*self.state).borrow().colocation_stack.is_empty()
+    }
+
+    /// Returns the device ops created within the scope are placed on, which
+    /// is the empty string if placement is left to TensorFlow.
+    pub(crate) fn device(&self) -> &str {
+        &self.device
+    }
+
+    /// Records the initializer of a variable created with this scope.
+    pub(crate) fn add_variable_initializer(&self, initializer: Operation) {
+        self.state
+            .borrow_mut()
+            .variable_initializers
+            .push(initializer);
+    }
+
+    /// Returns a `NoOp` which depends on the initializers of all variables
+    /// built so far with this scope, or any scope sharing its root, so that
+    /// running it initializes all of them.
+    ///
+    /// Running it again assigns the initial values again, which resets a
+    /// model to fresh weights, e.g. between trials, without rebuilding the
+    /// graph.  Random initial values are drawn again each time it runs.
+    /// Variables built after this call are not initialized by the returned op.
+    pub fn initializer_op(&mut self) -> Result<Operation> {
+        let initializers = (*self.state).borrow().variable_initializers.clone();
+        self.new_operation("NoOp", |nd| {
+            for initializer in &initializers {
+                nd.add_control_input(initializer);
+            }
+            Ok(())
+        })
+    }
+
+    /// Returns the initializers of all variables built so far with this
+    /// scope, or any scope sharing its root, in the order they were built.
+    pub fn variable_initializers(&self) -> Vec<Operation> {
+        (*self.state).borrow().variable_initializers.clone()
+    }
+
+    /// Builds a boolean scalar `PlaceholderWithDefault` named `name` which
+    /// switches a model between training and inference, so that a single
+    /// graph and `Session` serve both modes.  The flag is `false` unless it
+    /// is fed, so inference, and graphs exported for serving, need not know
+    /// about it, while training steps feed `true`.
+    ///
+    /// Ops whose behaviour depends on the mode, such as dropout or batch
+    /// normalization, are built for both modes and the flag picks one of the
+    /// results with `ops::select`:
+    ///
+    /// ```rust,ignore
+    /// let training = scope.training_flag("training")?;
+    /// let dropped = ops::mul(x.clone(), scaled_mask, &mut scope)?;
+    /// let y = ops::select(training, dropped, x, &mut scope)?;
+    /// ```
+    ///
+    /// Only the selected branch needs to be fetched, but both branches are
+    /// computed, so ops with side effects, such as the moving average updates
+    /// of batch normalization, should be made control dependencies of the
+    /// training op rather than of the selected output.
+    pub fn training_flag(&mut self, name: &str) -> Result<Operation> {
+        let default =
+            crate::ops::constant(false, &mut self.with_op_name(&format!("{}/default", name)))?;
+        crate::ops::PlaceholderWithDefault::new()
+            .dtype(DataType::Bool)
+            .shape(crate::Shape::new(Some(vec![])))
+            .build(default, &mut self.with_op_name(name))
+    }
+
+    /// Returns an error containing both shapes if the statically inferred
+    /// shapes of `a` and `b` are incompatible, to catch wiring mistakes while
+    /// the graph is being built rather than when it is run.
+    pub fn assert_compatible_shapes(&self, a: &Output, b: &Output) -> Result<()> {
+        let graph = self.graph();
+        let a_shape = graph.tensor_shape(a.clone())?;
+        let b_shape = graph.tensor_shape(b.clone())?;
+        if a_shape.is_compatible_with(&b_shape) {
+            Ok(())
+        } else {
+            Err(invalid_arg!(
+                "Shape {} of {}:{} is incompatible with shape {} of {}:{}",
+                a_shape,
+                a.operation.name()?,
+                a.index,
+                b_shape,
+                b.operation.name()?,
+                b.index
+            ))
+        }
+    }
+
+    /// Captures the naming state of the scope and the operations built so far,
+    /// so that construction can be rolled back with `restore` or continued in
+    /// a fresh graph with `fork`, e.g. to try out alternative architectures.
+    pub fn checkpoint(&self) -> Result<ScopeCheckpoint> {
+        let state = (*self.state).borrow();
+        let mut variable_initializers = Vec::new();
+        for initializer in &state.variable_initializers {
+            variable_initializers.push(initializer.name()?);
+        }
+        let mut int_constants = Vec::new();
+        for (key, constant) in &state.int_constants {
+            int_constants.push((key.clone(), constant.name()?));
+        }
+        Ok(ScopeCheckpoint {
+            children_names: (*self.children_names).borrow().clone(),
+            op_names: (*self.op_names).borrow().clone(),
+            seeded_ops: state.seeded_ops,
+            graph_def: self.graph().graph_def()?,
+            variable_initializers,
+            int_constants,
+        })
+    }
+
+    /// Rolls the naming state of the scope back to `checkpoint`, which must
+    /// have been taken from this scope, so that ops and sub-scopes get the
+    /// names and random seeds they would have gotten without the operations
+    /// built since.  Those operations stay in the graph, since operations
+    /// can't be removed, and their names are still not handed out again.
+    /// Variables built since are no longer initialized by `initializer_op`.
+    pub fn restore(&self, checkpoint: &ScopeCheckpoint) {
+        *self.children_names.borrow_mut() = checkpoint.children_names.clone();
+        *self.op_names.borrow_mut() = checkpoint.op_names.clone();
+        let mut state = self.state.borrow_mut();
+        state.seeded_ops = checkpoint.seeded_ops;
+        state
+            .variable_initializers
+            .truncate(checkpoint.variable_initializers.len());
+    }
+
+    /// Returns a scope with the same properties as this one which builds a
+    /// new graph containing the operations which existed when `checkpoint`
+    /// was taken from this scope.  Construction continues in the new graph as
+    /// it would have continued in the original graph from that point, so
+    /// several alternatives can be tried from the same checkpoint without
+    /// affecting each other.
+    ///
+    /// Returns an error if a control dependency of the scope was built after
+    /// the checkpoint.  Gradient tapes and colocation guards are not carried
+    /// over.
+    pub fn fork(&self, checkpoint: &ScopeCheckpoint) -> Result<Scope> {
+        let mut graph = Graph::new();
+        graph.import_graph_def(&checkpoint.graph_def, &ImportGraphDefOptions::new())?;
+        let mut control_deps = Vec::with_capacity(self.control_deps.len());
+        for control_dep in &self.control_deps {
+            control_deps.push(graph.operation_by_name_required(&control_dep.name()?)?);
+        }
+        let mut variable_initializers = Vec::new();
+        for name in &checkpoint.variable_initializers {
+            variable_initializers.push(graph.operation_by_name_required(name)?);
+        }
+        let mut int_constants = HashMap::new();
+        for (key, name) in &checkpoint.int_constants {
+            int_constants.insert(key.clone(), graph.operation_by_name_required(name)?);
+        }
+        let state = (*self.state).borrow();
+        let mut labels = BTreeMap::new();
+        for (name, label) in &state.labels {
+            if graph.operation_by_name(name)?.is_some() {
+                labels.insert(name.clone(), label.clone());
+            }
+        }
+        Ok(Scope {
+            graph: Rc::new(RefCell::new(graph)),
+            name: self.name.clone(),
+            children_names: Rc::new(RefCell::new(checkpoint.children_names.clone())),
+            op_name: self.op_name.clone(),
+            op_names: Rc::new(RefCell::new(checkpoint.op_names.clone())),
+            device: self.device.clone(),
+            attrs: self.attrs.clone(),
+            control_deps,
+            state: Rc::new(RefCell::new(ScopeState {
+                graph_seed: state.graph_seed,
+                seeded_ops: checkpoint.seeded_ops,
+                op_types_with_seed: state.op_types_with_seed.clone(),
+                variable_initializers,
+                int_constants,
+                labels,
+                ..ScopeState::default()
+            })),
+        })
+    }
+
+    /// Attaches a human-readable label, such as `"encoder layer 2 attention"`,
+    /// to `operation`, replacing any previous label.  Labels are kept next to
+    /// the graph rather than in it, and are shared by all scopes with the
+    /// same root, so they can be exported with `labels_json` alongside the
+    /// `GraphDef` for tools which would otherwise only show names like
+    /// `foo/MatMul_3`.
+    pub fn label(&self, operation: &Operation, text: &str) -> Result<()> {
+        self.state
+            .borrow_mut()
+            .labels
+            .insert(operation.name()?, text.to_string());
+        Ok(())
+    }
+
+    /// Returns the labels attached with `label`, by op name.
+    pub fn labels(&self) -> BTreeMap<String, String> {
+        (*self.state).borrow().labels.clone()
+    }
+
+    /// Returns the labels attached with `label` as a JSON object mapping op
+    /// names to labels, sorted by op name.
+    pub fn labels_json(&self) -> String {
+        let mut json = String::from("{");
+        for (i, (name, label)) in (*self.state).borrow().labels.iter().enumerate() {
+            if i > 0 {
+                json.push(',');
+            }
+            push_json_string(&mut json, name);
+            json.push(':');
+            push_json_string(&mut json, label);
+        }
+        json.push('}');
+        json
+    }
+
+    /// Returns the prefix of the names of ops created within the scope, which
+    /// is the empty string for the root scope.
+    pub fn prefix(&self) -> &str {
+        &self.name
+    }
+
+    /// Returns the operations in the graph whose names are within the scope's
+    /// prefix, in creation order.  For the root scope, this is every
+    /// operation in the graph.
+    pub fn operations(&self) -> Result<Vec<Operation>> {
+        let prefix = format!("{}/", self.name);
+        let mut operations = Vec::new();
+        for operation in self.graph().operation_iter() {
+            let name = operation.name()?;
+            if self.name.is_empty() || name == self.name || name.starts_with(&prefix) {
+                operations.push(operation);
+            }
+        }
+        Ok(operations)
+    }
+
     /// Returns the graph being built by the scope.
     pub fn graph(&self) -> impl Deref<Target = Graph> + '_ {
         let r: &RefCell<Graph> = self.graph.borrow();
@@ -206,10 +918,125 @@ impl Scope {
     }
 }
 
+/// Records the operations built while it is alive, so gradients can be
+/// computed in the style of TensorFlow 2's `tf.GradientTape`.
+///
+/// A tape is created with `Scope::gradient_tape`.  Like in TensorFlow 2, only
+/// sources which were created while recording, or which were explicitly
+/// `watch`ed, get a gradient.  Variables created before the tape must
+/// therefore be watched.  The gradients themselves are added to the graph by
+/// `Graph::add_gradients`.
+///
+/// ```
+/// # use tensorflow::ops;
+/// # use tensorflow::Scope;
+/// let mut scope = Scope::new_root_scope();
+/// let x = ops::constant(3.0f32, &mut scope)?;
+/// let tape = scope.gradient_tape();
+/// tape.watch(&x.clone().into());
+/// let y = ops::mul(x.clone(), x.clone(), &mut scope)?;
+/// let grads = tape.gradient(&mut scope, &[y.into()], &[x.into()])?;
+/// assert!(grads[0].is_some());
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+#[derive(Debug)]
+pub struct GradientTape {
+    recorded: Rc<RefCell<HashSet<*mut tf::TF_Operation>>>,
+}
+
+impl GradientTape {
+    /// Marks `output` as a source which may be differentiated against, even
+    /// though it was not created while recording.
+    pub fn watch(&self, output: &Output) {
+        self.recorded.borrow_mut().insert(output.operation.inner());
+    }
+
+    /// Returns true if the operation was recorded or watched by the tape.
+    pub fn is_recorded(&self, operation: &Operation) -> bool {
+        (*self.recorded).borrow().contains(&operation.inner())
+    }
+
+    /// Adds operations to the graph computing the gradients of the sum of
+    /// `targets` with respect to each of the `sources`.  A gradient is `None`
+    /// if the source was neither recorded nor watched, or if the targets do
+    /// not depend on it.  Returns an error if any of the targets was not
+    /// recorded by the tape.
+    pub fn gradient(
+        &self,
+        scope: &mut Scope,
+        targets: &[Output],
+        sources: &[Output],
+    ) -> Result<Vec<Option<Output>>> {
+        for target in targets {
+            if !self.is_recorded(&target.operation) {
+                return Err(invalid_arg!(
+                    "Target {} was not recorded by the gradient tape",
+                    target.operation.name()?
+                ));
+            }
+        }
+        let watched: Vec<Output> = sources
+            .iter()
+            .filter(|source| self.is_recorded(&source.operation))
+            .cloned()
+            .collect();
+        let mut gradients = if watched.is_empty() {
+            Vec::new()
+        } else {
+            scope
+                .graph_mut()
+                .add_gradients(None, targets, &watched, None)?
+        }
+        .into_iter();
+        Ok(sources
+            .iter()
+            .map(|source| {
+                if self.is_recorded(&source.operation) {
+                    gradients.next().unwrap_or(None)
+                } else {
+                    None
+                }
+            })
+            .collect())
+    }
+}
+
+/// The state of a scope captured by `Scope::checkpoint`.
+#[derive(Debug, Clone)]
+pub struct ScopeCheckpoint {
+    children_names: HashSet<String>,
+    op_names: HashMap<String, i32>,
+    seeded_ops: i64,
+    /// The serialized `GraphDef` of the graph.
+    graph_def: Vec<u8>,
+    /// Names of the initializers of the variables built so far.
+    variable_initializers: Vec<String>,
+    /// Names of the cached integer constants.
+    int_constants: Vec<(IntConstantKey, String)>,
+}
+
+/// Colocates operations with an operation while it is alive.  Created with
+/// `Scope::colocation`.
+#[derive(Debug)]
+pub struct ColocationGuard {
+    state: Rc<RefCell<ScopeState>>,
+    depth: usize,
+}
+
+impl Drop for ColocationGuard {
+    fn drop(&mut self) {
+        self.state
+            .borrow_mut()
+            .colocation_stack
+            .truncate(self.depth);
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     use crate::DataType;
+    use crate::Shape;
 
     #[test]
     fn smoke() {
@@ -245,4 +1072,500 @@ mod tests {
         assert_eq!(bar.get_unique_name_for_op("Add"), "foo/bar");
         assert_eq!(bar.get_unique_name_for_op("Add"), "foo/bar_1");
     }
+
+    #[test]
+    fn operations() {
+        let mut root = Scope::new_root_scope();
+        let mut linear = root.new_sub_scope("linear");
+        let mut linearity = root.new_sub_scope("linearity");
+        assert_eq!(root.prefix(), "");
+        assert_eq!(linear.prefix(), "linear");
+        let a = crate::ops::constant(1.0f32, &mut linear).unwrap();
+        let b = crate::ops::constant(2.0f32, &mut linear).unwrap();
+        crate::ops::constant(3.0f32, &mut linearity).unwrap();
+        crate::ops::constant(4.0f32, &mut root).unwrap();
+        let names = |ops: Vec<Operation>| -> Vec<String> {
+            ops.iter().map(|op| op.name().unwrap()).collect()
+        };
+        assert_eq!(
+            names(linear.operations().unwrap()),
+            vec![a.name().unwrap(), b.name().unwrap()]
+        );
+        assert_eq!(root.operations().unwrap().len(), 4);
+    }
+
+    #[test]
+    fn from_graph() {
+        let mut graph = Graph::new();
+        let mut nd = graph.new_operation("Const", "Const").unwrap();
+        nd.set_attr_tensor("value", 3.0f32.into()).unwrap();
+        nd.set_attr_type("dtype", DataType::Float).unwrap();
+        nd.finish().unwrap();
+        graph
+            .new_operation("NoOp", "linear/init")
+            .unwrap()
+            .finish()
+            .unwrap();
+        let mut scope = Scope::from_graph(graph).unwrap();
+        let c = crate::ops::constant(1.0f32, &mut scope).unwrap();
+        assert_eq!(c.name().unwrap(), "Const_1");
+        assert_eq!(scope.new_sub_scope("linear").prefix(), "linear_1");
+        assert!(scope.graph().operation_by_name("Const").unwrap().is_some());
+    }
+
+    #[test]
+    fn get_unique_name_for_op_mixed() {
+        let mut scope = Scope::new_root_scope();
+        let add = scope.with_op_name("Add");
+        let mut names = vec![add.get_unique_name_for_op("Const")];
+        scope
+            .graph_mut()
+            .new_operation("NoOp", "Add_1")
+            .unwrap()
+            .finish()
+            .unwrap();
+        names.push("Add_1".to_string());
+        for _ in 0..3 {
+            names.push(scope.get_unique_name_for_op("Add"));
+            names.push(add.get_unique_name_for_op("Const"));
+        }
+        names.push(scope.with_op_name("Add_2").get_unique_name_for_op("Const"));
+        names.push(scope.get_unique_name_for_op("Add_1"));
+        let distinct: HashSet<&String> = names.iter().collect();
+        assert_eq!(distinct.len(), names.len(), "{:?}", names);
+        assert_eq!(&names[..4], &["Add", "Add_1", "Add_2", "Add_3"]);
+    }
+
+    #[test]
+    fn call_function() {
+        let mut body = Scope::new_root_scope();
+        let x = crate::ops::Placeholder::new()
+            .dtype(DataType::Float)
+            .build(&mut body)
+            .unwrap();
+        let two = crate::ops::constant(2.0f32, &mut body).unwrap();
+        let y = crate::ops::mul(x.clone(), two, &mut body).unwrap();
+        let function = body
+            .graph()
+            .to_function(
+                "times_two",
+                false,
+                None,
+                &[x.into()],
+                &[y.into()],
+                None::<&[&str]>,
+                &crate::FunctionOptions::new(),
+                None,
+            )
+            .unwrap();
+
+        let mut scope = Scope::new_root_scope();
+        scope.graph_mut().copy_function(&function, None).unwrap();
+        let three = crate::ops::constant(3.0f32, &mut scope).unwrap();
+        let outputs = scope
+            .call_function("times_two", &[three.clone().into()])
+            .unwrap();
+        assert_eq!(outputs.len(), 1);
+        assert_eq!(outputs[0].operation.op_type().unwrap(), "PartitionedCall");
+        assert!(scope.call_function("missing", &[three.into()]).is_err());
+        assert!(scope.call_function("times_two", &[]).is_err());
+
+        let session = crate::Session::new(&crate::SessionOptions::new(), &scope.graph()).unwrap();
+        let mut run_args = crate::SessionRunArgs::new();
+        let fetch = run_args.request_fetch(&outputs[0].operation, outputs[0].index);
+        session.run(&mut run_args).unwrap();
+        assert_eq!(run_args.fetch::<f32>(fetch).unwrap()[0], 6.0);
+    }
+
+    #[test]
+    fn is_valid_name() {
+        assert!(Scope::is_valid_name("foo"));
+        assert!(Scope::is_valid_name("foo/bar_1"));
+        assert!(Scope::is_valid_name(".foo-bar.baz"));
+        assert!(!Scope::is_valid_name(""));
+        assert!(!Scope::is_valid_name("_foo"));
+        assert!(!Scope::is_valid_name("foo bar"));
+        assert!(!Scope::is_valid_name("foo:0"));
+    }
+
+    #[test]
+    fn sanitization() {
+        let scope = Scope::new_root_scope();
+        let foo = scope.new_sub_scope("my scope");
+        assert_eq!(&foo.name, "my_scope");
+        let bar = foo.with_op_name("op (1)");
+        assert_eq!(bar.get_unique_name_for_op("Add"), "my_scope/op__1_");
+
+        for name in &[
+            " x",
+            "  leading spaces",
+            "_foo",
+            "__init__",
+            "-a",
+            "/b",
+            " _.c",
+        ] {
+            let sanitized = sanitize_name(name);
+            assert!(Scope::is_valid_name(&sanitized), "{:?}", sanitized);
+        }
+        assert_eq!(sanitize_name(" x"), "x");
+        assert_eq!(sanitize_name("__init__"), "init__");
+        assert_eq!(sanitize_name(" _"), "");
+        let root = Scope::new_root_scope();
+        let c = crate::ops::constant(1.0f32, &mut root.with_op_name(" _private")).unwrap();
+        assert_eq!(c.name().unwrap(), "private");
+        let c = crate::ops::constant(1.0f32, &mut root.with_op_name("__")).unwrap();
+        assert_eq!(c.name().unwrap(), "Const");
+        let mut sub = root.new_sub_scope("_hidden layer");
+        let c = crate::ops::constant(1.0f32, &mut sub).unwrap();
+        assert_eq!(c.name().unwrap(), "hidden_layer/Const");
+    }
+
+    #[test]
+    fn default_device() {
+        let mut scope = Scope::new_root_scope_with_options(
+            ScopeOptions::default().with_default_device("/cpu:0"),
+        );
+        let c = crate::ops::constant(1.0f32, &mut scope).unwrap();
+        assert_eq!(c.device().unwrap(), "/cpu:0");
+        let mut sub = scope.new_sub_scope("sub");
+        let c = crate::ops::constant(1.0f32, &mut sub).unwrap();
+        assert_eq!(c.device().unwrap(), "/cpu:0");
+        let c = crate::ops::constant(1.0f32, &mut Scope::new_root_scope()).unwrap();
+        assert_eq!(c.device().unwrap(), "");
+    }
+
+    #[test]
+    fn with_device() {
+        let root = Scope::new_root_scope();
+        let mut cpu = root.with_device("/device:CPU:0");
+        let c = crate::ops::constant(1.0f32, &mut cpu).unwrap();
+        assert_eq!(c.device().unwrap(), "/device:CPU:0");
+        let mut sub = cpu.new_sub_scope("sub");
+        let c = crate::ops::constant(1.0f32, &mut sub).unwrap();
+        assert_eq!(c.device().unwrap(), "/device:CPU:0");
+        let c = crate::ops::constant(1.0f32, &mut sub.with_device("")).unwrap();
+        assert_eq!(c.device().unwrap(), "");
+    }
+
+    #[test]
+    fn graph_seed() {
+        let mut scope =
+            Scope::new_root_scope_with_options(ScopeOptions::default().with_graph_seed(42));
+        let shape = crate::ops::constant(&[2i32][..], &mut scope).unwrap();
+        let random = |scope: &mut Scope| {
+            crate::ops::RandomStandardNormal::new()
+                .dtype(DataType::Float)
+                .build(shape.clone(), scope)
+                .unwrap()
+        };
+        let r1 = random(&mut scope);
+        let r2 = random(&mut scope.new_sub_scope("sub"));
+        assert_eq!(r1.get_attr_int("seed").unwrap(), 42);
+        assert_eq!(r1.get_attr_int("seed2").unwrap(), 1);
+        assert_eq!(r2.get_attr_int("seed").unwrap(), 42);
+        assert_eq!(r2.get_attr_int("seed2").unwrap(), 2);
+        let explicit = crate::ops::RandomStandardNormal::new()
+            .dtype(DataType::Float)
+            .seed(7)
+            .build(shape.clone(), &mut scope)
+            .unwrap();
+        assert_eq!(explicit.get_attr_int("seed").unwrap(), 7);
+        assert_eq!(explicit.get_attr_int("seed2").unwrap(), 0);
+        let unseeded = crate::ops::RandomStandardNormal::new()
+            .dtype(DataType::Float)
+            .build(shape, &mut Scope::new_root_scope())
+            .unwrap();
+        assert_eq!(unseeded.get_attr_int("seed").unwrap(), 0);
+    }
+
+    #[test]
+    fn set_global_seed() {
+        let mut scope = Scope::new_root_scope();
+        let shape = crate::ops::constant(&[2i32][..], &mut scope).unwrap();
+        let random = |scope: &mut Scope| {
+            crate::ops::RandomStandardNormal::new()
+                .dtype(DataType::Float)
+                .build(shape.clone(), scope)
+                .unwrap()
+        };
+        assert_eq!(random(&mut scope).get_attr_int("seed").unwrap(), 0);
+        assert_eq!(scope.next_op_seeds(), (0, 0));
+        scope.set_global_seed(3);
+        let r1 = random(&mut scope);
+        assert_eq!(r1.get_attr_int("seed").unwrap(), 3);
+        assert_eq!(r1.get_attr_int("seed2").unwrap(), 1);
+        assert_eq!(scope.new_sub_scope("sub").next_op_seeds(), (3, 2));
+        scope.set_global_seed(3);
+        let r2 = random(&mut scope);
+        assert_eq!(r2.get_attr_int("seed").unwrap(), 3);
+        assert_eq!(r2.get_attr_int("seed2").unwrap(), 1);
+    }
+
+    #[test]
+    fn colocation() {
+        let mut scope = Scope::new_root_scope();
+        let classes =
+            |operation: &Operation| operation.get_attr_string_list("_class").unwrap_or_default();
+        let a = crate::ops::constant(1.0f32, &mut scope).unwrap();
+        let b = crate::ops::constant(2.0f32, &mut scope).unwrap();
+        {
+            let _a = scope.colocation(&a);
+            let c = crate::ops::constant(3.0f32, &mut scope.new_sub_scope("sub")).unwrap();
+            assert_eq!(classes(&c), ["loc:@Const"]);
+            {
+                let _c = scope.colocation(&c);
+                let _b = scope.colocation(&b);
+                let d = crate::ops::constant(4.0f32, &mut scope).unwrap();
+                assert_eq!(classes(&d), ["loc:@Const", "loc:@Const_1"]);
+            }
+            let e = crate::ops::constant(5.0f32, &mut scope).unwrap();
+            assert_eq!(classes(&e), ["loc:@Const"]);
+        }
+        let f = crate::ops::constant(6.0f32, &mut scope).unwrap();
+        assert!(classes(&f).is_empty());
+    }
+
+    #[test]
+    fn checkpoint() {
+        let mut scope = Scope::new_root_scope();
+        let name = |operation: Operation| operation.name().unwrap();
+        let variable = crate::Variable::builder()
+            .const_initial_value(3.0f32)
+            .build(&mut scope)
+            .unwrap();
+        let checkpoint = scope.checkpoint().unwrap();
+        assert_eq!(
+            name(crate::ops::constant(1.0f32, &mut scope).unwrap()),
+            "Const"
+        );
+        crate::Variable::builder()
+            .const_initial_value(4.0f32)
+            .build(&mut scope.new_sub_scope("trial"))
+            .unwrap();
+
+        let mut fork = scope.fork(&checkpoint).unwrap();
+        assert!(fork.graph().operation_by_name("Const").unwrap().is_none());
+        assert!(fork
+            .graph()
+            .operation_by_name("trial/Variable")
+            .unwrap()
+            .is_none());
+        assert_eq!(
+            name(crate::ops::constant(2.0f32, &mut fork).unwrap()),
+            "Const"
+        );
+        assert_eq!(fork.new_sub_scope("trial").prefix(), "trial");
+        let initializer = fork.initializer_op().unwrap();
+        let session = crate::Session::new(&crate::SessionOptions::new(), &fork.graph()).unwrap();
+        let mut args = crate::SessionRunArgs::new();
+        args.add_target(&initializer);
+        session.run(&mut args).unwrap();
+        let mut args = crate::SessionRunArgs::new();
+        let output = fork
+            .graph()
+            .operation_by_name_required(variable.name())
+            .unwrap();
+        let token = args.request_fetch(&output, 0);
+        session.run(&mut args).unwrap();
+        assert_eq!(args.fetch::<f32>(token).unwrap()[0], 3.0);
+
+        // The original graph keeps the trial operations, so their names
+        // are skipped.
+        scope.restore(&checkpoint);
+        assert_eq!(
+            name(crate::ops::constant(2.0f32, &mut scope).unwrap()),
+            "Const_1"
+        );
+        assert_eq!(scope.new_sub_scope("trial").prefix(), "trial");
+        assert_eq!((*scope.state).borrow().variable_initializers.len(), 1);
+    }
+
+    #[test]
+    fn checkpoint_int_constants() {
+        let mut scope = Scope::new_root_scope();
+        let name = |operation: Operation| operation.name().unwrap();
+        let axes = crate::ops::int_constant(&[0i64][..], &mut scope).unwrap();
+        let checkpoint = scope.checkpoint().unwrap();
+        let later = crate::ops::int_constant(&[1i64][..], &mut scope).unwrap();
+
+        // Constants cached before the checkpoint are reused in the fork, the
+        // later ones are built again.
+        let mut fork = scope.fork(&checkpoint).unwrap();
+        assert_eq!(
+            name(crate::ops::int_constant(&[0i64][..], &mut fork).unwrap()),
+            name(axes.clone())
+        );
+        assert_eq!(
+            name(crate::ops::int_constant(&[1i64][..], &mut fork).unwrap()),
+            name(later.clone())
+        );
+        assert_eq!((*fork.state).borrow().int_constants.len(), 2);
+
+        // The operations built since the checkpoint stay in the graph, so
+        // their constants stay cached after a restore.
+        scope.restore(&checkpoint);
+        assert_eq!(
+            name(crate::ops::int_constant(&[1i64][..], &mut scope).unwrap()),
+            name(later)
+        );
+        assert_eq!(
+            name(crate::ops::int_constant(&[0i64][..], &mut scope).unwrap()),
+            name(axes)
+        );
+        assert_eq!((*scope.state).borrow().int_constants.len(), 2);
+    }
+
+    #[test]
+    fn labels() {
+        let mut scope = Scope::new_root_scope();
+        let mut encoder = scope.new_sub_scope("encoder");
+        let a = crate::ops::constant(1.0f32, &mut encoder).unwrap();
+        let b = crate::ops::constant(2.0f32, &mut scope).unwrap();
+        scope.label(&a, "first").unwrap();
+        encoder.label(&b, "second").unwrap();
+        scope.label(&a, "encoder \"input\"").unwrap();
+        let checkpoint = scope.checkpoint().unwrap();
+        let c = crate::ops::constant(3.0f32, &mut scope).unwrap();
+        scope.label(&c, "after checkpoint").unwrap();
+        assert_eq!(
+            scope.labels().into_iter().collect::<Vec<_>>(),
+            vec![
+                ("Const".to_string(), "second".to_string()),
+                ("Const_1".to_string(), "after checkpoint".to_string()),
+                ("encoder/Const".to_string(), "encoder \"input\"".to_string()),
+            ]
+        );
+        assert_eq!(
+            encoder.labels_json(),
+            "{\"Const\":\"second\",\"Const_1\":\"after checkpoint\",\
+             \"encoder/Const\":\"encoder \\\"input\\\"\"}"
+        );
+        let fork = scope.fork(&checkpoint).unwrap();
+        assert_eq!(fork.labels().len(), 2);
+    }
+
+    #[test]
+    fn with_attr() {
+        let scope = Scope::new_root_scope();
+        let mut attr_scope = scope
+            .with_attr("_test_attr", 5)
+            .with_attr("_test_attr", "later")
+            .with_attr("dtype", DataType::Int32);
+        // Attributes set by the op win over the scope's attributes.
+        let c = crate::ops::constant(1.0f32, &mut attr_scope).unwrap();
+        assert_eq!(c.get_attr_string("_test_attr").unwrap(), "later");
+        assert_eq!(c.get_attr_type("dtype").unwrap(), DataType::Float);
+        let mut sub = attr_scope.new_sub_scope("sub");
+        let c = crate::ops::constant(1.0f32, &mut sub).unwrap();
+        assert_eq!(c.get_attr_string("_test_attr").unwrap(), "later");
+        let mut scope = scope;
+        let c = crate::ops::constant(1.0f32, &mut scope).unwrap();
+        assert!(c.get_attr_string("_test_attr").is_err());
+    }
+
+    #[test]
+    fn gradient_tape() {
+        let mut scope = Scope::new_root_scope();
+        let x = crate::ops::constant(3.0f32, &mut scope).unwrap();
+        let unwatched = crate::ops::constant(2.0f32, &mut scope).unwrap();
+        let before = crate::ops::mul(x.clone(), unwatched.clone(), &mut scope).unwrap();
+        let tape = scope.gradient_tape();
+        tape.watch(&x.clone().into());
+        let mut sub = scope.new_sub_scope("sub");
+        let y = crate::ops::mul(x.clone(), x.clone(), &mut sub).unwrap();
+        let y = crate::ops::add(y, unwatched.clone(), &mut scope).unwrap();
+        assert!(tape.is_recorded(&y));
+        assert!(!tape.is_recorded(&unwatched));
+        let grads = tape
+            .gradient(&mut scope, &[y.into()], &[x.into(), unwatched.into()])
+            .unwrap();
+        assert_eq!(grads.len(), 2);
+        assert!(grads[0].is_some());
+        assert!(grads[1].is_none());
+        // The gradient ops are added to the graph, not recorded.
+        assert!(!tape.is_recorded(&grads[0].as_ref().unwrap().operation));
+        assert!(tape.gradient(&mut scope, &[before.into()], &[]).is_err());
+
+        // Dropped tapes stop recording.
+        drop(tape);
+        crate::ops::constant(1.0f32, &mut scope).unwrap();
+        assert!((*scope.state).borrow().tapes.is_empty());
+    }
+
+    #[test]
+    fn with_control_dependencies() {
+        let mut scope = Scope::new_root_scope();
+        let a = crate::ops::constant(1.0f32, &mut scope).unwrap();
+        let b = crate::ops::constant(2.0f32, &mut scope).unwrap();
+        let mut sibling = scope.new_sub_scope("sibling");
+        let mut deps = scope.with_control_dependencies(&[a.clone()]);
+        let c = crate::ops::add(a.clone(), b.clone(), &mut deps).unwrap();
+        assert_eq!(c.num_control_inputs(), 1);
+        assert_eq!(c.control_inputs()[0].name().unwrap(), a.name().unwrap());
+        let mut sub = deps.new_sub_scope("sub");
+        let d = crate::ops::constant(3.0f32, &mut sub).unwrap();
+        assert_eq!(d.num_control_inputs(), 1);
+        let mut both = deps.with_control_dependencies(&[b.clone()]);
+        let e = crate::ops::constant(4.0f32, &mut both).unwrap();
+        assert_eq!(e.num_control_inputs(), 2);
+        let f = crate::ops::constant(5.0f32, &mut sibling).unwrap();
+        assert_eq!(f.num_control_inputs(), 0);
+        let g = crate::ops::constant(6.0f32, &mut scope).unwrap();
+        assert_eq!(g.num_control_inputs(), 0);
+    }
+
+    #[test]
+    fn training_flag() {
+        let mut scope = Scope::new_root_scope();
+        let training = scope.training_flag("training").unwrap();
+        assert_eq!(training.name().unwrap(), "training");
+        assert_eq!(training.op_type().unwrap(), "PlaceholderWithDefault");
+        // Inverted dropout with a fixed mask: the training branch zeroes some
+        // activations and scales the others, inference passes them through.
+        let x = crate::ops::constant(&[1.0f32, 2.0, 3.0, 4.0][..], &mut scope).unwrap();
+        let mask = crate::ops::constant(&[2.0f32, 0.0, 2.0, 0.0][..], &mut scope).unwrap();
+        let dropped = crate::ops::mul(x.clone(), mask, &mut scope).unwrap();
+        let y = crate::ops::select(training.clone(), dropped, x, &mut scope).unwrap();
+
+        let session = crate::Session::new(&crate::SessionOptions::new(), &scope.graph()).unwrap();
+        let run = |flag: Option<bool>| -> Vec<f32> {
+            let flag = flag.map(crate::Tensor::from);
+            let mut run_args = crate::SessionRunArgs::new();
+            if let Some(flag) = &flag {
+                run_args.add_feed(&training, 0, flag);
+            }
+            let fetch = run_args.request_fetch(&y, 0);
+            session.run(&mut run_args).unwrap();
+            run_args.fetch::<f32>(fetch).unwrap().to_vec()
+        };
+        assert_eq!(run(None), vec![1.0, 2.0, 3.0, 4.0]);
+        assert_eq!(run(Some(true)), vec![2.0, 0.0, 6.0, 0.0]);
+        assert_eq!(run(Some(false)), vec![1.0, 2.0, 3.0, 4.0]);
+    }
+
+    #[test]
+    fn assert_compatible_shapes() {
+        let mut scope = Scope::new_root_scope();
+        let placeholder = |shape: Shape, scope: &mut Scope| -> Output {
+            crate::ops::Placeholder::new()
+                .dtype(DataType::Float)
+                .shape(shape)
+                .build(scope)
+                .unwrap()
+                .into()
+        };
+        let batch_shape = Shape::new(Some(vec![None, Some(128)]));
+        let wide_shape = Shape::from(&[32i64, 256][..]);
+        let batch = placeholder(batch_shape.clone(), &mut scope);
+        let fixed = placeholder(Shape::from(&[32i64, 128][..]), &mut scope);
+        let wide = placeholder(wide_shape.clone(), &mut scope);
+        let unknown = placeholder(Shape::new(None), &mut scope);
+        scope.assert_compatible_shapes(&batch, &fixed).unwrap();
+        scope.assert_compatible_shapes(&wide, &unknown).unwrap();
+        let error = scope.assert_compatible_shapes(&batch, &wide).unwrap_err();
+        assert_eq!(error.code(), crate::Code::InvalidArgument);
+        assert!(error.to_string().contains(&batch_shape.to_string()));
+        assert!(error.to_string().contains(&wide_shape.to_string()));
+    }
 }
@@ -2,25 +2,77 @@ use super::AnyTensor;
 use super::Buffer;
 use super::Code;
 use super::DataType;
+use super::DeviceMemory;
+use super::DynTensor;
 use super::Graph;
+use super::MemoryStats;
 #[cfg(feature = "experimental_training")]
 use super::MetaGraphDef;
 use super::Operation;
+use super::Output;
 use super::Result;
+use super::RunStats;
 use super::SessionOptions;
 use super::Status;
 use super::Tensor;
 use super::TensorType;
 use crate::tf;
 use libc::{c_char, c_int};
+use protobuf::CodedInputStream;
+use protobuf::CodedOutputStream;
+use protobuf::ProtobufError;
+use std::collections::HashMap;
+use std::collections::HashSet;
 use std::ffi::CStr;
 use std::ffi::CString;
+use std::fmt;
+use std::fmt::Formatter;
 use std::marker;
+use std::mem;
 use std::path::Path;
 use std::ptr;
+use std::thread;
+use std::time::Duration;
+
+/// Field number of `timeout_in_ms` in tensorflow/core/protobuf/config.proto's
+/// `RunOptions`, which is not part of the generated protos.
+const RUN_OPTIONS_TIMEOUT_IN_MS: u32 = 2;
+
+/// Returns a copy of the serialized `RunOptions` proto `run_options` with the
+/// varint field `field_number` set to `value`, replacing any value it already
+/// has.  The other fields are copied unchanged.
+pub(crate) fn with_run_option(
+    run_options: &[u8],
+    field_number: u32,
+    value: i64,
+) -> Result<Vec<u8>> {
+    let invalid = |e: ProtobufError| invalid_arg!("Invalid serialized RunOptions: {}", e);
+    let mut output = Vec::with_capacity(run_options.len() + 11);
+    let mut input = CodedInputStream::from_bytes(run_options);
+    loop {
+        let start = input.pos() as usize;
+        if input.eof().map_err(invalid)? {
+            break;
+        }
+        let (number, wire_type) = input.read_tag_unpack().map_err(invalid)?;
+        input.skip_field(wire_type).map_err(invalid)?;
+        if number != field_number {
+            output.extend_from_slice(&run_options[start..input.pos() as usize]);
+        }
+    }
+    {
+        let mut stream = CodedOutputStream::vec(&mut output);
+        stream.write_int64(field_number, value).map_err(invalid)?;
+        stream.flush().map_err(invalid)?;
+    }
+    Ok(output)
+}
+
+/// Transforms a feed before it is passed to TensorFlow, returning the tensor
+/// to feed instead.
+type InputTransform = Box<dyn Fn(&dyn AnyTensor) -> Result<Box<dyn AnyTensor>> + Send + Sync>;
 
 /// Aggregation type for a saved model bundle.
-#[derive(Debug)]
 pub struct SavedModelBundle {
     /// The loaded session.
     pub session: Session,
@@ -28,6 +80,21 @@ pub struct SavedModelBundle {
     pub meta_graph_def: Vec<u8>,
     #[cfg(feature = "experimental_training")]
     meta_graph: MetaGraphDef,
+    input_transforms: HashMap<String, InputTransform>,
+}
+
+impl fmt::Debug for SavedModelBundle {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let mut transformed: Vec<&String> = self.input_transforms.keys().collect();
+        transformed.sort();
+        let mut debug = f.debug_struct("SavedModelBundle");
+        debug
+            .field("session", &self.session)
+            .field("meta_graph_def", &self.meta_graph_def);
+        #[cfg(feature = "experimental_training")]
+        debug.field("meta_graph", &self.meta_graph);
+        debug.field("input_transforms", &transformed).finish()
+    }
 }
 
 impl SavedModelBundle {
@@ -77,6 +144,7 @@ impl SavedModelBundle {
                 meta_graph_def: Vec::from(meta.as_ref()),
                 #[cfg(feature = "experimental_training")]
                 meta_graph: MetaGraphDef::from_serialized_proto(meta.as_ref())?,
+                input_transforms: HashMap::new(),
             })
         }
     }
@@ -86,6 +154,68 @@ impl SavedModelBundle {
     pub fn meta_graph_def(&self) -> &MetaGraphDef {
         &self.meta_graph
     }
+
+    /// Registers a transform which is applied to a copy of every tensor fed to
+    /// the operation named `name` before each `run`, e.g. to normalize inputs
+    /// on the host.  The caller's tensors are left untouched.  Feeding a
+    /// tensor of a type other than `T` to the operation makes the run fail.
+    ///
+    /// Replaces any transform previously registered for `name`.  Transforms
+    /// are only applied by `SavedModelBundle::run`, not when `session` is run
+    /// directly.
+    pub fn with_input_transform<T, F>(mut self, name: &str, transform: F) -> Self
+    where
+        T: TensorType,
+        F: Fn(&mut Tensor<T>) + Send + Sync + 'static,
+    {
+        let op_name = name.to_string();
+        self.input_transforms.insert(
+            name.to_string(),
+            Box::new(move |tensor: &dyn AnyTensor| {
+                let mut tensor = tensor
+                    .as_any()
+                    .downcast_ref::<Tensor<T>>()
+                    .ok_or_else(|| {
+                        invalid_arg!(
+                            "Feed for {} has type {}, but its input transform expects {}",
+                            op_name,
+                            tensor.data_type(),
+                            T::data_type()
+                        )
+                    })?
+                    .clone();
+                transform(&mut tensor);
+                Ok(Box::new(tensor) as Box<dyn AnyTensor>)
+            }),
+        );
+        self
+    }
+
+    /// Runs `step` with the session like `Session::run`, after applying the
+    /// input transforms to its feeds.
+    pub fn run(&self, step: &mut SessionRunArgs<'_>) -> Result<()> {
+        self.session
+            .run_with_transforms(step, Some(&self.input_transforms))
+    }
+}
+
+/// Applies `transforms` to the feeds of `step`, returning the transformed
+/// tensor for each feed which has a transform.
+fn transform_feeds(
+    transforms: &HashMap<String, InputTransform>,
+    step: &SessionRunArgs<'_>,
+) -> Result<Vec<Option<Box<dyn AnyTensor>>>> {
+    step.input_ports
+        .iter()
+        .zip(&step.input_tensors)
+        .map(|(port, tensor)| {
+            let name = unsafe { CStr::from_ptr(tf::TF_OperationName(port.oper)) };
+            match transforms.get(name.to_str()?) {
+                Some(transform) => transform(*tensor).map(Some),
+                None => Ok(None),
+            }
+        })
+        .collect()
 }
 
 /// Manages a single graph and execution.
@@ -150,6 +280,221 @@ impl Session {
         }
     }
 
+    /// Fetches the current values of the variables in `graph`, which must be
+    /// the graph of this session, paired with the names of the variables.
+    /// Only `VariableV2` and `Variable` operations are included; resource
+    /// variables are not supported.
+    pub fn variable_values(&self, graph: &Graph) -> Result<Vec<(String, DynTensor)>> {
+        let mut variables = Vec::new();
+        for operation in graph.operation_iter() {
+            if is_ref_variable(&operation)? {
+                variables.push(operation);
+            }
+        }
+        if variables.is_empty() {
+            return Ok(vec![]);
+        }
+        let mut args = SessionRunArgs::new();
+        let tokens: Vec<_> = variables
+            .iter()
+            .map(|variable| args.request_fetch(variable, 0))
+            .collect();
+        self.run(&mut args)?;
+        variables
+            .iter()
+            .zip(tokens)
+            .map(|(variable, token)| Ok((variable.name()?, args.fetch_dynamic(token)?)))
+            .collect()
+    }
+
+    /// Creates a session for `new_graph`, e.g. a modified copy of the graph of
+    /// this session, keeping the values of the variables it shares with
+    /// `old_graph`, which must be the graph of this session.  This allows a
+    /// model to evolve, e.g. by adding a class, without retraining it from
+    /// scratch.
+    ///
+    /// `init_targets`, typically the initializers of the variables, are run
+    /// first.  Then each variable of `new_graph` with the name, data type and
+    /// a shape compatible with a variable of `old_graph` is assigned that
+    /// variable's value, fed through a placeholder named `<name>/reload_value`
+    /// into an `Assign` named `<name>/reload`, which are added to `new_graph`
+    /// if they don't exist yet.  Other variables keep their initial values.
+    /// Like `variable_values`, this only supports `VariableV2` and `Variable`
+    /// operations.
+    pub fn reload(
+        &self,
+        old_graph: &Graph,
+        options: &SessionOptions,
+        new_graph: &mut Graph,
+        init_targets: &[Operation],
+    ) -> Result<Session> {
+        let values = self.variable_values(old_graph)?;
+        let mut feeds = Vec::new();
+        let mut assigns = Vec::new();
+        for (name, value) in &values {
+            let variable = match new_graph.operation_by_name(name)? {
+                Some(variable) => variable,
+                None => continue,
+            };
+            if !is_ref_variable(&variable)?
+                || variable.get_attr_type("dtype")? != value.dtype()
+                || !variable
+                    .get_attr_shape("shape")?
+                    .is_compatible_with(&value.shape())
+            {
+                continue;
+            }
+            let placeholder_name = format!("{}/reload_value", name);
+            let placeholder = match new_graph.operation_by_name(&placeholder_name)? {
+                Some(placeholder) => placeholder,
+                None => {
+                    let mut nd = new_graph.new_operation("Placeholder", &placeholder_name)?;
+                    nd.set_attr_type("dtype", value.dtype())?;
+                    nd.set_attr_shape("shape", &value.shape())?;
+                    nd.finish()?
+                }
+            };
+            let assign_name = format!("{}/reload", name);
+            let assign = match new_graph.operation_by_name(&assign_name)? {
+                Some(assign) => assign,
+                None => {
+                    let mut nd = new_graph.new_operation("Assign", &assign_name)?;
+                    nd.add_input(variable);
+                    nd.add_input(placeholder.clone());
+                    nd.finish()?
+                }
+            };
+            feeds.push((placeholder, value));
+            assigns.push(assign);
+        }
+        let session = Session::new(options, new_graph)?;
+        if !init_targets.is_empty() {
+            let mut args = SessionRunArgs::new();
+            for target in init_targets {
+                args.add_target(target);
+            }
+            session.run(&mut args)?;
+        }
+        if !assigns.is_empty() {
+            let mut args = SessionRunArgs::new();
+            for (placeholder, value) in &feeds {
+                args.input_ports.push(tf::TF_Output {
+                    oper: placeholder.inner(),
+                    index: 0,
+                });
+                args.input_tensors.push(value.as_any_tensor());
+            }
+            for assign in &assigns {
+                args.add_target(assign);
+            }
+            session.run(&mut args)?;
+        }
+        Ok(session)
+    }
+
+    /// Creates a session for `graph` and restores `variables`, given by
+    /// name, from the checkpoint with prefix `checkpoint_prefix`, e.g. to
+    /// resume training a graph built with `Scope`.  Other variables are left
+    /// uninitialized.
+    ///
+    /// The values are read with a `RestoreV2` and written with an `Assign`
+    /// per variable, which are added to `graph` in a new `restore_checkpoint`
+    /// name scope.  The checkpoint keys must be the variable names, as
+    /// written by `SavedModelSaver` or by TensorFlow's `Saver`.  Like
+    /// `variable_values`, this only supports `VariableV2` and `Variable`
+    /// operations.
+    pub fn from_graph_and_checkpoint<P: AsRef<Path>>(
+        options: &SessionOptions,
+        graph: &mut Graph,
+        checkpoint_prefix: P,
+        variables: &[&str],
+    ) -> Result<Session> {
+        let checkpoint_prefix = checkpoint_prefix.as_ref();
+        let prefix_value = Tensor::from(
+            checkpoint_prefix
+                .to_str()
+                .ok_or_else(|| {
+                    invalid_arg!(
+                        "Checkpoint prefix {:?} is not valid UTF-8",
+                        checkpoint_prefix
+                    )
+                })?
+                .to_string(),
+        );
+        let mut variable_ops = Vec::with_capacity(variables.len());
+        for name in variables {
+            let variable = graph.operation_by_name_required(name)?;
+            if !is_ref_variable(&variable)? {
+                return Err(invalid_arg!(
+                    "Operation {} is a {}, not a VariableV2 or Variable",
+                    name,
+                    variable.op_type()?
+                ));
+            }
+            variable_ops.push(variable);
+        }
+        if variable_ops.is_empty() {
+            return Session::new(options, graph);
+        }
+        let mut scope_name = "restore_checkpoint".to_string();
+        let mut suffix = 0;
+        while graph
+            .operation_by_name(&format!("{}/restore", scope_name))?
+            .is_some()
+        {
+            suffix += 1;
+            scope_name = format!("restore_checkpoint_{}", suffix);
+        }
+        let string_constant = |graph: &mut Graph, name: &str, values: &[String]| {
+            let mut nd = graph.new_operation("Const", &format!("{}/{}", scope_name, name))?;
+            nd.set_attr_type("dtype", DataType::String)?;
+            nd.set_attr_tensor(
+                "value",
+                Tensor::new(&[values.len() as u64]).with_values(values)?,
+            )?;
+            nd.finish()
+        };
+        let names: Vec<String> = variables.iter().map(|name| name.to_string()).collect();
+        let tensor_names = string_constant(graph, "tensor_names", &names)?;
+        let shape_and_slices =
+            string_constant(graph, "shape_and_slices", &vec![String::new(); names.len()])?;
+        let prefix = {
+            let mut nd = graph.new_operation("Placeholder", &format!("{}/prefix", scope_name))?;
+            nd.set_attr_type("dtype", DataType::String)?;
+            nd.finish()?
+        };
+        let dtypes = variable_ops
+            .iter()
+            .map(|variable| variable.get_attr_type("dtype"))
+            .collect::<Result<Vec<_>>>()?;
+        let restore = {
+            let mut nd = graph.new_operation("RestoreV2", &format!("{}/restore", scope_name))?;
+            nd.add_input(prefix.clone());
+            nd.add_input(tensor_names);
+            nd.add_input(shape_and_slices);
+            nd.set_attr_type_list("dtypes", &dtypes)?;
+            nd.finish()?
+        };
+        let mut assigns = Vec::with_capacity(variable_ops.len());
+        for (i, variable) in variable_ops.into_iter().enumerate() {
+            let mut nd = graph.new_operation("Assign", &format!("{}/assign_{}", scope_name, i))?;
+            nd.add_input(variable);
+            nd.add_input(Output {
+                operation: restore.clone(),
+                index: i as c_int,
+            });
+            assigns.push(nd.finish()?);
+        }
+        let session = Session::new(options, graph)?;
+        let mut args = SessionRunArgs::new();
+        args.add_feed(&prefix, 0, &prefix_value);
+        for assign in &assigns {
+            args.add_target(assign);
+        }
+        session.run(&mut args)?;
+        Ok(session)
+    }
+
     /// Closes the session.
     pub fn close(&mut self) -> Result<()> {
         let mut status = Status::new();
@@ -159,18 +504,82 @@ impl Session {
         status.into_result()
     }
 
+    /// Returns a handle which can be used from another thread to close the
+    /// session, which cancels all of its runs in progress, e.g. to abort
+    /// requests while shutting down.  See `SessionCloser` for what can be
+    /// cancelled.
+    pub fn closer(&self) -> SessionCloser<'_> {
+        SessionCloser { session: self }
+    }
+
+    /// Runs `step` like `run`, retrying with exponential backoff while the
+    /// run fails with one of the error codes of `policy`, e.g. the transient
+    /// `Unavailable` and `Aborted` errors of distributed sessions.  Returns
+    /// the error of the last attempt if all retries fail.
+    pub fn run_with_retry(
+        &self,
+        step: &mut SessionRunArgs<'_>,
+        policy: &RetryPolicy,
+    ) -> Result<()> {
+        policy.retry(|| self.run(step))
+    }
+
     /// Runs the graph, feeding the inputs and then fetching the outputs
     /// requested in the step.  Note that the session has interior mutability;
     /// this may mutate variables in the graph, and the caller is responsible
     /// for handling race conditions.
+    ///
+    /// Feeds and fetches are always host tensors, since the C API has no way
+    /// to feed a tensor which lives on a device (the `CallableOptions` feed
+    /// devices used by Python are not exposed).  To pass data produced on a
+    /// GPU to a later computation without copying it to the host and back,
+    /// either build both computations in the same graph and fetch only the
+    /// final result, or assign the intermediate result to a variable placed
+    /// on the GPU in one run and read the variable in the next.
+    ///
+    /// The same applies to graphs sharded across several devices: feeds and
+    /// fetches cannot be given per-tensor devices.  Place the operations
+    /// which consume the feeds on the first device and the operations which
+    /// produce the fetches on the last one (e.g. with `Scope::with_device`);
+    /// only the fed and fetched tensors are copied between the host and
+    /// those devices, and tensors flowing between the shards stay on the
+    /// devices.  The devices must exist in the session, see `device_list`.
+    ///
+    /// Before running, checks that every placeholder needed to compute the
+    /// fetches and targets is fed, and returns an `InvalidArgument` error
+    /// naming all of the unfed ones otherwise.
     pub fn run(&self, step: &mut SessionRunArgs<'_>) -> Result<()> {
+        self.run_with_transforms(step, None)
+    }
+
+    /// Runs `step`, feeding the tensors returned by `transforms` in place of
+    /// the feeds they apply to.
+    fn run_with_transforms(
+        &self,
+        step: &mut SessionRunArgs<'_>,
+        transforms: Option<&HashMap<String, InputTransform>>,
+    ) -> Result<()> {
         // In case we're running it a second time and not all outputs were taken out.
         step.drop_output_tensors();
         // make sure run_metadata is either None or an empty TF_Buffer
         step.maybe_reset_run_metadata();
+        step.check_placeholders_fed()?;
 
         let mut status = Status::new();
-        let maybe_tensors: Result<_> = step.input_tensors.iter().map(|t| t.inner()).collect();
+        // The transformed tensors must outlive the call to TF_SessionRun.
+        let transformed = match transforms {
+            Some(transforms) => transform_feeds(transforms, step)?,
+            None => Vec::new(),
+        };
+        let maybe_tensors: Result<_> = step
+            .input_tensors
+            .iter()
+            .enumerate()
+            .map(|(i, t)| match transformed.get(i) {
+                Some(Some(transformed)) => transformed.inner(),
+                _ => t.inner(),
+            })
+            .collect();
         let input_tensors: Vec<_> = maybe_tensors?;
         let run_options_ptr = match step.run_options.as_ref() {
             Some(buf) => buf.inner(),
@@ -207,6 +616,72 @@ impl Session {
         status.into_result()
     }
 
+    /// Runs the graph once for each feed set, fetching `fetches` each time.
+    /// Returns the fetched tensors for each run, in the same order as
+    /// `feed_sets` and `fetches`.  All fetches must have type `T`.
+    ///
+    /// Stops at the first run that fails.
+    pub fn run_batch<T: TensorType>(
+        &self,
+        feed_sets: &[FeedSet<'_>],
+        fetches: &[Output],
+    ) -> Result<Vec<Vec<Tensor<T>>>> {
+        self.run_batch_with_progress(feed_sets, fetches, |_, _| {})
+    }
+
+    /// Like `run_batch`, but calls `progress(completed, total)` after each
+    /// run, which is useful for reporting progress on long batch jobs.
+    pub fn run_batch_with_progress<T: TensorType, F: FnMut(usize, usize)>(
+        &self,
+        feed_sets: &[FeedSet<'_>],
+        fetches: &[Output],
+        mut progress: F,
+    ) -> Result<Vec<Vec<Tensor<T>>>> {
+        let mut results = Vec::with_capacity(feed_sets.len());
+        for (i, feed_set) in feed_sets.iter().enumerate() {
+            results.push(self.run_feed_set(feed_set, fetches)?);
+            progress(i + 1, feed_sets.len());
+        }
+        Ok(results)
+    }
+
+    /// Runs the graph with `feeds`, and passes the fetched value of `output`
+    /// to `f`, returning its result.
+    ///
+    /// The tensor is moved into `f`, so unless `f` returns it, it is dropped
+    /// as soon as `f` is done with it.  This bounds peak memory when
+    /// processing large fetches, e.g. in batch jobs.
+    pub fn run_with_fetch_callback<T: TensorType, R, F: FnOnce(Tensor<T>) -> R>(
+        &self,
+        feeds: &FeedSet<'_>,
+        output: &Output,
+        f: F,
+    ) -> Result<R> {
+        let mut tensors = self.run_feed_set(feeds, std::slice::from_ref(output))?;
+        Ok(f(tensors.pop().unwrap()))
+    }
+
+    fn run_feed_set<T: TensorType>(
+        &self,
+        feed_set: &FeedSet<'_>,
+        fetches: &[Output],
+    ) -> Result<Vec<Tensor<T>>> {
+        let mut step = SessionRunArgs::new();
+        for (operation, index, tensor) in &feed_set.feeds {
+            step.input_ports.push(tf::TF_Output {
+                oper: operation.inner(),
+                index: *index,
+            });
+            step.input_tensors.push(*tensor);
+        }
+        let tokens: Vec<_> = fetches
+            .iter()
+            .map(|fetch| step.request_fetch(&fetch.operation, fetch.index))
+            .collect();
+        self.run(&mut step)?;
+        tokens.into_iter().map(|token| step.fetch(token)).collect()
+    }
+
     /// Lists all devices in a session.
     pub fn device_list(&self) -> Result<Vec<Device>> {
         let status = Status::new();
@@ -248,6 +723,31 @@ impl Session {
             result
         }
     }
+
+    /// Returns the memory used on each device by the traced run which
+    /// produced `run_metadata`, together with the amount of memory on each
+    /// device of the session, e.g. to decide how many models fit on a GPU.
+    /// Every device of the session is included, and devices which only
+    /// appear in `run_metadata` are included with unknown `memory_bytes`.
+    ///
+    /// The run must set a trace level in its `RunOptions` and request
+    /// metadata, see `SessionRunArgs::get_run_stats`.  See `MemoryStats` for
+    /// the accuracy limits.
+    pub fn memory_usage(&self, run_metadata: &[u8]) -> Result<MemoryStats> {
+        let mut stats = MemoryStats::from_run_metadata(run_metadata)?;
+        for device in self.device_list()? {
+            match stats.devices.iter_mut().find(|d| d.device == device.name) {
+                Some(memory) => memory.memory_bytes = Some(device.memory_bytes),
+                None => stats.devices.push(DeviceMemory {
+                    device: device.name,
+                    peak_bytes: 0,
+                    memory_bytes: Some(device.memory_bytes),
+                }),
+            }
+        }
+        stats.devices.sort_by(|a, b| a.device.cmp(&b.device));
+        Ok(stats)
+    }
 }
 
 impl Drop for Session {
@@ -264,6 +764,36 @@ unsafe impl Send for Session {}
 
 unsafe impl Sync for Session {}
 
+/// Closes a `Session` from another thread, cancelling its runs.
+///
+/// The C API has no way to cancel a single run, so this closes the whole
+/// session, which cancels all of its runs in progress, not just one of them,
+/// and makes every later run fail. The cancelled runs return an error once
+/// every op which is already executing has finished, so ops blocked waiting
+/// for input, such as dequeuing from an empty queue, are interrupted, but a
+/// single long-running kernel, such as a large matrix multiplication, is
+/// not.  A closed session can't be run again, so a new session must be
+/// created afterwards.
+///
+/// To limit the duration of a single run instead, use
+/// `SessionRunArgs::set_timeout`.
+#[derive(Debug, Clone, Copy)]
+pub struct SessionCloser<'s> {
+    session: &'s Session,
+}
+
+impl<'s> SessionCloser<'s> {
+    /// Closes the session, cancelling all of its runs in progress.  The
+    /// session can't be run again afterwards.
+    pub fn close(&self) -> Result<()> {
+        let mut status = Status::new();
+        unsafe {
+            tf::TF_CloseSession(self.session.inner, status.inner());
+        }
+        status.into_result()
+    }
+}
+
 ////////////////////////
 
 /// An opaque token for retrieving an output from a computation.
@@ -337,6 +867,15 @@ impl<'l> SessionRunArgs<'l> {
         }
     }
 
+    /// Returns a builder for setting up the feeds, fetches and targets with
+    /// method chaining.
+    pub fn builder() -> SessionRunArgsBuilder<'l> {
+        SessionRunArgsBuilder {
+            args: SessionRunArgs::new(),
+            fetches: vec![],
+        }
+    }
+
     /// Adds an input to be fed to the graph. The index selects which output of
     /// the operation to feed. For most operations, there is only one output,
     /// so the index should be 0.
@@ -380,6 +919,48 @@ impl<'l> SessionRunArgs<'l> {
         }
     }
 
+    /// Requests a boolean scalar which is true if every element of `output`
+    /// is finite, i.e. neither NaN nor infinite, so the health of activations
+    /// can be monitored without failing the run.  Use `fetch_all_finite` to
+    /// extract it.
+    ///
+    /// The check is added to `graph`, which must be the session's graph, the
+    /// first time it is requested for `output` and reused afterwards.
+    /// `output` must have a floating point type.
+    pub fn request_all_finite(&mut self, graph: &mut Graph, output: &Output) -> Result<FetchToken> {
+        let name = format!("{}/all_finite_{}", output.operation.name()?, output.index);
+        let all = match graph.operation_by_name(&name)? {
+            Some(all) => all,
+            None => {
+                let mut nd = graph.new_operation("IsFinite", &format!("{}/is_finite", name))?;
+                nd.add_input(output.clone());
+                let is_finite = nd.finish()?;
+                let mut nd = graph.new_operation("Const", &format!("{}/shape", name))?;
+                nd.set_attr_type("dtype", DataType::Int32)?;
+                nd.set_attr_tensor("value", Tensor::from(&[-1i32][..]))?;
+                let shape = nd.finish()?;
+                let mut nd = graph.new_operation("Reshape", &format!("{}/flat", name))?;
+                nd.add_input(is_finite);
+                nd.add_input(shape);
+                let flat = nd.finish()?;
+                let mut nd = graph.new_operation("Const", &format!("{}/axis", name))?;
+                nd.set_attr_type("dtype", DataType::Int32)?;
+                nd.set_attr_tensor("value", Tensor::from(0i32))?;
+                let axis = nd.finish()?;
+                let mut nd = graph.new_operation("All", &name)?;
+                nd.add_input(flat);
+                nd.add_input(axis);
+                nd.finish()?
+            }
+        };
+        Ok(self.request_fetch(&all, 0))
+    }
+
+    /// Extracts the flag requested with `request_all_finite`.
+    pub fn fetch_all_finite(&mut self, token: FetchToken) -> Result<bool> {
+        Ok(self.fetch::<bool>(token)?[0])
+    }
+
     /// Deprecated alias for request_fetch.
     #[deprecated(note = "Use request_fetch instead.", since = "0.10.0")]
     #[allow(deprecated)]
@@ -427,6 +1008,65 @@ impl<'l> SessionRunArgs<'l> {
         Ok(tensor)
     }
 
+    /// Copies a tensor output given a token into `buf`, e.g. a buffer owned
+    /// by a rendering pipeline, instead of returning it as a new `Tensor`.
+    /// The output is consumed as with `fetch`.  Returns an error if the
+    /// output is unavailable, its type is not `T`, or its number of elements
+    /// is not `buf.len()`.
+    pub fn fetch_into<T: TensorType>(&mut self, token: FetchToken, buf: &mut [T]) -> Result<()> {
+        if let Some(c_tensor) = self.output_tensors.get(token.index) {
+            if !c_tensor.is_null() {
+                let num_elements: i64 = unsafe {
+                    (0..tf::TF_NumDims(*c_tensor))
+                        .map(|i| tf::TF_Dim(*c_tensor, i))
+                        .product()
+                };
+                if num_elements != buf.len() as i64 {
+                    return Err(invalid_arg!(
+                        "Output has {} elements, but the buffer has length {}",
+                        num_elements,
+                        buf.len()
+                    ));
+                }
+            }
+        }
+        let tensor = self.fetch::<T>(token)?;
+        buf.clone_from_slice(&tensor);
+        Ok(())
+    }
+
+    /// Extracts a tensor output given a token, with whatever element type it
+    /// has.  This is useful for generic tools which don't know the output
+    /// types at compile time.
+    pub fn fetch_dynamic(&mut self, token: FetchToken) -> Result<DynTensor> {
+        if token.index >= self.output_tensors.len() {
+            return Err(Status::new_set_lossy(
+                Code::OutOfRange,
+                &format!(
+                    "Requested output index is out of range: {} vs {}",
+                    token.index,
+                    self.output_tensors.len()
+                ),
+            ));
+        }
+        let data_type = match self.output_data_type(token.index) {
+            Some(data_type) => data_type,
+            None => {
+                return Err(Status::new_set_lossy(
+                    Code::Unavailable,
+                    "Output not available. Either it was already taken, or \
+                     this step has not been successfully run yet.",
+                ))
+            }
+        };
+        DynTensor::fetch(self, token, data_type).unwrap_or_else(|| {
+            Err(Status::new_set_lossy(
+                Code::Unimplemented,
+                &format!("Fetching tensors of type {} is not supported", data_type),
+            ))
+        })
+    }
+
     /// Deprecated alias for fetch.
     #[deprecated(note = "Use fetch instead.", since = "0.10.0")]
     #[allow(deprecated)]
@@ -439,6 +1079,24 @@ impl<'l> SessionRunArgs<'l> {
         self.target_operations.push(operation.inner());
     }
 
+    /// Removes all feeds, and releases the outputs which haven't been
+    /// fetched and the run metadata, so their buffers are freed now rather
+    /// than when the `SessionRunArgs` is dropped.  Fetch requests, targets
+    /// and run options are kept, so new feeds can be added and the graph run
+    /// again with the same fetch tokens.
+    ///
+    /// Feed tensors are borrowed, not owned, so their buffers are freed when
+    /// the caller drops them, and the borrow checker only allows that once
+    /// the `SessionRunArgs` which borrowed them is gone.  When feeding large
+    /// tensors in a loop, create the feeds and the `SessionRunArgs` inside
+    /// the loop body so both are dropped after each run.
+    pub fn clear(&mut self) {
+        self.input_ports.clear();
+        self.input_tensors.clear();
+        self.drop_output_tensors();
+        self.run_metadata = None;
+    }
+
     /// Retuns the type of the tensor given an index.
     /// Returns `None` if the index is out of range or the output is not yet available.
     pub fn output_data_type(&self, output_idx: usize) -> Option<DataType> {
@@ -460,6 +1118,22 @@ impl<'l> SessionRunArgs<'l> {
         self.run_options = Some(Buffer::from(run_options))
     }
 
+    /// Makes the run fail with a `DeadlineExceeded` error if it takes longer
+    /// than `timeout`.  This sets the `timeout_in_ms` field of the run
+    /// options, replacing any timeout set with `set_run_options` and keeping
+    /// the other options.  Returns an error if the run options are not a
+    /// valid serialized `RunOptions` proto.
+    pub fn set_timeout(&mut self, timeout: Duration) -> Result<()> {
+        let timeout_in_ms = timeout.as_millis().min(i64::MAX as u128) as i64;
+        let run_options = with_run_option(
+            self.get_run_options().unwrap_or(&[]),
+            RUN_OPTIONS_TIMEOUT_IN_MS,
+            timeout_in_ms,
+        )?;
+        self.set_run_options(&run_options);
+        Ok(())
+    }
+
     /// Returns the serialized [`RunOptions` proto](https://github.com/tensorflow/tensorflow/blob/master/tensorflow/core/protobuf/config.proto)
     /// Returns none if `RunOption` are not set.
     pub fn get_run_options(&self) -> Option<&[u8]> {
@@ -472,6 +1146,17 @@ impl<'l> SessionRunArgs<'l> {
         self.run_metadata.as_ref().map(std::convert::AsRef::as_ref)
     }
 
+    /// Returns a summary of the step statistics in the `RunMetadata`.
+    /// Returns none if `self::set_request_metadata` is not set to true.
+    ///
+    /// Step statistics are only collected if the `RunOptions` set a trace
+    /// level, otherwise the summary is empty.
+    pub fn get_run_stats(&self) -> Option<Result<RunStats>> {
+        self.run_metadata
+            .as_ref()
+            .map(|run_metadata| RunStats::from_run_metadata(run_metadata))
+    }
+
     /// Requests `run_metadata`. The serialized [`RunMetadata` proto](https://github.com/tensorflow/tensorflow/blob/master/tensorflow/core/protobuf/config.proto)
     /// can be retrieved via `self::get_metadata` after calling `Session::run`.
     pub fn set_request_metadata(&mut self, request: bool) {
@@ -483,6 +1168,57 @@ impl<'l> SessionRunArgs<'l> {
         self.request_metadata
     }
 
+    /// Returns a copy of these args whose feeds, fetches and targets refer to
+    /// the operations with the same names in `graph`, e.g. a copy of the
+    /// original graph placed on other devices.
+    pub(crate) fn remapped_to(&self, graph: &Graph) -> Result<SessionRunArgs<'l>> {
+        let remap_operation = |operation: *mut tf::TF_Operation| -> Result<*mut tf::TF_Operation> {
+            let name = unsafe { CStr::from_ptr(tf::TF_OperationName(operation)) }.to_str()?;
+            Ok(graph.operation_by_name_required(name)?.inner())
+        };
+        let remap_port = |port: &tf::TF_Output| -> Result<tf::TF_Output> {
+            Ok(tf::TF_Output {
+                oper: remap_operation(port.oper)?,
+                index: port.index,
+            })
+        };
+        let mut args = SessionRunArgs::new();
+        args.input_ports = self
+            .input_ports
+            .iter()
+            .map(remap_port)
+            .collect::<Result<_>>()?;
+        args.input_tensors = self.input_tensors.clone();
+        args.output_ports = self
+            .output_ports
+            .iter()
+            .map(remap_port)
+            .collect::<Result<_>>()?;
+        args.output_tensors = vec![ptr::null_mut(); self.output_tensors.len()];
+        args.target_operations = self
+            .target_operations
+            .iter()
+            .map(|operation| remap_operation(*operation as *mut _).map(|op| op as *const _))
+            .collect::<Result<_>>()?;
+        args.run_options = self.run_options.clone();
+        args.request_metadata = self.request_metadata;
+        Ok(args)
+    }
+
+    /// Moves the fetched outputs and run metadata of `other`, which must have
+    /// the same fetches, into these args.
+    pub(crate) fn take_results_from(&mut self, other: &mut SessionRunArgs<'_>) {
+        self.drop_output_tensors();
+        for (tensor, other_tensor) in self
+            .output_tensors
+            .iter_mut()
+            .zip(&mut other.output_tensors)
+        {
+            *tensor = mem::replace(other_tensor, ptr::null_mut());
+        }
+        self.run_metadata = other.run_metadata.take();
+    }
+
     fn drop_output_tensors(&mut self) {
         for tensor in &mut self.output_tensors {
             // TODO: Is TF_DeleteTensor NULL safe?
@@ -495,6 +1231,67 @@ impl<'l> SessionRunArgs<'l> {
         }
     }
 
+    /// Returns an error naming all of the placeholders which must be fed to
+    /// compute the fetches and targets, but aren't, whereas TensorFlow only
+    /// reports the first one it runs into.
+    fn check_placeholders_fed(&self) -> Result<()> {
+        let fed: HashSet<(*mut tf::TF_Operation, c_int)> = self
+            .input_ports
+            .iter()
+            .map(|port| (port.oper, port.index))
+            .collect();
+        let mut pending: Vec<*mut tf::TF_Operation> = Vec::new();
+        for port in &self.output_ports {
+            if !fed.contains(&(port.oper, port.index)) {
+                pending.push(port.oper);
+            }
+        }
+        pending.extend(self.target_operations.iter().map(|op| *op as *mut _));
+        let mut visited = HashSet::new();
+        let mut unfed = Vec::new();
+        while let Some(operation) = pending.pop() {
+            if !visited.insert(operation) {
+                continue;
+            }
+            unsafe {
+                let op_type = CStr::from_ptr(tf::TF_OperationOpType(operation)).to_bytes();
+                if op_type == b"Placeholder" || op_type == b"PlaceholderV2" {
+                    unfed.push(
+                        CStr::from_ptr(tf::TF_OperationName(operation))
+                            .to_string_lossy()
+                            .into_owned(),
+                    );
+                    continue;
+                }
+                for index in 0..tf::TF_OperationNumInputs(operation) {
+                    let input = tf::TF_OperationInput(tf::TF_Input {
+                        oper: operation,
+                        index,
+                    });
+                    if !fed.contains(&(input.oper, input.index)) {
+                        pending.push(input.oper);
+                    }
+                }
+                let num_control_inputs = tf::TF_OperationNumControlInputs(operation);
+                let mut control_inputs = vec![ptr::null_mut(); num_control_inputs as usize];
+                let len = tf::TF_OperationGetControlInputs(
+                    operation,
+                    control_inputs.as_mut_ptr(),
+                    num_control_inputs,
+                );
+                pending.extend(&control_inputs[..len as usize]);
+            }
+        }
+        if unfed.is_empty() {
+            return Ok(());
+        }
+        unfed.sort();
+        Err(invalid_arg!(
+            "The following placeholders must be fed: {}",
+            unfed.join(", ")
+        ))
+    }
+
     fn maybe_reset_run_metadata(&mut self) {
         self.run_metadata = None;
     }
@@ -506,12 +1303,379 @@ impl<'l> Drop for SessionRunArgs<'l> {
     }
 }
 
+/// Builds a `SessionRunArgs` with method chaining, keeping track of the fetch
+/// token for each requested output.
+///
+/// ```rust,ignore
+/// let (mut args, fetches) = SessionRunArgs::builder()
+///     .feed(&x, 0, &x_value)
+///     .fetch(&y)
+///     .target(&train)
+///     .build();
+/// session.run(&mut args)?;
+/// let y_value: Tensor<f32> = args.fetch(fetches.get(&y).unwrap())?;
+/// ```
+#[derive(Debug)]
+pub struct SessionRunArgsBuilder<'l> {
+    args: SessionRunArgs<'l>,
+    fetches: Vec<(Output, FetchToken)>,
+}
+
+impl<'l> SessionRunArgsBuilder<'l> {
+    /// Adds an input to be fed to the graph.  See `SessionRunArgs::add_feed`.
+    pub fn feed<T: TensorType>(
+        mut self,
+        operation: &Operation,
+        index: c_int,
+        tensor: &'l Tensor<T>,
+    ) -> Self {
+        self.args.add_feed(operation, index, tensor);
+        self
+    }
+
+    /// Requests that an output is fetched from the graph.  Requesting the
+    /// same output more than once only fetches it once.
+    pub fn fetch(mut self, output: &Output) -> Self {
+        if find_fetch(&self.fetches, output).is_none() {
+            let token = self.args.request_fetch(&output.operation, output.index);
+            self.fetches.push((output.clone(), token));
+        }
+        self
+    }
+
+    /// Adds a target operation to be executed when running the graph.
+    pub fn target(mut self, operation: &Operation) -> Self {
+        self.args.add_target(operation);
+        self
+    }
+
+    /// Returns the args, along with the tokens for the requested fetches.
+    pub fn build(self) -> (SessionRunArgs<'l>, FetchTokens) {
+        (
+            self.args,
+            FetchTokens {
+                fetches: self.fetches,
+            },
+        )
+    }
+}
+
+/// Maps the outputs requested with `SessionRunArgsBuilder::fetch` to their
+/// fetch tokens.
+#[derive(Debug, Clone)]
+pub struct FetchTokens {
+    fetches: Vec<(Output, FetchToken)>,
+}
+
+impl FetchTokens {
+    /// Returns the token for fetching `output`, or `None` if it was not
+    /// requested.
+    pub fn get(&self, output: &Output) -> Option<FetchToken> {
+        find_fetch(&self.fetches, output)
+    }
+}
+
+fn find_fetch(fetches: &[(Output, FetchToken)], output: &Output) -> Option<FetchToken> {
+    fetches
+        .iter()
+        .find(|(o, _)| o.operation.inner() == output.operation.inner() && o.index == output.index)
+        .map(|(_, token)| *token)
+}
+
+/// Returns true if `operation` is a reference variable, i.e. a `VariableV2`
+/// or `Variable` operation.
+fn is_ref_variable(operation: &Operation) -> Result<bool> {
+    let op_type = operation.op_type()?;
+    Ok(op_type == "VariableV2" || op_type == "Variable")
+}
+
+/// The inputs for a single run in `Session::run_batch`.
+#[derive(Debug, Default)]
+pub struct FeedSet<'l> {
+    feeds: Vec<(Operation, c_int, &'l dyn AnyTensor)>,
+}
+
+impl<'l> FeedSet<'l> {
+    /// Creates an empty FeedSet.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds an input to be fed to the graph. The index selects which output of
+    /// the operation to feed.
+    pub fn add_feed<T: TensorType>(
+        &mut self,
+        operation: &Operation,
+        index: c_int,
+        tensor: &'l Tensor<T>,
+    ) {
+        self.feeds.push((operation.clone(), index, tensor));
+    }
+}
+
+/// Runs a graph repeatedly with inputs and outputs of fixed shapes, reusing
+/// the same buffers for every run.
+///
+/// This is intended for latency-sensitive loops, such as real-time control,
+/// which run a small model at a high frequency.  The input tensors are
+/// allocated once and fed directly on every run, and each fetched tensor is
+/// copied into a preallocated output tensor and freed immediately, so `run`
+/// does not allocate on the Rust side.  TensorFlow itself still allocates
+/// the fetched tensors, since the C API does not allow fetching into existing
+/// buffers.  The `fixed_runner` example counts the allocations per run.
+///
+/// All inputs and outputs have type `T`, which must have the same
+/// representation in C and Rust (i.e. anything but `String`).
+///
+/// ```rust,ignore
+/// let mut runner = FixedRunner::new(&session, &[(x, &[1, 4])], &[(y, &[1, 2])])?;
+/// loop {
+///     runner.input_mut(0).copy_from_slice(&read_sensors());
+///     runner.run()?;
+///     actuate(&runner.output(0)[..]);
+/// }
+/// ```
+#[derive(Debug)]
+pub struct FixedRunner<'s, T: TensorType> {
+    session: &'s Session,
+    input_ports: Vec<tf::TF_Output>,
+    input_tensors: Vec<*mut tf::TF_Tensor>,
+    inputs: Vec<Tensor<T>>,
+    output_ports: Vec<tf::TF_Output>,
+    output_tensors: Vec<*mut tf::TF_Tensor>,
+    outputs: Vec<Tensor<T>>,
+}
+
+impl<'s, T: TensorType> FixedRunner<'s, T> {
+    /// Creates a runner which feeds `inputs` and fetches `outputs`, each of
+    /// which is given with its fixed dimensions.  The input tensors are
+    /// initialized to zeros.
+    pub fn new(
+        session: &'s Session,
+        inputs: &[(Output, &[u64])],
+        outputs: &[(Output, &[u64])],
+    ) -> Result<Self> {
+        if !T::is_repr_c() {
+            return Err(invalid_arg!(
+                "FixedRunner does not support {} tensors",
+                T::data_type()
+            ));
+        }
+        Ok(FixedRunner {
+            session,
+            input_ports: inputs.iter().map(|(output, _)| output.to_c()).collect(),
+            input_tensors: vec![ptr::null_mut(); inputs.len()],
+            inputs: inputs.iter().map(|(_, dims)| Tensor::new(dims)).collect(),
+            output_ports: outputs.iter().map(|(output, _)| output.to_c()).collect(),
+            output_tensors: vec![ptr::null_mut(); outputs.len()],
+            outputs: outputs.iter().map(|(_, dims)| Tensor::new(dims)).collect(),
+        })
+    }
+
+    /// Returns the tensor fed to input `index`, which can be modified before
+    /// the next run.
+    pub fn input_mut(&mut self, index: usize) -> &mut Tensor<T> {
+        &mut self.inputs[index]
+    }
+
+    /// Returns the value of output `index` fetched by the last run.
+    pub fn output(&self, index: usize) -> &Tensor<T> {
+        &self.outputs[index]
+    }
+
+    /// Runs the graph with the current inputs and copies the fetched values
+    /// into the output tensors.  Returns an error if a fetched value does not
+    /// have type `T` and the dimensions given when the runner was created.
+    pub fn run(&mut self) -> Result<()> {
+        // The input tensors may have been replaced through input_mut, so look
+        // up their pointers again.
+        for (ptr, input) in self.input_tensors.iter_mut().zip(&self.inputs) {
+            *ptr = input.inner()?;
+        }
+        let mut status = Status::new();
+        unsafe {
+            tf::TF_SessionRun(
+                self.session.inner,
+                ptr::null(),
+                self.input_ports.as_ptr(),
+                self.input_tensors.as_ptr() as *const *const tf::TF_Tensor,
+                self.input_tensors.len() as c_int,
+                self.output_ports.as_ptr(),
+                self.output_tensors.as_mut_ptr(),
+                self.output_tensors.len() as c_int,
+                ptr::null(),
+                0,
+                ptr::null_mut(),
+                status.inner(),
+            );
+        }
+        let result = status.into_result().and_then(|()| self.copy_outputs());
+        self.drop_output_tensors();
+        result
+    }
+
+    fn copy_outputs(&mut self) -> Result<()> {
+        for (i, (&tensor, output)) in self
+            .output_tensors
+            .iter()
+            .zip(self.outputs.iter_mut())
+            .enumerate()
+        {
+            unsafe {
+                let dtype = DataType::from_c(tf::TF_TensorType(tensor));
+                let num_dims = tf::TF_NumDims(tensor) as usize;
+                let dims_match = num_dims == output.dims().len()
+                    && (0..num_dims)
+                        .all(|d| tf::TF_Dim(tensor, d as c_int) as u64 == output.dims()[d]);
+                if dtype != T::data_type() || !dims_match {
+                    return Err(invalid_arg!(
+                        "Output {} has type {} and shape {:?}, expected type {} and shape {:?}",
+                        i,
+                        dtype,
+                        (0..num_dims)
+                            .map(|d| tf::TF_Dim(tensor, d as c_int))
+                            .collect::<Vec<_>>(),
+                        T::data_type(),
+                        output.dims()
+                    ));
+                }
+                let size = tf::TF_TensorByteSize(tensor);
+                assert_eq!(size, mem::size_of_val(&output[..]));
+                ptr::copy_nonoverlapping(
+                    tf::TF_TensorData(tensor) as *const u8,
+                    output.as_mut_ptr() as *mut u8,
+                    size,
+                );
+            }
+        }
+        Ok(())
+    }
+
+    fn drop_output_tensors(&mut self) {
+        for tensor in &mut self.output_tensors {
+            if !tensor.is_null() {
+                unsafe {
+                    tf::TF_DeleteTensor(*tensor);
+                }
+            }
+            *tensor = ptr::null_mut();
+        }
+    }
+}
+
+impl<'s, T: TensorType> Drop for FixedRunner<'s, T> {
+    fn drop(&mut self) {
+        self.drop_output_tensors();
+    }
+}
+
 /// Deprecated alias for SessionRunArgs.
 #[deprecated(note = "Use SessionRunArgs instead.", since = "0.10.0")]
 pub type StepWithGraph<'l> = SessionRunArgs<'l>;
 
 ////////////////////////
 
+/// Configures how `Session::run_with_retry` retries failed runs.
+///
+/// By default, runs failing with `Unavailable` or `Aborted` are retried up to
+/// 3 times, waiting 100ms before the first retry and doubling the wait for
+/// each further retry, up to 10s.
+#[derive(Debug, Clone)]
+pub struct RetryPolicy {
+    codes: Vec<Code>,
+    max_retries: usize,
+    initial_backoff: Duration,
+    max_backoff: Duration,
+    multiplier: f64,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            codes: vec![Code::Unavailable, Code::Aborted],
+            max_retries: 3,
+            initial_backoff: Duration::from_millis(100),
+            max_backoff: Duration::from_secs(10),
+            multiplier: 2.0,
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Creates the default policy.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the error codes which are retried.
+    pub fn with_codes(self, codes: &[Code]) -> Self {
+        Self {
+            codes: codes.to_vec(),
+            ..self
+        }
+    }
+
+    /// Sets the maximum number of retries after the first attempt.
+    pub fn with_max_retries(self, max_retries: usize) -> Self {
+        Self {
+            max_retries,
+            ..self
+        }
+    }
+
+    /// Sets how long to wait before the first retry.
+    pub fn with_initial_backoff(self, initial_backoff: Duration) -> Self {
+        Self {
+            initial_backoff,
+            ..self
+        }
+    }
+
+    /// Sets the longest time to wait before a retry.
+    pub fn with_max_backoff(self, max_backoff: Duration) -> Self {
+        Self {
+            max_backoff,
+            ..self
+        }
+    }
+
+    /// Sets the factor the wait is multiplied by after each retry.
+    pub fn with_multiplier(self, multiplier: f64) -> Self {
+        Self { multiplier, ..self }
+    }
+
+    /// Returns whether runs failing with `code` are retried.
+    fn retries(&self, code: Code) -> bool {
+        self.codes.contains(&code)
+    }
+
+    /// Calls `attempt` until it succeeds, fails with a code which isn't
+    /// retried or runs out of retries, and returns its last result.
+    fn retry<F: FnMut() -> Result<()>>(&self, mut attempt: F) -> Result<()> {
+        let mut retries = 0;
+        loop {
+            match attempt() {
+                Err(status) if retries < self.max_retries && self.retries(status.code()) => {
+                    thread::sleep(self.backoff(retries));
+                    retries += 1;
+                }
+                result => return result,
+            }
+        }
+    }
+
+    /// Returns how long to wait before retry number `retry`, counting from 0.
+    fn backoff(&self, retry: usize) -> Duration {
+        let exponent = retry.min(i32::MAX as usize) as i32;
+        let nanos = self.initial_backoff.as_nanos() as f64 * self.multiplier.powi(exponent);
+        if nanos.is_finite() && nanos < self.max_backoff.as_nanos() as f64 {
+            Duration::from_nanos(nanos.max(0.0).round() as u64)
+        } else {
+            self.max_backoff
+        }
+    }
+}
+
 /// Metadata about a device.
 #[derive(Debug, Eq, PartialEq, Clone, Hash)]
 pub struct Device {
@@ -575,6 +1739,354 @@ mod tests {
         create_session();
     }
 
+    #[test]
+    fn test_fetch_dynamic() {
+        let (session, x, y) = create_session();
+        let x_value = Tensor::from(3.0f32);
+        let mut args = SessionRunArgs::new();
+        args.add_feed(&x, 0, &x_value);
+        let token = args.request_fetch(&y, 0);
+        session.run(&mut args).unwrap();
+        let output = args.fetch_dynamic(token).unwrap();
+        assert_eq!(output.dtype(), DataType::Float);
+        match output {
+            DynTensor::Float(tensor) => assert_eq!(&tensor[..], &[6.0]),
+            _ => panic!("Unexpected output: {:?}", output),
+        }
+        assert_eq!(
+            args.fetch_dynamic(token).unwrap_err().code(),
+            Code::Unavailable
+        );
+
+        assert_eq!(
+            args.fetch_dynamic(FetchToken { index: 1 })
+                .unwrap_err()
+                .code(),
+            Code::OutOfRange
+        );
+        // Outputs are unavailable before the args are run.
+        let mut args = SessionRunArgs::new();
+        let token = args.request_fetch(&x, 0);
+        assert_eq!(
+            args.fetch_dynamic(token).unwrap_err().code(),
+            Code::Unavailable
+        );
+    }
+
+    #[test]
+    fn test_clear() {
+        let (session, x, y) = create_session();
+        let x1 = Tensor::from(3.0f32);
+        let x2 = Tensor::from(4.0f32);
+        let mut args = SessionRunArgs::new();
+        args.add_feed(&x, 0, &x1);
+        let token = args.request_fetch(&y, 0);
+        session.run(&mut args).unwrap();
+        args.clear();
+        assert_eq!(
+            args.fetch::<f32>(token).unwrap_err().code(),
+            Code::Unavailable
+        );
+        assert!(session.run(&mut args).is_err());
+        args.add_feed(&x, 0, &x2);
+        session.run(&mut args).unwrap();
+        assert_eq!(&args.fetch::<f32>(token).unwrap()[..], &[8.0]);
+    }
+
+    #[test]
+    fn test_run_with_retry() {
+        let policy = RetryPolicy::new()
+            .with_initial_backoff(Duration::from_millis(10))
+            .with_max_backoff(Duration::from_millis(25));
+        assert_eq!(policy.backoff(0), Duration::from_millis(10));
+        assert_eq!(policy.backoff(1), Duration::from_millis(20));
+        assert_eq!(policy.backoff(2), Duration::from_millis(25));
+        assert_eq!(policy.backoff(100), Duration::from_millis(25));
+        assert!(policy.retries(Code::Unavailable));
+        assert!(!policy.retries(Code::InvalidArgument));
+
+        let (session, x, y) = create_session();
+        let x_value = Tensor::from(3.0f32);
+        let mut args = SessionRunArgs::new();
+        args.add_feed(&x, 0, &x_value);
+        let token = args.request_fetch(&y, 0);
+        session.run_with_retry(&mut args, &policy).unwrap();
+        assert_eq!(&args.fetch::<f32>(token).unwrap()[..], &[6.0]);
+
+        // Not feeding x fails with InvalidArgument every time.
+        let mut args = SessionRunArgs::new();
+        args.request_fetch(&y, 0);
+        let status = session.run_with_retry(&mut args, &policy).unwrap_err();
+        assert_eq!(status.code(), Code::InvalidArgument);
+
+        let policy = policy
+            .with_initial_backoff(Duration::from_millis(0))
+            .with_max_retries(2);
+        let fail = |code| Err(Status::new_set_lossy(code, "failed"));
+        // Codes which aren't retried are only attempted once.
+        let mut attempts = 0;
+        let status = policy
+            .retry(|| {
+                attempts += 1;
+                fail(Code::InvalidArgument)
+            })
+            .unwrap_err();
+        assert_eq!(status.code(), Code::InvalidArgument);
+        assert_eq!(attempts, 1);
+        // Retried codes are attempted max_retries + 1 times.
+        let mut attempts = 0;
+        let status = policy
+            .retry(|| {
+                attempts += 1;
+                fail(Code::Unavailable)
+            })
+            .unwrap_err();
+        assert_eq!(status.code(), Code::Unavailable);
+        assert_eq!(attempts, 3);
+        // Retrying stops at the first success.
+        let mut attempts = 0;
+        policy
+            .retry(|| {
+                attempts += 1;
+                if attempts < 2 {
+                    fail(Code::Aborted)
+                } else {
+                    Ok(())
+                }
+            })
+            .unwrap();
+        assert_eq!(attempts, 2);
+    }
+
+    #[test]
+    fn test_fetch_into() {
+        let (session, x, y) = create_session();
+        let x_value = Tensor::from(3.0f32);
+        let mut args = SessionRunArgs::new();
+        args.add_feed(&x, 0, &x_value);
+        let token = args.request_fetch(&y, 0);
+        session.run(&mut args).unwrap();
+        let mut too_long = [0.0f32; 2];
+        assert_eq!(
+            args.fetch_into(token, &mut too_long).unwrap_err().code(),
+            Code::InvalidArgument
+        );
+        let mut wrong_type = [0i32; 1];
+        assert_eq!(
+            args.fetch_into(token, &mut wrong_type).unwrap_err().code(),
+            Code::InvalidArgument
+        );
+        let mut buf = [0.0f32; 1];
+        args.fetch_into(token, &mut buf).unwrap();
+        assert_eq!(buf, [6.0]);
+        assert_eq!(
+            args.fetch_into(token, &mut buf).unwrap_err().code(),
+            Code::Unavailable
+        );
+    }
+
+    #[test]
+    fn test_all_finite() {
+        let mut g = Graph::new();
+        let x = {
+            let mut nd = g.new_operation("Placeholder", "x").unwrap();
+            nd.set_attr_type("dtype", DataType::Float).unwrap();
+            nd.finish().unwrap()
+        };
+        let session = Session::new(&SessionOptions::new(), &g).unwrap();
+        let x_output = Output {
+            operation: x.clone(),
+            index: 0,
+        };
+        for (values, expected) in &[
+            (vec![1.0f32, 2.0], true),
+            (vec![1.0, f32::NAN], false),
+            (vec![f32::INFINITY], false),
+        ] {
+            let x_value = Tensor::new(&[values.len() as u64])
+                .with_values(values)
+                .unwrap();
+            let mut args = SessionRunArgs::new();
+            args.add_feed(&x, 0, &x_value);
+            let token = args.request_all_finite(&mut g, &x_output).unwrap();
+            session.run(&mut args).unwrap();
+            assert_eq!(args.fetch_all_finite(token).unwrap(), *expected);
+        }
+        // The check is only added to the graph once.
+        assert_eq!(g.operation_iter().count(), 6);
+    }
+
+    #[test]
+    fn test_run_batch() {
+        let (session, x_operation, y_operation) = create_session();
+        let inputs: Vec<_> = (0..3)
+            .map(|i| {
+                Tensor::<f32>::new(&[2])
+                    .with_values(&[i as f32, 1.0])
+                    .unwrap()
+            })
+            .collect();
+        let feed_sets: Vec<_> = inputs
+            .iter()
+            .map(|input| {
+                let mut feed_set = FeedSet::new();
+                feed_set.add_feed(&x_operation, 0, input);
+                feed_set
+            })
+            .collect();
+        let fetches = [Output {
+            operation: y_operation,
+            index: 0,
+        }];
+        let mut reported = vec![];
+        let results = session
+            .run_batch_with_progress::<f32, _>(&feed_sets, &fetches, |done, total| {
+                reported.push((done, total))
+            })
+            .unwrap();
+        assert_eq!(reported, [(1, 3), (2, 3), (3, 3)]);
+        assert_eq!(results.len(), 3);
+        for (i, result) in results.iter().enumerate() {
+            assert_eq!(result.len(), 1);
+            assert_eq!(&result[0][..], &[2.0 * i as f32, 2.0]);
+        }
+        assert!(session.run_batch::<i32>(&feed_sets, &fetches).is_err());
+    }
+
+    #[test]
+    fn test_run_with_fetch_callback() {
+        let (session, x_operation, y_operation) = create_session();
+        let x = Tensor::<f32>::new(&[2]).with_values(&[2.0, 3.0]).unwrap();
+        let mut feeds = FeedSet::new();
+        feeds.add_feed(&x_operation, 0, &x);
+        let output = Output {
+            operation: y_operation,
+            index: 0,
+        };
+        let sum = session
+            .run_with_fetch_callback(&feeds, &output, |y: Tensor<f32>| y.iter().sum::<f32>())
+            .unwrap();
+        assert_eq!(sum, 10.0);
+        assert!(session
+            .run_with_fetch_callback(&feeds, &output, |_: Tensor<i32>| ())
+            .is_err());
+    }
+
+    #[test]
+    fn test_session_run_args_builder() {
+        let (session, x_operation, y_operation) = create_session();
+        let x = Tensor::<f32>::new(&[2]).with_values(&[2.0, 3.0]).unwrap();
+        let y = Output {
+            operation: y_operation,
+            index: 0,
+        };
+        let unfetched = Output {
+            operation: x_operation.clone(),
+            index: 0,
+        };
+        let (mut args, fetches) = SessionRunArgs::builder()
+            .feed(&x_operation, 0, &x)
+            .fetch(&y)
+            .fetch(&y)
+            .build();
+        assert_eq!(args.output_tensors.len(), 1);
+        assert!(fetches.get(&unfetched).is_none());
+        session.run(&mut args).unwrap();
+        let result: Tensor<f32> = args.fetch(fetches.get(&y).unwrap()).unwrap();
+        assert_eq!(&result[..], &[4.0, 6.0]);
+    }
+
+    #[test]
+    fn test_session_closer() {
+        let (session, x_operation, y_operation) = create_session();
+        let x = Tensor::<f32>::new(&[2]).with_values(&[2.0, 3.0]).unwrap();
+        let run = || {
+            let mut args = SessionRunArgs::new();
+            args.add_feed(&x_operation, 0, &x);
+            args.request_fetch(&y_operation, 0);
+            session.run(&mut args)
+        };
+        run().unwrap();
+        session.closer().close().unwrap();
+        assert!(run().is_err());
+    }
+
+    #[test]
+    fn test_set_timeout() {
+        let mut args = SessionRunArgs::new();
+        args.set_timeout(Duration::from_millis(1000)).unwrap();
+        assert_eq!(args.get_run_options(), Some(&[0x10, 0xe8, 0x07][..]));
+        // A trace_level of FULL_TRACE is kept.
+        let mut args = SessionRunArgs::new();
+        args.set_run_options(&[0x08, 0x03]);
+        args.set_timeout(Duration::from_millis(5)).unwrap();
+        assert_eq!(args.get_run_options(), Some(&[0x08, 0x03, 0x10, 0x05][..]));
+        // An existing timeout is replaced rather than duplicated.
+        let mut args = SessionRunArgs::new();
+        args.set_run_options(&[0x10, 0x05, 0x08, 0x03]);
+        args.set_timeout(Duration::from_millis(1000)).unwrap();
+        args.set_timeout(Duration::from_millis(1000)).unwrap();
+        assert_eq!(
+            args.get_run_options(),
+            Some(&[0x08, 0x03, 0x10, 0xe8, 0x07][..])
+        );
+        let mut args = SessionRunArgs::new();
+        args.set_run_options(&[0x0a, 0xff]);
+        assert_eq!(
+            args.set_timeout(Duration::from_millis(5))
+                .unwrap_err()
+                .code(),
+            Code::InvalidArgument
+        );
+    }
+
+    #[test]
+    fn test_fixed_runner() {
+        let (session, x_operation, y_operation) = create_session();
+        let x = Output {
+            operation: x_operation,
+            index: 0,
+        };
+        let y = Output {
+            operation: y_operation,
+            index: 0,
+        };
+        let mut runner =
+            FixedRunner::<f32>::new(&session, &[(x.clone(), &[2])], &[(y.clone(), &[2])]).unwrap();
+        runner.input_mut(0).copy_from_slice(&[2.0, 3.0]);
+        runner.run().unwrap();
+        assert_eq!(&runner.output(0)[..], &[4.0, 6.0]);
+        runner.input_mut(0)[1] = 5.0;
+        runner.run().unwrap();
+        assert_eq!(&runner.output(0)[..], &[4.0, 10.0]);
+
+        let mut wrong_shape =
+            FixedRunner::<f32>::new(&session, &[(x.clone(), &[2])], &[(y.clone(), &[3])]).unwrap();
+        assert_eq!(wrong_shape.run().unwrap_err().code(), Code::InvalidArgument);
+        assert!(FixedRunner::<String>::new(&session, &[], &[(y, &[2])]).is_err());
+    }
+
+    #[test]
+    fn test_run_unfed_placeholder() {
+        let (session, _, y_operation) = create_session();
+        let mut step = SessionRunArgs::new();
+        step.request_fetch(&y_operation, 0);
+        let status = session.run(&mut step).unwrap_err();
+        assert_eq!(status.code(), Code::InvalidArgument);
+        assert!(format!("{}", status).contains("must be fed: x"));
+
+        // Feeding y directly means x isn't needed.
+        let y = <Tensor<f32>>::new(&[2]);
+        let mut step = SessionRunArgs::new();
+        step.add_feed(&y_operation, 0, &y);
+        step.request_fetch(&y_operation, 0);
+        session.run(&mut step).unwrap();
+
+        let mut step = SessionRunArgs::new();
+        step.add_target(&y_operation);
+        assert!(session.run(&mut step).is_err());
+    }
+
     #[test]
     fn test_close() {
         let (mut session, _, _) = create_session();
@@ -618,6 +2130,9 @@ mod tests {
         assert_eq!(output_tensor[0], 4.0);
         assert_eq!(output_tensor[1], 6.0);
 
+        let run_stats = step.get_run_stats().unwrap().unwrap();
+        assert!(run_stats.num_ops > 0);
+
         // ensure multiple calls with the same SessionRunArgs work
         session.run(&mut step).unwrap();
         step.get_metadata().unwrap();
@@ -627,6 +2142,34 @@ mod tests {
         assert_eq!(output_tensor[1], 6.0);
     }
 
+    #[test]
+    #[serial] // Full trace enable profile session
+    fn test_memory_usage() {
+        let (session, x_operation, y_operation) = create_session();
+        let x = Tensor::<f32>::from(&[2.0, 3.0][..]);
+        let mut step = SessionRunArgs::new();
+        step.add_feed(&x_operation, 0, &x);
+        // hard coded RunOptions proto with full tracelevel
+        step.set_run_options(&[8u8, 3u8]);
+        step.set_request_metadata(true);
+        step.request_fetch(&y_operation, 0);
+        session.run(&mut step).unwrap();
+        let memory = session.memory_usage(step.get_metadata().unwrap()).unwrap();
+        let devices = session.device_list().unwrap();
+        assert!(memory.devices.len() >= devices.len());
+        for device in devices {
+            assert!(memory
+                .devices
+                .iter()
+                .any(|d| d.device == device.name && d.memory_bytes == Some(device.memory_bytes)));
+        }
+        assert!(memory
+            .devices
+            .windows(2)
+            .all(|w| w[0].device <= w[1].device));
+        assert!(session.memory_usage(&[0x0a, 0xff]).is_err());
+    }
+
     #[test]
     #[serial] // Full_trace enable profile session
     fn test_run_options() {
@@ -681,6 +2224,7 @@ mod tests {
             meta_graph_def,
             #[cfg(feature = "experimental_training")]
                 meta_graph: _,
+            input_transforms: _,
         } = bundle;
 
         assert!(!meta_graph_def.is_empty());
@@ -698,6 +2242,240 @@ mod tests {
         assert_eq!(output_tensor.len(), 1);
     }
 
+    #[test]
+    fn test_savedmodelbundle_input_transform() {
+        let mut graph = Graph::new();
+        let bundle = SavedModelBundle::load(
+            &SessionOptions::new(),
+            ["train", "serve"],
+            &mut graph,
+            "test_resources/regression-model",
+        )
+        .unwrap()
+        .with_input_transform("x", |tensor: &mut Tensor<f32>| {
+            for value in tensor.iter_mut() {
+                *value = (*value - 1.0) / 2.0;
+            }
+        });
+        let x_op = graph.operation_by_name_required("x").unwrap();
+        let y_hat_op = graph.operation_by_name_required("y_hat").unwrap();
+        let run = |x_value: f32, transformed: bool| {
+            let x = Tensor::new(&[1]).with_values(&[x_value]).unwrap();
+            let mut step = SessionRunArgs::new();
+            step.add_feed(&x_op, 0, &x);
+            let token = step.request_fetch(&y_hat_op, 0);
+            if transformed {
+                bundle.run(&mut step).unwrap();
+            } else {
+                bundle.session.run(&mut step).unwrap();
+            }
+            assert_eq!(&x[..], &[x_value]);
+            step.fetch::<f32>(token).unwrap()[0]
+        };
+        assert_eq!(run(5.0, true), run(2.0, false));
+
+        let x = Tensor::new(&[1]).with_values(&[5i32]).unwrap();
+        let mut step = SessionRunArgs::new();
+        step.add_feed(&x_op, 0, &x);
+        step.request_fetch(&y_hat_op, 0);
+        let error = bundle.run(&mut step).unwrap_err();
+        assert_eq!(error.code(), Code::InvalidArgument);
+    }
+
+    #[test]
+    fn test_from_graph_and_checkpoint() {
+        fn string_constant(g: &mut Graph, name: &str, values: &[&str]) -> Operation {
+            let values: Vec<String> = values.iter().map(|v| v.to_string()).collect();
+            let mut nd = g.new_operation("Const", name).unwrap();
+            nd.set_attr_type("dtype", DataType::String).unwrap();
+            nd.set_attr_tensor(
+                "value",
+                Tensor::new(&[values.len() as u64])
+                    .with_values(&values)
+                    .unwrap(),
+            )
+            .unwrap();
+            nd.finish().unwrap()
+        }
+        fn variable(g: &mut Graph, name: &str) -> Operation {
+            let mut nd = g.new_operation("VariableV2", name).unwrap();
+            nd.set_attr_type("dtype", DataType::Float).unwrap();
+            nd.set_attr_shape("shape", &Shape::from(Some(vec![Some(2)])))
+                .unwrap();
+            nd.finish().unwrap()
+        }
+        let prefix =
+            std::env::temp_dir().join(format!("session_checkpoint_{}", std::process::id()));
+        let prefix_str = prefix.to_str().unwrap();
+
+        // Writes a checkpoint with "w" = [1, 2].
+        let options = SessionOptions::new();
+        let mut g = Graph::new();
+        let save = {
+            let value = {
+                let mut nd = g.new_operation("Const", "w").unwrap();
+                nd.set_attr_type("dtype", DataType::Float).unwrap();
+                nd.set_attr_tensor(
+                    "value",
+                    Tensor::new(&[2]).with_values(&[1.0f32, 2.0]).unwrap(),
+                )
+                .unwrap();
+                nd.finish().unwrap()
+            };
+            let prefix = {
+                let mut nd = g.new_operation("Const", "prefix").unwrap();
+                nd.set_attr_type("dtype", DataType::String).unwrap();
+                nd.set_attr_tensor("value", Tensor::from(prefix_str.to_string()))
+                    .unwrap();
+                nd.finish().unwrap()
+            };
+            let names = string_constant(&mut g, "names", &["w"]);
+            let slices = string_constant(&mut g, "slices", &[""]);
+            let mut nd = g.new_operation("SaveV2", "save").unwrap();
+            nd.add_input(prefix);
+            nd.add_input(names);
+            nd.add_input(slices);
+            nd.add_input_list(&[value.into()]);
+            nd.set_attr_type_list("dtypes", &[DataType::Float]).unwrap();
+            nd.finish().unwrap()
+        };
+        let session = Session::new(&options, &g).unwrap();
+        let mut args = SessionRunArgs::new();
+        args.add_target(&save);
+        session.run(&mut args).unwrap();
+
+        let mut g = Graph::new();
+        let w = variable(&mut g, "w");
+        let add = {
+            let mut nd = g.new_operation("Add", "add").unwrap();
+            nd.add_input(w.clone());
+            nd.add_input(w);
+            nd.finish().unwrap()
+        };
+        let session =
+            Session::from_graph_and_checkpoint(&options, &mut g, &prefix, &["w"]).unwrap();
+        let mut args = SessionRunArgs::new();
+        let fetch = args.request_fetch(&add, 0);
+        session.run(&mut args).unwrap();
+        assert_eq!(&args.fetch::<f32>(fetch).unwrap()[..], &[2.0, 4.0]);
+        assert!(g
+            .operation_by_name("restore_checkpoint/restore")
+            .unwrap()
+            .is_some());
+
+        // Restoring again into the same graph uses a new name scope.
+        Session::from_graph_and_checkpoint(&options, &mut g, &prefix, &["w"]).unwrap();
+        assert!(g
+            .operation_by_name("restore_checkpoint_1/restore")
+            .unwrap()
+            .is_some());
+        assert_eq!(
+            Session::from_graph_and_checkpoint(&options, &mut g, &prefix, &["add"])
+                .unwrap_err()
+                .code(),
+            Code::InvalidArgument
+        );
+        for entry in std::fs::read_dir(std::env::temp_dir()).unwrap() {
+            let path = entry.unwrap().path();
+            if path.to_str().unwrap().starts_with(prefix_str) {
+                std::fs::remove_file(path).unwrap();
+            }
+        }
+    }
+
+    #[test]
+    fn test_reload() {
+        // Adds a variable with shape [values.len()] initialized to `values`.
+        fn variable(g: &mut Graph, name: &str, values: &[f32]) -> (Operation, Operation) {
+            let value = {
+                let mut nd = g
+                    .new_operation("Const", &format!("{}/initial_value", name))
+                    .unwrap();
+                nd.set_attr_type("dtype", DataType::Float).unwrap();
+                let tensor = Tensor::new(&[values.len() as u64])
+                    .with_values(values)
+                    .unwrap();
+                nd.set_attr_tensor("value", tensor).unwrap();
+                nd.finish().unwrap()
+            };
+            let variable = {
+                let mut nd = g.new_operation("VariableV2", name).unwrap();
+                nd.set_attr_type("dtype", DataType::Float).unwrap();
+                nd.set_attr_shape("shape", &Shape::from(Some(vec![Some(values.len() as i64)])))
+                    .unwrap();
+                nd.finish().unwrap()
+            };
+            let assign = {
+                let mut nd = g
+                    .new_operation("Assign", &format!("{}/Assign", name))
+                    .unwrap();
+                nd.add_input(variable.clone());
+                nd.add_input(value);
+                nd.finish().unwrap()
+            };
+            (variable, assign)
+        }
+        let options = SessionOptions::new();
+        let mut old_graph = Graph::new();
+        let (w, w_init) = variable(&mut old_graph, "w", &[1.0, 2.0]);
+        let (_, v_init) = variable(&mut old_graph, "v", &[3.0, 4.0]);
+        let session = Session::new(&options, &old_graph).unwrap();
+        let mut args = SessionRunArgs::new();
+        args.add_target(&w_init);
+        args.add_target(&v_init);
+        session.run(&mut args).unwrap();
+        let mut args = SessionRunArgs::new();
+        let update_value = {
+            let mut nd = old_graph.new_operation("Const", "w/update_value").unwrap();
+            nd.set_attr_type("dtype", DataType::Float).unwrap();
+            nd.set_attr_tensor(
+                "value",
+                Tensor::new(&[2]).with_values(&[5.0f32, 6.0]).unwrap(),
+            )
+            .unwrap();
+            nd.finish().unwrap()
+        };
+        let update = {
+            let mut nd = old_graph.new_operation("Assign", "w/update").unwrap();
+            nd.add_input(w);
+            nd.add_input(update_value);
+            nd.finish().unwrap()
+        };
+        args.add_target(&update);
+        session.run(&mut args).unwrap();
+        let values = session.variable_values(&old_graph).unwrap();
+        assert_eq!(values.len(), 2);
+        assert_eq!(values[0].0, "w");
+        assert_eq!(
+            &values[0].1.clone().downcast::<f32>().unwrap()[..],
+            &[5.0, 6.0]
+        );
+
+        // "w" is kept, "v" changes shape and "b" is new.
+        let mut new_graph = Graph::new();
+        let (w, w_init) = variable(&mut new_graph, "w", &[0.0, 0.0]);
+        let (v, v_init) = variable(&mut new_graph, "v", &[7.0, 8.0, 9.0]);
+        let (b, b_init) = variable(&mut new_graph, "b", &[10.0]);
+        let new_session = session
+            .reload(
+                &old_graph,
+                &options,
+                &mut new_graph,
+                &[w_init, v_init, b_init],
+            )
+            .unwrap();
+        assert!(new_graph.operation_by_name("w/reload").unwrap().is_some());
+        assert!(new_graph.operation_by_name("v/reload").unwrap().is_none());
+        let mut args = SessionRunArgs::new();
+        let w_token = args.request_fetch(&w, 0);
+        let v_token = args.request_fetch(&v, 0);
+        let b_token = args.request_fetch(&b, 0);
+        new_session.run(&mut args).unwrap();
+        assert_eq!(&args.fetch::<f32>(w_token).unwrap()[..], &[5.0, 6.0]);
+        assert_eq!(&args.fetch::<f32>(v_token).unwrap()[..], &[7.0, 8.0, 9.0]);
+        assert_eq!(&args.fetch::<f32>(b_token).unwrap()[..], &[10.0]);
+    }
+
     #[test]
     fn test_device_list() {
         let (session, _, _) = create_session();
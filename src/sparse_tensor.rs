@@ -0,0 +1,175 @@
+use crate::Result;
+use crate::Tensor;
+use crate::TensorType;
+
+/// A sparse tensor, stored as the indices and values of its non-default
+/// elements along with its dense shape, like TensorFlow's `SparseTensor`.
+///
+/// `indices` has shape `[num_values, rank]`, where row `i` is the index of
+/// `values[i]` in the dense tensor, and `dense_shape` has `rank` entries.
+/// The indices must be in row-major order without repeats, which is the
+/// order TensorFlow's sparse ops expect.
+///
+/// With the `experimental_training` feature, `ops::SparseInput` creates the
+/// three placeholders for a sparse input and feeds them from a
+/// `SparseTensor`.
+///
+/// ```
+/// # use tensorflow::SparseTensor;
+/// # use tensorflow::Tensor;
+/// let indices = Tensor::new(&[2, 2]).with_values(&[0i64, 1, 2, 0])?;
+/// let values = Tensor::new(&[2]).with_values(&[5.0f32, 7.0])?;
+/// let sparse = SparseTensor::new(indices, values, vec![3, 2])?;
+/// assert_eq!(&sparse.to_dense(0.0)[..], &[0.0, 5.0, 0.0, 0.0, 7.0, 0.0]);
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct SparseTensor<T: TensorType> {
+    indices: Tensor<i64>,
+    values: Tensor<T>,
+    dense_shape: Tensor<i64>,
+}
+
+impl<T: TensorType> SparseTensor<T> {
+    /// Creates a sparse tensor from its components.  Returns an error if the
+    /// shapes of the components don't match, or if an index is out of bounds
+    /// or out of order.
+    pub fn new(indices: Tensor<i64>, values: Tensor<T>, dense_shape: Vec<i64>) -> Result<Self> {
+        let rank = dense_shape.len();
+        if let Some(size) = dense_shape.iter().find(|size| **size < 0) {
+            return Err(invalid_arg!(
+                "SparseTensor dense shape {:?} has negative size {}",
+                dense_shape,
+                size
+            ));
+        }
+        let num_values = values.len();
+        if indices.dims() != [num_values as u64, rank as u64] || values.dims().len() != 1 {
+            return Err(invalid_arg!(
+                "SparseTensor indices of shape {:?} and values of shape {:?} don't match \
+                 {} values of a rank {} tensor",
+                indices.dims(),
+                values.dims(),
+                num_values,
+                rank
+            ));
+        }
+        let mut previous: Option<&[i64]> = None;
+        for i in 0..num_values {
+            let index = &indices[i * rank..(i + 1) * rank];
+            if index
+                .iter()
+                .zip(&dense_shape)
+                .any(|(i, size)| *i < 0 || i >= size)
+            {
+                return Err(invalid_arg!(
+                    "SparseTensor index {:?} is out of bounds for dense shape {:?}",
+                    index,
+                    dense_shape
+                ));
+            }
+            if let Some(previous) = previous {
+                if previous >= index {
+                    return Err(invalid_arg!(
+                        "SparseTensor index {:?} is out of order or repeated",
+                        index
+                    ));
+                }
+            }
+            previous = Some(index);
+        }
+        let dense_shape = Tensor::new(&[rank as u64]).with_values(&dense_shape)?;
+        Ok(SparseTensor {
+            indices,
+            values,
+            dense_shape,
+        })
+    }
+
+    /// Returns the indices of the values, with shape `[num_values, rank]`.
+    pub fn indices(&self) -> &Tensor<i64> {
+        &self.indices
+    }
+
+    /// Returns the values, with shape `[num_values]`.
+    pub fn values(&self) -> &Tensor<T> {
+        &self.values
+    }
+
+    /// Returns the shape of the dense tensor as a rank 1 tensor, which is how
+    /// it is fed to a graph.
+    pub fn dense_shape(&self) -> &Tensor<i64> {
+        &self.dense_shape
+    }
+
+    /// Returns the dense tensor, with `default` for every element which has
+    /// no value.
+    pub fn to_dense(&self, default: T) -> Tensor<T> {
+        let dims: Vec<u64> = self.dense_shape.iter().map(|size| *size as u64).collect();
+        let rank = dims.len();
+        let mut dense = Tensor::<T>::new(&dims);
+        for value in dense.iter_mut() {
+            value.clone_from(&default);
+        }
+        for (i, value) in self.values.iter().enumerate() {
+            let index = &self.indices[i * rank..(i + 1) * rank];
+            let offset = index
+                .iter()
+                .zip(&dims)
+                .fold(0, |offset, (i, size)| offset * *size as usize + *i as usize);
+            dense[offset].clone_from(value);
+        }
+        dense
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Code;
+
+    #[test]
+    fn sparse_tensor() {
+        let indices = Tensor::new(&[3, 2])
+            .with_values(&[0i64, 1, 1, 0, 1, 2])
+            .unwrap();
+        let values = Tensor::new(&[3]).with_values(&[1i32, 2, 3]).unwrap();
+        let sparse = SparseTensor::new(indices.clone(), values.clone(), vec![2, 3]).unwrap();
+        assert_eq!(sparse.indices(), &indices);
+        assert_eq!(sparse.values(), &values);
+        assert_eq!(&sparse.dense_shape()[..], &[2, 3]);
+        let dense = sparse.to_dense(-1);
+        assert_eq!(dense.dims(), &[2, 3]);
+        assert_eq!(&dense[..], &[-1, 1, -1, 2, -1, 3]);
+
+        let empty =
+            SparseTensor::new(Tensor::new(&[0, 1]), Tensor::<f32>::new(&[0]), vec![2]).unwrap();
+        assert_eq!(&empty.to_dense(0.5)[..], &[0.5, 0.5]);
+
+        let check_error = |indices: &[i64], dense_shape: Vec<i64>| {
+            let num_values = indices.len() / 2;
+            let indices = Tensor::new(&[num_values as u64, 2])
+                .with_values(indices)
+                .unwrap();
+            let values = Tensor::new(&[num_values as u64])
+                .with_values(&vec![0i32; num_values])
+                .unwrap();
+            assert_eq!(
+                SparseTensor::new(indices, values, dense_shape)
+                    .unwrap_err()
+                    .code(),
+                Code::InvalidArgument
+            );
+        };
+        // Out of bounds.
+        check_error(&[0, 3], vec![2, 3]);
+        check_error(&[-1, 0], vec![2, 3]);
+        // Out of order and repeated.
+        check_error(&[1, 0, 0, 1], vec![2, 3]);
+        check_error(&[1, 0, 1, 0], vec![2, 3]);
+        // Wrong rank.
+        check_error(&[1, 0], vec![2, 3, 4]);
+        // Negative size.
+        check_error(&[], vec![-1, 3]);
+    }
+}
@@ -0,0 +1,154 @@
+use crate::tf;
+use crate::Code;
+use crate::DataType;
+use crate::Result;
+use crate::Status;
+use crate::Tensor;
+use crate::TensorDataCRepr;
+use crate::TensorType;
+use libc::c_int;
+use std::fs::File;
+use std::mem;
+use std::os::raw::c_void as std_c_void;
+use std::os::unix::io::AsRawFd;
+use std::path::Path;
+use std::ptr;
+
+unsafe extern "C" fn unmap(data: *mut std_c_void, len: usize, _arg: *mut std_c_void) {
+    libc::munmap(data, len);
+}
+
+impl<T: TensorType<InnerType = TensorDataCRepr<T>>> Tensor<T> {
+    /// Creates a tensor with the given dimensions whose data is the contents
+    /// of the file at `path`, in native byte order, without reading the file
+    /// into memory up front.  Pages are loaded lazily as they are accessed,
+    /// which helps with large constants such as embeddings.
+    ///
+    /// The file is mapped privately, so writes to the tensor are not written
+    /// back to the file.  The file must not be truncated while the tensor (or
+    /// any TensorFlow tensor sharing its data) is alive.  Returns an error if
+    /// the size of the file does not match `dims`, or if `T` is `bool` and
+    /// the file contains bytes other than 0 and 1, which reads every page.
+    ///
+    /// This requires the `tensor_mmap` feature and is only available on Unix.
+    pub fn mmap<P: AsRef<Path>>(path: P, dims: &[u64]) -> Result<Tensor<T>> {
+        let path = path.as_ref();
+        let io_error = |e: std::io::Error| {
+            Status::new_set_lossy(
+                Code::Unavailable,
+                &format!("Unable to map {}: {}", path.display(), e),
+            )
+        };
+        let file = File::open(path).map_err(io_error)?;
+        let file_len = file.metadata().map_err(io_error)?.len();
+        let len = dims
+            .iter()
+            .try_fold(mem::size_of::<T>() as u64, |len, d| len.checked_mul(*d))
+            .ok_or_else(|| invalid_arg!("Tensor of shape {:?} is too large", dims))?;
+        if file_len != len {
+            return Err(invalid_arg!(
+                "File {} has {} bytes, but a tensor of shape {:?} needs {}",
+                path.display(),
+                file_len,
+                dims,
+                len
+            ));
+        }
+        if len == 0 {
+            return Ok(Tensor::new(dims));
+        }
+        let c_dims: Vec<i64> = dims.iter().map(|d| *d as i64).collect();
+        unsafe {
+            let data = libc::mmap(
+                ptr::null_mut(),
+                len as usize,
+                libc::PROT_READ | libc::PROT_WRITE,
+                libc::MAP_PRIVATE,
+                file.as_raw_fd(),
+                0,
+            );
+            if data == libc::MAP_FAILED {
+                return Err(io_error(std::io::Error::last_os_error()));
+            }
+            // Any byte other than 0 or 1 is not a valid bool.
+            if T::data_type() == DataType::Bool
+                && std::slice::from_raw_parts(data as *const u8, len as usize)
+                    .iter()
+                    .any(|&b| b > 1)
+            {
+                libc::munmap(data, len as usize);
+                return Err(invalid_arg!(
+                    "File {} is not a valid bool tensor; bytes must be 0 or 1",
+                    path.display()
+                ));
+            }
+            let inner = tf::TF_NewTensor(
+                T::data_type().to_c(),
+                c_dims.as_ptr(),
+                c_dims.len() as c_int,
+                data as *mut std_c_void,
+                len as usize,
+                Some(unmap),
+                ptr::null_mut(),
+            );
+            if inner.is_null() {
+                libc::munmap(data, len as usize);
+                return Err(Status::new_set_lossy(
+                    Code::Internal,
+                    "TF_NewTensor returned null",
+                ));
+            }
+            Ok(Tensor::from_tf_tensor(inner).unwrap())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn mmap() {
+        let values = [1.0f32, 2.0, 3.0, 4.0, 5.0, 6.0];
+        let bytes: Vec<u8> = values
+            .iter()
+            .flat_map(|v| v.to_ne_bytes().to_vec())
+            .collect();
+        let path = std::env::temp_dir().join(format!("tensor_mmap_{}.bin", std::process::id()));
+        fs::write(&path, &bytes).unwrap();
+        let mut tensor = Tensor::<f32>::mmap(&path, &[2, 3]).unwrap();
+        assert_eq!(tensor.dims(), &[2, 3]);
+        assert_eq!(&tensor[..], &values);
+        tensor[0] = 10.0;
+        assert_eq!(fs::read(&path).unwrap(), bytes);
+        assert_eq!(
+            Tensor::<f32>::mmap(&path, &[2, 2]).unwrap_err().code(),
+            Code::InvalidArgument
+        );
+        assert_eq!(
+            Tensor::<f32>::mmap(&path, &[u64::MAX, 2])
+                .unwrap_err()
+                .code(),
+            Code::InvalidArgument
+        );
+        drop(tensor);
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn mmap_bool() {
+        let path =
+            std::env::temp_dir().join(format!("tensor_mmap_bool_{}.bin", std::process::id()));
+        fs::write(&path, [0u8, 1, 1]).unwrap();
+        let tensor = Tensor::<bool>::mmap(&path, &[3]).unwrap();
+        assert_eq!(&tensor[..], &[false, true, true]);
+        fs::write(&path, [0u8, 2, 1]).unwrap();
+        assert_eq!(
+            Tensor::<bool>::mmap(&path, &[3]).unwrap_err().code(),
+            Code::InvalidArgument
+        );
+        drop(tensor);
+        fs::remove_file(&path).unwrap();
+    }
+}
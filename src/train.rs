@@ -3,11 +3,16 @@
 //! This module currently requires the `experimental_training` feature.
 
 use crate::ops;
+use crate::Code;
 use crate::DataType;
+use crate::DynTensor;
 use crate::Operation;
 use crate::Output;
 use crate::Result;
 use crate::Scope;
+use crate::Session;
+use crate::SessionRunArgs;
+use crate::Status;
 use crate::Tensor;
 use crate::TensorType;
 use crate::Variable;
@@ -271,17 +276,593 @@ impl Optimizer for AdadeltaOptimizer {
     }
 }
 
+/// Optimizer wrapper for mixed precision training.
+///
+/// Variables are stored at full precision, and the model reads them through
+/// `cast_variable`, which casts them to a lower precision compute type (half
+/// precision by default) so that the expensive operations such as matrix
+/// multiplications and convolutions run at that precision.  Gradients flow
+/// back through the casts, so the wrapped optimizer updates the full precision
+/// variables.
+///
+/// To keep small gradients from underflowing to zero at the lower precision,
+/// the loss is multiplied by the loss scale before computing gradients, and the
+/// gradients are divided by it afterwards.  For a loss scale which adapts
+/// during training, use a loss scale of 1 and minimize with
+/// `LossScaler::new_dynamic` instead.
+#[derive(Debug)]
+pub struct MixedPrecisionOptimizer<O> {
+    optimizer: O,
+    compute_type: DataType,
+    loss_scale: f32,
+}
+
+impl<O: Optimizer> MixedPrecisionOptimizer<O> {
+    /// Wraps `optimizer` with a compute type of `DataType::Half` and the given
+    /// loss scale.
+    pub fn new(optimizer: O, loss_scale: f32) -> Self {
+        Self {
+            optimizer,
+            compute_type: DataType::Half,
+            loss_scale,
+        }
+    }
+
+    /// Sets the type that `cast_variable` casts variables to.  Default is
+    /// `DataType::Half`.
+    pub fn set_compute_type(&mut self, compute_type: DataType) {
+        self.compute_type = compute_type;
+    }
+
+    /// Returns the compute type.
+    pub fn compute_type(&self) -> DataType {
+        self.compute_type
+    }
+
+    /// Returns the loss scale.
+    pub fn loss_scale(&self) -> f32 {
+        self.loss_scale
+    }
+
+    /// Returns the value of `variable` cast to the compute type.  Use this
+    /// instead of the variable's output when building the model.
+    pub fn cast_variable(&self, variable: &Variable, scope: &mut Scope) -> Result<Output> {
+        cast_to(variable.output.clone(), self.compute_type, scope)
+    }
+}
+
+fn cast_to(value: Output, dtype: DataType, scope: &mut Scope) -> Result<Output> {
+    if value.operation.output_type(value.index as usize) == dtype {
+        return Ok(value);
+    }
+    Ok(ops::Cast::new().DstT(dtype).build(value, scope)?.into())
+}
+
+impl<O: Optimizer> Optimizer for MixedPrecisionOptimizer<O> {
+    fn compute_gradients(
+        &self,
+        scope: &mut Scope,
+        loss: Output,
+        opts: ComputeGradientsOptions,
+    ) -> Result<Vec<(Option<Output>, Variable)>> {
+        let loss_scale: Output = ops::constant(self.loss_scale, scope)?.into();
+        let loss_type = loss.operation.output_type(loss.index as usize);
+        let scaled_loss = ops::mul(loss, cast_to(loss_scale.clone(), loss_type, scope)?, scope)?;
+        let grads_and_vars = self
+            .optimizer
+            .compute_gradients(scope, scaled_loss.into(), opts)?;
+        let mut output = Vec::with_capacity(grads_and_vars.len());
+        for (grad, var) in grads_and_vars {
+            let grad = match grad {
+                Some(grad) => {
+                    let scale = cast_to(loss_scale.clone(), var.dtype, scope)?;
+                    Some(ops::div(grad, scale, scope)?.into())
+                }
+                None => None,
+            };
+            output.push((grad, var));
+        }
+        Ok(output)
+    }
+
+    fn apply_gradients(
+        &self,
+        scope: &mut Scope,
+        opts: ApplyGradientsOptions,
+    ) -> Result<(Vec<Variable>, Operation)> {
+        self.optimizer.apply_gradients(scope, opts)
+    }
+}
+
+/// Scales the loss before computing gradients and unscales the gradients
+/// before applying them, which keeps small gradients from underflowing when
+/// training at reduced precision.
+///
+/// A static loss scaler always uses the same scale.  A dynamic loss scaler
+/// keeps the scale in a variable.  If any gradient is infinite or NaN, the
+/// gradients for that step are replaced with zeros and the scale is divided by
+/// the growth factor (but not below 1).  After `growth_interval` consecutive
+/// steps with finite gradients, the scale is multiplied by the growth factor.
+#[derive(Debug, Clone, Copy)]
+pub struct LossScaler {
+    initial_scale: f32,
+    dynamic: bool,
+    growth_interval: i64,
+    growth_factor: f32,
+}
+
+impl LossScaler {
+    /// Creates a loss scaler which always uses `scale`.
+    pub fn new_static(scale: f32) -> Self {
+        Self {
+            initial_scale: scale,
+            dynamic: false,
+            growth_interval: 2000,
+            growth_factor: 2.0,
+        }
+    }
+
+    /// Creates a dynamic loss scaler with default parameters
+    /// (initial_scale=32768, growth_interval=2000, growth_factor=2).
+    pub fn new_dynamic() -> Self {
+        Self {
+            initial_scale: 32768.0,
+            dynamic: true,
+            growth_interval: 2000,
+            growth_factor: 2.0,
+        }
+    }
+
+    /// Sets the initial scale, or the scale for a static loss scaler.
+    pub fn set_initial_scale(&mut self, initial_scale: f32) {
+        self.initial_scale = initial_scale;
+    }
+
+    /// Sets the number of consecutive steps with finite gradients after which
+    /// a dynamic loss scaler increases the scale.  Default is 2000.
+    pub fn set_growth_interval(&mut self, growth_interval: i64) {
+        self.growth_interval = growth_interval;
+    }
+
+    /// Sets the factor by which a dynamic loss scaler increases or decreases
+    /// the scale.  Default is 2.
+    pub fn set_growth_factor(&mut self, growth_factor: f32) {
+        self.growth_factor = growth_factor;
+    }
+
+    /// Adds operations to the graph to minimize the scaled loss with respect
+    /// to the variables using `optimizer`.
+    ///
+    /// This returns newly created variables, which must be initialized before
+    /// training, as well as an operation which performs a single step of
+    /// minimization and, for a dynamic loss scaler, updates the scale.  For a
+    /// dynamic loss scaler, the first returned variable holds the current
+    /// scale.
+    pub fn minimize<O: Optimizer>(
+        &self,
+        optimizer: &O,
+        scope: &mut Scope,
+        loss: Output,
+        opts: MinimizeOptions,
+    ) -> Result<(Vec<Variable>, Operation)> {
+        let mut variables = Vec::new();
+        let scale: Output = if self.dynamic {
+            let scale_var = Variable::builder()
+                .const_initial_value(self.initial_scale)
+                .build(&mut scope.with_op_name("loss_scale"))?;
+            let scale = scale_var.output.clone();
+            variables.push(scale_var);
+            scale
+        } else {
+            ops::constant(self.initial_scale, scope)?.into()
+        };
+        let loss_type = loss.operation.output_type(loss.index as usize);
+        let scaled_loss = ops::mul(loss, cast_to(scale.clone(), loss_type, scope)?, scope)?;
+        let grads_and_vars = optimizer.compute_gradients(
+            scope,
+            scaled_loss.into(),
+            ComputeGradientsOptions {
+                variables: opts.variables,
+            },
+        )?;
+        let mut unscaled = Vec::with_capacity(grads_and_vars.len());
+        for (grad, var) in grads_and_vars {
+            let grad = match grad {
+                Some(grad) => {
+                    let var_scale = cast_to(scale.clone(), var.dtype, scope)?;
+                    Some(ops::div(grad, var_scale, scope)?.into())
+                }
+                None => None,
+            };
+            unscaled.push((grad, var));
+        }
+        if !self.dynamic {
+            let (mut optimizer_vars, train) = optimizer.apply_gradients(
+                scope,
+                ApplyGradientsOptions {
+                    grads_and_vars: &unscaled,
+                },
+            )?;
+            variables.append(&mut optimizer_vars);
+            return Ok((variables, train));
+        }
+
+        let flat_shape = ops::constant(&[-1i64][..], scope)?;
+        let first_axis = ops::constant(0i32, scope)?;
+        let mut all_finite: Output = ops::constant(true, scope)?.into();
+        for (grad, _) in &unscaled {
+            if let Some(grad) = grad {
+                let flat = ops::reshape(grad.clone(), flat_shape.clone(), scope)?;
+                let finite = ops::is_finite(flat, scope)?;
+                let grad_finite = ops::all(finite, first_axis.clone(), scope)?;
+                all_finite = ops::logical_and(all_finite, grad_finite, scope)?.into();
+            }
+        }
+        let mut safe_grads = Vec::with_capacity(unscaled.len());
+        for (grad, var) in unscaled {
+            let grad = match grad {
+                Some(grad) => {
+                    let zeros = ops::zeros_like(grad.clone(), scope)?;
+                    Some(ops::select(all_finite.clone(), grad, zeros, scope)?.into())
+                }
+                None => None,
+            };
+            safe_grads.push((grad, var));
+        }
+        let (mut optimizer_vars, apply) = optimizer.apply_gradients(
+            scope,
+            ApplyGradientsOptions {
+                grads_and_vars: &safe_grads,
+            },
+        )?;
+
+        let good_steps_var = Variable::builder()
+            .const_initial_value(0i64)
+            .build(&mut scope.with_op_name("loss_scale_good_steps"))?;
+        let good_steps = good_steps_var.output.clone();
+        let one = ops::constant(1i64, scope)?;
+        let zero = ops::constant(0i64, scope)?;
+        let incremented = ops::add(good_steps, one, scope)?;
+        let good_steps = ops::select(all_finite.clone(), incremented, zero.clone(), scope)?;
+        let growth_interval = ops::constant(self.growth_interval, scope)?;
+        let grow = ops::greater_equal(good_steps.clone(), growth_interval, scope)?;
+        let good_steps = ops::select(grow.clone(), zero, good_steps, scope)?;
+        let growth_factor = ops::constant(self.growth_factor, scope)?;
+        let grown = ops::mul(scale.clone(), growth_factor.clone(), scope)?;
+        let grown = ops::select(grow, grown, scale.clone(), scope)?;
+        let shrunk = ops::div(scale.clone(), growth_factor, scope)?;
+        let min_scale = ops::constant(1.0f32, scope)?;
+        let shrunk = ops::maximum(shrunk, min_scale, scope)?;
+        let new_scale = ops::select(all_finite, grown, shrunk, scope)?;
+        let update_scale = ops::Assign::new()
+            .add_control_input(apply.clone())
+            .build(scale, new_scale, scope)?;
+        let update_good_steps = ops::Assign::new().add_control_input(apply).build(
+            good_steps_var.output.clone(),
+            good_steps,
+            scope,
+        )?;
+        let train = ops::NoOp::new()
+            .add_control_input(update_scale)
+            .add_control_input(update_good_steps)
+            .build(scope)?;
+        variables.push(good_steps_var);
+        variables.append(&mut optimizer_vars);
+        Ok((variables, train))
+    }
+}
+
+/// Options for `TrainStep::build`.
+#[derive(Default, Debug, Clone)]
+pub struct TrainStepOptions<'a> {
+    variables: &'a [Variable],
+    update_ops: &'a [Operation],
+    finite_checks: bool,
+}
+
+impl<'a> TrainStepOptions<'a> {
+    /// Sets the variables which will be optimized.
+    pub fn with_variables(self, variables: &'a [Variable]) -> Self {
+        Self { variables, ..self }
+    }
+
+    /// Sets operations which must run on every step, such as the updates of
+    /// the moving averages of batch normalization.
+    pub fn with_update_ops(self, update_ops: &'a [Operation]) -> Self {
+        Self { update_ops, ..self }
+    }
+
+    /// Sets whether the loss and the gradients are checked with
+    /// `assert_all_finite` before the gradients are applied, so that a step
+    /// with a NaN or an infinity fails without updating the variables.
+    pub fn with_finite_checks(self, finite_checks: bool) -> Self {
+        Self {
+            finite_checks,
+            ..self
+        }
+    }
+}
+
+/// A complete training step: computing and applying the gradients of the
+/// loss, incrementing the global step, and running the update ops, grouped
+/// into a single operation.
+///
+/// ```rust,ignore
+/// let step = TrainStep::build(&optimizer, &mut scope, loss, opts)?;
+/// // Initialize the model's variables and step.variables(), then:
+/// let mut args = SessionRunArgs::new();
+/// args.add_feed(&x, 0, &batch);
+/// args.add_target(step.train_op());
+/// let loss = args.request_fetch(&step.loss().operation, step.loss().index);
+/// session.run(&mut args)?;
+/// ```
+#[derive(Debug, Clone)]
+pub struct TrainStep {
+    train_op: Operation,
+    loss: Output,
+    global_step: Variable,
+    variables: Vec<Variable>,
+}
+
+impl TrainStep {
+    /// Adds the operations for a training step minimizing `loss` with
+    /// `optimizer` to the graph.
+    pub fn build<O: Optimizer>(
+        optimizer: &O,
+        scope: &mut Scope,
+        loss: Output,
+        opts: TrainStepOptions,
+    ) -> Result<TrainStep> {
+        let global_step = Variable::builder()
+            .const_initial_value(0i64)
+            .build(&mut scope.with_op_name("global_step"))?;
+        let (mut variables, apply) = if opts.finite_checks {
+            let grads_and_vars = optimizer.compute_gradients(
+                scope,
+                loss.clone(),
+                ComputeGradientsOptions {
+                    variables: opts.variables,
+                },
+            )?;
+            let mut checked = vec![loss.clone()];
+            checked.extend(grads_and_vars.iter().filter_map(|(grad, _)| grad.clone()));
+            let check = assert_all_finite(&checked, scope)?;
+            optimizer.apply_gradients(
+                &mut scope.with_control_dependencies(&[check]),
+                ApplyGradientsOptions {
+                    grads_and_vars: &grads_and_vars,
+                },
+            )?
+        } else {
+            optimizer.minimize(
+                scope,
+                loss.clone(),
+                MinimizeOptions {
+                    variables: opts.variables,
+                },
+            )?
+        };
+        let one = ops::constant(1i64, scope)?;
+        let increment = ops::AssignAdd::new()
+            .add_control_input(apply.clone())
+            .build(global_step.output.clone(), one, scope)?;
+        let mut train = ops::NoOp::new()
+            .add_control_input(apply)
+            .add_control_input(increment);
+        for update_op in opts.update_ops {
+            train = train.add_control_input(update_op.clone());
+        }
+        let train_op = train.build(&mut scope.with_op_name("train_step"))?;
+        variables.push(global_step.clone());
+        Ok(TrainStep {
+            train_op,
+            loss,
+            global_step,
+            variables,
+        })
+    }
+
+    /// Returns the operation which runs one training step.
+    pub fn train_op(&self) -> &Operation {
+        &self.train_op
+    }
+
+    /// Returns the loss, for fetching it together with the training step.
+    pub fn loss(&self) -> &Output {
+        &self.loss
+    }
+
+    /// Returns the number of steps run so far, which is incremented after
+    /// the gradients are applied.
+    pub fn global_step(&self) -> &Variable {
+        &self.global_step
+    }
+
+    /// Returns the variables created for the step, including the global
+    /// step and the optimizer's state, which must be initialized before
+    /// training.
+    pub fn variables(&self) -> &[Variable] {
+        &self.variables
+    }
+}
+
+/// Returns an operation which fails if any of `outputs`, e.g. the loss and
+/// the gradients, contains a NaN or an infinity, with an error naming the
+/// tensor.  Running it runs a `CheckNumerics` for each output.
+///
+/// Make the training operation depend on the returned operation, e.g. by
+/// building it with `scope.with_control_dependencies`, so that a bad step
+/// aborts before the variables are updated.  `TrainStepOptions` can do this
+/// for the loss and gradients of a `TrainStep`.
+pub fn assert_all_finite(outputs: &[Output], scope: &mut Scope) -> Result<Operation> {
+    let mut group = ops::NoOp::new();
+    for output in outputs {
+        let check = ops::CheckNumerics::new()
+            .message(format!("{}:{}", output.operation.name()?, output.index))
+            .build(output.clone(), scope)?;
+        group = group.add_control_input(check);
+    }
+    group.build(&mut scope.with_op_name("assert_all_finite"))
+}
+
+/// A histogram of the values of a variable, see `variable_histograms`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct VariableHistogram {
+    /// The name of the variable.
+    pub name: String,
+    /// The smallest finite value, or 0 if there are no finite values.
+    pub min: f64,
+    /// The largest finite value, or 0 if there are no finite values.
+    pub max: f64,
+    /// The mean of the finite values.
+    pub mean: f64,
+    /// The L2 norm of the finite values.
+    pub l2_norm: f64,
+    /// The number of NaNs and infinities, which are not counted in any
+    /// bucket.
+    pub non_finite: u64,
+    /// The edges of the buckets, which split `[min, max]` into equal widths.
+    /// Bucket `i` counts the values in `[bucket_edges[i], bucket_edges[i + 1])`,
+    /// and the last bucket includes `max`.
+    pub bucket_edges: Vec<f64>,
+    /// The number of values in each bucket.
+    pub bucket_counts: Vec<u64>,
+}
+
+impl VariableHistogram {
+    fn new(name: String, values: &[f64], num_buckets: usize) -> Self {
+        let finite: Vec<f64> = values.iter().cloned().filter(|v| v.is_finite()).collect();
+        let (min, max) = if finite.is_empty() {
+            (0.0, 0.0)
+        } else {
+            finite
+                .iter()
+                .fold((f64::INFINITY, f64::NEG_INFINITY), |(min, max), v| {
+                    (min.min(*v), max.max(*v))
+                })
+        };
+        let width = (max - min) / num_buckets as f64;
+        let mut bucket_counts = vec![0; num_buckets];
+        for value in &finite {
+            let bucket = if width > 0.0 {
+                ((value - min) / width) as usize
+            } else {
+                0
+            };
+            bucket_counts[bucket.min(num_buckets - 1)] += 1;
+        }
+        VariableHistogram {
+            name,
+            min,
+            max,
+            mean: finite.iter().sum::<f64>() / finite.len().max(1) as f64,
+            l2_norm: finite.iter().map(|v| v * v).sum::<f64>().sqrt(),
+            non_finite: (values.len() - finite.len()) as u64,
+            bucket_edges: (0..=num_buckets).map(|i| min + width * i as f64).collect(),
+            bucket_counts,
+        }
+    }
+}
+
+/// Fetches the values of `variables` with `session` and computes a histogram
+/// with `num_buckets` buckets of each, e.g. to log them every few hundred
+/// steps and spot exploding or vanishing weights during training.
+///
+/// Returns an `InvalidArgument` error if `num_buckets` is zero, or if a
+/// variable is not of a floating point type.
+pub fn variable_histograms(
+    session: &Session,
+    variables: &[Variable],
+    num_buckets: usize,
+) -> Result<Vec<VariableHistogram>> {
+    if num_buckets == 0 {
+        return Err(invalid_arg!("num_buckets must be positive"));
+    }
+    let mut args = SessionRunArgs::new();
+    let tokens: Vec<_> = variables
+        .iter()
+        .map(|v| args.request_fetch(&v.output().operation, v.output().index))
+        .collect();
+    session.run(&mut args)?;
+    let mut histograms = Vec::with_capacity(variables.len());
+    for (variable, token) in variables.iter().zip(tokens) {
+        let values: Vec<f64> = match args.fetch_dynamic(token)? {
+            DynTensor::Float(tensor) => tensor.iter().map(|v| f64::from(*v)).collect(),
+            DynTensor::Double(tensor) => tensor.to_vec(),
+            DynTensor::Half(tensor) => tensor.iter().map(|v| v.to_f64()).collect(),
+            tensor => {
+                return Err(Status::new_set_lossy(
+                    Code::InvalidArgument,
+                    &format!(
+                        "Variable {} has type {}, which is not a floating point type",
+                        variable.name(),
+                        tensor.dtype()
+                    ),
+                ))
+            }
+        };
+        histograms.push(VariableHistogram::new(
+            variable.name().to_string(),
+            &values,
+            num_buckets,
+        ));
+    }
+    Ok(histograms)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     use crate::ops;
     use crate::Scope;
-    use crate::Session;
     use crate::SessionOptions;
-    use crate::SessionRunArgs;
     use crate::Shape;
     use crate::Tensor;
 
+    #[test]
+    fn histograms_of_variables() {
+        let mut scope = Scope::new_root_scope();
+        let weights = Variable::builder()
+            .const_initial_value(
+                Tensor::new(&[5])
+                    .with_values(&[0.0f32, 1.0, 2.5, 4.0, f32::NAN])
+                    .unwrap(),
+            )
+            .build(&mut scope.with_op_name("weights"))
+            .unwrap();
+        let constant = Variable::builder()
+            .const_initial_value(Tensor::new(&[2]).with_values(&[3.0f64, 3.0]).unwrap())
+            .build(&mut scope.with_op_name("constant"))
+            .unwrap();
+        let step = Variable::builder()
+            .const_initial_value(0i64)
+            .build(&mut scope.with_op_name("step"))
+            .unwrap();
+        let session = Session::new(&SessionOptions::new(), &scope.graph()).unwrap();
+        let mut run_args = SessionRunArgs::new();
+        run_args.add_target(&scope.initializer_op().unwrap());
+        session.run(&mut run_args).unwrap();
+
+        let histograms = variable_histograms(&session, &[weights.clone(), constant], 2).unwrap();
+        assert_eq!(
+            histograms[0],
+            VariableHistogram {
+                name: "weights".to_string(),
+                min: 0.0,
+                max: 4.0,
+                mean: 1.875,
+                l2_norm: (1.0f64 + 6.25 + 16.0).sqrt(),
+                non_finite: 1,
+                bucket_edges: vec![0.0, 2.0, 4.0],
+                bucket_counts: vec![2, 2],
+            }
+        );
+        assert_eq!(histograms[1].bucket_counts, vec![2, 0]);
+        assert_eq!(histograms[1].mean, 3.0);
+        assert!(variable_histograms(&session, &[weights.clone()], 0).is_err());
+        assert!(variable_histograms(&session, &[weights, step], 2).is_err());
+    }
+
     #[test]
     fn simple_gradient_descent() {
         let mut scope = Scope::new_root_scope();
@@ -345,6 +926,118 @@ mod tests {
         );
     }
 
+    #[test]
+    fn train_step() {
+        let mut scope = Scope::new_root_scope();
+        let x_var = Variable::builder()
+            .const_initial_value(3.0f32)
+            .build(&mut scope.with_op_name("x"))
+            .unwrap();
+        let counter = Variable::builder()
+            .const_initial_value(0i32)
+            .build(&mut scope.with_op_name("counter"))
+            .unwrap();
+        let one = ops::constant(1i32, &mut scope).unwrap();
+        let update_op = ops::assign_add(counter.output.clone(), one, &mut scope).unwrap();
+        let x_squared = ops::mul(x_var.output.clone(), x_var.output.clone(), &mut scope).unwrap();
+        let sgd = GradientDescentOptimizer::new(ops::constant(0.1f32, &mut scope).unwrap());
+        let variables = [x_var.clone()];
+        let update_ops = [update_op];
+        let step = TrainStep::build(
+            &sgd,
+            &mut scope,
+            x_squared.into(),
+            TrainStepOptions::default()
+                .with_variables(&variables)
+                .with_update_ops(&update_ops),
+        )
+        .unwrap();
+        assert_eq!(step.global_step().name(), "global_step");
+        let options = SessionOptions::new();
+        let session = Session::new(&options, &scope.graph()).unwrap();
+
+        let mut run_args = SessionRunArgs::new();
+        run_args.add_target(&x_var.initializer);
+        run_args.add_target(&counter.initializer);
+        for var in step.variables() {
+            run_args.add_target(&var.initializer);
+        }
+        session.run(&mut run_args).unwrap();
+
+        let mut run_args = SessionRunArgs::new();
+        run_args.add_target(step.train_op());
+        let loss_fetch = run_args.request_fetch(&step.loss().operation, step.loss().index);
+        session.run(&mut run_args).unwrap();
+        assert_eq!(run_args.fetch::<f32>(loss_fetch).unwrap()[0], 9.0);
+        session.run(&mut run_args).unwrap();
+
+        let mut run_args = SessionRunArgs::new();
+        let x_fetch = run_args.request_fetch(&x_var.output.operation, 0);
+        let step_fetch = run_args.request_fetch(&step.global_step().output.operation, 0);
+        let counter_fetch = run_args.request_fetch(&counter.output.operation, 0);
+        session.run(&mut run_args).unwrap();
+        let x = run_args.fetch::<f32>(x_fetch).unwrap()[0];
+        assert!((1.91..=1.93).contains(&x), "x = {}", x);
+        assert_eq!(run_args.fetch::<i64>(step_fetch).unwrap()[0], 2);
+        assert_eq!(run_args.fetch::<i32>(counter_fetch).unwrap()[0], 2);
+    }
+
+    #[test]
+    fn train_step_with_finite_checks() {
+        let mut scope = Scope::new_root_scope();
+        let x_var = Variable::builder()
+            .const_initial_value(3.0f32)
+            .build(&mut scope.with_op_name("x"))
+            .unwrap();
+        let scale = ops::Placeholder::new()
+            .dtype(DataType::Float)
+            .build(&mut scope.with_op_name("scale"))
+            .unwrap();
+        let loss = ops::mul(
+            x_var.output.clone(),
+            scale.clone(),
+            &mut scope.with_op_name("loss"),
+        )
+        .unwrap();
+        let sgd = GradientDescentOptimizer::new(ops::constant(0.5f32, &mut scope).unwrap());
+        let variables = [x_var.clone()];
+        let step = TrainStep::build(
+            &sgd,
+            &mut scope,
+            loss.into(),
+            TrainStepOptions::default()
+                .with_variables(&variables)
+                .with_finite_checks(true),
+        )
+        .unwrap();
+        let options = SessionOptions::new();
+        let session = Session::new(&options, &scope.graph()).unwrap();
+        let mut run_args = SessionRunArgs::new();
+        run_args.add_target(&x_var.initializer);
+        for var in step.variables() {
+            run_args.add_target(&var.initializer);
+        }
+        session.run(&mut run_args).unwrap();
+
+        let run_step = |scale_value: f32| {
+            let scale_value = Tensor::from(scale_value);
+            let mut run_args = SessionRunArgs::new();
+            run_args.add_feed(&scale, 0, &scale_value);
+            run_args.add_target(step.train_op());
+            session.run(&mut run_args)
+        };
+        let error = run_step(f32::NAN).unwrap_err();
+        assert_eq!(error.code(), Code::InvalidArgument);
+        assert!(error.to_string().contains("loss:0"), "{}", error);
+        run_step(2.0).unwrap();
+
+        // Only the second step updated x.
+        let mut run_args = SessionRunArgs::new();
+        let x_fetch = run_args.request_fetch(&x_var.output.operation, 0);
+        session.run(&mut run_args).unwrap();
+        assert_eq!(run_args.fetch::<f32>(x_fetch).unwrap()[0], 2.0);
+    }
+
     #[test]
     fn simple_adadelta() {
         let mut scope = Scope::new_root_scope();
@@ -404,6 +1097,107 @@ mod tests {
         );
     }
 
+    #[test]
+    fn mixed_precision_gradient_descent() {
+        let mut scope = Scope::new_root_scope();
+        let x_var = Variable::builder()
+            .const_initial_value(3.0f32)
+            .build(&mut scope.with_op_name("x"))
+            .unwrap();
+        let optimizer = MixedPrecisionOptimizer::new(
+            GradientDescentOptimizer::new(ops::constant(0.1f32, &mut scope).unwrap()),
+            128.0,
+        );
+        let x = optimizer.cast_variable(&x_var, &mut scope).unwrap();
+        assert_eq!(x.operation.output_type(0), DataType::Half);
+        let x_squared = ops::mul(x.clone(), x, &mut scope).unwrap();
+        let (minimizer_vars, minimize) = optimizer
+            .minimize(
+                &mut scope,
+                x_squared.into(),
+                MinimizeOptions::default().with_variables(&[x_var.clone()]),
+            )
+            .unwrap();
+        let options = SessionOptions::new();
+        let session = Session::new(&options, &scope.graph()).unwrap();
+
+        let mut run_args = SessionRunArgs::new();
+        run_args.add_target(&x_var.initializer);
+        for var in &minimizer_vars {
+            run_args.add_target(&var.initializer);
+        }
+        session.run(&mut run_args).unwrap();
+
+        let mut run_args = SessionRunArgs::new();
+        run_args.add_target(&minimize);
+        let x_fetch = run_args.request_fetch(&x_var.output.operation, 0);
+        session.run(&mut run_args).unwrap();
+        let x_output = run_args.fetch::<f32>(x_fetch).unwrap();
+        assert_eq!(x_output.len(), 1);
+        assert!(
+            x_output[0] >= 2.39 && x_output[0] <= 2.41,
+            "x_output[0] = {}",
+            x_output[0]
+        );
+    }
+
+    #[test]
+    fn dynamic_loss_scaler() {
+        let mut scope = Scope::new_root_scope();
+        let x_var = Variable::builder()
+            .const_initial_value(3.0f32)
+            .build(&mut scope.with_op_name("x"))
+            .unwrap();
+        let x_squared = ops::mul(x_var.output.clone(), x_var.output.clone(), &mut scope).unwrap();
+        let sgd = GradientDescentOptimizer::new(ops::constant(0.1f32, &mut scope).unwrap());
+        let mut loss_scaler = LossScaler::new_dynamic();
+        // Large enough that the first scaled gradient overflows.
+        loss_scaler.set_initial_scale(1e38);
+        let (minimizer_vars, minimize) = loss_scaler
+            .minimize(
+                &sgd,
+                &mut scope,
+                x_squared.into(),
+                MinimizeOptions::default().with_variables(&[x_var.clone()]),
+            )
+            .unwrap();
+        let scale = minimizer_vars[0].clone();
+        let options = SessionOptions::new();
+        let session = Session::new(&options, &scope.graph()).unwrap();
+
+        let mut run_args = SessionRunArgs::new();
+        run_args.add_target(&x_var.initializer);
+        for var in &minimizer_vars {
+            run_args.add_target(&var.initializer);
+        }
+        session.run(&mut run_args).unwrap();
+
+        let mut run_args = SessionRunArgs::new();
+        run_args.add_target(&minimize);
+        session.run(&mut run_args).unwrap();
+
+        let mut run_args = SessionRunArgs::new();
+        let x_fetch = run_args.request_fetch(&x_var.output.operation, 0);
+        let scale_fetch = run_args.request_fetch(&scale.output.operation, 0);
+        session.run(&mut run_args).unwrap();
+        assert_eq!(run_args.fetch::<f32>(x_fetch).unwrap()[0], 3.0);
+        assert_eq!(run_args.fetch::<f32>(scale_fetch).unwrap()[0], 5e37);
+
+        let mut run_args = SessionRunArgs::new();
+        run_args.add_target(&minimize);
+        session.run(&mut run_args).unwrap();
+
+        let mut run_args = SessionRunArgs::new();
+        let x_fetch = run_args.request_fetch(&x_var.output.operation, 0);
+        session.run(&mut run_args).unwrap();
+        let x_output = run_args.fetch::<f32>(x_fetch).unwrap();
+        assert!(
+            x_output[0] >= 2.39 && x_output[0] <= 2.41,
+            "x_output[0] = {}",
+            x_output[0]
+        );
+    }
+
     #[test]
     fn xor_nn() {
         let mut scope = Scope::new_root_scope();
@@ -1,11 +1,14 @@
 use crate::ops;
 use crate::AnyTensor;
+use crate::Code;
 use crate::DataType;
+use crate::Graph;
 use crate::Operation;
 use crate::Output;
 use crate::Result;
 use crate::Scope;
 use crate::Shape;
+use crate::Status;
 use crate::Tensor;
 use crate::TensorType;
 use std::borrow::Borrow;
@@ -26,6 +29,61 @@ impl Variable {
         VariableBuilder::default()
     }
 
+    /// Wraps an existing variable in `graph`, e.g. one imported from a saved
+    /// model, so that it can be used with an optimizer.
+    ///
+    /// `name` must name a `VariableV2` or `Variable` operation.  The
+    /// initializer is the `Assign` operation which assigns to the variable,
+    /// preferring one named `name/Assign`, which is how TensorFlow names
+    /// initializers.  Resource variables are not supported.
+    pub fn from_graph(graph: &Graph, name: &str) -> Result<Variable> {
+        let operation = graph.operation_by_name_required(name)?;
+        let op_type = operation.op_type()?;
+        if op_type != "VariableV2" && op_type != "Variable" {
+            return Err(Status::new_set_lossy(
+                if op_type == "VarHandleOp" {
+                    Code::Unimplemented
+                } else {
+                    Code::InvalidArgument
+                },
+                &format!(
+                    "Operation {} has type {}, but only VariableV2 and Variable are supported",
+                    name, op_type
+                ),
+            ));
+        }
+        let mut assigns = Vec::new();
+        for (consumer, index) in operation.output_consumers(0) {
+            if index == 0 && consumer.op_type()? == "Assign" {
+                assigns.push(consumer);
+            }
+        }
+        let initializer_name = format!("{}/Assign", name);
+        let mut initializer = None;
+        for assign in assigns {
+            if assign.name()? == initializer_name {
+                initializer = Some(assign);
+                break;
+            }
+            if initializer.is_none() {
+                initializer = Some(assign);
+            }
+        }
+        let initializer = initializer.ok_or_else(|| {
+            Status::new_set_lossy(
+                Code::NotFound,
+                &format!("No initializer found for variable {}", name),
+            )
+        })?;
+        Ok(Variable {
+            name: name.to_string(),
+            initializer,
+            dtype: operation.get_attr_type("dtype")?,
+            shape: operation.get_attr_shape("shape")?,
+            output: operation.into(),
+        })
+    }
+
     /// Returns the name.
     pub fn name(&self) -> &str {
         &self.name
@@ -81,6 +139,12 @@ impl<'a> Default for VariableBuilder<'a> {
 impl<'a> VariableBuilder<'a> {
     /// Sets the initial value from anything that can be converted into a Tensor.
     /// This also sets the type and shape.
+    ///
+    /// This can be used for weights computed on the host, e.g. by a custom
+    /// initialization scheme such as PCA.  The data type of the variable is
+    /// `T` and its shape is the shape of the tensor, overriding any type or
+    /// shape set before.  The tensor is embedded in the graph as a `Const` op
+    /// which the initializer assigns to the variable.
     pub fn const_initial_value<T: TensorType, TT: Into<Tensor<T>>>(self, value: TT) -> Self {
         let t: Tensor<T> = value.into();
         let shape = t.shape();
@@ -129,18 +193,17 @@ impl<'a> VariableBuilder<'a> {
 
     /// Builds the Variable.
     pub fn build(self, scope: &mut Scope) -> Result<Variable> {
-        let name = scope.get_unique_name_for_op("VariableV2");
         let dtype = match self.dtype {
             Some(d) => d,
             None => return Err(invalid_arg!("data_type must be specified")),
         };
-        let variable_op = {
-            let mut graph = scope.graph_mut();
-            let mut nd = graph.new_operation("VariableV2", &name)?;
+        let shape = &self.shape;
+        let variable_op = scope.new_operation("VariableV2", |nd| {
             nd.set_attr_type("dtype", dtype)?;
-            nd.set_attr_shape("shape", &self.shape)?;
-            nd.finish()?
-        };
+            nd.set_attr_shape("shape", shape)?;
+            Ok(())
+        })?;
+        let name = variable_op.name()?;
         let initial_value = match self.initial_value {
             VariableInitialValue::Unspecified => {
                 return Err(invalid_arg!("an initial value is required"))
@@ -150,6 +213,7 @@ impl<'a> VariableBuilder<'a> {
             VariableInitialValue::Output(o) => o,
         };
         let initializer = ops::assign(variable_op.clone(), initial_value, scope)?;
+        scope.add_variable_initializer(initializer.clone());
         Ok(Variable {
             name,
             output: variable_op.into(),
@@ -165,7 +229,6 @@ impl<'a> VariableBuilder<'a> {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::Code;
     use crate::Session;
     use crate::SessionOptions;
     use crate::SessionRunArgs;
@@ -203,6 +266,70 @@ mod tests {
         assert_eq!(&output[..], &[3.0f32]);
     }
 
+    #[test]
+    fn scope_initializer_op() {
+        let mut scope = Scope::new_root_scope();
+        let a = Variable::builder()
+            .const_initial_value(1.0f32)
+            .build(&mut scope)
+            .unwrap();
+        let b = Variable::builder()
+            .const_initial_value(2i32)
+            .build(&mut scope.new_sub_scope("sub"))
+            .unwrap();
+        let initializer = scope.initializer_op().unwrap();
+        assert_eq!(initializer.num_control_inputs(), 2);
+
+        let options = SessionOptions::new();
+        let session = Session::new(&options, &scope.graph()).unwrap();
+        let mut run_args = SessionRunArgs::new();
+        run_args.add_target(&initializer);
+        session.run(&mut run_args).unwrap();
+
+        let mut run_args = SessionRunArgs::new();
+        let a_fetch = run_args.request_fetch(&a.output.operation, 0);
+        let b_fetch = run_args.request_fetch(&b.output.operation, 0);
+        session.run(&mut run_args).unwrap();
+        assert_eq!(&run_args.fetch::<f32>(a_fetch).unwrap()[..], &[1.0]);
+        assert_eq!(&run_args.fetch::<i32>(b_fetch).unwrap()[..], &[2]);
+    }
+
+    #[test]
+    fn scope_reset_variables() {
+        let mut scope = Scope::new_root_scope();
+        let a = Variable::builder()
+            .const_initial_value(1.0f32)
+            .build(&mut scope)
+            .unwrap();
+        let initializers = scope.variable_initializers();
+        assert_eq!(initializers.len(), 1);
+        assert_eq!(
+            initializers[0].name().unwrap(),
+            a.initializer.name().unwrap()
+        );
+        let increment = {
+            let one = ops::constant(1.0f32, &mut scope).unwrap();
+            ops::assign_add(a.output.clone(), one, &mut scope).unwrap()
+        };
+        let reset = scope.initializer_op().unwrap();
+
+        let options = SessionOptions::new();
+        let session = Session::new(&options, &scope.graph()).unwrap();
+        let fetch_value = |target: &Operation| {
+            let mut run_args = SessionRunArgs::new();
+            run_args.add_target(target);
+            session.run(&mut run_args).unwrap();
+            let mut run_args = SessionRunArgs::new();
+            let fetch = run_args.request_fetch(&a.output.operation, 0);
+            session.run(&mut run_args).unwrap();
+            run_args.fetch::<f32>(fetch).unwrap()[0]
+        };
+        assert_eq!(fetch_value(&reset), 1.0);
+        assert_eq!(fetch_value(&increment), 2.0);
+        assert_eq!(fetch_value(&increment), 3.0);
+        assert_eq!(fetch_value(&reset), 1.0);
+    }
+
     #[test]
     fn const_initialized_matrix() {
         let scope = Scope::new_root_scope();
@@ -239,6 +366,34 @@ mod tests {
         assert_eq!(&output[..], &initial[..]);
     }
 
+    #[test]
+    fn host_initialized() {
+        let scope = Scope::new_root_scope();
+
+        let initial: Vec<f64> = (0..6).map(|i| (i as f64).sqrt()).collect();
+        let initial = Tensor::new(&[3, 2]).with_values(&initial).unwrap();
+        let variable = Variable::builder()
+            .shape([6i64])
+            .data_type(DataType::Float)
+            .const_initial_value(initial.clone())
+            .build(&mut scope.with_op_name("foo"))
+            .unwrap();
+        assert_eq!(variable.shape, Shape(Some(vec![Some(3), Some(2)])));
+        assert_eq!(variable.dtype, DataType::Double);
+
+        let options = SessionOptions::new();
+        let session = Session::new(&options, &scope.graph()).unwrap();
+        let mut run_args = SessionRunArgs::new();
+        run_args.add_target(&variable.initializer);
+        session.run(&mut run_args).unwrap();
+
+        let mut run_args = SessionRunArgs::new();
+        let fetch = run_args.request_fetch(&variable.output.operation, 0);
+        session.run(&mut run_args).unwrap();
+        let output = run_args.fetch::<f64>(fetch).unwrap();
+        assert_eq!(output, initial);
+    }
+
     #[test]
     fn custom_initializer_missing_dtype() {
         let mut scope = Scope::new_root_scope();
@@ -290,4 +445,36 @@ mod tests {
         let output = run_args.fetch::<f32>(fetch).unwrap();
         assert_eq!(&output[..], &[3.0f32]);
     }
+
+    #[test]
+    fn from_graph() {
+        let mut scope = Scope::new_root_scope();
+        let value = Tensor::new(&[2]).with_values(&[1, 2]).unwrap();
+        let variable = Variable::builder()
+            .const_initial_tensor(&value)
+            .build(&mut scope.with_op_name("foo"))
+            .unwrap();
+        let const_op = ops::constant(3.0f32, &mut scope).unwrap();
+
+        let found = Variable::from_graph(&scope.graph(), "foo").unwrap();
+        assert_eq!(found.name, "foo");
+        assert_eq!(found.shape, Shape(Some(vec![Some(2)])));
+        assert_eq!(found.dtype, DataType::Int32);
+        assert_eq!(
+            found.output.name().unwrap(),
+            variable.output.name().unwrap()
+        );
+        assert_eq!(
+            found.initializer.name().unwrap(),
+            variable.initializer.name().unwrap()
+        );
+
+        assert_eq!(
+            Variable::from_graph(&scope.graph(), &const_op.name().unwrap())
+                .unwrap_err()
+                .code(),
+            Code::InvalidArgument
+        );
+        assert!(Variable::from_graph(&scope.graph(), "bar").is_err());
+    }
 }
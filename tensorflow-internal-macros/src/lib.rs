@@ -279,17 +279,16 @@ impl<'a> ToTokens for BuildFn<'a> {
             #[doc = #op_name]
             #[doc = "` operation."]
             pub fn build#build_fn_generics(&self, #build_fn_args scope: &mut crate::Scope) -> crate::Result<crate::Operation> {
-                let name = scope.get_unique_name_for_op(#op_name);
-                let mut graph = scope.graph_mut();
-                let mut nd = graph.new_operation(#op_name, &name)?;
-                #(
-                    nd.add_input(#arg_names);
-                )*
-                for op in &self.control_inputs {
-                    nd.add_control_input(op);
-                }
-                #(#set_attrs)*
-                nd.finish()
+                scope.new_operation(#op_name, |nd| {
+                    #(
+                        nd.add_input(#arg_names);
+                    )*
+                    for op in &self.control_inputs {
+                        nd.add_control_input(op);
+                    }
+                    #(#set_attrs)*
+                    ::std::result::Result::Ok(())
+                })
             }
         });
     }